@@ -0,0 +1,263 @@
+//! Reading source files straight out of archives
+//!
+//! Lets `lgrep index release.zip` index an archive's contents without
+//! extracting it. Supports `.zip`, `.tar`, `.tar.gz` and `.tgz`. Entries
+//! keep their in-archive path, and the usual ignore rules are applied by
+//! path. Archives inside the archive are not recursed into.
+
+use crate::config::Config;
+use crate::error::{LgrepError, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// A text file read from an archive
+#[derive(Debug)]
+pub struct ArchiveEntry {
+    /// Path inside the archive (`/`-separated, no leading `./`)
+    pub path: String,
+    /// File content
+    pub content: String,
+}
+
+/// Check whether a path names a supported archive
+pub fn is_archive(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    [".zip", ".tar", ".tar.gz", ".tgz"]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+}
+
+/// Read the indexable text files from an archive
+///
+/// Applies the same path rules as a directory walk: extension and
+/// lock-file checks, the size limit, hidden paths, and any `.gitignore`,
+/// `.ignore` or `.lgrepignore` (see [`Config::ignore_filename`]) files found
+/// inside the archive. `include_hidden` and `no_ignore` lift the last two.
+/// `.git` and `.lgrep` directories are always skipped.
+pub fn read_archive(path: &Path, config: &Config) -> Result<Vec<ArchiveEntry>> {
+    let name = path.to_string_lossy().to_lowercase();
+    let files = if name.ends_with(".zip") {
+        read_zip(path, config.max_file_size)?
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        let decoder = flate2::read::GzDecoder::new(File::open(path)?);
+        read_tar(decoder, config.max_file_size)?
+    } else {
+        read_tar(File::open(path)?, config.max_file_size)?
+    };
+
+    let ignores = if config.no_ignore {
+        HashMap::new()
+    } else {
+        ignore_matchers(&files, &config.ignore_filename)
+    };
+
+    let entries = files
+        .into_iter()
+        .filter(|(path, _)| config.should_index(Path::new(path)))
+        .filter(|(path, _)| {
+            !is_internal(path)
+                && (config.include_hidden || !is_hidden(path))
+                && !is_ignored(&ignores, path)
+        })
+        .filter_map(|(path, bytes)| {
+            String::from_utf8(bytes)
+                .ok()
+                .map(|content| ArchiveEntry { path, content })
+        })
+        .collect();
+
+    Ok(entries)
+}
+
+/// All regular files in a zip archive under the size limit
+fn read_zip(path: &Path, max_file_size: u64) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut archive =
+        zip::ZipArchive::new(File::open(path)?).map_err(|e| LgrepError::Archive(e.to_string()))?;
+    let mut files = Vec::new();
+
+    for i in 0..archive.len() {
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|e| LgrepError::Archive(e.to_string()))?;
+        if !entry.is_file() || entry.size() > max_file_size {
+            continue;
+        }
+        // Skip entries whose names would escape the archive root
+        let Some(name) = entry.enclosed_name() else {
+            continue;
+        };
+        let name = normalize(&name.to_string_lossy());
+
+        let mut bytes = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut bytes)?;
+        files.push((name, bytes));
+    }
+
+    Ok(files)
+}
+
+/// All regular files in a tar stream under the size limit
+fn read_tar<R: Read>(reader: R, max_file_size: u64) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut files = Vec::new();
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() || entry.size() > max_file_size {
+            continue;
+        }
+        let name = normalize(&entry.path()?.to_string_lossy());
+
+        let mut bytes = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut bytes)?;
+        files.push((name, bytes));
+    }
+
+    Ok(files)
+}
+
+/// Use `/` separators and drop a leading `./`
+fn normalize(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    path.strip_prefix("./").unwrap_or(&path).to_string()
+}
+
+/// Check for hidden components (dotfiles and dot-directories)
+fn is_hidden(path: &str) -> bool {
+    path.split('/').any(|c| c.starts_with('.'))
+}
+
+/// Check for `.git` and `.lgrep` components, which are never indexed
+fn is_internal(path: &str) -> bool {
+    path.split('/').any(|c| c == ".git" || c == ".lgrep")
+}
+
+/// Build a matcher per directory that has ignore files inside the archive
+///
+/// Matchers are rooted at a virtual `/` so in-archive paths can be
+/// matched without touching the filesystem.
+fn ignore_matchers(
+    files: &[(String, Vec<u8>)],
+    ignore_filename: &str,
+) -> HashMap<String, Gitignore> {
+    let mut builders: HashMap<String, GitignoreBuilder> = HashMap::new();
+
+    for (path, bytes) in files {
+        let (dir, name) = path.rsplit_once('/').unwrap_or(("", path));
+        if ![".gitignore", ".ignore", ignore_filename].contains(&name) {
+            continue;
+        }
+
+        let builder = builders
+            .entry(dir.to_string())
+            .or_insert_with(|| GitignoreBuilder::new(Path::new("/").join(dir)));
+        for line in String::from_utf8_lossy(bytes).lines() {
+            let _ = builder.add_line(None, line);
+        }
+    }
+
+    builders
+        .into_iter()
+        .filter_map(|(dir, builder)| builder.build().ok().map(|m| (dir, m)))
+        .collect()
+}
+
+/// Check a path against the ignore files of its ancestor directories
+fn is_ignored(ignores: &HashMap<String, Gitignore>, path: &str) -> bool {
+    let absolute = Path::new("/").join(path);
+
+    ignores.iter().any(|(dir, matcher)| {
+        let in_dir = dir.is_empty() || path.starts_with(&format!("{}/", dir));
+        in_dir
+            && matcher
+                .matched_path_or_any_parents(&absolute, false)
+                .is_ignore()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    /// Write a zip with the given (path, content) entries
+    fn write_zip(path: &Path, entries: &[(&str, &str)]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, content) in entries {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_is_archive() {
+        assert!(is_archive(Path::new("release.zip")));
+        assert!(is_archive(Path::new("src-1.0.tar.gz")));
+        assert!(is_archive(Path::new("SRC.TGZ")));
+        assert!(!is_archive(Path::new("src/main.rs")));
+    }
+
+    #[test]
+    fn test_read_zip_applies_ignore_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("release.zip");
+        write_zip(
+            &archive,
+            &[
+                ("src/lib.rs", "pub fn lib() {}"),
+                ("src/gen/out.rs", "// generated"),
+                (".gitignore", "gen/\n"),
+                (".hidden/x.rs", "fn hidden() {}"),
+                ("package-lock.json", "{}"),
+                ("vendor.zip", "not recursed"),
+            ],
+        );
+
+        let config = Config::new(PathBuf::from(dir.path()));
+        let entries = read_archive(&archive, &config).unwrap();
+        let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+
+        assert_eq!(paths, vec!["src/lib.rs"]);
+        assert_eq!(entries[0].content, "pub fn lib() {}");
+
+        let config = config.with_no_ignore(true).with_include_hidden(true);
+        let entries = read_archive(&archive, &config).unwrap();
+        let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["src/lib.rs", "src/gen/out.rs", ".hidden/x.rs"]);
+    }
+
+    #[test]
+    fn test_read_tar_gz() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("src.tar.gz");
+
+        let encoder =
+            flate2::write::GzEncoder::new(File::create(&archive).unwrap(), Default::default());
+        let mut builder = tar::Builder::new(encoder);
+        let content = b"fn main() {}";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "./app/main.rs", &content[..])
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let config = Config::new(PathBuf::from(dir.path()));
+        let entries = read_archive(&archive, &config).unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path, "app/main.rs");
+    }
+}
@@ -0,0 +1,215 @@
+//! Self-test and throughput benchmark
+//!
+//! Runs the whole pipeline (discovery, chunking, embedding, search) over a
+//! generated corpus in a temporary directory, so users can check that their
+//! machine and model perform reasonably without touching a real project.
+
+use crate::chunker::{Chunk, Chunker};
+use crate::config::{Config, EmbeddingModel};
+use crate::embedder::Embed;
+use crate::error::Result;
+use crate::index::VectorIndex;
+use crate::indexer::discover_files;
+use crate::searcher::Searcher;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Queries run against the generated corpus
+const QUERIES: &[&str] = &[
+    "open a database connection",
+    "verify the user's password",
+    "retry a failed http request",
+    "parse the configuration file",
+    "evict old entries from the cache",
+];
+
+/// Topics the generated files are written about
+const TOPICS: &[(&str, &str)] = &[
+    ("database", "connection pool query transaction"),
+    ("auth", "password hash session token"),
+    ("http", "request retry timeout backoff"),
+    ("config", "parse file settings defaults"),
+    ("cache", "entry eviction expiry capacity"),
+];
+
+/// Batch size used when embedding chunks
+const BATCH_SIZE: usize = 32;
+
+/// Timings and counts from a benchmark run
+#[derive(Debug, Clone)]
+pub struct BenchReport {
+    /// Files in the generated corpus
+    pub files: usize,
+    /// Chunks produced from them
+    pub chunks: usize,
+    /// Time spent discovering and reading files
+    pub discovery: Duration,
+    /// Time spent chunking
+    pub chunking: Duration,
+    /// Time spent embedding chunks
+    pub embedding: Duration,
+    /// Number of queries searched
+    pub queries: usize,
+    /// Total time spent on the queries (embedding plus lookup)
+    pub search: Duration,
+}
+
+impl BenchReport {
+    /// Chunks embedded per second
+    pub fn chunks_per_sec(&self) -> f64 {
+        self.chunks as f64 / self.embedding.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Mean latency of a query
+    pub fn query_latency(&self) -> Duration {
+        self.search / self.queries.max(1) as u32
+    }
+}
+
+impl std::fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Files:      {}", self.files)?;
+        writeln!(f, "Chunks:     {}", self.chunks)?;
+        writeln!(f, "Discovery:  {:.2?}", self.discovery)?;
+        writeln!(f, "Chunking:   {:.2?}", self.chunking)?;
+        writeln!(
+            f,
+            "Embedding:  {:.2?} ({:.1} chunks/sec)",
+            self.embedding,
+            self.chunks_per_sec()
+        )?;
+        write!(
+            f,
+            "Search:     {} queries, {:.2?} average",
+            self.queries,
+            self.query_latency()
+        )
+    }
+}
+
+/// Removes the benchmark directory when dropped, even on error
+struct TempDir(PathBuf);
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Run the pipeline over `files` generated source files
+///
+/// The corpus and its index live in a temporary directory that is removed
+/// afterwards. `model` only sets the index dimension; `embedder` does the
+/// actual work.
+pub fn run_bench(
+    model: EmbeddingModel,
+    embedder: Box<dyn Embed>,
+    files: usize,
+) -> Result<BenchReport> {
+    let dir = TempDir(std::env::temp_dir().join(format!("lgrep-bench-{}", std::process::id())));
+    write_corpus(&dir.0, files)?;
+
+    let config = Config::new(dir.0.clone()).with_model(model);
+
+    let start = Instant::now();
+    let discovered = discover_files(&config)?;
+    let discovery = start.elapsed();
+
+    let start = Instant::now();
+    let chunker = Chunker::from_config(&config);
+    let mut chunks: Vec<Chunk> = Vec::new();
+    for file in &discovered {
+        let next_id = chunks.len() as u64;
+        chunks.extend(file.chunk(&chunker, next_id)?);
+    }
+    let chunking = start.elapsed();
+
+    // Prefixed as the indexer does, since searches add the query prefix
+    let start = Instant::now();
+    let prefix = config.model.document_prefix();
+    let mut embeddings = Vec::with_capacity(chunks.len());
+    for batch in chunks.chunks(BATCH_SIZE) {
+        let texts: Vec<String> = batch
+            .iter()
+            .map(|c| format!("{}{}", prefix, c.text))
+            .collect();
+        embeddings.extend(embedder.embed_batch(texts.iter().map(String::as_str).collect())?);
+    }
+    let embedding = start.elapsed();
+
+    let chunk_count = chunks.len();
+    let mut index = VectorIndex::new(config)?;
+    index.add_chunks(chunks, embeddings)?;
+    let searcher = Searcher::with_embedder(index, embedder);
+
+    let start = Instant::now();
+    for query in QUERIES {
+        searcher.search(query, 10)?;
+    }
+    let search = start.elapsed();
+
+    Ok(BenchReport {
+        files: discovered.len(),
+        chunks: chunk_count,
+        discovery,
+        chunking,
+        embedding,
+        queries: QUERIES.len(),
+        search,
+    })
+}
+
+/// Write `files` small Rust files spread across the benchmark topics
+fn write_corpus(dir: &std::path::Path, files: usize) -> Result<()> {
+    for i in 0..files {
+        let (topic, words) = TOPICS[i % TOPICS.len()];
+        let module = dir.join(topic);
+        std::fs::create_dir_all(&module)?;
+
+        let functions: String = words
+            .split(' ')
+            .enumerate()
+            .map(|(j, word)| {
+                format!(
+                    "/// Handle the {word} part of {topic} (variant {i}.{j})\n\
+                     pub fn {topic}_{word}_{i}_{j}(input: &str) -> Result<String, Error> {{\n    \
+                         let {word} = {topic}::load(input)?;\n    \
+                         if {word}.is_empty() {{\n        \
+                             return Err(Error::Missing(\"{word}\"));\n    \
+                         }}\n    \
+                         Ok({word}.to_string())\n\
+                     }}\n\n"
+                )
+            })
+            .collect();
+
+        std::fs::write(module.join(format!("{}_{}.rs", topic, i)), functions)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedder::StubEmbedder;
+
+    #[test]
+    fn test_bench_runs_end_to_end() {
+        let model = EmbeddingModel::default();
+        let embedder = StubEmbedder {
+            dimension: model.dimension(),
+        };
+
+        let report = run_bench(model, Box::new(embedder), 10).unwrap();
+
+        assert_eq!(report.files, 10);
+        assert!(report.chunks >= report.files);
+        assert!(report.chunks_per_sec() > 0.0);
+        assert_eq!(report.queries, QUERIES.len());
+        assert!(report.query_latency() > Duration::ZERO);
+        assert!(!std::env::temp_dir()
+            .join(format!("lgrep-bench-{}", std::process::id()))
+            .exists());
+    }
+}
@@ -0,0 +1,187 @@
+//! Bookmarked code locations
+//!
+//! Stores `file:line` locations (with optional notes) worth coming back to.
+
+use crate::error::{LgrepError, Result};
+use crate::filter::SearchFilter;
+use crate::indexer::relative_path;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A single bookmarked location
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    /// File path relative to the index root
+    pub file: String,
+    /// Line number (1-indexed)
+    pub line: usize,
+    /// Note to remember why the location matters
+    pub note: Option<String>,
+    /// Timestamp (Unix timestamp)
+    pub timestamp: u64,
+}
+
+impl Bookmark {
+    /// Whether the bookmarked file no longer exists under `root`
+    pub fn is_stale(&self, root: &Path) -> bool {
+        !root.join(&self.file).is_file()
+    }
+}
+
+/// Bookmark manager
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Bookmarks {
+    /// Bookmarks in the order they were added
+    bookmarks: Vec<Bookmark>,
+    /// Path to bookmarks file
+    #[serde(skip)]
+    bookmarks_path: PathBuf,
+}
+
+impl Bookmarks {
+    /// Create or load bookmarks
+    pub fn load(index_dir: &Path) -> Result<Self> {
+        let bookmarks_path = index_dir.join("bookmarks.json");
+
+        if bookmarks_path.exists() {
+            let content = std::fs::read_to_string(&bookmarks_path)?;
+            let mut bookmarks: Bookmarks = serde_json::from_str(&content)?;
+            bookmarks.bookmarks_path = bookmarks_path;
+            Ok(bookmarks)
+        } else {
+            Ok(Self {
+                bookmarks: Vec::new(),
+                bookmarks_path,
+            })
+        }
+    }
+
+    /// Bookmark a line of `file`, which must be under `root`
+    ///
+    /// Bookmarking the same location again replaces its note.
+    pub fn add(
+        &mut self,
+        root: &Path,
+        file: &Path,
+        line: usize,
+        note: Option<String>,
+    ) -> Result<()> {
+        let file = relative_path(root, file).ok_or_else(|| {
+            LgrepError::InvalidPath(format!("{} is outside {}", file.display(), root.display()))
+        })?;
+
+        self.bookmarks.retain(|b| b.file != file || b.line != line);
+        self.bookmarks.push(Bookmark {
+            file,
+            line,
+            note,
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        });
+
+        self.save()
+    }
+
+    /// Save bookmarks to disk
+    pub fn save(&self) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(&self.bookmarks_path, json)?;
+        Ok(())
+    }
+
+    /// Get all bookmarks (oldest first)
+    pub fn all(&self) -> &[Bookmark] {
+        &self.bookmarks
+    }
+
+    /// Filter limiting a search to the bookmarked files
+    pub fn search_filter(&self) -> SearchFilter {
+        let patterns = self
+            .bookmarks
+            .iter()
+            .map(|b| format!("^{}$", regex::escape(&b.file)))
+            .collect();
+        SearchFilter::new().with_path_patterns(patterns)
+    }
+
+    /// Get total number of bookmarks
+    pub fn len(&self) -> usize {
+        self.bookmarks.len()
+    }
+
+    /// Check if there are no bookmarks
+    pub fn is_empty(&self) -> bool {
+        self.bookmarks.is_empty()
+    }
+}
+
+/// Split a `file:line` location into its parts
+pub fn parse_location(location: &str) -> Result<(PathBuf, usize)> {
+    let (file, line) = location
+        .rsplit_once(':')
+        .ok_or_else(|| LgrepError::Config(format!("Expected file:line, got '{}'", location)))?;
+    let line = line
+        .parse()
+        .map_err(|_| LgrepError::Config(format!("Invalid line number '{}'", line)))?;
+
+    Ok((PathBuf::from(file), line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_add_and_round_trip() {
+        let root = tempdir().unwrap();
+        let index_dir = root.path().join(".lgrep");
+        std::fs::create_dir(&index_dir).unwrap();
+        std::fs::write(root.path().join("main.rs"), "fn main() {}").unwrap();
+
+        {
+            let mut bookmarks = Bookmarks::load(&index_dir).unwrap();
+            let file = root.path().join("main.rs");
+            bookmarks.add(root.path(), &file, 1, None).unwrap();
+            bookmarks
+                .add(root.path(), &file, 1, Some("entry point".to_string()))
+                .unwrap();
+            assert!(bookmarks
+                .add(root.path(), Path::new("/elsewhere/x.rs"), 1, None)
+                .is_err());
+        }
+
+        let bookmarks = Bookmarks::load(&index_dir).unwrap();
+        assert_eq!(bookmarks.len(), 1);
+        let bookmark = &bookmarks.all()[0];
+        assert_eq!(bookmark.file, "main.rs");
+        assert_eq!(bookmark.line, 1);
+        assert_eq!(bookmark.note.as_deref(), Some("entry point"));
+    }
+
+    #[test]
+    fn test_stale_bookmark() {
+        let root = tempdir().unwrap();
+        let file = root.path().join("gone.rs");
+        std::fs::write(&file, "fn gone() {}").unwrap();
+
+        let mut bookmarks = Bookmarks::load(root.path()).unwrap();
+        bookmarks.add(root.path(), &file, 1, None).unwrap();
+        assert!(!bookmarks.all()[0].is_stale(root.path()));
+
+        std::fs::remove_file(&file).unwrap();
+        assert!(bookmarks.all()[0].is_stale(root.path()));
+    }
+
+    #[test]
+    fn test_parse_location() {
+        let (file, line) = parse_location("src/lib.rs:42").unwrap();
+        assert_eq!(file, PathBuf::from("src/lib.rs"));
+        assert_eq!(line, 42);
+
+        assert!(parse_location("src/lib.rs").is_err());
+        assert!(parse_location("src/lib.rs:x").is_err());
+    }
+}
@@ -0,0 +1,353 @@
+//! Portable JSON Lines export of an index
+//!
+//! A bundle holds every chunk of an index, and optionally its embedding, in
+//! plain JSON so an index can be inspected, shared or rebuilt without the
+//! usearch and bincode files. The first line is a [`BundleHeader`], each
+//! following line a [`BundleChunk`].
+
+use crate::chunker::Chunk;
+use crate::config::Config;
+use crate::embedder::Embed;
+use crate::error::{LgrepError, Result};
+use crate::index::VectorIndex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+/// Identifies a bundle file in its header
+const BUNDLE_FORMAT: &str = "lgrep-bundle";
+
+/// Version of the bundle layout written by this build
+const BUNDLE_VERSION: u32 = 1;
+
+/// Batch size used when embedding chunks stored without vectors
+const BATCH_SIZE: usize = 32;
+
+/// First line of a bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleHeader {
+    /// Always `lgrep-bundle`
+    pub format: String,
+    /// Bundle layout version
+    pub version: u32,
+    /// Model the embeddings were made with
+    pub model: String,
+    /// Revision of the model files, if known
+    pub model_revision: Option<String>,
+    /// Embedding dimension
+    pub dimension: usize,
+    /// Whether chunks carry their embeddings
+    pub embeddings: bool,
+    /// Whether the embeddings were made with the model's instruction prefixes
+    #[serde(default)]
+    pub model_prefixes: bool,
+    /// Number of chunks that follow
+    pub chunks: usize,
+}
+
+/// A chunk line of a bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleChunk {
+    /// The chunk and its location
+    #[serde(flatten)]
+    pub chunk: Chunk,
+    /// Modification time (Unix seconds) of the chunk's file, if recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_mtime: Option<u64>,
+    /// Size in bytes of the chunk's file, if recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_size: Option<u64>,
+    /// Stored embedding, if the bundle includes them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<f32>>,
+}
+
+/// Write all chunks of `index` to `writer`, returning the number written
+pub fn export_bundle<W: Write>(
+    index: &VectorIndex,
+    mut writer: W,
+    include_embeddings: bool,
+) -> Result<usize> {
+    let header = BundleHeader {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        model: index.config().model.model_name().to_string(),
+        model_revision: index.model_revision().map(String::from),
+        dimension: index.dimension(),
+        embeddings: include_embeddings,
+        model_prefixes: index.model_prefixes(),
+        chunks: index.chunk_count(),
+    };
+    serde_json::to_writer(&mut writer, &header)?;
+    writeln!(writer)?;
+
+    // One line at a time, so a large index isn't held in memory twice
+    let mut write_line = |chunk: Chunk, embedding: Option<Vec<f32>>| -> Result<()> {
+        let line = BundleChunk {
+            file_mtime: index.file_mtime(&chunk.file_path),
+            file_size: index.file_size(&chunk.file_path),
+            chunk,
+            embedding,
+        };
+        serde_json::to_writer(&mut writer, &line)?;
+        writeln!(writer)?;
+        Ok(())
+    };
+    if include_embeddings {
+        for pair in index.iter_with_embeddings() {
+            let (chunk, vector) = pair?;
+            write_line(chunk, Some(vector))?;
+        }
+    } else {
+        for chunk in index.chunks() {
+            write_line(chunk?, None)?;
+        }
+    }
+
+    writer.flush()?;
+    Ok(index.chunk_count())
+}
+
+/// Read a bundle's header and chunks
+pub fn read_bundle<R: BufRead>(reader: R) -> Result<(BundleHeader, Vec<BundleChunk>)> {
+    let mut lines = reader.lines();
+
+    let header: BundleHeader = match lines.next() {
+        Some(line) => serde_json::from_str(&line?)?,
+        None => return Err(LgrepError::Index("Bundle is empty".to_string())),
+    };
+    if header.format != BUNDLE_FORMAT {
+        return Err(LgrepError::Index(format!(
+            "Not an lgrep bundle (format {:?})",
+            header.format
+        )));
+    }
+    if header.version > BUNDLE_VERSION {
+        return Err(LgrepError::Index(format!(
+            "Bundle version {} is newer than this lgrep supports ({}); upgrade lgrep",
+            header.version, BUNDLE_VERSION
+        )));
+    }
+
+    // The count is only a hint from the file, so it can't size the buffer alone
+    let mut chunks = Vec::with_capacity(header.chunks.min(1 << 16));
+    for line in lines {
+        let line = line?;
+        if !line.trim().is_empty() {
+            chunks.push(serde_json::from_str(&line)?);
+        }
+    }
+
+    Ok((header, chunks))
+}
+
+/// Build an index for `config` from bundle contents
+///
+/// Stored embeddings are used as they are, so they must come from the
+/// model of `config`; chunks without one are embedded with `embedder`,
+/// which is only needed when the bundle has no vectors. Recorded file
+/// times and sizes are kept, so the time and size filters work on the
+/// imported index.
+/// The index is not saved.
+pub fn import_bundle(
+    header: &BundleHeader,
+    chunks: Vec<BundleChunk>,
+    config: Config,
+    embedder: Option<&dyn Embed>,
+) -> Result<VectorIndex> {
+    let dimension = config.model.dimension();
+    if header.embeddings && header.dimension != dimension {
+        return Err(LgrepError::Index(format!(
+            "Bundle has {}-dimensional embeddings from {}, but {} uses {}; \
+             import with the matching --model",
+            header.dimension,
+            header.model,
+            config.model.model_name(),
+            dimension
+        )));
+    }
+    // Vectors of another model don't compare, even at the same dimension
+    if header.embeddings && header.model != config.model.model_name() {
+        return Err(LgrepError::Index(format!(
+            "Bundle has embeddings from {}, but the index uses {}; \
+             import with the matching --model",
+            header.model,
+            config.model.model_name()
+        )));
+    }
+
+    let files: HashMap<String, (Option<u64>, Option<u64>)> = chunks
+        .iter()
+        .map(|c| (c.chunk.file_path.clone(), (c.file_mtime, c.file_size)))
+        .collect();
+
+    let (with_vectors, without_vectors): (Vec<_>, Vec<_>) =
+        chunks.into_iter().partition(|c| c.embedding.is_some());
+
+    let mut all_chunks = Vec::with_capacity(with_vectors.len() + without_vectors.len());
+    let mut embeddings = Vec::with_capacity(all_chunks.capacity());
+    for bundled in with_vectors {
+        all_chunks.push(bundled.chunk);
+        embeddings.push(bundled.embedding.unwrap_or_default());
+    }
+
+    // Chunks embedded here must match the stored ones
+    let model_prefixes = if header.embeddings {
+        header.model_prefixes
+    } else {
+        config.model.uses_prefixes()
+    };
+
+    if !without_vectors.is_empty() {
+        let embedder = embedder.ok_or_else(|| {
+            LgrepError::Embedding("Bundle has no embeddings and no model was loaded".to_string())
+        })?;
+        let prefix = if model_prefixes {
+            config.model.document_prefix()
+        } else {
+            ""
+        };
+        let chunks: Vec<Chunk> = without_vectors.into_iter().map(|c| c.chunk).collect();
+        for batch in chunks.chunks(BATCH_SIZE) {
+            let texts: Vec<String> = batch
+                .iter()
+                .map(|c| format!("{}{}", prefix, c.text))
+                .collect();
+            embeddings.extend(embedder.embed_batch(texts.iter().map(String::as_str).collect())?);
+        }
+        all_chunks.extend(chunks);
+    }
+
+    let mut index = VectorIndex::from_chunks_and_embeddings(config, all_chunks, embeddings)?;
+    index.set_model_prefixes(model_prefixes);
+    if header.embeddings {
+        index.set_model_revision(header.model_revision.clone());
+    }
+    for (path, (mtime, size)) in files {
+        index.set_file_mtime(&path, mtime);
+        if let Some(size) = size {
+            index.set_file_size(&path, size);
+        }
+    }
+
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedder::StubEmbedder;
+    use crate::searcher::Searcher;
+    use std::path::PathBuf;
+
+    /// Build a small index rooted at `root`
+    fn index_in(root: &std::path::Path) -> VectorIndex {
+        let config = Config::new(PathBuf::from(root));
+        let embedder = StubEmbedder {
+            dimension: config.model.dimension(),
+        };
+        let texts = [
+            ("src/db.rs", "open a database connection pool"),
+            ("src/auth.rs", "verify the password hash"),
+            ("src/http.rs", "retry the http request"),
+        ];
+
+        let chunker = crate::chunker::Chunker::from_config(&config);
+        let chunks: Vec<Chunk> = texts
+            .iter()
+            .enumerate()
+            .flat_map(|(i, (path, text))| chunker.chunk_text(text, path, "hash", i as u64))
+            .collect();
+        let embeddings = chunks
+            .iter()
+            .map(|c| embedder.embed_one(&c.text).unwrap())
+            .collect();
+
+        let mut index = VectorIndex::new(config).unwrap();
+        index.add_chunks(chunks, embeddings).unwrap();
+        index
+    }
+
+    fn search(index: VectorIndex, query: &str) -> Vec<(String, f32)> {
+        let embedder = StubEmbedder {
+            dimension: index.dimension(),
+        };
+        Searcher::with_embedder(index, Box::new(embedder))
+            .search(query, 3)
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.chunk.file_path, r.score))
+            .collect()
+    }
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let source = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        let mut index = index_in(source.path());
+        index.set_file_mtime("src/db.rs", Some(1_700_000_000));
+        index.set_file_size("src/db.rs", 31);
+
+        let mut bundle = Vec::new();
+        assert_eq!(export_bundle(&index, &mut bundle, true).unwrap(), 3);
+
+        let (header, chunks) = read_bundle(bundle.as_slice()).unwrap();
+        let config = Config::new(PathBuf::from(target.path()));
+        let imported = import_bundle(&header, chunks, config, None).unwrap();
+        imported.save().unwrap();
+        let reloaded = VectorIndex::load(Config::new(PathBuf::from(target.path()))).unwrap();
+        assert_eq!(reloaded.file_mtime("src/db.rs"), Some(1_700_000_000));
+        assert_eq!(reloaded.file_size("src/db.rs"), Some(31));
+        assert_eq!(reloaded.file_mtime("src/auth.rs"), None);
+
+        let query = "database connection";
+        assert_eq!(search(index, query), search(reloaded, query));
+    }
+
+    #[test]
+    fn test_import_rejects_dimension_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut bundle = Vec::new();
+        export_bundle(&index_in(dir.path()), &mut bundle, true).unwrap();
+
+        let (header, chunks) = read_bundle(bundle.as_slice()).unwrap();
+        let config = Config::new(PathBuf::from(dir.path()))
+            .with_model(crate::config::EmbeddingModel::NomicEmbedTextV15);
+        let err = import_bundle(&header, chunks, config, None)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("384-dimensional"), "{}", err);
+    }
+
+    #[test]
+    fn test_import_rejects_other_model_of_same_dimension() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut bundle = Vec::new();
+        export_bundle(&index_in(dir.path()), &mut bundle, true).unwrap();
+
+        let (header, chunks) = read_bundle(bundle.as_slice()).unwrap();
+        let config = Config::new(PathBuf::from(dir.path()))
+            .with_model(crate::config::EmbeddingModel::MultilingualE5Small);
+        assert_eq!(config.model.dimension(), header.dimension);
+        let err = import_bundle(&header, chunks, config, None)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("embeddings from"), "{}", err);
+    }
+
+    #[test]
+    fn test_read_bundle_ignores_huge_chunk_count() {
+        let header = serde_json::json!({
+            "format": BUNDLE_FORMAT,
+            "version": BUNDLE_VERSION,
+            "model": "m",
+            "dimension": 384,
+            "embeddings": false,
+            "chunks": usize::MAX,
+        });
+        let (_, chunks) = read_bundle(format!("{}\n", header).as_bytes()).unwrap();
+        assert!(chunks.is_empty());
+    }
+}
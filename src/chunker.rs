@@ -3,7 +3,16 @@
 //! Splits source files into overlapping chunks suitable for embedding.
 //! Preserves line number information for search result display.
 
+use crate::config::{
+    default_hnsw_connectivity, default_hnsw_expansion_add, default_hnsw_expansion_search,
+    ChunkStrategy, Config,
+};
+use crate::error::{LgrepError, Result};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
 use std::path::Path;
 
 /// A chunk of text with metadata for search results
@@ -23,10 +32,76 @@ pub struct Chunk {
     pub file_hash: String,
     /// Programming language hint for syntax highlighting
     pub language: Option<String>,
+    /// Leading lines repeated from the previous chunk of the same file
+    ///
+    /// Skip this many lines of `text` when concatenating adjacent chunks.
+    /// Always 0 for the first chunk and for fixed-window chunks, whose
+    /// overlap is in characters rather than whole lines.
+    #[serde(default)]
+    pub overlap_with_prev: usize,
+    /// What the chunk mostly contains (code, comments or documentation)
+    #[serde(default)]
+    pub content_kind: ContentKind,
+    /// SHA-256 hash of `text`, used to reuse embeddings of unchanged chunks
+    #[serde(default)]
+    pub text_hash: String,
+    /// Lowercased identifier parts (`parse_config` and `parseConfig` both
+    /// give `parse`, `config`), used for case-insensitive keyword matching
+    #[serde(default)]
+    pub symbols: Vec<String>,
+}
+
+/// Coarse classification of a chunk's dominant content
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentKind {
+    /// Mostly source code
+    #[default]
+    Code,
+    /// Mostly ordinary comments
+    Comments,
+    /// Mostly doc comments, docstrings or documentation files
+    Docs,
+}
+
+impl std::str::FromStr for ContentKind {
+    type Err = LgrepError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "code" => Ok(Self::Code),
+            "comments" | "comment" => Ok(Self::Comments),
+            "docs" | "doc" => Ok(Self::Docs),
+            _ => Err(LgrepError::Config(format!(
+                "Unknown content kind: {}. Valid options: code, comments, docs",
+                s
+            ))),
+        }
+    }
 }
 
+/// Version of the on-disk index format written by this build
+///
+/// - 1: bare bincode `IndexMetadata` (lgrep 0.1.0 and earlier builds)
+/// - 2: [`METADATA_MAGIC`], a little-endian `u32` version, then bincode
+///   `IndexMetadata`
+/// - 3: as 2, with [`Chunk::symbols`]
+/// - 4: as 3, with the HNSW parameters the index was built with
+/// - 5: as 4, with file modification times
+/// - 6: as 5, with file sizes
+/// - 7: as 6, with whether instruction prefixes were used
+/// - 8: as 7, followed by the [`SavedFiles`] sizes
+/// - 9: as 8, with chunk text moved to `texts.bin` (see [`crate::texts`])
+///
+/// Older versions are read and rewritten in this format on the next save.
+/// Newer versions are refused with [`LgrepError::NewerFormat`].
+pub const FORMAT_VERSION: u32 = 9;
+
+/// Marker at the start of versioned metadata files
+pub const METADATA_MAGIC: &[u8; 4] = b"LGRP";
+
 /// Metadata for all indexed chunks
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexMetadata {
     /// All chunks in the index
     pub chunks: Vec<Chunk>,
@@ -38,6 +113,80 @@ pub struct IndexMetadata {
     pub model_name: String,
     /// Embedding vector dimension
     pub dimension: usize,
+    /// Revision (commit hash) of the model files, if known
+    #[serde(default)]
+    pub model_revision: Option<String>,
+    /// Git commit the indexed files were read at, if the root is in a repository
+    #[serde(default)]
+    pub indexed_commit: Option<String>,
+    /// Files that differed from `indexed_commit` when they were read
+    #[serde(default)]
+    pub uncommitted_files: Vec<String>,
+    /// HNSW graph degree the vectors were inserted with
+    pub hnsw_connectivity: usize,
+    /// HNSW insertion expansion factor the vectors were inserted with
+    pub hnsw_expansion_add: usize,
+    /// HNSW search expansion factor
+    pub hnsw_expansion_search: usize,
+    /// Modification time (seconds since the Unix epoch) of each file when read
+    pub file_mtimes: HashMap<String, u64>,
+    /// Size in bytes of each file when read
+    pub file_sizes: HashMap<String, u64>,
+    /// Whether chunks were embedded with the model's document prefix
+    pub model_prefixes: bool,
+}
+
+/// Sizes of the files saved together with a metadata file
+///
+/// Saves rename the files into place one at a time, the metadata last, so
+/// a save interrupted midway leaves new vectors next to old metadata. The
+/// recorded sizes let a load notice.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedFiles {
+    /// Size in bytes of the vector index file
+    pub vectors: u64,
+    /// Size in bytes of the per-file vectors, if they were saved
+    pub file_vectors: Option<u64>,
+    /// Size in bytes of the chunk text file (not recorded before format 9)
+    pub texts: Option<u64>,
+}
+
+/// [`SavedFiles`] as recorded in format 8, before `texts.bin`
+#[derive(Deserialize)]
+struct V8SavedFiles {
+    vectors: u64,
+    file_vectors: Option<u64>,
+}
+
+impl From<V8SavedFiles> for SavedFiles {
+    fn from(files: V8SavedFiles) -> Self {
+        Self {
+            vectors: files.vectors,
+            file_vectors: files.file_vectors,
+            texts: None,
+        }
+    }
+}
+
+impl Default for IndexMetadata {
+    fn default() -> Self {
+        Self {
+            chunks: Vec::new(),
+            file_hashes: HashMap::new(),
+            next_id: 0,
+            model_name: String::new(),
+            dimension: 0,
+            model_revision: None,
+            indexed_commit: None,
+            uncommitted_files: Vec::new(),
+            hnsw_connectivity: default_hnsw_connectivity(),
+            hnsw_expansion_add: default_hnsw_expansion_add(),
+            hnsw_expansion_search: default_hnsw_expansion_search(),
+            file_mtimes: HashMap::new(),
+            file_sizes: HashMap::new(),
+            model_prefixes: false,
+        }
+    }
 }
 
 impl IndexMetadata {
@@ -49,12 +198,384 @@ impl IndexMetadata {
             ..Default::default()
         }
     }
+
+    /// Encode metadata with the format header and the sizes of the files
+    /// saved with it
+    pub fn to_bytes(&self, files: &SavedFiles) -> Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(METADATA_MAGIC.len() + 4);
+        bytes.extend_from_slice(METADATA_MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bincode::serialize_into(&mut bytes, &(self, files))?;
+        Ok(bytes)
+    }
+
+    /// Decode metadata written by this or an older version of lgrep
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Self::from_bytes_with_files(bytes).map(|(metadata, _)| metadata)
+    }
+
+    /// Decode metadata, with the sizes of the files saved with it if they
+    /// were recorded (format 8 and later)
+    pub fn from_bytes_with_files(bytes: &[u8]) -> Result<(Self, Option<SavedFiles>)> {
+        let (version, payload) = Self::split_header(bytes);
+        if version > FORMAT_VERSION {
+            return Err(LgrepError::NewerFormat {
+                found: version,
+                supported: FORMAT_VERSION,
+            });
+        }
+        if version == FORMAT_VERSION {
+            let (metadata, files) = bincode::deserialize(payload)?;
+            return Ok((metadata, Some(files)));
+        }
+
+        let metadata = match version {
+            // Same layout, but the chunks still hold their text
+            8 => {
+                let (metadata, files) =
+                    bincode::deserialize::<(IndexMetadata, V8SavedFiles)>(payload)?;
+                return Ok((metadata, Some(files.into())));
+            }
+            7 => bincode::deserialize(payload)?,
+            6 => bincode::deserialize::<V6IndexMetadata>(payload)?.into(),
+            5 => bincode::deserialize::<V5IndexMetadata>(payload)?.into(),
+            4 => bincode::deserialize::<V4IndexMetadata>(payload)?.into(),
+            3 => bincode::deserialize::<V3IndexMetadata>(payload)?.into(),
+            2 => bincode::deserialize::<V2IndexMetadata>(payload)?.into(),
+            _ => bincode::deserialize(payload).or_else(|err| {
+                if let Ok(v3) = bincode::deserialize::<V3IndexMetadata>(payload) {
+                    return Ok(v3.into());
+                }
+                if let Ok(v2) = bincode::deserialize::<V2IndexMetadata>(payload) {
+                    return Ok(v2.into());
+                }
+                let legacy: LegacyIndexMetadata = bincode::deserialize(payload).map_err(|_| err)?;
+                Ok::<_, bincode::Error>(legacy.into())
+            })?,
+        };
+        Ok((metadata, None))
+    }
+
+    /// Format version of encoded metadata (1 for files without a header)
+    pub fn format_version(bytes: &[u8]) -> u32 {
+        Self::split_header(bytes).0
+    }
+
+    /// Split encoded metadata into its format version and bincode payload
+    fn split_header(bytes: &[u8]) -> (u32, &[u8]) {
+        match bytes.strip_prefix(METADATA_MAGIC.as_slice()) {
+            Some(rest) if rest.len() >= 4 => {
+                let version = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]);
+                (version, &rest[4..])
+            }
+            _ => (1, bytes),
+        }
+    }
+}
+
+/// Chunk layout of the original (0.1.0) index format
+#[derive(Deserialize)]
+struct LegacyChunk {
+    id: u64,
+    text: String,
+    file_path: String,
+    start_line: usize,
+    end_line: usize,
+    file_hash: String,
+    language: Option<String>,
+}
+
+/// Metadata layout of the original (0.1.0) index format
+#[derive(Deserialize)]
+struct LegacyIndexMetadata {
+    chunks: Vec<LegacyChunk>,
+    file_hashes: std::collections::HashMap<String, String>,
+    next_id: u64,
+    model_name: String,
+    dimension: usize,
+}
+
+impl From<LegacyIndexMetadata> for IndexMetadata {
+    fn from(legacy: LegacyIndexMetadata) -> Self {
+        let chunks = legacy
+            .chunks
+            .into_iter()
+            .map(|c| Chunk {
+                id: c.id,
+                text: c.text,
+                file_path: c.file_path,
+                start_line: c.start_line,
+                end_line: c.end_line,
+                file_hash: c.file_hash,
+                language: c.language,
+                overlap_with_prev: 0,
+                content_kind: ContentKind::Code,
+                text_hash: String::new(),
+                symbols: Vec::new(),
+            })
+            .collect();
+
+        Self {
+            chunks,
+            file_hashes: legacy.file_hashes,
+            next_id: legacy.next_id,
+            model_name: legacy.model_name,
+            dimension: legacy.dimension,
+            model_revision: None,
+            indexed_commit: None,
+            uncommitted_files: Vec::new(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Chunk layout of format version 2, before symbols were recorded
+#[derive(Deserialize)]
+struct V2Chunk {
+    id: u64,
+    text: String,
+    file_path: String,
+    start_line: usize,
+    end_line: usize,
+    file_hash: String,
+    language: Option<String>,
+    overlap_with_prev: usize,
+    content_kind: ContentKind,
+    text_hash: String,
+}
+
+/// Metadata layout of format version 2
+#[derive(Deserialize)]
+struct V2IndexMetadata {
+    chunks: Vec<V2Chunk>,
+    file_hashes: std::collections::HashMap<String, String>,
+    next_id: u64,
+    model_name: String,
+    dimension: usize,
+    model_revision: Option<String>,
+    indexed_commit: Option<String>,
+    uncommitted_files: Vec<String>,
+}
+
+impl From<V2IndexMetadata> for IndexMetadata {
+    fn from(v2: V2IndexMetadata) -> Self {
+        // Symbols stay empty until the files are re-indexed
+        let chunks = v2
+            .chunks
+            .into_iter()
+            .map(|c| Chunk {
+                id: c.id,
+                text: c.text,
+                file_path: c.file_path,
+                start_line: c.start_line,
+                end_line: c.end_line,
+                file_hash: c.file_hash,
+                language: c.language,
+                overlap_with_prev: c.overlap_with_prev,
+                content_kind: c.content_kind,
+                text_hash: c.text_hash,
+                symbols: Vec::new(),
+            })
+            .collect();
+
+        Self {
+            chunks,
+            file_hashes: v2.file_hashes,
+            next_id: v2.next_id,
+            model_name: v2.model_name,
+            dimension: v2.dimension,
+            model_revision: v2.model_revision,
+            indexed_commit: v2.indexed_commit,
+            uncommitted_files: v2.uncommitted_files,
+            ..Default::default()
+        }
+    }
+}
+
+/// Metadata layout of format version 3, before HNSW parameters were recorded
+#[derive(Deserialize)]
+struct V3IndexMetadata {
+    chunks: Vec<Chunk>,
+    file_hashes: std::collections::HashMap<String, String>,
+    next_id: u64,
+    model_name: String,
+    dimension: usize,
+    model_revision: Option<String>,
+    indexed_commit: Option<String>,
+    uncommitted_files: Vec<String>,
+}
+
+impl From<V3IndexMetadata> for IndexMetadata {
+    fn from(v3: V3IndexMetadata) -> Self {
+        // Older builds always used the default parameters
+        Self {
+            chunks: v3.chunks,
+            file_hashes: v3.file_hashes,
+            next_id: v3.next_id,
+            model_name: v3.model_name,
+            dimension: v3.dimension,
+            model_revision: v3.model_revision,
+            indexed_commit: v3.indexed_commit,
+            uncommitted_files: v3.uncommitted_files,
+            ..Default::default()
+        }
+    }
+}
+
+/// Metadata layout of format version 4, before modification times were recorded
+#[derive(Deserialize)]
+struct V4IndexMetadata {
+    chunks: Vec<Chunk>,
+    file_hashes: std::collections::HashMap<String, String>,
+    next_id: u64,
+    model_name: String,
+    dimension: usize,
+    model_revision: Option<String>,
+    indexed_commit: Option<String>,
+    uncommitted_files: Vec<String>,
+    hnsw_connectivity: usize,
+    hnsw_expansion_add: usize,
+    hnsw_expansion_search: usize,
+}
+
+impl From<V4IndexMetadata> for IndexMetadata {
+    fn from(v4: V4IndexMetadata) -> Self {
+        // Modification times stay unknown until the files are re-indexed
+        Self {
+            chunks: v4.chunks,
+            file_hashes: v4.file_hashes,
+            next_id: v4.next_id,
+            model_name: v4.model_name,
+            dimension: v4.dimension,
+            model_revision: v4.model_revision,
+            indexed_commit: v4.indexed_commit,
+            uncommitted_files: v4.uncommitted_files,
+            hnsw_connectivity: v4.hnsw_connectivity,
+            hnsw_expansion_add: v4.hnsw_expansion_add,
+            hnsw_expansion_search: v4.hnsw_expansion_search,
+            ..Default::default()
+        }
+    }
+}
+
+/// Metadata layout of format version 5, before file sizes were recorded
+#[derive(Deserialize)]
+struct V5IndexMetadata {
+    chunks: Vec<Chunk>,
+    file_hashes: std::collections::HashMap<String, String>,
+    next_id: u64,
+    model_name: String,
+    dimension: usize,
+    model_revision: Option<String>,
+    indexed_commit: Option<String>,
+    uncommitted_files: Vec<String>,
+    hnsw_connectivity: usize,
+    hnsw_expansion_add: usize,
+    hnsw_expansion_search: usize,
+    file_mtimes: HashMap<String, u64>,
+}
+
+impl From<V5IndexMetadata> for IndexMetadata {
+    fn from(v5: V5IndexMetadata) -> Self {
+        // File sizes stay unknown until the files are re-indexed
+        Self {
+            chunks: v5.chunks,
+            file_hashes: v5.file_hashes,
+            next_id: v5.next_id,
+            model_name: v5.model_name,
+            dimension: v5.dimension,
+            model_revision: v5.model_revision,
+            indexed_commit: v5.indexed_commit,
+            uncommitted_files: v5.uncommitted_files,
+            hnsw_connectivity: v5.hnsw_connectivity,
+            hnsw_expansion_add: v5.hnsw_expansion_add,
+            hnsw_expansion_search: v5.hnsw_expansion_search,
+            file_mtimes: v5.file_mtimes,
+            ..Default::default()
+        }
+    }
+}
+
+/// Metadata layout of format version 6, before instruction prefixes
+#[derive(Deserialize)]
+struct V6IndexMetadata {
+    chunks: Vec<Chunk>,
+    file_hashes: std::collections::HashMap<String, String>,
+    next_id: u64,
+    model_name: String,
+    dimension: usize,
+    model_revision: Option<String>,
+    indexed_commit: Option<String>,
+    uncommitted_files: Vec<String>,
+    hnsw_connectivity: usize,
+    hnsw_expansion_add: usize,
+    hnsw_expansion_search: usize,
+    file_mtimes: HashMap<String, u64>,
+    file_sizes: HashMap<String, u64>,
+}
+
+impl From<V6IndexMetadata> for IndexMetadata {
+    fn from(v6: V6IndexMetadata) -> Self {
+        // Older versions embedded raw text, so searches keep doing the same
+        Self {
+            chunks: v6.chunks,
+            file_hashes: v6.file_hashes,
+            next_id: v6.next_id,
+            model_name: v6.model_name,
+            dimension: v6.dimension,
+            model_revision: v6.model_revision,
+            indexed_commit: v6.indexed_commit,
+            uncommitted_files: v6.uncommitted_files,
+            hnsw_connectivity: v6.hnsw_connectivity,
+            hnsw_expansion_add: v6.hnsw_expansion_add,
+            hnsw_expansion_search: v6.hnsw_expansion_search,
+            file_mtimes: v6.file_mtimes,
+            file_sizes: v6.file_sizes,
+            model_prefixes: false,
+        }
+    }
 }
 
 /// Splits text into overlapping chunks
 pub struct Chunker {
     chunk_size: usize,
     overlap: usize,
+    min_chunk_size: usize,
+    strategy: ChunkStrategy,
+    language_strategies: HashMap<String, ChunkStrategy>,
+    language_sizes: HashMap<String, usize>,
+}
+
+/// A span of text with its 1-indexed line range, before ids and metadata
+struct Piece {
+    text: String,
+    start_line: usize,
+    end_line: usize,
+    overlap: usize,
+}
+
+impl Piece {
+    fn into_chunk(
+        self,
+        id: u64,
+        file_path: &str,
+        file_hash: &str,
+        language: &Option<String>,
+    ) -> Chunk {
+        Chunk {
+            id,
+            content_kind: classify_content(&self.text, language.as_deref()),
+            text_hash: hex::encode(Sha256::digest(&self.text)),
+            symbols: extract_symbols(&self.text, language.as_deref()),
+            text: self.text,
+            file_path: file_path.to_string(),
+            start_line: self.start_line,
+            end_line: self.end_line,
+            file_hash: file_hash.to_string(),
+            language: language.clone(),
+            overlap_with_prev: self.overlap,
+        }
+    }
 }
 
 impl Chunker {
@@ -64,13 +585,64 @@ impl Chunker {
     /// * `chunk_size` - Target size for each chunk in characters
     /// * `overlap` - Number of characters to overlap between chunks
     pub fn new(chunk_size: usize, overlap: usize) -> Self {
-        Self { chunk_size, overlap }
+        Self {
+            chunk_size,
+            overlap,
+            min_chunk_size: 0,
+            strategy: ChunkStrategy::default(),
+            language_strategies: HashMap::new(),
+            language_sizes: HashMap::new(),
+        }
+    }
+
+    /// Create a chunker with specified sizes and the given strategy
+    pub fn new_with_strategy(chunk_size: usize, overlap: usize, strategy: ChunkStrategy) -> Self {
+        Self::new(chunk_size, overlap).with_strategy(strategy)
+    }
+
+    /// Create a chunker using the sizes and strategies from a config
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(config.chunk_size, config.chunk_overlap)
+            .with_min_chunk_size(config.min_chunk_size)
+            .with_strategy(config.chunk_strategy)
+            .with_language_strategies(config.language_strategies.clone())
+            .with_language_sizes(config.language_chunk_sizes.clone())
+    }
+
+    /// Merge a final chunk shorter than `size` characters into the one before
+    ///
+    /// A file that is shorter than `size` as a whole still becomes one chunk.
+    pub fn with_min_chunk_size(mut self, size: usize) -> Self {
+        self.min_chunk_size = size;
+        self
+    }
+
+    /// Set the default chunking strategy
+    pub fn with_strategy(mut self, strategy: ChunkStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Set per-language strategy overrides, keyed by detected language
+    pub fn with_language_strategies(mut self, strategies: HashMap<String, ChunkStrategy>) -> Self {
+        self.language_strategies = strategies;
+        self
+    }
+
+    /// Set per-language chunk size overrides, keyed by detected language
+    pub fn with_language_sizes(mut self, sizes: HashMap<String, usize>) -> Self {
+        self.language_sizes = sizes;
+        self
     }
 
     /// Split text into chunks with metadata
     ///
-    /// Chunks are split on line boundaries to preserve code structure.
-    /// Overlapping ensures context isn't lost at chunk boundaries.
+    /// The strategy and chunk size are picked per file from its detected
+    /// language, falling back to the chunker's defaults.
+    ///
+    /// Line numbers follow editors: a trailing newline ends the last line
+    /// rather than starting a new one, while a trailing blank line counts.
+    /// Empty and whitespace-only files produce no chunks.
     pub fn chunk_text(
         &self,
         text: &str,
@@ -78,75 +650,417 @@ impl Chunker {
         file_hash: &str,
         start_id: u64,
     ) -> Vec<Chunk> {
+        if text.trim().is_empty() {
+            return vec![];
+        }
+
         let language = detect_language(file_path);
+        if let Some(sized) = self.sized_for(language.as_deref()) {
+            return sized.chunk_text(text, file_path, file_hash, start_id);
+        }
         let lines: Vec<&str> = text.lines().collect();
+        let mut pieces = match self.strategy_for(language.as_deref()) {
+            ChunkStrategy::Lines => self.split_lines(&lines, 1),
+            ChunkStrategy::Paragraphs => self.split_paragraphs(text),
+            // Merges its own tail, by characters
+            ChunkStrategy::FixedChars => self.split_fixed(text),
+            ChunkStrategy::Syntax => self.split_syntax(text, language.as_deref()),
+        };
+        if self.strategy_for(language.as_deref()) != ChunkStrategy::FixedChars {
+            self.merge_small_tail(&mut pieces, &lines);
+        }
+
+        pieces
+            .into_iter()
+            .enumerate()
+            .map(|(i, piece)| {
+                piece.into_chunk(start_id + i as u64, file_path, file_hash, &language)
+            })
+            .collect()
+    }
+
+    /// Chunk a file as it is read, without loading it into memory first
+    ///
+    /// Produces the same chunks as [`chunk_text`](Self::chunk_text) on the
+    /// file's content, with the path as given used as `file_path`.
+    pub fn chunk_file(&self, path: &Path, file_hash: &str, start_id: u64) -> Result<Vec<Chunk>> {
+        let reader = BufReader::new(File::open(path)?);
+        self.chunk_reader(reader, &path.to_string_lossy(), file_hash, start_id)
+    }
+
+    /// Chunk text read line by line from `reader`
+    ///
+    /// Only line packing (the default strategy) is done incrementally;
+    /// other strategies need the whole text and read it in first.
+    pub fn chunk_reader<R: BufRead>(
+        &self,
+        mut reader: R,
+        file_path: &str,
+        file_hash: &str,
+        start_id: u64,
+    ) -> Result<Vec<Chunk>> {
+        let language = detect_language(file_path);
+        if let Some(sized) = self.sized_for(language.as_deref()) {
+            return sized.chunk_reader(reader, file_path, file_hash, start_id);
+        }
+        if self.strategy_for(language.as_deref()) != ChunkStrategy::Lines {
+            let mut text = String::new();
+            reader.read_to_string(&mut text)?;
+            return Ok(self.chunk_text(&text, file_path, file_hash, start_id));
+        }
+
+        let mut chunks = Vec::new();
+        let mut push = |piece: Piece| {
+            let id = start_id + chunks.len() as u64;
+            chunks.push(piece.into_chunk(id, file_path, file_hash, &language));
+        };
+        // The last finished piece, held back in case the tail is merged into it
+        let mut previous: Option<Piece> = None;
+
+        // Same packing as `split_lines`, keeping only the current chunk's
+        // lines (`window`, starting at line `window_start`)
+        let mut window: Vec<String> = Vec::new();
+        let mut window_start = 1;
+        let mut current_size = 0;
+        let mut overlap = 0;
+        let mut has_content = false;
+        let mut buf = Vec::new();
+
+        while read_line(&mut reader, &mut buf)? {
+            let line = String::from_utf8(std::mem::take(&mut buf))
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            let line_len = line.len() + 1;
+            has_content |= !line.trim().is_empty();
 
+            if current_size + line_len > self.chunk_size && !window.is_empty() {
+                let piece = Piece {
+                    text: window.join("\n"),
+                    start_line: window_start,
+                    end_line: window_start + window.len() - 1,
+                    overlap,
+                };
+                if let Some(piece) = previous.replace(piece) {
+                    push(piece);
+                }
+
+                let lines: Vec<&str> = window.iter().map(String::as_str).collect();
+                overlap = self.calculate_overlap_lines(&lines).min(window.len());
+                let kept = window.len() - overlap;
+                window.drain(..kept);
+                window_start += kept;
+                current_size = window.iter().map(|l| l.len() + 1).sum();
+            }
+
+            current_size += line_len;
+            window.push(line);
+        }
+
+        if !has_content {
+            return Ok(vec![]);
+        }
+        let tail = (!window.is_empty()).then(|| Piece {
+            text: window.join("\n"),
+            start_line: window_start,
+            end_line: window_start + window.len() - 1,
+            overlap,
+        });
+        match (previous, tail) {
+            // Line packing leaves no gaps: the tail's first `overlap` lines
+            // end the previous piece
+            (Some(mut prev), Some(tail)) if tail.text.len() < self.min_chunk_size => {
+                for line in &window[overlap..] {
+                    prev.text.push('\n');
+                    prev.text.push_str(line);
+                }
+                prev.end_line = tail.end_line;
+                push(prev);
+            }
+            (prev, tail) => {
+                prev.into_iter().chain(tail).for_each(push);
+            }
+        }
+
+        Ok(chunks)
+    }
+
+    /// Fold a final piece shorter than the minimum into the piece before it
+    ///
+    /// `lines` are the file's lines, so lines between the two pieces (blank
+    /// lines between paragraphs or items) are kept in the merged piece.
+    fn merge_small_tail(&self, pieces: &mut Vec<Piece>, lines: &[&str]) {
+        if pieces.len() < 2 || pieces[pieces.len() - 1].text.len() >= self.min_chunk_size {
+            return;
+        }
+        let tail = pieces.pop().unwrap();
+        let prev = pieces.pop().unwrap();
+        pieces.push(Piece {
+            text: lines[prev.start_line - 1..tail.end_line].join("\n"),
+            start_line: prev.start_line,
+            end_line: tail.end_line,
+            overlap: prev.overlap,
+        });
+    }
+
+    /// Strategy for a file of the given language
+    fn strategy_for(&self, language: Option<&str>) -> ChunkStrategy {
+        language
+            .and_then(|lang| self.language_strategies.get(lang))
+            .copied()
+            .unwrap_or(self.strategy)
+    }
+
+    /// A chunker for a language whose chunk size is overridden
+    ///
+    /// It carries the language's resolved strategy and no overrides, so it
+    /// chunks that language's files directly.
+    fn sized_for(&self, language: Option<&str>) -> Option<Chunker> {
+        let size = *self.language_sizes.get(language?)?;
+        Some(
+            Self::new(size, self.overlap)
+                .with_min_chunk_size(self.min_chunk_size)
+                .with_strategy(self.strategy_for(language)),
+        )
+    }
+
+    /// Pack lines into chunks, where `lines[0]` is line `first_line`
+    ///
+    /// Chunks are split on line boundaries to preserve code structure.
+    /// Overlapping ensures context isn't lost at chunk boundaries.
+    fn split_lines(&self, lines: &[&str], first_line: usize) -> Vec<Piece> {
         if lines.is_empty() {
             return vec![];
         }
 
-        let mut chunks = Vec::new();
-        let mut current_chunk_lines: Vec<&str> = Vec::new();
+        let mut pieces = Vec::new();
+        // Current chunk is lines[chunk_start..i], tracked by index so line
+        // numbers are always derived from the lines actually kept
+        let mut chunk_start = 0;
         let mut current_size = 0;
-        let mut chunk_start_line = 1;
-        let mut chunk_id = start_id;
+        let mut overlap = 0;
+
+        let piece = |start: usize, end: usize, overlap: usize| Piece {
+            text: lines[start..end].join("\n"),
+            start_line: first_line + start,
+            end_line: first_line + end - 1,
+            overlap,
+        };
 
         for (i, line) in lines.iter().enumerate() {
             let line_len = line.len() + 1; // +1 for newline
 
             // If adding this line exceeds chunk size, finalize current chunk
-            if current_size + line_len > self.chunk_size && !current_chunk_lines.is_empty() {
-                let chunk_text = current_chunk_lines.join("\n");
-                let end_line = chunk_start_line + current_chunk_lines.len() - 1;
-
-                chunks.push(Chunk {
-                    id: chunk_id,
-                    text: chunk_text,
-                    file_path: file_path.to_string(),
-                    start_line: chunk_start_line,
-                    end_line,
-                    file_hash: file_hash.to_string(),
-                    language: language.clone(),
-                });
-                chunk_id += 1;
-
-                // Keep some lines for overlap/context
-                let overlap_lines = self.calculate_overlap_lines(&current_chunk_lines);
-                let keep_count = overlap_lines.min(current_chunk_lines.len());
-
-                if keep_count > 0 {
-                    let start_idx = current_chunk_lines.len() - keep_count;
-                    current_chunk_lines = current_chunk_lines[start_idx..].to_vec();
-                    current_size = current_chunk_lines.iter().map(|l| l.len() + 1).sum();
-                    chunk_start_line = i + 1 - keep_count + 1;
-                } else {
-                    current_chunk_lines.clear();
-                    current_size = 0;
-                    chunk_start_line = i + 2;
-                }
+            if current_size + line_len > self.chunk_size && i > chunk_start {
+                pieces.push(piece(chunk_start, i, overlap));
+
+                // Keep some trailing lines for overlap/context
+                overlap = self
+                    .calculate_overlap_lines(&lines[chunk_start..i])
+                    .min(i - chunk_start);
+                chunk_start = i - overlap;
+                current_size = lines[chunk_start..i].iter().map(|l| l.len() + 1).sum();
             }
 
-            current_chunk_lines.push(line);
             current_size += line_len;
         }
 
         // Don't forget the last chunk
-        if !current_chunk_lines.is_empty() {
-            let chunk_text = current_chunk_lines.join("\n");
-            let end_line = chunk_start_line + current_chunk_lines.len() - 1;
-
-            chunks.push(Chunk {
-                id: chunk_id,
-                text: chunk_text,
-                file_path: file_path.to_string(),
-                start_line: chunk_start_line,
+        pieces.push(piece(chunk_start, lines.len(), overlap));
+
+        pieces
+    }
+
+    /// Split on blank-line runs, packing small paragraphs together
+    ///
+    /// Paragraphs are never split unless a single one exceeds the chunk
+    /// size, in which case it falls back to line packing.
+    fn split_paragraphs(&self, text: &str) -> Vec<Piece> {
+        let lines: Vec<&str> = text.lines().collect();
+
+        // (first, last) 0-based line indices of each paragraph
+        let mut paragraphs: Vec<(usize, usize)> = Vec::new();
+        let mut start = None;
+        for (i, line) in lines.iter().enumerate() {
+            match (line.trim().is_empty(), start) {
+                (false, None) => start = Some(i),
+                (true, Some(first)) => {
+                    paragraphs.push((first, i - 1));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(first) = start {
+            paragraphs.push((first, lines.len() - 1));
+        }
+
+        let size = |first: usize, last: usize| -> usize {
+            lines[first..=last].iter().map(|l| l.len() + 1).sum()
+        };
+
+        let mut pieces = Vec::new();
+        let mut current: Option<(usize, usize)> = None;
+
+        let flush = |span: (usize, usize), pieces: &mut Vec<Piece>| {
+            pieces.push(Piece {
+                text: lines[span.0..=span.1].join("\n"),
+                start_line: span.0 + 1,
+                end_line: span.1 + 1,
+                overlap: 0,
+            });
+        };
+
+        for (first, last) in paragraphs {
+            if size(first, last) > self.chunk_size {
+                if let Some(span) = current.take() {
+                    flush(span, &mut pieces);
+                }
+                pieces.extend(self.split_lines(&lines[first..=last], first + 1));
+                continue;
+            }
+
+            current = match current {
+                Some((cur_first, _)) if size(cur_first, last) <= self.chunk_size => {
+                    Some((cur_first, last))
+                }
+                Some(span) => {
+                    flush(span, &mut pieces);
+                    Some((first, last))
+                }
+                None => Some((first, last)),
+            };
+        }
+
+        if let Some(span) = current {
+            flush(span, &mut pieces);
+        }
+
+        pieces
+    }
+
+    /// Split on item boundaries (functions, classes, impls) for Rust and
+    /// Python, falling back to line packing
+    ///
+    /// Boundaries come from a line scanner that tracks braces (Rust) or
+    /// indentation and brackets (Python) while skipping strings and
+    /// comments. It is a heuristic, not a parser: code it misreads, such as
+    /// macros with unbalanced braces, only gets worse split points.
+    ///
+    /// Consecutive items are packed together up to the chunk size. An item
+    /// that doesn't fit is split at its outermost statement boundaries, so
+    /// an oversized `impl` or class is split between methods and an oversized
+    /// function between statements. Only when no boundary exists does it
+    /// fall back to packing lines.
+    fn split_syntax(&self, text: &str, language: Option<&str>) -> Vec<Piece> {
+        let lines: Vec<&str> = text.lines().collect();
+        let cuts = match language {
+            Some("rust") => rust_cuts(&lines),
+            Some("python") => python_cuts(&lines),
+            _ => return self.split_lines(&lines, 1),
+        };
+
+        let mut pieces = Vec::new();
+        self.pack_syntax(&lines, &cuts, 0, lines.len(), &mut pieces);
+        pieces
+    }
+
+    /// Pack `lines[start..end]` into pieces, splitting at the lowest-level cuts
+    fn pack_syntax(
+        &self,
+        lines: &[&str],
+        cuts: &[Option<usize>],
+        start: usize,
+        end: usize,
+        pieces: &mut Vec<Piece>,
+    ) {
+        let size =
+            |from: usize, to: usize| -> usize { lines[from..to].iter().map(|l| l.len() + 1).sum() };
+
+        if size(start, end) <= self.chunk_size {
+            pieces.extend(trimmed_piece(lines, start, end));
+            return;
+        }
+
+        let Some(level) = (start + 1..end).filter_map(|i| cuts[i]).min() else {
+            // A single statement bigger than a chunk
+            let (start, end) = trim_blank(lines, start, end);
+            pieces.extend(self.split_lines(&lines[start..end], start + 1));
+            return;
+        };
+
+        let mut bounds: Vec<usize> = (start + 1..end)
+            .filter(|&i| cuts[i] == Some(level))
+            .collect();
+        bounds.insert(0, start);
+        bounds.push(end);
+
+        let mut current: Option<(usize, usize)> = None;
+        for segment in bounds.windows(2) {
+            let (from, to) = (segment[0], segment[1]);
+            current = match current {
+                Some((cur_from, _)) if size(cur_from, to) <= self.chunk_size => {
+                    Some((cur_from, to))
+                }
+                Some((cur_from, cur_to)) => {
+                    pieces.extend(trimmed_piece(lines, cur_from, cur_to));
+                    Some((from, to))
+                }
+                None => Some((from, to)),
+            };
+
+            if let Some((from, to)) = current {
+                if size(from, to) > self.chunk_size {
+                    self.pack_syntax(lines, cuts, from, to, pieces);
+                    current = None;
+                }
+            }
+        }
+
+        if let Some((from, to)) = current {
+            pieces.extend(trimmed_piece(lines, from, to));
+        }
+    }
+
+    /// Split into fixed-size character windows overlapping by `overlap`
+    fn split_fixed(&self, text: &str) -> Vec<Piece> {
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        if chars.is_empty() || self.chunk_size == 0 {
+            return vec![];
+        }
+
+        let step = self.chunk_size.saturating_sub(self.overlap).max(1);
+        let mut pieces = Vec::new();
+        let mut start = 0;
+
+        loop {
+            let mut end = (start + self.chunk_size).min(chars.len());
+            // The next window would be the last; take it in if it's too short
+            if end < chars.len() && chars.len() - (start + step) < self.min_chunk_size {
+                end = chars.len();
+            }
+            let byte_start = chars[start].0;
+            let byte_end = chars.get(end).map(|c| c.0).unwrap_or(text.len());
+
+            let start_line = text[..byte_start].matches('\n').count() + 1;
+            let end_line = start_line
+                + text[byte_start..byte_end]
+                    .trim_end_matches('\n')
+                    .matches('\n')
+                    .count();
+
+            pieces.push(Piece {
+                text: text[byte_start..byte_end].to_string(),
+                start_line,
                 end_line,
-                file_hash: file_hash.to_string(),
-                language: language.clone(),
+                overlap: 0,
             });
+
+            if end == chars.len() {
+                break;
+            }
+            start += step;
         }
 
-        chunks
+        pieces
     }
 
     /// Calculate how many lines to keep for overlap
@@ -166,8 +1080,218 @@ impl Chunker {
     }
 }
 
+/// Read one line into `buf` without its line ending, like [`str::lines`]
+///
+/// Returns `false` at end of input. A `\r` is only dropped before `\n`.
+fn read_line<R: BufRead>(reader: &mut R, buf: &mut Vec<u8>) -> std::io::Result<bool> {
+    buf.clear();
+    if reader.read_until(b'\n', buf)? == 0 {
+        return Ok(false);
+    }
+    if buf.last() == Some(&b'\n') {
+        buf.pop();
+        if buf.last() == Some(&b'\r') {
+            buf.pop();
+        }
+    }
+    Ok(true)
+}
+
+/// `lines[start..end]` without leading and trailing blank lines
+fn trim_blank(lines: &[&str], mut start: usize, mut end: usize) -> (usize, usize) {
+    while start < end && lines[start].trim().is_empty() {
+        start += 1;
+    }
+    while end > start && lines[end - 1].trim().is_empty() {
+        end -= 1;
+    }
+    (start, end)
+}
+
+/// A piece of `lines[start..end]` without surrounding blank lines, if any remain
+fn trimmed_piece(lines: &[&str], start: usize, end: usize) -> Option<Piece> {
+    let (start, end) = trim_blank(lines, start, end);
+    (start < end).then(|| Piece {
+        text: lines[start..end].join("\n"),
+        start_line: start + 1,
+        end_line: end,
+        overlap: 0,
+    })
+}
+
+/// Where Rust source may be split: `cuts[i]` is the brace depth at the start
+/// of line `i` if a new item or statement can begin there
+///
+/// A split is allowed after a line ending in `;` or `}`, which keeps doc
+/// comments and attributes with the item that follows them. Strings, char
+/// literals and comments are skipped when counting braces.
+fn rust_cuts(lines: &[&str]) -> Vec<Option<usize>> {
+    let mut cuts = vec![None; lines.len()];
+    let mut depth = 0usize;
+    let mut in_block_comment = 0usize;
+    let mut in_string: Option<usize> = None; // closing `#` count of the open string
+    let mut splittable = false;
+
+    for (i, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if splittable && in_block_comment == 0 && in_string.is_none() {
+            cuts[i] = Some(depth);
+        }
+
+        let chars: Vec<char> = line.chars().collect();
+        let mut last_code = None;
+        let mut j = 0;
+        while j < chars.len() {
+            let c = chars[j];
+            let next = chars.get(j + 1).copied();
+
+            if in_block_comment > 0 {
+                if c == '*' && next == Some('/') {
+                    in_block_comment -= 1;
+                    j += 1;
+                } else if c == '/' && next == Some('*') {
+                    in_block_comment += 1;
+                    j += 1;
+                }
+            } else if let Some(hashes) = in_string {
+                if c == '\\' && hashes == 0 {
+                    j += 1;
+                } else if c == '"'
+                    && chars[j + 1..].iter().take_while(|&&h| h == '#').count() >= hashes
+                {
+                    in_string = None;
+                    j += hashes;
+                    last_code = Some('"');
+                }
+            } else if c == '/' && next == Some('/') {
+                break;
+            } else if c == '/' && next == Some('*') {
+                in_block_comment = 1;
+                j += 1;
+            } else if c == '"' {
+                in_string = Some(0);
+            } else if c == 'r' && matches!(next, Some('"') | Some('#')) {
+                let hashes = chars[j + 1..].iter().take_while(|&&h| h == '#').count();
+                if chars.get(j + 1 + hashes) == Some(&'"') {
+                    in_string = Some(hashes);
+                    j += 1 + hashes;
+                } else {
+                    last_code = Some(c);
+                }
+            } else if c == '\'' && next == Some('\\') {
+                // Escaped char literal (`'\''`, `'\u{7b}'`): the quote after the
+                // escaped character closes it
+                let close = chars
+                    .get(j + 3..)
+                    .and_then(|rest| rest.iter().position(|&q| q == '\''));
+                j += close.map_or(chars.len(), |p| p + 3);
+                last_code = Some('\'');
+            } else if c == '\'' && chars.get(j + 2) == Some(&'\'') {
+                // Plain char literal (not a lifetime)
+                j += 2;
+                last_code = Some('\'');
+            } else {
+                match c {
+                    '{' => depth += 1,
+                    '}' => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+                if !c.is_whitespace() {
+                    last_code = Some(c);
+                }
+            }
+            j += 1;
+        }
+
+        let attribute = line.trim_start().starts_with("#[") || line.trim_start().starts_with("#![");
+        if let Some(last) = last_code {
+            splittable = !attribute
+                && in_string.is_none()
+                && (matches!(last, ';' | '}') || line.trim_end().ends_with("},"));
+        } else if in_block_comment == 0 {
+            // Comment-only lines belong to what follows
+            splittable = false;
+        }
+    }
+
+    cuts
+}
+
+/// Where Python source may be split: `cuts[i]` is the indentation of line
+/// `i` if a new statement begins there
+///
+/// Continuation lines (inside brackets, after `\`, or in a triple-quoted
+/// string) can't be split before, and neither can a line following a
+/// decorator or comment, which belong to what follows them.
+fn python_cuts(lines: &[&str]) -> Vec<Option<usize>> {
+    let mut cuts = vec![None; lines.len()];
+    let mut brackets = 0usize;
+    let mut in_triple: Option<char> = None;
+    let mut continued = false;
+    let mut attached = true; // nothing to split from before the first line
+
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() && in_triple.is_none() {
+            continue;
+        }
+
+        let statement_start = brackets == 0 && in_triple.is_none() && !continued;
+        if statement_start && !attached {
+            let indent = line.len() - line.trim_start().len();
+            cuts[i] = Some(indent);
+        }
+        if statement_start {
+            attached = trimmed.starts_with('@') || trimmed.starts_with('#');
+        }
+
+        let chars: Vec<char> = line.chars().collect();
+        let mut j = 0;
+        while j < chars.len() {
+            let c = chars[j];
+            let triple = |q: char| chars.get(j + 1) == Some(&q) && chars.get(j + 2) == Some(&q);
+
+            if let Some(q) = in_triple {
+                if c == '\\' {
+                    j += 1;
+                } else if c == q && triple(q) {
+                    in_triple = None;
+                    j += 2;
+                }
+            } else if c == '#' {
+                break;
+            } else if (c == '"' || c == '\'') && triple(c) {
+                in_triple = Some(c);
+                j += 2;
+            } else if c == '"' || c == '\'' {
+                // Single-line string: skip to the closing quote
+                j += 1;
+                while j < chars.len() && chars[j] != c {
+                    if chars[j] == '\\' {
+                        j += 1;
+                    }
+                    j += 1;
+                }
+            } else {
+                match c {
+                    '(' | '[' | '{' => brackets += 1,
+                    ')' | ']' | '}' => brackets = brackets.saturating_sub(1),
+                    _ => {}
+                }
+            }
+            j += 1;
+        }
+
+        continued = in_triple.is_none() && trimmed.ends_with('\\');
+    }
+
+    cuts
+}
+
 /// Detect programming language from file extension
-fn detect_language(file_path: &str) -> Option<String> {
+pub(crate) fn detect_language(file_path: &str) -> Option<String> {
     let path = Path::new(file_path);
     let ext = path.extension()?.to_str()?;
 
@@ -207,6 +1331,288 @@ fn detect_language(file_path: &str) -> Option<String> {
     Some(lang.to_string())
 }
 
+/// Comment markers of a language family
+struct CommentSyntax {
+    /// Line comment prefixes
+    line: &'static [&'static str],
+    /// Doc comment prefixes, checked before `line`
+    doc_line: &'static [&'static str],
+    /// Block comment (opener, closer, kind), most specific opener first
+    blocks: &'static [(&'static str, &'static str, ContentKind)],
+}
+
+const C_BLOCKS: &[(&str, &str, ContentKind)] = &[
+    ("/**", "*/", ContentKind::Docs),
+    ("/*!", "*/", ContentKind::Docs),
+    ("/*", "*/", ContentKind::Comments),
+];
+
+const MARKUP_BLOCKS: &[(&str, &str, ContentKind)] = &[("<!--", "-->", ContentKind::Comments)];
+
+/// Comment markers for a detected language (C-like and `#` when unknown)
+fn comment_syntax(language: Option<&str>) -> CommentSyntax {
+    let (line, doc_line, blocks): (&[&str], &[&str], _) = match language.unwrap_or_default() {
+        "rust" => (&["//"], &["///", "//!"], C_BLOCKS),
+        "c" | "cpp" | "java" | "javascript" | "typescript" | "javascriptreact"
+        | "typescriptreact" | "go" | "kotlin" | "csharp" | "swift" | "scala" => {
+            (&["//"], &["///"], C_BLOCKS)
+        }
+        "php" => (&["//", "#"], &[], C_BLOCKS),
+        "css" | "scss" => (&["//"], &[], C_BLOCKS),
+        "python" => (
+            &["#"],
+            &[],
+            &[
+                ("\"\"\"", "\"\"\"", ContentKind::Docs),
+                ("'''", "'''", ContentKind::Docs),
+            ],
+        ),
+        "ruby" | "shell" | "yaml" | "toml" => (&["#"], &[], &[]),
+        "terraform" => (&["#", "//"], &[], C_BLOCKS),
+        "sql" => (&["--"], &[], &[("/*", "*/", ContentKind::Comments)]),
+        "html" | "xml" => (&[], &[], MARKUP_BLOCKS),
+        "vue" | "svelte" => (&["//"], &[], MARKUP_BLOCKS),
+        _ => (&["//", "#"], &[], C_BLOCKS),
+    };
+
+    CommentSyntax {
+        line,
+        doc_line,
+        blocks,
+    }
+}
+
+/// Classify text as mostly code, comments or documentation
+///
+/// Counts non-blank lines with a per-language heuristic (line comments,
+/// block comments, doc comments, Python docstrings). Mixed chunks go to
+/// the majority, with ties going to code. Markdown is always documentation.
+pub(crate) fn classify_content(text: &str, language: Option<&str>) -> ContentKind {
+    if language == Some("markdown") {
+        return ContentKind::Docs;
+    }
+
+    let syntax = comment_syntax(language);
+    let mut counts = [0usize; 3];
+    let mut open_block: Option<(&str, ContentKind)> = None;
+
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let kind = if let Some((closer, kind)) = open_block {
+            if line.contains(closer) {
+                open_block = None;
+            }
+            kind
+        } else if syntax.doc_line.iter().any(|p| line.starts_with(p)) {
+            ContentKind::Docs
+        } else if let Some(&(opener, closer, kind)) = syntax
+            .blocks
+            .iter()
+            .find(|(opener, ..)| line.starts_with(opener))
+        {
+            if !line[opener.len()..].contains(closer) {
+                open_block = Some((closer, kind));
+            }
+            kind
+        } else if syntax.line.iter().any(|p| line.starts_with(p)) {
+            ContentKind::Comments
+        } else {
+            ContentKind::Code
+        };
+
+        counts[kind as usize] += 1;
+    }
+
+    let [code, comments, docs] = counts;
+    if comments > code && comments >= docs {
+        ContentKind::Comments
+    } else if docs > code && docs > comments {
+        ContentKind::Docs
+    } else {
+        ContentKind::Code
+    }
+}
+
+/// Words that are syntax rather than names in a language's source
+fn language_keywords(language: Option<&str>) -> &'static [&'static str] {
+    match language.unwrap_or_default() {
+        "rust" => &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+            "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+            "trait", "true", "type", "unsafe", "use", "where", "while",
+        ],
+        "python" => &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+            "elif", "else", "except", "False", "finally", "for", "from", "global", "if", "import",
+            "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return",
+            "self", "True", "try", "while", "with", "yield",
+        ],
+        "javascript" | "typescript" | "javascriptreact" | "typescriptreact" => &[
+            "async",
+            "await",
+            "break",
+            "case",
+            "catch",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "delete",
+            "do",
+            "else",
+            "export",
+            "extends",
+            "false",
+            "finally",
+            "for",
+            "from",
+            "function",
+            "if",
+            "import",
+            "in",
+            "instanceof",
+            "interface",
+            "let",
+            "new",
+            "null",
+            "return",
+            "switch",
+            "this",
+            "throw",
+            "true",
+            "try",
+            "type",
+            "typeof",
+            "undefined",
+            "var",
+            "void",
+            "while",
+            "yield",
+        ],
+        "go" => &[
+            "break",
+            "case",
+            "chan",
+            "const",
+            "continue",
+            "default",
+            "defer",
+            "else",
+            "false",
+            "for",
+            "func",
+            "go",
+            "if",
+            "import",
+            "interface",
+            "map",
+            "nil",
+            "package",
+            "range",
+            "return",
+            "select",
+            "struct",
+            "switch",
+            "true",
+            "type",
+            "var",
+        ],
+        "java" | "kotlin" | "csharp" | "c" | "cpp" => &[
+            "break",
+            "case",
+            "catch",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "do",
+            "else",
+            "enum",
+            "extends",
+            "false",
+            "final",
+            "for",
+            "fun",
+            "if",
+            "implements",
+            "import",
+            "include",
+            "new",
+            "null",
+            "package",
+            "private",
+            "protected",
+            "public",
+            "return",
+            "static",
+            "struct",
+            "switch",
+            "this",
+            "throw",
+            "true",
+            "try",
+            "typedef",
+            "val",
+            "var",
+            "void",
+            "while",
+        ],
+        _ => &[],
+    }
+}
+
+/// Split an identifier into lowercased snake_case and camelCase parts
+///
+/// `HTTPServer_config2` gives `http`, `server`, `config2`.
+pub fn split_identifier(ident: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    for segment in ident.split('_').filter(|s| !s.is_empty()) {
+        let chars: Vec<char> = segment.chars().collect();
+        let mut start = 0;
+        for i in 1..chars.len() {
+            let (prev, cur) = (chars[i - 1], chars[i]);
+            let next_lower = chars.get(i + 1).is_some_and(|c| c.is_lowercase());
+            // fooBar | FOOBar
+            if cur.is_uppercase() && (!prev.is_uppercase() || next_lower) {
+                parts.push(chars[start..i].iter().collect::<String>().to_lowercase());
+                start = i;
+            }
+        }
+        parts.push(chars[start..].iter().collect::<String>().to_lowercase());
+    }
+    parts
+}
+
+/// Extract the identifier parts of `text`, skipping language keywords
+///
+/// Parts shorter than two characters and bare numbers are dropped. Each
+/// part appears once, in order of first occurrence.
+pub fn extract_symbols(text: &str, language: Option<&str>) -> Vec<String> {
+    let keywords = language_keywords(language);
+    let mut seen = std::collections::HashSet::new();
+    let mut symbols = Vec::new();
+
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    for word in text.split(|c: char| !is_ident(c)) {
+        if word.is_empty() || word.starts_with(|c: char| c.is_ascii_digit()) {
+            continue;
+        }
+        if keywords.contains(&word) {
+            continue;
+        }
+        for part in split_identifier(word) {
+            if part.chars().count() < 2 || part.chars().all(|c| c.is_ascii_digit()) {
+                continue;
+            }
+            if seen.insert(part.clone()) {
+                symbols.push(part);
+            }
+        }
+    }
+
+    symbols
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -222,6 +1628,32 @@ mod tests {
         assert_eq!(chunks[0].end_line, 3);
     }
 
+    #[test]
+    fn test_classify_content() {
+        let comments = "// Retry with backoff.\n// Gives up after five attempts\n// and logs each failure.\nretry(op);";
+        assert_eq!(
+            classify_content(comments, Some("rust")),
+            ContentKind::Comments
+        );
+
+        let docs =
+            "def connect():\n    \"\"\"Open a connection.\n\n    Retries on failure.\n    \"\"\"";
+        assert_eq!(classify_content(docs, Some("python")), ContentKind::Docs);
+
+        let code = "/// Adds one\nfn inc(x: u32) -> u32 {\n    x + 1\n}";
+        assert_eq!(classify_content(code, Some("rust")), ContentKind::Code);
+
+        // Preprocessor lines are code in C, but comments in shell
+        assert_eq!(
+            classify_content("#include <stdio.h>", Some("c")),
+            ContentKind::Code
+        );
+        assert_eq!(
+            classify_content("# install deps", Some("shell")),
+            ContentKind::Comments
+        );
+    }
+
     #[test]
     fn test_chunking_large_file() {
         let chunker = Chunker::new(50, 10);
@@ -234,6 +1666,251 @@ mod tests {
         }
     }
 
+    /// Small deterministic PRNG so property tests need no extra crates
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, bound: usize) -> usize {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((self.0 >> 33) as usize) % bound
+        }
+    }
+
+    /// A random file: varied line lengths, blank runs, and a long line now and then
+    fn random_file(rng: &mut Lcg) -> String {
+        let line_count = 50 + rng.next(400);
+        (0..line_count)
+            .map(|n| match rng.next(10) {
+                0 => String::new(),
+                1 => "x".repeat(100 + rng.next(300)),
+                _ => format!("line {} {}", n, "y".repeat(rng.next(60))),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_start_line_matches_chunk_text() {
+        let mut rng = Lcg(42);
+
+        for _ in 0..50 {
+            let text = random_file(&mut rng);
+            let lines: Vec<&str> = text.lines().collect();
+            let chunk_size = 64 + rng.next(512);
+            let overlap = rng.next(chunk_size / 2);
+
+            for strategy in [ChunkStrategy::Lines, ChunkStrategy::Paragraphs] {
+                let chunker = Chunker::new(chunk_size, overlap).with_strategy(strategy);
+                let chunks = chunker.chunk_text(&text, "big.rs", "hash", 0);
+                assert!(chunks.len() > 1);
+
+                for chunk in &chunks {
+                    let chunk_lines: Vec<&str> = chunk.text.split('\n').collect();
+                    assert_eq!(lines[chunk.start_line - 1], chunk_lines[0]);
+                    assert_eq!(lines[chunk.end_line - 1], *chunk_lines.last().unwrap());
+                    assert_eq!(chunk.end_line - chunk.start_line + 1, chunk_lines.len());
+                }
+                assert_eq!(chunks.last().unwrap().end_line, lines.len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_stitch_overlapping_chunks() {
+        let chunker = Chunker::new(40, 15);
+        let lines: Vec<String> = (1..=8)
+            .map(|i| format!("let value_{} = {};", i, i))
+            .collect();
+        let text = lines.join("\n");
+        let chunks = chunker.chunk_text(&text, "test.rs", "hash", 0);
+
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks[0].overlap_with_prev, 0);
+        assert!(chunks[1].overlap_with_prev > 0);
+
+        // Concatenate, dropping each chunk's repeated leading lines
+        let mut stitched: Vec<&str> = Vec::new();
+        for chunk in &chunks {
+            stitched.extend(chunk.text.lines().skip(chunk.overlap_with_prev));
+        }
+        assert_eq!(stitched, text.lines().collect::<Vec<_>>());
+    }
+
+    /// Assert each chunk's text is exactly its line range of `text`
+    fn assert_lines_match(chunks: &[Chunk], text: &str) {
+        let lines: Vec<&str> = text.lines().collect();
+        for chunk in chunks {
+            assert_eq!(
+                chunk.text,
+                lines[chunk.start_line - 1..chunk.end_line].join("\n")
+            );
+        }
+    }
+
+    #[test]
+    fn test_syntax_chunking_rust_items() {
+        let text = r#"use std::fmt;
+
+/// Opens the connection
+#[inline]
+fn open(url: &str) -> Conn {
+    let brace = '{';
+    let s = "}";
+    Conn::new(url)
+}
+
+impl Conn {
+    fn close(&self) {
+        self.flush();
+    }
+}
+"#;
+        let chunker = Chunker::new_with_strategy(140, 0, ChunkStrategy::Syntax);
+        let chunks = chunker.chunk_text(text, "db.rs", "hash", 0);
+        assert_lines_match(&chunks, text);
+
+        // The import packs with the doc comment, attribute and function
+        assert_eq!(chunks.len(), 2);
+        assert_eq!((chunks[0].start_line, chunks[0].end_line), (1, 9));
+        assert_eq!((chunks[1].start_line, chunks[1].end_line), (11, 15));
+        assert!(chunks[1].text.starts_with("impl Conn {"));
+    }
+
+    #[test]
+    fn test_rust_cuts_skip_escaped_char_literals() {
+        let lines = [
+            "fn quotes() {",
+            "    let v = ['\\'','{'];",
+            "    let w = ('\\\\', '\\u{7b}');",
+            "}",
+            "fn next<'a>(s: &'a str) {}",
+        ];
+        // The escaped quote doesn't end the literal, so `'{'` stays a char
+        assert_eq!(rust_cuts(&lines), vec![None, None, Some(1), Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn test_syntax_chunking_splits_oversized_function_between_statements() {
+        let body: Vec<String> = (1..=12)
+            .map(|i| format!("    let value_{} = compute({});", i, i))
+            .collect();
+        let text = format!(
+            "fn big() {{\n{}\n    if ready {{\n        go();\n    }}\n}}\n",
+            body.join("\n")
+        );
+        let chunker = Chunker::new_with_strategy(150, 0, ChunkStrategy::Syntax);
+        let chunks = chunker.chunk_text(&text, "big.rs", "hash", 0);
+        assert_lines_match(&chunks, &text);
+
+        assert!(chunks.len() > 1);
+        assert!(chunks[0].text.starts_with("fn big() {"));
+        for pair in chunks.windows(2) {
+            assert_eq!(pair[1].start_line, pair[0].end_line + 1);
+            let last = pair[0].text.lines().last().unwrap().trim_end();
+            assert!(last.ends_with(';') || last.ends_with('}'), "{}", last);
+        }
+        // The `if` block is never cut in half
+        assert!(chunks
+            .iter()
+            .any(|c| c.text.contains("if ready {\n        go();\n    }")));
+    }
+
+    #[test]
+    fn test_syntax_chunking_python_methods() {
+        let text = r#"class Store:
+    """Key-value store.
+
+    def not_a_method(): inside the docstring
+    """
+
+    @property
+    def size(self):
+        return len(
+            self.items)
+
+    def get(self, key):
+        # look it up
+        return self.items[key]
+"#;
+        let chunker = Chunker::new_with_strategy(100, 0, ChunkStrategy::Syntax);
+        let chunks = chunker.chunk_text(text, "store.py", "hash", 0);
+        assert_lines_match(&chunks, text);
+
+        let starts: Vec<usize> = chunks.iter().map(|c| c.start_line).collect();
+        assert_eq!(starts, vec![1, 7, 12]);
+        assert!(chunks[1].text.starts_with("    @property\n    def size"));
+    }
+
+    #[test]
+    fn test_syntax_chunking_falls_back_to_lines() {
+        let text = (1..=20)
+            .map(|i| format!("const value{} = {};", i, i))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let syntax = Chunker::new_with_strategy(100, 20, ChunkStrategy::Syntax)
+            .chunk_text(&text, "app.js", "hash", 0);
+        let lines = Chunker::new(100, 20).chunk_text(&text, "app.js", "hash", 0);
+
+        let ranges = |chunks: &[Chunk]| -> Vec<(usize, usize)> {
+            chunks.iter().map(|c| (c.start_line, c.end_line)).collect()
+        };
+        assert_eq!(ranges(&syntax), ranges(&lines));
+    }
+
+    #[test]
+    fn test_chunk_reader_matches_chunk_text() {
+        let long: Vec<String> = (1..=40)
+            .map(|i| format!("    let value_{} = compute({});", i, i))
+            .collect();
+        let long = long.join("\n");
+        let crlf = long.replace('\n', "\r\n") + "\r\n";
+        let texts = [
+            long.as_str(),
+            &crlf,
+            "fn a() {}\n\n\nfn b() {}\n\n",
+            "trailing\r",
+            "  \n\t\n",
+            "",
+        ];
+
+        let chunker = Chunker::new(120, 40);
+        let key = |chunks: Vec<Chunk>| -> Vec<(u64, String, usize, usize, usize, String)> {
+            chunks
+                .into_iter()
+                .map(|c| {
+                    (
+                        c.id,
+                        c.text,
+                        c.start_line,
+                        c.end_line,
+                        c.overlap_with_prev,
+                        c.text_hash,
+                    )
+                })
+                .collect()
+        };
+        for text in texts {
+            let streamed = chunker
+                .chunk_reader(std::io::Cursor::new(text), "a.rs", "hash", 7)
+                .unwrap();
+            assert_eq!(
+                key(streamed),
+                key(chunker.chunk_text(text, "a.rs", "hash", 7))
+            );
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("big.rs");
+        std::fs::write(&path, &long).unwrap();
+        let chunks = chunker.chunk_file(&path, "hash", 0).unwrap();
+        assert!(chunks.len() > 1);
+        assert!(chunks[0].file_path.ends_with("big.rs"));
+        assert_eq!(chunks.last().unwrap().end_line, 40);
+    }
+
     #[test]
     fn test_language_detection() {
         assert_eq!(detect_language("main.rs"), Some("rust".to_string()));
@@ -242,10 +1919,301 @@ mod tests {
         assert_eq!(detect_language("unknown.xyz"), None);
     }
 
+    #[test]
+    fn test_paragraph_chunking_markdown() {
+        let chunker = Chunker::new(70, 10).with_strategy(ChunkStrategy::Paragraphs);
+        let text = "# Title\n\nFirst paragraph about installing.\nIt spans two lines.\n\n\nSecond paragraph on usage.\n";
+        let chunks = chunker.chunk_text(text, "README.md", "hash", 0);
+
+        // The heading packs with the first paragraph; the second doesn't fit
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].start_line, 1);
+        assert_eq!(chunks[0].end_line, 4);
+        assert!(chunks[0].text.ends_with("It spans two lines."));
+        assert_eq!(chunks[1].text, "Second paragraph on usage.");
+        assert_eq!(chunks[1].start_line, 7);
+        assert_eq!(chunks[1].end_line, 7);
+    }
+
+    #[test]
+    fn test_paragraph_chunking_splits_oversized() {
+        let chunker = Chunker::new(30, 0).with_strategy(ChunkStrategy::Paragraphs);
+        let text = "aaaaaaaaaa\nbbbbbbbbbb\ncccccccccc\ndddddddddd\n\nshort";
+        let chunks = chunker.chunk_text(text, "notes.md", "hash", 0);
+
+        assert!(chunks.len() >= 3);
+        assert!(chunks.iter().all(|c| c.text.len() <= 30));
+        assert_eq!(chunks.last().unwrap().text, "short");
+    }
+
+    #[test]
+    fn test_fixed_window_chunking() {
+        let chunker = Chunker::new(16, 4).with_strategy(ChunkStrategy::FixedChars);
+        let text = "2024-01-01 INFO start\n2024-01-01 WARN slow\n2024-01-01 INFO done\n";
+        let chunks = chunker.chunk_text(text, "app.log", "hash", 0);
+
+        assert!(chunks.len() > 1);
+        let (last, rest) = chunks.split_last().unwrap();
+        assert!(rest.iter().all(|c| c.text.chars().count() == 16));
+        assert!(last.text.chars().count() <= 16);
+        assert_eq!(&chunks[1].text[..4], &chunks[0].text[12..]);
+        assert_eq!(chunks[0].start_line, 1);
+        assert_eq!(last.end_line, 3);
+    }
+
+    #[test]
+    fn test_language_strategy_override() {
+        let mut strategies = HashMap::new();
+        strategies.insert("markdown".to_string(), ChunkStrategy::Paragraphs);
+        let chunker = Chunker::new(12, 0).with_language_strategies(strategies);
+        let text = "alpha beta\n\ngamma delta";
+
+        let md = chunker.chunk_text(text, "a.md", "hash", 0);
+        let texts: Vec<&str> = md.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, vec!["alpha beta", "gamma delta"]);
+        assert_eq!(md[1].start_line, 3);
+
+        // Other languages keep the line strategy, which carries the blank line
+        let rs = chunker.chunk_text(text, "a.rs", "hash", 0);
+        assert_eq!(rs[0].text, "alpha beta\n");
+    }
+
+    #[test]
+    fn test_language_size_override() {
+        let mut sizes = HashMap::new();
+        sizes.insert("markdown".to_string(), 200);
+        let chunker = Chunker::new(20, 0).with_language_sizes(sizes);
+        let text = "first line of text\nsecond line of text\nthird line of text\n";
+
+        assert_eq!(chunker.chunk_text(text, "a.md", "hash", 0).len(), 1);
+        assert_eq!(chunker.chunk_text(text, "a.rs", "hash", 0).len(), 3);
+
+        // Streaming picks the same size
+        let streamed = chunker
+            .chunk_reader(text.as_bytes(), "a.md", "hash", 0)
+            .unwrap();
+        assert_eq!(streamed.len(), 1);
+    }
+
+    #[test]
+    fn test_small_tail_merges_into_previous_chunk() {
+        let lines: Vec<String> = (1..=7).map(|i| format!("let v{} = {};", i, i)).collect();
+        let text = lines.join("\n") + "\n";
+
+        // 14-character lines, two per chunk, one-line overlap: the last
+        // chunk would be lines 6-7 on its own
+        let chunker = Chunker::new(30, 15);
+        let split = chunker.chunk_text(&text, "a.rs", "hash", 0);
+        assert_eq!(split.last().unwrap().start_line, 6);
+
+        let chunker = chunker.with_min_chunk_size(40);
+        let merged = chunker.chunk_text(&text, "a.rs", "hash", 0);
+        assert_eq!(merged.len(), split.len() - 1);
+        let last = merged.last().unwrap();
+        assert_eq!((last.start_line, last.end_line), (5, 7));
+        assert_eq!(last.text, lines[4..].join("\n"));
+
+        // Streaming merges the same way
+        let streamed = chunker
+            .chunk_reader(text.as_bytes(), "a.rs", "hash", 0)
+            .unwrap();
+        let spans = |chunks: &[Chunk]| -> Vec<(String, usize, usize)> {
+            chunks
+                .iter()
+                .map(|c| (c.text.clone(), c.start_line, c.end_line))
+                .collect()
+        };
+        assert_eq!(spans(&streamed), spans(&merged));
+
+        // A file smaller than the minimum is still one chunk
+        let small = chunker.chunk_text("let x = 1;\n", "a.rs", "hash", 0);
+        assert_eq!(small.len(), 1);
+        assert_eq!((small[0].start_line, small[0].end_line), (1, 1));
+
+        // Paragraphs keep the blank lines between them
+        let chunker = Chunker::new(40, 0)
+            .with_strategy(ChunkStrategy::Paragraphs)
+            .with_min_chunk_size(10);
+        let text = "A first paragraph of some length.\n\nEnd.\n";
+        let chunks = chunker.chunk_text(text, "a.md", "hash", 0);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].text, "A first paragraph of some length.\n\nEnd.");
+        assert_eq!(chunks[0].end_line, 3);
+
+        // Fixed windows stretch the last full window to the end
+        let chunker = Chunker::new(16, 4)
+            .with_strategy(ChunkStrategy::FixedChars)
+            .with_min_chunk_size(8);
+        let text = "0123456789abcdefghijklmnopqrstu";
+        let chunks = chunker.chunk_text(text, "app.log", "hash", 0);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].text, &text[12..]);
+    }
+
+    #[test]
+    fn test_legacy_metadata_has_unknown_revision() {
+        #[derive(Serialize)]
+        struct OldChunk<'a> {
+            id: u64,
+            text: &'a str,
+            file_path: &'a str,
+            start_line: usize,
+            end_line: usize,
+            file_hash: &'a str,
+            language: Option<&'a str>,
+        }
+
+        #[derive(Serialize)]
+        struct OldMetadata<'a> {
+            chunks: Vec<OldChunk<'a>>,
+            file_hashes: HashMap<String, String>,
+            next_id: u64,
+            model_name: &'a str,
+            dimension: usize,
+        }
+
+        let old = OldMetadata {
+            chunks: vec![OldChunk {
+                id: 0,
+                text: "fn main() {}",
+                file_path: "main.rs",
+                start_line: 1,
+                end_line: 1,
+                file_hash: "hash",
+                language: Some("rust"),
+            }],
+            file_hashes: HashMap::new(),
+            next_id: 1,
+            model_name: "sentence-transformers/all-MiniLM-L6-v2",
+            dimension: 384,
+        };
+
+        let metadata = IndexMetadata::from_bytes(&bincode::serialize(&old).unwrap()).unwrap();
+        assert_eq!(metadata.chunks[0].text, "fn main() {}");
+        assert_eq!(
+            metadata.model_name,
+            "sentence-transformers/all-MiniLM-L6-v2"
+        );
+        assert_eq!(metadata.model_revision, None);
+    }
+
+    #[test]
+    fn test_extract_symbols() {
+        assert_eq!(
+            split_identifier("HTTPServer_config2"),
+            vec!["http", "server", "config2"]
+        );
+
+        let text = "pub fn parse_config(path: &Path) -> ConfigLoader { let x = 42; }";
+        assert_eq!(
+            extract_symbols(text, Some("rust")),
+            vec!["parse", "config", "path", "loader"]
+        );
+
+        let chunks = Chunker::new(200, 0).chunk_text(text, "lib.rs", "hash", 0);
+        assert_eq!(chunks[0].symbols, extract_symbols(text, Some("rust")));
+    }
+
+    #[test]
+    fn test_version_2_metadata_has_no_symbols() {
+        #[derive(Serialize)]
+        struct V2Chunk<'a> {
+            id: u64,
+            text: &'a str,
+            file_path: &'a str,
+            start_line: usize,
+            end_line: usize,
+            file_hash: &'a str,
+            language: Option<&'a str>,
+            overlap_with_prev: usize,
+            content_kind: ContentKind,
+            text_hash: &'a str,
+        }
+
+        #[derive(Serialize)]
+        struct V2Metadata<'a> {
+            chunks: Vec<V2Chunk<'a>>,
+            file_hashes: HashMap<String, String>,
+            next_id: u64,
+            model_name: &'a str,
+            dimension: usize,
+            model_revision: Option<&'a str>,
+            indexed_commit: Option<&'a str>,
+            uncommitted_files: Vec<String>,
+        }
+
+        let old = V2Metadata {
+            chunks: vec![V2Chunk {
+                id: 0,
+                text: "fn parse_config() {}",
+                file_path: "main.rs",
+                start_line: 1,
+                end_line: 1,
+                file_hash: "hash",
+                language: Some("rust"),
+                overlap_with_prev: 0,
+                content_kind: ContentKind::Code,
+                text_hash: "text",
+            }],
+            file_hashes: HashMap::new(),
+            next_id: 1,
+            model_name: "sentence-transformers/all-MiniLM-L6-v2",
+            dimension: 384,
+            model_revision: Some("rev"),
+            indexed_commit: None,
+            uncommitted_files: Vec::new(),
+        };
+
+        let mut bytes = METADATA_MAGIC.to_vec();
+        bytes.extend_from_slice(&2u32.to_le_bytes());
+        bytes.extend(bincode::serialize(&old).unwrap());
+        for bytes in [&bytes[..], &bytes[8..]] {
+            let metadata = IndexMetadata::from_bytes(bytes).unwrap();
+            assert_eq!(metadata.chunks[0].text, "fn parse_config() {}");
+            assert_eq!(metadata.chunks[0].text_hash, "text");
+            assert!(metadata.chunks[0].symbols.is_empty());
+            assert_eq!(metadata.model_revision.as_deref(), Some("rev"));
+        }
+    }
+
     #[test]
     fn test_empty_file() {
         let chunker = Chunker::new(100, 20);
         let chunks = chunker.chunk_text("", "empty.rs", "hash", 0);
         assert!(chunks.is_empty());
     }
+
+    #[test]
+    fn test_trailing_newline() {
+        let chunker = Chunker::new(100, 20);
+        let chunks = chunker.chunk_text("fn a() {}\nfn b() {}\n", "lib.rs", "hash", 0);
+
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].end_line, 2);
+        assert_eq!(chunks[0].text, "fn a() {}\nfn b() {}");
+    }
+
+    #[test]
+    fn test_trailing_blank_line() {
+        let chunker = Chunker::new(100, 20);
+        let chunks = chunker.chunk_text("fn a() {}\nfn b() {}\n\n", "lib.rs", "hash", 0);
+
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].start_line, 1);
+        assert_eq!(chunks[0].end_line, 3);
+    }
+
+    #[test]
+    fn test_whitespace_only_file() {
+        for strategy in [
+            ChunkStrategy::Lines,
+            ChunkStrategy::Paragraphs,
+            ChunkStrategy::FixedChars,
+        ] {
+            let chunker = Chunker::new(100, 20).with_strategy(strategy);
+            for text in ["\n", "  \n\t\n\n"] {
+                assert!(chunker.chunk_text(text, "a.rs", "hash", 0).is_empty());
+            }
+        }
+    }
 }
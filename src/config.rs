@@ -3,8 +3,10 @@
 //! Defines embedding models, index configuration, and file filtering rules.
 
 use crate::error::{LgrepError, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
 /// Supported embedding models (all run locally via ONNX)
 ///
@@ -21,19 +23,46 @@ pub enum EmbeddingModel {
     NomicEmbedTextV15,
     /// Multilingual support (384 dims, ~470MB)
     MultilingualE5Small,
+    /// A local ONNX model: a directory holding `model.onnx`, `tokenizer.json`,
+    /// `config.json`, `special_tokens_map.json` and `tokenizer_config.json`
+    Custom {
+        /// Model directory
+        path: PathBuf,
+        /// Embedding dimension the model outputs (checked on first embed)
+        dimension: usize,
+    },
 }
 
 impl EmbeddingModel {
-    /// Get the HuggingFace model identifier
-    pub fn model_name(&self) -> &'static str {
+    /// All supported models
+    pub fn all() -> [EmbeddingModel; 4] {
+        [
+            Self::AllMiniLmL6V2,
+            Self::BgeSmallEnV15,
+            Self::NomicEmbedTextV15,
+            Self::MultilingualE5Small,
+        ]
+    }
+
+    /// Get the HuggingFace model identifier (the directory for custom models)
+    pub fn model_name(&self) -> &str {
         match self {
             Self::AllMiniLmL6V2 => "sentence-transformers/all-MiniLM-L6-v2",
             Self::BgeSmallEnV15 => "BAAI/bge-small-en-v1.5",
             Self::NomicEmbedTextV15 => "nomic-ai/nomic-embed-text-v1.5",
             Self::MultilingualE5Small => "intfloat/multilingual-e5-small",
+            Self::Custom { path, .. } => path.to_str().unwrap_or("custom"),
         }
     }
 
+    /// Set the dimension of a custom model (built-in models are unchanged)
+    pub fn with_dimension(mut self, dimension: usize) -> Self {
+        if let Self::Custom { dimension: d, .. } = &mut self {
+            *d = dimension;
+        }
+        self
+    }
+
     /// Get the embedding vector dimension
     pub fn dimension(&self) -> usize {
         match self {
@@ -41,21 +70,136 @@ impl EmbeddingModel {
             Self::BgeSmallEnV15 => 384,
             Self::NomicEmbedTextV15 => 768,
             Self::MultilingualE5Small => 384,
+            Self::Custom { dimension, .. } => *dimension,
         }
     }
+
+    /// Approximate download size in MB (custom models are never downloaded)
+    pub fn download_size_mb(&self) -> Option<u64> {
+        match self {
+            Self::AllMiniLmL6V2 => Some(30),
+            Self::BgeSmallEnV15 => Some(90),
+            Self::NomicEmbedTextV15 => Some(90),
+            Self::MultilingualE5Small => Some(470),
+            Self::Custom { .. } => None,
+        }
+    }
+
+    /// Instruction the model expects before a search query
+    pub fn query_prefix(&self) -> &'static str {
+        match self {
+            Self::BgeSmallEnV15 => "Represent this sentence for searching relevant passages: ",
+            Self::NomicEmbedTextV15 => "search_query: ",
+            Self::MultilingualE5Small => "query: ",
+            Self::AllMiniLmL6V2 | Self::Custom { .. } => "",
+        }
+    }
+
+    /// Instruction the model expects before a passage to be searched
+    pub fn document_prefix(&self) -> &'static str {
+        match self {
+            Self::NomicEmbedTextV15 => "search_document: ",
+            Self::MultilingualE5Small => "passage: ",
+            Self::AllMiniLmL6V2 | Self::BgeSmallEnV15 | Self::Custom { .. } => "",
+        }
+    }
+
+    /// Whether the model was trained with query or document prefixes
+    pub fn uses_prefixes(&self) -> bool {
+        !self.query_prefix().is_empty() || !self.document_prefix().is_empty()
+    }
 }
 
 impl std::str::FromStr for EmbeddingModel {
     type Err = LgrepError;
 
+    /// Also accepts `custom:/path/to/model`, with the dimension left at 0 to
+    /// be set with [`with_dimension`](EmbeddingModel::with_dimension)
     fn from_str(s: &str) -> Result<Self> {
+        if let Some(path) = s.strip_prefix("custom:") {
+            if path.is_empty() {
+                return Err(LgrepError::Config(
+                    "Custom model needs a path: custom:/path/to/model".to_string(),
+                ));
+            }
+            return Ok(Self::Custom {
+                path: PathBuf::from(path),
+                dimension: 0,
+            });
+        }
+
         match s.to_lowercase().as_str() {
             "minilm" | "all-minilm-l6-v2" | "default" => Ok(Self::AllMiniLmL6V2),
             "bge" | "bge-small" | "bge-small-en-v1.5" => Ok(Self::BgeSmallEnV15),
             "nomic" | "nomic-embed" | "nomic-embed-text-v1.5" => Ok(Self::NomicEmbedTextV15),
             "multilingual" | "e5" | "multilingual-e5-small" => Ok(Self::MultilingualE5Small),
             _ => Err(LgrepError::Config(format!(
-                "Unknown model: {}. Valid options: minilm, bge, nomic, multilingual",
+                "Unknown model: {}. Valid options: minilm, bge, nomic, multilingual, custom:PATH",
+                s
+            ))),
+        }
+    }
+}
+
+/// How files are split into chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ChunkStrategy {
+    /// Pack whole lines up to the chunk size (good for code)
+    #[default]
+    Lines,
+    /// Split on blank-line runs (good for prose and docs)
+    Paragraphs,
+    /// Fixed-size character windows (good for logs and data)
+    FixedChars,
+    /// Whole items (functions, classes, impls) for Rust and Python, found by
+    /// scanning braces and indentation rather than parsing; oversized ones
+    /// are split between statements, other languages use line packing
+    Syntax,
+}
+
+impl std::str::FromStr for ChunkStrategy {
+    type Err = LgrepError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "lines" | "line" => Ok(Self::Lines),
+            "paragraphs" | "paragraph" => Ok(Self::Paragraphs),
+            "fixed" | "fixed-chars" | "chars" => Ok(Self::FixedChars),
+            "syntax" => Ok(Self::Syntax),
+            _ => Err(LgrepError::Config(format!(
+                "Unknown chunk strategy: {}. Valid options: lines, paragraphs, fixed, syntax",
+                s
+            ))),
+        }
+    }
+}
+
+/// ONNX Runtime execution provider used to compute embeddings
+///
+/// Providers other than `Cpu` need an onnxruntime build that includes them
+/// (for CUDA, build lgrep with the `cuda` feature); when one can't be
+/// initialized the embedder warns and falls back to the CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExecutionProvider {
+    /// Run on the CPU (always available)
+    #[default]
+    Cpu,
+    /// NVIDIA GPUs through CUDA
+    Cuda,
+    /// Apple Neural Engine and GPUs through Core ML
+    CoreMl,
+}
+
+impl std::str::FromStr for ExecutionProvider {
+    type Err = LgrepError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "cpu" => Ok(Self::Cpu),
+            "cuda" | "gpu" => Ok(Self::Cuda),
+            "coreml" | "core-ml" => Ok(Self::CoreMl),
+            _ => Err(LgrepError::Config(format!(
+                "Unknown device: {}. Valid options: cpu, cuda, coreml",
                 s
             ))),
         }
@@ -67,6 +211,9 @@ impl std::str::FromStr for EmbeddingModel {
 pub struct Config {
     /// Root directory being indexed
     pub root_path: PathBuf,
+    /// Workspace roots indexed into this index instead of `root_path` alone
+    #[serde(default)]
+    pub root_paths: Vec<PathBuf>,
     /// Directory to store index data
     pub index_dir: PathBuf,
     /// Embedding model to use
@@ -75,27 +222,151 @@ pub struct Config {
     pub chunk_size: usize,
     /// Overlap between chunks in characters
     pub chunk_overlap: usize,
+    /// Smallest final chunk kept on its own, in characters; a shorter one
+    /// is merged into the chunk before it (0 keeps every chunk)
+    #[serde(default)]
+    pub min_chunk_size: usize,
+    /// How files are split into chunks
+    #[serde(default)]
+    pub chunk_strategy: ChunkStrategy,
+    /// Per-language chunk strategy overrides (e.g. `markdown` -> `Paragraphs`)
+    #[serde(default)]
+    pub language_strategies: HashMap<String, ChunkStrategy>,
+    /// Per-language chunk size overrides in characters (e.g. `markdown` -> 1024)
+    #[serde(default)]
+    pub language_chunk_sizes: HashMap<String, usize>,
     /// Maximum file size to index (bytes)
     pub max_file_size: u64,
     /// Number of parallel workers for processing
     pub workers: usize,
+    /// File names never indexed, even with an indexable extension (lock files etc.)
+    #[serde(default = "default_ignored_filenames")]
+    pub ignored_filenames: Vec<String>,
+    /// File extensions (lowercase, without the dot) that are indexed
+    #[serde(default = "default_extensions")]
+    pub extensions: Vec<String>,
+    /// Index any file that reads as UTF-8 text, whatever its extension
+    #[serde(default)]
+    pub all_text: bool,
+    /// Extra gitignore-style patterns, relative to each root, that are never indexed
+    #[serde(default)]
+    pub ignore_patterns: Vec<String>,
+    /// Name of lgrep's own ignore files, read like `.gitignore` in every directory
+    #[serde(default = "default_ignore_filename")]
+    pub ignore_filename: String,
+    /// Index files excluded by `.gitignore`, `.ignore` and `ignore_filename`
+    #[serde(default)]
+    pub no_ignore: bool,
+    /// Index hidden files and directories (the index directory and `.git` stay excluded)
+    #[serde(default)]
+    pub include_hidden: bool,
+    /// Skip files marked `linguist-generated` or `linguist-vendored` in `.gitattributes`
+    #[serde(default)]
+    pub respect_gitattributes: bool,
+    /// Also store a per-file embedding (mean of its chunks) for file-level search
+    #[serde(default)]
+    pub file_embeddings: bool,
+    /// Archive (`.zip`, `.tar`, `.tar.gz`) indexed in place of the root directory
+    #[serde(default)]
+    pub archive: Option<PathBuf>,
+    /// Only index programming-language files, skipping docs, config and data
+    #[serde(default)]
+    pub code_only: bool,
+    /// Ignore whitespace-only edits when deciding whether a file changed
+    #[serde(default)]
+    pub ignore_whitespace_changes: bool,
+    /// Files larger than this (bytes) are hashed and chunked as they are read
+    /// instead of being loaded whole
+    #[serde(default = "default_stream_threshold")]
+    pub stream_threshold: u64,
+    /// HNSW graph degree (`M`); higher improves recall at the cost of memory
+    #[serde(default = "default_hnsw_connectivity")]
+    pub hnsw_connectivity: usize,
+    /// Candidates considered while inserting (`ef_construction`)
+    #[serde(default = "default_hnsw_expansion_add")]
+    pub hnsw_expansion_add: usize,
+    /// Candidates considered while searching (`ef`)
+    #[serde(default = "default_hnsw_expansion_search")]
+    pub hnsw_expansion_search: usize,
+    /// Where embeddings are computed; a property of the machine, so it is
+    /// not saved with the index
+    #[serde(skip)]
+    pub execution_provider: ExecutionProvider,
+}
+
+fn default_stream_threshold() -> u64 {
+    1024 * 1024 // 1 MB
+}
+
+pub(crate) fn default_hnsw_connectivity() -> usize {
+    16
+}
+
+pub(crate) fn default_hnsw_expansion_add() -> usize {
+    128
+}
+
+pub(crate) fn default_hnsw_expansion_search() -> usize {
+    64
+}
+
+fn default_extensions() -> Vec<String> {
+    CODE_EXTENSIONS.iter().map(|s| s.to_string()).collect()
+}
+
+fn default_ignore_filename() -> String {
+    ".lgrepignore".to_string()
+}
+
+fn default_ignored_filenames() -> Vec<String> {
+    DEFAULT_IGNORED_FILENAMES
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             root_path: PathBuf::from("."),
+            root_paths: Vec::new(),
             index_dir: PathBuf::from(".lgrep"),
             model: EmbeddingModel::default(),
             chunk_size: 512,
             chunk_overlap: 64,
+            min_chunk_size: 0,
+            chunk_strategy: ChunkStrategy::default(),
+            language_strategies: HashMap::new(),
+            language_chunk_sizes: HashMap::new(),
             max_file_size: 10 * 1024 * 1024, // 10 MB
             workers: num_cpus::get(),
+            ignored_filenames: default_ignored_filenames(),
+            extensions: default_extensions(),
+            all_text: false,
+            ignore_patterns: Vec::new(),
+            ignore_filename: default_ignore_filename(),
+            no_ignore: false,
+            include_hidden: false,
+            respect_gitattributes: false,
+            file_embeddings: false,
+            archive: None,
+            code_only: false,
+            ignore_whitespace_changes: false,
+            stream_threshold: default_stream_threshold(),
+            hnsw_connectivity: default_hnsw_connectivity(),
+            hnsw_expansion_add: default_hnsw_expansion_add(),
+            hnsw_expansion_search: default_hnsw_expansion_search(),
+            execution_provider: ExecutionProvider::default(),
         }
     }
 }
 
 impl Config {
+    /// Start building a validated config (root defaults to `.`)
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+
     /// Create a new config for the given root path
     pub fn new(root_path: PathBuf) -> Self {
         let index_dir = root_path.join(".lgrep");
@@ -118,6 +389,215 @@ impl Config {
         self
     }
 
+    /// Set the smallest final chunk kept on its own
+    pub fn with_min_chunk_size(mut self, size: usize) -> Self {
+        self.min_chunk_size = size;
+        self
+    }
+
+    /// Set the default chunking strategy
+    pub fn with_chunk_strategy(mut self, strategy: ChunkStrategy) -> Self {
+        self.chunk_strategy = strategy;
+        self
+    }
+
+    /// Override the chunking strategy for one language
+    pub fn with_language_strategy(mut self, language: &str, strategy: ChunkStrategy) -> Self {
+        self.language_strategies
+            .insert(language.to_string(), strategy);
+        self
+    }
+
+    /// Override the chunk size for one language
+    pub fn with_language_chunk_size(mut self, language: &str, size: usize) -> Self {
+        self.language_chunk_sizes.insert(language.to_string(), size);
+        self
+    }
+
+    /// Index several workspace roots into this config's index
+    pub fn with_root_paths(mut self, roots: Vec<PathBuf>) -> Self {
+        self.root_paths = roots;
+        self
+    }
+
+    /// Resolve the roots to index, canonicalized with overlapping roots removed
+    ///
+    /// Without `root_paths` this is just `root_path`, and stored paths stay
+    /// plain relative paths. Otherwise each root is labelled with its
+    /// directory name and its files are stored under that label.
+    pub fn roots(&self) -> Result<Vec<WorkspaceRoot>> {
+        if self.root_paths.is_empty() {
+            return Ok(vec![WorkspaceRoot {
+                label: String::new(),
+                path: self.root_path.canonicalize()?,
+            }]);
+        }
+
+        let mut paths = self
+            .root_paths
+            .iter()
+            .map(|p| p.canonicalize())
+            .collect::<std::io::Result<Vec<_>>>()?;
+
+        // Shallowest first, so a root nested in another is dropped
+        paths.sort_by_key(|p| p.components().count());
+        let mut kept: Vec<PathBuf> = Vec::new();
+        for path in paths {
+            if !kept.iter().any(|k| path.starts_with(k)) {
+                kept.push(path);
+            }
+        }
+
+        let mut labels = HashSet::new();
+        let roots = kept
+            .into_iter()
+            .map(|path| WorkspaceRoot {
+                label: root_label(&path, &mut labels),
+                path,
+            })
+            .collect();
+
+        Ok(roots)
+    }
+
+    /// Label of the workspace root a stored path came from, if multi-root
+    pub fn root_of(&self, file_path: &str) -> Option<String> {
+        if self.root_paths.is_empty() {
+            return None;
+        }
+        file_path.split('/').next().map(|s| s.to_string())
+    }
+
+    /// Set the file names to skip (replaces the default lock-file list)
+    pub fn with_ignored_filenames(mut self, names: Vec<String>) -> Self {
+        self.ignored_filenames = names;
+        self
+    }
+
+    /// Add and remove indexed extensions (a leading `.` is optional)
+    pub fn with_extensions(mut self, include: &[String], exclude: &[String]) -> Self {
+        let normalize = |ext: &String| ext.trim_start_matches('.').to_lowercase();
+        for ext in include.iter().map(normalize) {
+            if !self.extensions.contains(&ext) {
+                self.extensions.push(ext);
+            }
+        }
+        let exclude: Vec<String> = exclude.iter().map(normalize).collect();
+        self.extensions.retain(|ext| !exclude.contains(ext));
+        self
+    }
+
+    /// Index every UTF-8 text file regardless of extension (binary files
+    /// are skipped when they fail to decode)
+    pub fn with_all_text(mut self, all_text: bool) -> Self {
+        self.all_text = all_text;
+        self
+    }
+
+    /// Skip paths matching these gitignore-style patterns (in addition to
+    /// `.gitignore` and `.lgrepignore`)
+    pub fn with_ignore_patterns(mut self, patterns: Vec<String>) -> Self {
+        self.ignore_patterns = patterns;
+        self
+    }
+
+    /// Read ignore rules from files called `name` instead of `.lgrepignore`
+    pub fn with_ignore_filename(mut self, name: &str) -> Self {
+        self.ignore_filename = name.to_string();
+        self
+    }
+
+    /// Index files that ignore files would otherwise exclude
+    pub fn with_no_ignore(mut self, no_ignore: bool) -> Self {
+        self.no_ignore = no_ignore;
+        self
+    }
+
+    /// Index hidden files and directories
+    pub fn with_include_hidden(mut self, include_hidden: bool) -> Self {
+        self.include_hidden = include_hidden;
+        self
+    }
+
+    /// Honor `.gitattributes` linguist-generated/vendored markers
+    pub fn with_respect_gitattributes(mut self, respect: bool) -> Self {
+        self.respect_gitattributes = respect;
+        self
+    }
+
+    /// Store per-file embeddings for file-level search
+    pub fn with_file_embeddings(mut self, enabled: bool) -> Self {
+        self.file_embeddings = enabled;
+        self
+    }
+
+    /// Index the files inside an archive instead of walking `root_path`
+    pub fn with_archive(mut self, archive: PathBuf) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    /// Skip docs, config and data files (see [`NON_CODE_EXTENSIONS`])
+    pub fn with_code_only(mut self, code_only: bool) -> Self {
+        self.code_only = code_only;
+        self
+    }
+
+    /// Treat files whose only changes are whitespace as unchanged
+    ///
+    /// Their stored chunks (text and line numbers) stay as they were indexed.
+    pub fn with_ignore_whitespace_changes(mut self, ignore: bool) -> Self {
+        self.ignore_whitespace_changes = ignore;
+        self
+    }
+
+    /// Stream files larger than `bytes` rather than reading them whole
+    pub fn with_stream_threshold(mut self, bytes: u64) -> Self {
+        self.stream_threshold = bytes;
+        self
+    }
+
+    /// Set the HNSW connectivity and expansion factors used by new indexes
+    ///
+    /// An existing index keeps the values it was built with.
+    pub fn with_hnsw(
+        mut self,
+        connectivity: usize,
+        expansion_add: usize,
+        expansion_search: usize,
+    ) -> Self {
+        self.hnsw_connectivity = connectivity;
+        self.hnsw_expansion_add = expansion_add;
+        self.hnsw_expansion_search = expansion_search;
+        self
+    }
+
+    /// Compute embeddings with `provider` (falling back to the CPU)
+    pub fn with_execution_provider(mut self, provider: ExecutionProvider) -> Self {
+        self.execution_provider = provider;
+        self
+    }
+
+    /// Check if a file should be indexed under this configuration
+    pub fn should_index(&self, path: &std::path::Path) -> bool {
+        (self.all_text || has_extension(path, &self.extensions))
+            && !(self.code_only && has_non_code_extension(path))
+            && !is_ignored_filename(path, &self.ignored_filenames)
+    }
+
+    /// Matcher for [`ignore_patterns`](Self::ignore_patterns) under `root`
+    pub fn ignore_matcher(&self, root: &Path) -> Result<Gitignore> {
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in &self.ignore_patterns {
+            builder.add_line(None, pattern).map_err(|e| {
+                LgrepError::Config(format!("Invalid ignore pattern {:?}: {}", pattern, e))
+            })?;
+        }
+        builder
+            .build()
+            .map_err(|e| LgrepError::Config(format!("Invalid ignore patterns: {}", e)))
+    }
+
     /// Get path to the vector index file
     pub fn index_path(&self) -> PathBuf {
         self.index_dir.join("vectors.usearch")
@@ -128,6 +608,16 @@ impl Config {
         self.index_dir.join("metadata.bin")
     }
 
+    /// Get path to the chunk text file
+    pub fn texts_path(&self) -> PathBuf {
+        self.index_dir.join("texts.bin")
+    }
+
+    /// Get path to the per-file embeddings
+    pub fn file_vectors_path(&self) -> PathBuf {
+        self.index_dir.join("file_vectors.bin")
+    }
+
     /// Get path to the config file
     pub fn config_path(&self) -> PathBuf {
         self.index_dir.join("config.json")
@@ -137,12 +627,79 @@ impl Config {
     pub fn save(&self) -> Result<()> {
         std::fs::create_dir_all(&self.index_dir)?;
         let json = serde_json::to_string_pretty(self)?;
-        std::fs::write(self.config_path(), json)?;
+
+        // Renamed over the old settings, so a crash can't leave half a file
+        let path = self.config_path();
+        let temp = path.with_extension("json.tmp");
+        std::fs::write(&temp, json)?;
+        std::fs::rename(&temp, &path)?;
+        Ok(())
+    }
+
+    /// Check that the settings are consistent
+    pub fn validate(&self) -> Result<()> {
+        if self.chunk_size == 0 {
+            return Err(LgrepError::Config(
+                "Chunk size must be greater than 0".to_string(),
+            ));
+        }
+        if self.chunk_overlap >= self.chunk_size {
+            return Err(LgrepError::Config(format!(
+                "Chunk overlap ({}) must be smaller than chunk size ({})",
+                self.chunk_overlap, self.chunk_size
+            )));
+        }
+        if self.min_chunk_size > self.chunk_size {
+            return Err(LgrepError::Config(format!(
+                "Minimum chunk size ({}) must not exceed chunk size ({})",
+                self.min_chunk_size, self.chunk_size
+            )));
+        }
+        for (language, &size) in &self.language_chunk_sizes {
+            if self.chunk_overlap >= size {
+                return Err(LgrepError::Config(format!(
+                    "Chunk overlap ({}) must be smaller than the {} chunk size ({})",
+                    self.chunk_overlap, language, size
+                )));
+            }
+        }
+        if self.ignore_filename.is_empty() || self.ignore_filename.contains(['/', '\\']) {
+            return Err(LgrepError::Config(format!(
+                "Ignore filename must be a plain file name, not {:?}",
+                self.ignore_filename
+            )));
+        }
+        if self.workers == 0 {
+            return Err(LgrepError::Config(
+                "Worker count must be greater than 0".to_string(),
+            ));
+        }
+        if self.model.dimension() == 0 {
+            return Err(LgrepError::Config(format!(
+                "Custom model {} needs its embedding dimension (--model-dimension)",
+                self.model.model_name()
+            )));
+        }
+        if self.max_file_size == 0 {
+            return Err(LgrepError::Config(
+                "Maximum file size must be greater than 0".to_string(),
+            ));
+        }
+        if self.hnsw_connectivity == 0
+            || self.hnsw_expansion_add == 0
+            || self.hnsw_expansion_search == 0
+        {
+            return Err(LgrepError::Config(
+                "HNSW connectivity and expansion factors must be greater than 0".to_string(),
+            ));
+        }
+        self.ignore_matcher(&self.root_path)?;
+
         Ok(())
     }
 
     /// Load configuration from disk
-    pub fn load(index_dir: &PathBuf) -> Result<Self> {
+    pub fn load(index_dir: &Path) -> Result<Self> {
         let config_path = index_dir.join("config.json");
         if !config_path.exists() {
             return Err(LgrepError::NoIndex);
@@ -151,9 +708,306 @@ impl Config {
         let config: Config = serde_json::from_str(&json)?;
         Ok(config)
     }
+
+    /// Load the project settings in `<root>/.lgrep.toml`, if there is one
+    ///
+    /// The file may set `model` (and `model_dimension` for a custom model),
+    /// `chunk_size`, `chunk_overlap`, `min_chunk_size`, `max_file_size`
+    /// (bytes, or a size like `"2M"`) and `ignore`, a list of extra
+    /// gitignore-style patterns.
+    /// Settings it leaves out keep their defaults. Command-line flags are
+    /// applied on top of the returned config.
+    pub fn from_project_file(root: &Path) -> Result<Option<Config>> {
+        let path = root.join(PROJECT_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let text = std::fs::read_to_string(&path)?;
+        let project: ProjectFile = toml::from_str(&text)
+            .map_err(|e| LgrepError::Config(format!("Invalid {}: {}", path.display(), e)))?;
+
+        let mut config = Config::new(root.to_path_buf());
+        if let Some(model) = project.model {
+            config.model = model.parse()?;
+        }
+        if let Some(dimension) = project.model_dimension {
+            config.model = config.model.with_dimension(dimension);
+        }
+        if let Some(size) = project.chunk_size {
+            config.chunk_size = size;
+        }
+        if let Some(overlap) = project.chunk_overlap {
+            config.chunk_overlap = overlap;
+        }
+        if let Some(size) = project.min_chunk_size {
+            config.min_chunk_size = size;
+        }
+        if let Some(size) = project.max_file_size {
+            config.max_file_size = match size {
+                ProjectSize::Bytes(bytes) => bytes,
+                ProjectSize::Spec(spec) => crate::filter::parse_size(&spec)?,
+            };
+        }
+        config.ignore_patterns = project.ignore;
+
+        config.validate().map_err(|e| match e {
+            LgrepError::Config(message) => {
+                LgrepError::Config(format!("{}: {}", path.display(), message))
+            }
+            e => e,
+        })?;
+        Ok(Some(config))
+    }
+}
+
+/// Project settings file read from the root of an indexed directory
+pub const PROJECT_FILE: &str = ".lgrep.toml";
+
+/// Contents of a [`PROJECT_FILE`]
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProjectFile {
+    model: Option<String>,
+    model_dimension: Option<usize>,
+    chunk_size: Option<usize>,
+    chunk_overlap: Option<usize>,
+    min_chunk_size: Option<usize>,
+    max_file_size: Option<ProjectSize>,
+    #[serde(default)]
+    ignore: Vec<String>,
+}
+
+/// A size in a project file: a byte count or a string such as `"2M"`
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ProjectSize {
+    Bytes(u64),
+    Spec(String),
 }
 
-/// File extensions that should be indexed
+/// Builder for a [`Config`] that is validated on [`build`](Self::build)
+#[derive(Debug, Clone, Default)]
+pub struct ConfigBuilder {
+    config: Config,
+    index_dir: Option<PathBuf>,
+}
+
+impl ConfigBuilder {
+    /// Set the root directory (the index goes in `<root>/.lgrep` by default)
+    pub fn root_path(mut self, root_path: PathBuf) -> Self {
+        self.config.root_path = root_path;
+        self
+    }
+
+    /// Store the index somewhere other than `<root>/.lgrep`
+    pub fn index_dir(mut self, index_dir: PathBuf) -> Self {
+        self.index_dir = Some(index_dir);
+        self
+    }
+
+    /// Set the embedding model
+    pub fn model(mut self, model: EmbeddingModel) -> Self {
+        self.config.model = model;
+        self
+    }
+
+    /// Set the chunk size in characters
+    pub fn chunk_size(mut self, size: usize) -> Self {
+        self.config.chunk_size = size;
+        self
+    }
+
+    /// Set the overlap between chunks in characters
+    pub fn chunk_overlap(mut self, overlap: usize) -> Self {
+        self.config.chunk_overlap = overlap;
+        self
+    }
+
+    /// Set the smallest final chunk kept on its own, in characters
+    pub fn min_chunk_size(mut self, size: usize) -> Self {
+        self.config.min_chunk_size = size;
+        self
+    }
+
+    /// Set the default chunking strategy
+    pub fn chunk_strategy(mut self, strategy: ChunkStrategy) -> Self {
+        self.config.chunk_strategy = strategy;
+        self
+    }
+
+    /// Override the chunking strategy for one language
+    pub fn language_strategy(mut self, language: &str, strategy: ChunkStrategy) -> Self {
+        self.config
+            .language_strategies
+            .insert(language.to_string(), strategy);
+        self
+    }
+
+    /// Override the chunk size for one language
+    pub fn language_chunk_size(mut self, language: &str, size: usize) -> Self {
+        self.config
+            .language_chunk_sizes
+            .insert(language.to_string(), size);
+        self
+    }
+
+    /// Set the maximum size of an indexed file in bytes
+    pub fn max_file_size(mut self, bytes: u64) -> Self {
+        self.config.max_file_size = bytes;
+        self
+    }
+
+    /// Set the number of parallel workers
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.config.workers = workers;
+        self
+    }
+
+    /// Index several workspace roots into this config's index
+    pub fn root_paths(mut self, roots: Vec<PathBuf>) -> Self {
+        self.config.root_paths = roots;
+        self
+    }
+
+    /// Set the file names to skip (replaces the default lock-file list)
+    pub fn ignored_filenames(mut self, names: Vec<String>) -> Self {
+        self.config.ignored_filenames = names;
+        self
+    }
+
+    /// Skip paths matching these gitignore-style patterns
+    pub fn ignore_patterns(mut self, patterns: Vec<String>) -> Self {
+        self.config.ignore_patterns = patterns;
+        self
+    }
+
+    /// Read ignore rules from files called `name` instead of `.lgrepignore`
+    pub fn ignore_filename(mut self, name: &str) -> Self {
+        self.config.ignore_filename = name.to_string();
+        self
+    }
+
+    /// Index files that ignore files would otherwise exclude
+    pub fn no_ignore(mut self, no_ignore: bool) -> Self {
+        self.config.no_ignore = no_ignore;
+        self
+    }
+
+    /// Index hidden files and directories
+    pub fn include_hidden(mut self, include_hidden: bool) -> Self {
+        self.config.include_hidden = include_hidden;
+        self
+    }
+
+    /// Honor `.gitattributes` linguist-generated/vendored markers
+    pub fn respect_gitattributes(mut self, respect: bool) -> Self {
+        self.config.respect_gitattributes = respect;
+        self
+    }
+
+    /// Store per-file embeddings for file-level search
+    pub fn file_embeddings(mut self, enabled: bool) -> Self {
+        self.config.file_embeddings = enabled;
+        self
+    }
+
+    /// Index the files inside an archive instead of walking the root
+    pub fn archive(mut self, archive: PathBuf) -> Self {
+        self.config.archive = Some(archive);
+        self
+    }
+
+    /// Skip docs, config and data files
+    pub fn code_only(mut self, code_only: bool) -> Self {
+        self.config.code_only = code_only;
+        self
+    }
+
+    /// Treat whitespace-only edits as no change
+    pub fn ignore_whitespace_changes(mut self, ignore: bool) -> Self {
+        self.config.ignore_whitespace_changes = ignore;
+        self
+    }
+
+    /// Stream files larger than this many bytes
+    pub fn stream_threshold(mut self, bytes: u64) -> Self {
+        self.config.stream_threshold = bytes;
+        self
+    }
+
+    /// HNSW graph degree (`M`)
+    pub fn hnsw_connectivity(mut self, connectivity: usize) -> Self {
+        self.config.hnsw_connectivity = connectivity;
+        self
+    }
+
+    /// HNSW candidates considered while inserting
+    pub fn hnsw_expansion_add(mut self, expansion: usize) -> Self {
+        self.config.hnsw_expansion_add = expansion;
+        self
+    }
+
+    /// HNSW candidates considered while searching
+    pub fn hnsw_expansion_search(mut self, expansion: usize) -> Self {
+        self.config.hnsw_expansion_search = expansion;
+        self
+    }
+
+    /// ONNX Runtime execution provider for embeddings
+    pub fn execution_provider(mut self, provider: ExecutionProvider) -> Self {
+        self.config.execution_provider = provider;
+        self
+    }
+
+    /// Validate the settings and build the config
+    pub fn build(self) -> Result<Config> {
+        let mut config = self.config;
+        config.index_dir = self
+            .index_dir
+            .unwrap_or_else(|| config.root_path.join(".lgrep"));
+
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// Label for a root directory: its name, with `-2`, `-3`, ... appended when
+/// an earlier root in `taken` has the same name
+pub(crate) fn root_label(path: &Path, taken: &mut HashSet<String>) -> String {
+    let base = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "root".to_string());
+    let mut label = base.clone();
+    let mut n = 2;
+    while !taken.insert(label.clone()) {
+        label = format!("{}-{}", base, n);
+        n += 1;
+    }
+    label
+}
+
+/// A directory indexed into a (possibly shared) index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceRoot {
+    /// Prefix for paths stored from this root (empty for a single root)
+    pub label: String,
+    /// Canonical path of the root directory
+    pub path: PathBuf,
+}
+
+impl WorkspaceRoot {
+    /// Path as stored in chunk metadata for a path relative to this root
+    pub fn stored_path(&self, relative: &str) -> String {
+        if self.label.is_empty() {
+            relative.to_string()
+        } else {
+            format!("{}/{}", self.label, relative)
+        }
+    }
+}
+
+/// File extensions indexed by default (see [`Config::extensions`])
 pub const CODE_EXTENSIONS: &[&str] = &[
     // Rust
     "rs",
@@ -193,22 +1047,185 @@ pub const CODE_EXTENSIONS: &[&str] = &[
     "xml", "csv",
 ];
 
-/// Check if a file should be indexed based on its extension
-pub fn should_index_file(path: &std::path::Path) -> bool {
+/// Entries of `CODE_EXTENSIONS` that are docs, config or data rather than code
+///
+/// Skipped when indexing with `code_only`.
+pub const NON_CODE_EXTENSIONS: &[&str] = &[
+    // Config
+    "json", "yaml", "yml", "toml", "ini", "cfg", "conf",
+    // Documentation
+    "md", "mdx", "rst", "txt",
+    // Data
+    "xml", "csv",
+];
+
+/// Generated and lock files that are large, noisy, and rarely worth searching
+pub const DEFAULT_IGNORED_FILENAMES: &[&str] = &[
+    // JavaScript
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lockb",
+    "deno.lock",
+    // Rust
+    "Cargo.lock",
+    // Python
+    "poetry.lock",
+    "Pipfile.lock",
+    "pdm.lock",
+    "uv.lock",
+    // Ruby / PHP / Elixir
+    "Gemfile.lock",
+    "composer.lock",
+    "mix.lock",
+    // Go
+    "go.sum",
+    // .NET / Swift / Nix
+    "packages.lock.json",
+    "Package.resolved",
+    "Podfile.lock",
+    "flake.lock",
+];
+
+/// Check if a file should be indexed under `config`
+pub fn should_index_file(path: &std::path::Path, config: &Config) -> bool {
+    config.should_index(path)
+}
+
+/// Lowercased extension of a path, if it has one
+fn extension(path: &std::path::Path) -> Option<String> {
     path.extension()
         .and_then(|ext| ext.to_str())
-        .map(|ext| CODE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .map(|ext| ext.to_lowercase())
+}
+
+/// Check if a file has one of the given extensions
+fn has_extension(path: &std::path::Path, extensions: &[String]) -> bool {
+    extension(path).is_some_and(|ext| extensions.contains(&ext))
+}
+
+/// Check if a file has a docs, config or data extension
+fn has_non_code_extension(path: &std::path::Path) -> bool {
+    extension(path).is_some_and(|ext| NON_CODE_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// Check if a file's base name is in the given denylist
+fn is_ignored_filename(path: &std::path::Path, names: &[String]) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .map(|n| names.iter().any(|name| name == n))
         .unwrap_or(false)
 }
 
+/// Check if a file looks like an editor swap, backup, or atomic-save temp file
+///
+/// Covers vim (`.swp`/`.swo`, `4913`, `file~`), emacs (`#file#`, `.#file`),
+/// JetBrains (`___jb_tmp___`/`___jb_old___`) and generic `.tmp` files.
+pub fn is_editor_temp_file(path: &std::path::Path) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+
+    name == "4913"
+        || name.ends_with('~')
+        || (name.starts_with('#') && name.ends_with('#'))
+        || name.starts_with(".#")
+        || name.ends_with("___jb_tmp___")
+        || name.ends_with("___jb_old___")
+        || EDITOR_TEMP_EXTENSIONS
+            .iter()
+            .any(|ext| name.to_lowercase().ends_with(ext))
+}
+
+/// File name suffixes used by editors for swap and temp files
+const EDITOR_TEMP_EXTENSIONS: &[&str] = &[".swp", ".swo", ".swx", ".tmp", ".temp", ".bak"];
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_builder_builds_valid_config() {
+        let config = Config::builder()
+            .root_path(PathBuf::from("/repo"))
+            .chunk_size(256)
+            .chunk_overlap(32)
+            .workers(2)
+            .build()
+            .unwrap();
+
+        assert_eq!(config.chunk_size, 256);
+        assert_eq!(config.chunk_overlap, 32);
+        assert_eq!(config.workers, 2);
+        assert_eq!(config.index_dir, PathBuf::from("/repo/.lgrep"));
+    }
+
+    #[test]
+    fn test_builder_rejects_overlap_not_below_chunk_size() {
+        let result = Config::builder().chunk_size(64).chunk_overlap(64).build();
+        assert!(matches!(result, Err(LgrepError::Config(_))));
+    }
+
+    #[test]
+    fn test_builder_rejects_language_size_not_above_overlap() {
+        let result = Config::builder()
+            .chunk_overlap(64)
+            .language_chunk_size("markdown", 32)
+            .build();
+        assert!(matches!(result, Err(LgrepError::Config(_))));
+    }
+
+    #[test]
+    fn test_builder_rejects_zero_chunk_size() {
+        let result = Config::builder().chunk_size(0).chunk_overlap(0).build();
+        assert!(matches!(result, Err(LgrepError::Config(_))));
+    }
+
+    #[test]
+    fn test_builder_rejects_min_chunk_size_above_chunk_size() {
+        let result = Config::builder()
+            .chunk_size(128)
+            .min_chunk_size(200)
+            .build();
+        assert!(matches!(result, Err(LgrepError::Config(_))));
+        let config = Config::builder().min_chunk_size(100).build().unwrap();
+        assert_eq!(config.min_chunk_size, 100);
+    }
+
+    #[test]
+    fn test_builder_rejects_zero_workers() {
+        let result = Config::builder().workers(0).build();
+        assert!(matches!(result, Err(LgrepError::Config(_))));
+    }
+
+    #[test]
+    fn test_builder_rejects_zero_max_file_size() {
+        let result = Config::builder().max_file_size(0).build();
+        assert!(matches!(result, Err(LgrepError::Config(_))));
+    }
+
+    #[test]
+    fn test_builder_rejects_zero_hnsw_parameters() {
+        let result = Config::builder().hnsw_connectivity(0).build();
+        assert!(matches!(result, Err(LgrepError::Config(_))));
+
+        let config = Config::builder()
+            .hnsw_connectivity(32)
+            .hnsw_expansion_add(256)
+            .hnsw_expansion_search(128)
+            .build()
+            .unwrap();
+        assert_eq!(config.hnsw_connectivity, 32);
+        assert_eq!(config.hnsw_expansion_add, 256);
+        assert_eq!(config.hnsw_expansion_search, 128);
+    }
+
     #[test]
     fn test_model_parsing() {
         let model: EmbeddingModel = "minilm".parse().unwrap();
         assert_eq!(model.dimension(), 384);
+        assert_eq!(model.download_size_mb(), Some(30));
 
         let model: EmbeddingModel = "nomic".parse().unwrap();
         assert_eq!(model.dimension(), 768);
@@ -216,15 +1233,177 @@ mod tests {
         assert!("invalid".parse::<EmbeddingModel>().is_err());
     }
 
+    #[test]
+    fn test_custom_model_parsing() {
+        let model: EmbeddingModel = "custom:/models/code-embed".parse().unwrap();
+        assert_eq!(model.model_name(), "/models/code-embed");
+        assert_eq!(model.dimension(), 0);
+        assert_eq!(model.download_size_mb(), None);
+        assert!("custom:".parse::<EmbeddingModel>().is_err());
+
+        // The dimension is required before the config is usable
+        let config = Config::default().with_model(model.clone());
+        assert!(config.validate().is_err());
+        let config = config.with_model(model.with_dimension(768));
+        config.validate().unwrap();
+        assert_eq!(config.model.dimension(), 768);
+        assert_eq!(
+            EmbeddingModel::default().with_dimension(768).dimension(),
+            384
+        );
+    }
+
+    #[test]
+    fn test_model_prefixes() {
+        assert!(!EmbeddingModel::AllMiniLmL6V2.uses_prefixes());
+        assert_eq!(
+            EmbeddingModel::MultilingualE5Small.query_prefix(),
+            "query: "
+        );
+        assert_eq!(
+            EmbeddingModel::MultilingualE5Small.document_prefix(),
+            "passage: "
+        );
+        assert_eq!(
+            EmbeddingModel::NomicEmbedTextV15.document_prefix(),
+            "search_document: "
+        );
+        // BGE only instructs the query side
+        assert!(EmbeddingModel::BgeSmallEnV15.uses_prefixes());
+        assert_eq!(EmbeddingModel::BgeSmallEnV15.document_prefix(), "");
+    }
+
     #[test]
     fn test_should_index_file() {
         use std::path::Path;
 
-        assert!(should_index_file(Path::new("main.rs")));
-        assert!(should_index_file(Path::new("app.py")));
-        assert!(should_index_file(Path::new("index.tsx")));
-        assert!(!should_index_file(Path::new("image.png")));
-        assert!(!should_index_file(Path::new("binary.exe")));
+        let config = Config::default();
+        assert!(should_index_file(Path::new("main.rs"), &config));
+        assert!(should_index_file(Path::new("app.py"), &config));
+        assert!(should_index_file(Path::new("index.tsx"), &config));
+        assert!(!should_index_file(Path::new("image.png"), &config));
+        assert!(!should_index_file(Path::new("binary.exe"), &config));
+    }
+
+    #[test]
+    fn test_roots_dedupes_overlapping() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().canonicalize().unwrap();
+        for sub in ["a/nested", "b", "other/b"] {
+            std::fs::create_dir_all(base.join(sub)).unwrap();
+        }
+
+        let config = Config::new(base.clone()).with_root_paths(vec![
+            base.join("a/nested"),
+            base.join("a"),
+            base.join("b"),
+            base.join("other/b"),
+            base.join("b"),
+        ]);
+        let roots = config.roots().unwrap();
+
+        let labels: Vec<&str> = roots.iter().map(|r| r.label.as_str()).collect();
+        assert_eq!(labels, vec!["a", "b", "b-2"]);
+        assert_eq!(roots[0].path, base.join("a"));
+        assert_eq!(roots[1].stored_path("src/lib.rs"), "b/src/lib.rs");
+        assert_eq!(config.root_of("b-2/src/lib.rs"), Some("b-2".to_string()));
+
+        let single = Config::new(base.clone()).roots().unwrap();
+        assert_eq!(single[0].stored_path("src/lib.rs"), "src/lib.rs");
+    }
+
+    #[test]
+    fn test_code_only_skips_docs_and_config() {
+        use std::path::Path;
+
+        let config = Config::default().with_code_only(true);
+        assert!(config.should_index(Path::new("src/main.rs")));
+        assert!(!config.should_index(Path::new("README.md")));
+        assert!(!config.should_index(Path::new("config.yaml")));
+
+        let config = Config::default();
+        assert!(config.should_index(Path::new("README.md")));
+        assert!(config.should_index(Path::new("config.yaml")));
+    }
+
+    #[test]
+    fn test_extension_lists_and_all_text() {
+        use std::path::Path;
+
+        let config = Config::default();
+        assert!(!config.should_index(Path::new("api.proto")));
+        assert!(config.should_index(Path::new("README.md")));
+
+        let config = config.with_extensions(&[".proto".to_string()], &["MD".to_string()]);
+        assert!(config.should_index(Path::new("api.proto")));
+        assert!(!config.should_index(Path::new("README.md")));
+
+        let config = Config::default().with_all_text(true);
+        assert!(config.should_index(Path::new("Makefile")));
+        assert!(config.should_index(Path::new("schema.graphql")));
+        assert!(!config.should_index(Path::new("Cargo.lock")));
+
+        let config = config.with_code_only(true);
+        assert!(!config.should_index(Path::new("notes.txt")));
+    }
+
+    #[test]
+    fn test_lock_files_ignored_by_default() {
+        use std::path::Path;
+
+        let config = Config::default();
+        assert!(!should_index_file(
+            Path::new("web/package-lock.json"),
+            &config
+        ));
+        assert!(!should_index_file(Path::new("pnpm-lock.yaml"), &config));
+        assert!(should_index_file(Path::new("web/package.json"), &config));
+        assert!(!config.should_index(Path::new("web/package-lock.json")));
+        assert!(config.should_index(Path::new("web/package.json")));
+
+        // Overriding the list brings lock files back
+        let config = Config::default().with_ignored_filenames(vec![]);
+        assert!(config.should_index(Path::new("web/package-lock.json")));
+    }
+
+    #[test]
+    fn test_is_editor_temp_file() {
+        use std::path::Path;
+
+        assert!(is_editor_temp_file(Path::new("src/.main.rs.swp")));
+        assert!(is_editor_temp_file(Path::new("src/4913")));
+        assert!(is_editor_temp_file(Path::new("src/main.rs~")));
+        assert!(is_editor_temp_file(Path::new("src/#main.rs#")));
+        assert!(is_editor_temp_file(Path::new("src/.#main.rs")));
+        assert!(is_editor_temp_file(Path::new("src/main.rs___jb_tmp___")));
+        assert!(!is_editor_temp_file(Path::new("src/main.rs")));
+    }
+
+    #[test]
+    fn test_chunk_strategy_parsing() {
+        assert_eq!(
+            "paragraphs".parse::<ChunkStrategy>().unwrap(),
+            ChunkStrategy::Paragraphs
+        );
+        assert_eq!(
+            "fixed".parse::<ChunkStrategy>().unwrap(),
+            ChunkStrategy::FixedChars
+        );
+        assert!("words".parse::<ChunkStrategy>().is_err());
+    }
+
+    #[test]
+    fn test_execution_provider_not_saved() {
+        assert_eq!(
+            "CUDA".parse::<ExecutionProvider>().unwrap(),
+            ExecutionProvider::Cuda
+        );
+        assert!("tpu".parse::<ExecutionProvider>().is_err());
+
+        let config = Config::default().with_execution_provider(ExecutionProvider::CoreMl);
+        let json = serde_json::to_string(&config).unwrap();
+        let loaded: Config = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.execution_provider, ExecutionProvider::Cpu);
     }
 
     #[test]
@@ -234,4 +1413,38 @@ mod tests {
         assert_eq!(config.chunk_overlap, 64);
         assert_eq!(config.max_file_size, 10 * 1024 * 1024);
     }
+
+    #[test]
+    fn test_from_project_file() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(Config::from_project_file(dir.path()).unwrap().is_none());
+
+        std::fs::write(
+            dir.path().join(PROJECT_FILE),
+            "model = \"bge\"\nchunk_size = 256\nchunk_overlap = 32\n\
+             max_file_size = \"2M\"\nignore = [\"generated/\", \"*.pb.go\"]\n",
+        )
+        .unwrap();
+        let config = Config::from_project_file(dir.path()).unwrap().unwrap();
+        assert!(matches!(config.model, EmbeddingModel::BgeSmallEnV15));
+        assert_eq!(config.chunk_size, 256);
+        assert_eq!(config.chunk_overlap, 32);
+        assert_eq!(config.max_file_size, 2 * 1024 * 1024);
+        assert_eq!(config.index_dir, dir.path().join(".lgrep"));
+
+        let matcher = config.ignore_matcher(dir.path()).unwrap();
+        let ignored = |path: &str| {
+            matcher
+                .matched_path_or_any_parents(dir.path().join(path), false)
+                .is_ignore()
+        };
+        assert!(ignored("generated/api.rs"));
+        assert!(ignored("src/api.pb.go"));
+        assert!(!ignored("src/api.go"));
+
+        std::fs::write(dir.path().join(PROJECT_FILE), "chunk_sise = 256\n").unwrap();
+        assert!(Config::from_project_file(dir.path()).is_err());
+        std::fs::write(dir.path().join(PROJECT_FILE), "chunk_overlap = 600\n").unwrap();
+        assert!(Config::from_project_file(dir.path()).is_err());
+    }
 }
@@ -0,0 +1,116 @@
+//! Opening search results in an editor
+//!
+//! Editors disagree on how to be told a line: terminal editors take
+//! `+LINE FILE`, VS Code and its forks `--goto FILE:LINE`, and a few GUI
+//! editors `FILE:LINE`. The editor is taken from `--editor`, `$VISUAL` or
+//! `$EDITOR`, and may include its own arguments (e.g. `code -w`).
+
+use crate::config::Config;
+use crate::error::{LgrepError, Result};
+use crate::index::SearchResult;
+use crate::searcher::source_path;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Editor to use: the override, then `$VISUAL`, then `$EDITOR`
+pub fn resolve_editor(editor: Option<&str>) -> Option<String> {
+    editor
+        .map(String::from)
+        .or_else(|| std::env::var("VISUAL").ok())
+        .or_else(|| std::env::var("EDITOR").ok())
+        .filter(|editor| !editor.trim().is_empty())
+}
+
+/// Program and arguments that open `file` at `line` in `editor`
+pub fn editor_command(editor: &str, file: &Path, line: usize) -> (String, Vec<String>) {
+    let mut words = editor.split_whitespace().map(String::from);
+    let program = words.next().unwrap_or_default();
+    let mut args: Vec<String> = words.collect();
+
+    let name = Path::new(&program)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let file = file.to_string_lossy();
+    match name.as_str() {
+        "code" | "code-insiders" | "codium" | "vscodium" | "cursor" | "windsurf" => {
+            args.push("--goto".to_string());
+            args.push(format!("{}:{}", file, line));
+        }
+        "subl" | "sublime_text" | "zed" | "hx" | "helix" | "mate" => {
+            args.push(format!("{}:{}", file, line));
+        }
+        "idea" | "pycharm" | "webstorm" | "goland" | "clion" | "rustrover" => {
+            args.push("--line".to_string());
+            args.push(line.to_string());
+            args.push(file.to_string());
+        }
+        // vi, vim, nvim, emacs, nano, micro, kak and most others
+        _ => {
+            args.push(format!("+{}", line));
+            args.push(file.to_string());
+        }
+    }
+
+    (program, args)
+}
+
+/// File on disk for a result, resolved against the index's roots
+pub fn result_file(config: &Config, result: &SearchResult) -> Result<PathBuf> {
+    if config.archive.is_some() {
+        return Err(LgrepError::InvalidPath(format!(
+            "{} is inside an archive and can't be opened",
+            result.chunk.file_path
+        )));
+    }
+    source_path(&config.roots()?, &result.chunk.file_path).ok_or_else(|| {
+        LgrepError::InvalidPath(format!("No indexed root holds {}", result.chunk.file_path))
+    })
+}
+
+/// Open `file` at `line` in `editor` and wait for it to exit
+pub fn open_in_editor(editor: &str, file: &Path, line: usize) -> Result<()> {
+    let (program, args) = editor_command(editor, file, line);
+    let status = Command::new(&program)
+        .args(&args)
+        .status()
+        .map_err(|e| LgrepError::Config(format!("Could not start editor {:?}: {}", program, e)))?;
+    if !status.success() {
+        return Err(LgrepError::Config(format!(
+            "Editor {:?} exited with {}",
+            program, status
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(editor: &str) -> Vec<String> {
+        let (program, args) = editor_command(editor, Path::new("/repo/src/db.rs"), 42);
+        std::iter::once(program).chain(args).collect()
+    }
+
+    #[test]
+    fn test_editor_conventions() {
+        assert_eq!(command("vim"), ["vim", "+42", "/repo/src/db.rs"]);
+        assert_eq!(
+            command("/usr/bin/nvim"),
+            ["/usr/bin/nvim", "+42", "/repo/src/db.rs"]
+        );
+        assert_eq!(
+            command("code -w"),
+            ["code", "-w", "--goto", "/repo/src/db.rs:42"]
+        );
+        assert_eq!(command("subl"), ["subl", "/repo/src/db.rs:42"]);
+        assert_eq!(command("idea"), ["idea", "--line", "42", "/repo/src/db.rs"]);
+    }
+
+    #[test]
+    fn test_override_wins() {
+        assert_eq!(resolve_editor(Some("hx")), Some("hx".to_string()));
+        assert_eq!(resolve_editor(Some("  ")), None);
+    }
+}
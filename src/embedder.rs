@@ -3,48 +3,155 @@
 //! Generates embeddings entirely locally - no API calls required.
 //! Models are downloaded once and cached in ~/.cache/huggingface/
 
-use crate::config::EmbeddingModel;
+use crate::config::{EmbeddingModel, ExecutionProvider};
 use crate::error::{LgrepError, Result};
-use fastembed::{EmbeddingModel as FastEmbedModel, InitOptions, TextEmbedding};
+use fastembed::{
+    EmbeddingModel as FastEmbedModel, ExecutionProviderDispatch, InitOptions,
+    InitOptionsUserDefined, Pooling, TextEmbedding, TokenizerFiles, UserDefinedEmbeddingModel,
+};
+use ort::execution_providers::{CUDAExecutionProvider, CoreMLExecutionProvider};
+use std::path::PathBuf;
 use std::sync::Arc;
-use tracing::info;
+use tracing::{info, warn};
+
+/// Anything that can turn text into embedding vectors
+///
+/// Implemented by [`Embedder`]; the searcher is written against this trait so
+/// alternative backends (or test stubs) can be plugged in.
+pub trait Embed: Send + Sync {
+    /// Get the embedding dimension
+    fn dimension(&self) -> usize;
+
+    /// Embed a single text string
+    fn embed_one(&self, text: &str) -> Result<Vec<f32>>;
+
+    /// Embed multiple texts in a single batch
+    fn embed_batch(&self, texts: Vec<&str>) -> Result<Vec<Vec<f32>>>;
+
+    /// Revision (commit hash) of the model files, if known
+    fn revision(&self) -> Option<&str> {
+        None
+    }
+}
 
 /// Local embedder using fastembed with ONNX runtime
 pub struct Embedder {
     model: Arc<TextEmbedding>,
     dimension: usize,
+    revision: Option<String>,
 }
 
 impl Embedder {
-    /// Create a new embedder with the specified model
+    /// Create a new embedder with the specified model, run by `provider`
     ///
     /// On first use, downloads the model from HuggingFace (~30-470MB).
-    /// Subsequent uses load from cache instantly.
-    pub fn new(model_config: &EmbeddingModel) -> Result<Self> {
-        info!("Loading embedding model: {:?}", model_config);
-
-        let fastembed_model = match model_config {
-            EmbeddingModel::AllMiniLmL6V2 => FastEmbedModel::AllMiniLML6V2,
-            EmbeddingModel::BgeSmallEnV15 => FastEmbedModel::BGESmallENV15,
-            EmbeddingModel::NomicEmbedTextV15 => FastEmbedModel::NomicEmbedTextV15,
-            EmbeddingModel::MultilingualE5Small => FastEmbedModel::MultilingualE5Small,
+    /// Subsequent uses load from cache instantly. Custom models are read
+    /// from their directory (see [`EmbeddingModel::Custom`]). If `provider`
+    /// can't be initialized (no GPU, or lgrep built without it), logs a
+    /// warning and runs on the CPU instead.
+    pub fn new(model_config: &EmbeddingModel, provider: ExecutionProvider) -> Result<Self> {
+        info!(
+            "Loading embedding model: {:?} ({:?})",
+            model_config, provider
+        );
+
+        // A failure before the files are in the cache is a failed download
+        let downloading = !Self::is_cached(model_config);
+        let load = |providers: Vec<ExecutionProviderDispatch>| -> Result<TextEmbedding> {
+            match fastembed_model(model_config) {
+                Some(model) => TextEmbedding::try_new(
+                    InitOptions::new(model)
+                        .with_show_download_progress(true)
+                        .with_execution_providers(providers),
+                )
+                .map_err(|e| {
+                    if downloading && !Self::is_cached(model_config) {
+                        LgrepError::ModelDownload(format!("{}: {}", model_config.model_name(), e))
+                    } else {
+                        LgrepError::Embedding(e.to_string())
+                    }
+                }),
+                None => TextEmbedding::try_new_from_user_defined(
+                    user_defined_model(model_config)?,
+                    InitOptionsUserDefined::new().with_execution_providers(providers),
+                )
+                .map_err(|e| LgrepError::Embedding(e.to_string())),
+            }
         };
+        let model = match execution_provider_dispatch(provider) {
+            Some(dispatch) => load(vec![dispatch.error_on_failure()]).or_else(|e| {
+                warn!(
+                    "{:?} execution provider unavailable, using CPU: {}",
+                    provider, e
+                );
+                load(Vec::new())
+            }),
+            None => load(Vec::new()),
+        }?;
 
-        let model = TextEmbedding::try_new(
-            InitOptions::new(fastembed_model).with_show_download_progress(true),
-        )
-        .map_err(|e| LgrepError::Embedding(e.to_string()))?;
+        if downloading {
+            if let Some(dir) = Self::cache_dir(model_config) {
+                info!("Model downloaded to {}", dir.display());
+            }
+        }
 
         let dimension = model_config.dimension();
+        let revision = Self::cached_revision(model_config);
 
-        info!("Model loaded successfully (dimension: {})", dimension);
+        info!(
+            "Model loaded successfully (dimension: {}, revision: {})",
+            dimension,
+            revision.as_deref().unwrap_or("unknown")
+        );
 
         Ok(Self {
             model: Arc::new(model),
             dimension,
+            revision,
         })
     }
 
+    /// Check whether a model has already been downloaded to the local cache
+    /// (for custom models, whether its ONNX file exists)
+    pub fn is_cached(model_config: &EmbeddingModel) -> bool {
+        if let EmbeddingModel::Custom { path, .. } = model_config {
+            return path.join(CUSTOM_MODEL_FILE).is_file();
+        }
+        model_cache_dir(model_config)
+            .map(|dir| dir.join("snapshots").is_dir())
+            .unwrap_or(false)
+    }
+
+    /// Directory the model's files are (or will be) cached in; for custom
+    /// models, the model directory itself
+    pub fn cache_dir(model_config: &EmbeddingModel) -> Option<PathBuf> {
+        if let EmbeddingModel::Custom { path, .. } = model_config {
+            return Some(path.clone());
+        }
+        model_cache_dir(model_config)
+    }
+
+    /// HuggingFace commit hash of the cached model files, if downloaded
+    pub fn cached_revision(model_config: &EmbeddingModel) -> Option<String> {
+        let dir = model_cache_dir(model_config)?;
+
+        if let Ok(main) = std::fs::read_to_string(dir.join("refs").join("main")) {
+            return Some(main.trim().to_string());
+        }
+
+        // No ref recorded: fall back to the snapshot directory name
+        std::fs::read_dir(dir.join("snapshots"))
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .find(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+    }
+
+    /// Get the revision of the loaded model, if known
+    pub fn revision(&self) -> Option<&str> {
+        self.revision.as_deref()
+    }
+
     /// Get the embedding dimension
     pub fn dimension(&self) -> usize {
         self.dimension
@@ -52,12 +159,7 @@ impl Embedder {
 
     /// Embed a single text string
     pub fn embed_one(&self, text: &str) -> Result<Vec<f32>> {
-        let embeddings = self
-            .model
-            .embed(vec![text], None)
-            .map_err(|e| LgrepError::Embedding(e.to_string()))?;
-
-        embeddings
+        self.embed_batch(vec![text])?
             .into_iter()
             .next()
             .ok_or_else(|| LgrepError::Embedding("No embedding returned".to_string()))
@@ -69,9 +171,22 @@ impl Embedder {
             return Ok(vec![]);
         }
 
-        self.model
+        let embeddings = self
+            .model
             .embed(texts, None)
-            .map_err(|e| LgrepError::Embedding(e.to_string()))
+            .map_err(|e| LgrepError::Embedding(e.to_string()))?;
+
+        // Custom models only declare their dimension, so hold them to it
+        if let Some(embedding) = embeddings.first() {
+            if embedding.len() != self.dimension {
+                return Err(LgrepError::DimensionMismatch {
+                    expected: self.dimension,
+                    got: embedding.len(),
+                });
+            }
+        }
+
+        Ok(embeddings)
     }
 
     /// Embed texts with progress callback for large batches
@@ -100,6 +215,89 @@ impl Embedder {
     }
 }
 
+/// ONNX file a custom model directory must contain
+const CUSTOM_MODEL_FILE: &str = "model.onnx";
+
+/// Directory a model is cached in (HuggingFace hub layout)
+fn model_cache_dir(model_config: &EmbeddingModel) -> Option<PathBuf> {
+    let model = fastembed_model(model_config)?;
+    let info = TextEmbedding::get_model_info(&model).ok()?;
+
+    // Same lookup order as fastembed: HF_HOME, then its own cache dir
+    let cache_dir = std::env::var("HF_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(fastembed::get_cache_dir()));
+
+    Some(cache_dir.join(format!("models--{}", info.model_code.replace('/', "--"))))
+}
+
+/// Map our model names onto fastembed's (`None` for custom models)
+fn fastembed_model(model_config: &EmbeddingModel) -> Option<FastEmbedModel> {
+    match model_config {
+        EmbeddingModel::AllMiniLmL6V2 => Some(FastEmbedModel::AllMiniLML6V2),
+        EmbeddingModel::BgeSmallEnV15 => Some(FastEmbedModel::BGESmallENV15),
+        EmbeddingModel::NomicEmbedTextV15 => Some(FastEmbedModel::NomicEmbedTextV15),
+        EmbeddingModel::MultilingualE5Small => Some(FastEmbedModel::MultilingualE5Small),
+        EmbeddingModel::Custom { .. } => None,
+    }
+}
+
+/// Read a custom model's ONNX file and tokenizer files from its directory
+///
+/// Embeddings are mean-pooled over the token outputs, as in
+/// sentence-transformers models.
+fn user_defined_model(model_config: &EmbeddingModel) -> Result<UserDefinedEmbeddingModel> {
+    let EmbeddingModel::Custom { path, .. } = model_config else {
+        return Err(LgrepError::Embedding(format!(
+            "{} is not a custom model",
+            model_config.model_name()
+        )));
+    };
+    let read = |name: &str| {
+        let file = path.join(name);
+        std::fs::read(&file)
+            .map_err(|e| LgrepError::Embedding(format!("Cannot read model file {:?}: {}", file, e)))
+    };
+
+    let tokenizer_files = TokenizerFiles {
+        tokenizer_file: read("tokenizer.json")?,
+        config_file: read("config.json")?,
+        special_tokens_map_file: read("special_tokens_map.json")?,
+        tokenizer_config_file: read("tokenizer_config.json")?,
+    };
+    Ok(
+        UserDefinedEmbeddingModel::new(read(CUSTOM_MODEL_FILE)?, tokenizer_files)
+            .with_pooling(Pooling::Mean),
+    )
+}
+
+/// ONNX Runtime registration for a non-CPU provider (`None` for the CPU)
+fn execution_provider_dispatch(provider: ExecutionProvider) -> Option<ExecutionProviderDispatch> {
+    match provider {
+        ExecutionProvider::Cpu => None,
+        ExecutionProvider::Cuda => Some(CUDAExecutionProvider::default().build()),
+        ExecutionProvider::CoreMl => Some(CoreMLExecutionProvider::default().build()),
+    }
+}
+
+impl Embed for Embedder {
+    fn dimension(&self) -> usize {
+        Embedder::dimension(self)
+    }
+
+    fn embed_one(&self, text: &str) -> Result<Vec<f32>> {
+        Embedder::embed_one(self, text)
+    }
+
+    fn embed_batch(&self, texts: Vec<&str>) -> Result<Vec<Vec<f32>>> {
+        Embedder::embed_batch(self, texts)
+    }
+
+    fn revision(&self) -> Option<&str> {
+        Embedder::revision(self)
+    }
+}
+
 /// Normalize embedding vector to unit length (for cosine similarity)
 #[allow(dead_code)]
 pub fn normalize(embedding: &mut [f32]) {
@@ -112,15 +310,78 @@ pub fn normalize(embedding: &mut [f32]) {
 }
 
 /// Compute cosine similarity between two normalized vectors
-#[allow(dead_code)]
 pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
 }
 
+/// Deterministic bag-of-words embedder for tests (no model download)
+#[cfg(test)]
+pub(crate) struct StubEmbedder {
+    pub dimension: usize,
+}
+
+#[cfg(test)]
+impl Embed for StubEmbedder {
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn embed_one(&self, text: &str) -> Result<Vec<f32>> {
+        use std::hash::{Hash, Hasher};
+
+        // Small constant component keeps empty input from being a zero vector
+        let mut embedding = vec![0.0; self.dimension];
+        embedding[0] = 0.01;
+
+        for word in text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+        {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            word.to_lowercase().hash(&mut hasher);
+            embedding[1 + (hasher.finish() as usize) % (self.dimension - 1)] += 1.0;
+        }
+
+        normalize(&mut embedding);
+        Ok(embedding)
+    }
+
+    fn embed_batch(&self, texts: Vec<&str>) -> Result<Vec<Vec<f32>>> {
+        texts.into_iter().map(|t| self.embed_one(t)).collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_custom_model_needs_its_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let model = EmbeddingModel::Custom {
+            path: dir.path().to_path_buf(),
+            dimension: 768,
+        };
+        assert!(!Embedder::is_cached(&model));
+
+        let err = Embedder::new(&model, ExecutionProvider::Cpu)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("tokenizer.json"), "{}", err);
+    }
+
+    #[test]
+    fn test_load_cached_models() {
+        // Only models already downloaded are loaded; this never hits the network
+        for model in EmbeddingModel::all() {
+            if Embedder::is_cached(&model) {
+                let embedder = Embedder::new(&model, ExecutionProvider::Cpu).unwrap();
+                assert_eq!(embedder.dimension(), model.dimension());
+            }
+        }
+    }
+
     #[test]
     fn test_normalize() {
         let mut v = vec![3.0, 4.0];
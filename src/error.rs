@@ -3,6 +3,7 @@
 //! Uses thiserror for ergonomic error definitions with automatic
 //! Display and Error trait implementations.
 
+use serde::Serialize;
 use thiserror::Error;
 
 /// Application-specific errors for lgrep
@@ -16,6 +17,19 @@ pub enum LgrepError {
     #[error("Embedding error: {0}")]
     Embedding(String),
 
+    /// Model files could not be downloaded
+    #[error("Model download failed: {0}")]
+    ModelDownload(String),
+
+    /// An embedding doesn't have the dimension of the model or index
+    #[error("Dimension mismatch: expected {expected}-dimensional embeddings, got {got}")]
+    DimensionMismatch {
+        /// Dimension of the model or index
+        expected: usize,
+        /// Dimension of the embedding given
+        got: usize,
+    },
+
     /// Vector index operations failed
     #[error("Index error: {0}")]
     Index(String),
@@ -28,10 +42,23 @@ pub enum LgrepError {
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
 
+    /// Index was written in a format newer than this build understands
+    #[error("Index created by a newer lgrep (format version {found}, this build reads up to {supported}); upgrade lgrep to use it")]
+    NewerFormat {
+        /// Format version found on disk
+        found: u32,
+        /// Newest format version this build reads
+        supported: u32,
+    },
+
     /// No index exists at the expected location
     #[error("No index found. Run `lgrep index` first.")]
     NoIndex,
 
+    /// An index exists but holds no chunks
+    #[error("Index is empty; nothing was indexed (check ignore rules / extensions)")]
+    EmptyIndex,
+
     /// Invalid file or directory path
     #[error("Invalid path: {0}")]
     InvalidPath(String),
@@ -43,7 +70,83 @@ pub enum LgrepError {
     /// Configuration errors
     #[error("Configuration error: {0}")]
     Config(String),
+
+    /// Archive could not be read
+    #[error("Archive error: {0}")]
+    Archive(String),
+
+    /// Search daemon errors
+    #[error("Server error: {0}")]
+    Server(String),
+
+    /// An operation exceeded its time budget
+    #[error("Timed out after {0:?}")]
+    Timeout(std::time::Duration),
 }
 
 /// Result type alias for convenience
 pub type Result<T> = std::result::Result<T, LgrepError>;
+
+impl LgrepError {
+    /// Stable name of the error kind, for scripts matching on it
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Io(_) => "Io",
+            Self::Embedding(_) => "Embedding",
+            Self::ModelDownload(_) => "ModelDownload",
+            Self::DimensionMismatch { .. } => "DimensionMismatch",
+            Self::Index(_) => "Index",
+            Self::Serialization(_) => "Serialization",
+            Self::Json(_) => "Json",
+            Self::NewerFormat { .. } => "NewerFormat",
+            Self::NoIndex => "NoIndex",
+            Self::EmptyIndex => "EmptyIndex",
+            Self::InvalidPath(_) => "InvalidPath",
+            Self::Watch(_) => "Watch",
+            Self::Config(_) => "Config",
+            Self::Archive(_) => "Archive",
+            Self::Server(_) => "Server",
+            Self::Timeout(_) => "Timeout",
+        }
+    }
+
+    /// What the user can do about the error, if there's something specific
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            Self::NoIndex => Some("run lgrep index"),
+            Self::EmptyIndex => Some("check ignore rules and extensions, then re-run lgrep index"),
+            Self::NewerFormat { .. } => Some("upgrade lgrep"),
+            Self::Serialization(_) | Self::Index(_) => Some("rebuild with lgrep index --force"),
+            Self::Server(_) => Some("restart lgrep serve, or search without it"),
+            Self::ModelDownload(_) => {
+                Some("check network access to huggingface.co, or set HF_ENDPOINT to a mirror")
+            }
+            Self::DimensionMismatch { .. } => {
+                Some("use the model the index was built with, or rebuild with lgrep index --force")
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Machine-readable form of an error, for `--error-format json`
+#[derive(Debug, Serialize)]
+pub struct ErrorReport {
+    /// Error kind (see [`LgrepError::code`])
+    pub error: &'static str,
+    /// Human-readable description
+    pub message: String,
+    /// Suggested fix, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<&'static str>,
+}
+
+impl From<&LgrepError> for ErrorReport {
+    fn from(err: &LgrepError) -> Self {
+        Self {
+            error: err.code(),
+            message: err.to_string(),
+            hint: err.hint(),
+        }
+    }
+}
@@ -2,9 +2,12 @@
 //!
 //! Allows filtering search results by file type, language, path patterns, etc.
 
-use crate::chunker::Chunk;
+use crate::chunker::{split_identifier, Chunk, ContentKind};
+use crate::error::{LgrepError, Result};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// Search filter criteria
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -13,14 +16,49 @@ pub struct SearchFilter {
     pub extensions: Option<Vec<String>>,
     /// Filter by programming languages (e.g., ["rust", "python"])
     pub languages: Option<Vec<String>>,
-    /// Filter by file path patterns (regex)
-    pub path_pattern: Option<String>,
-    /// Exclude file path patterns (regex)
-    pub exclude_pattern: Option<String>,
+    /// Filter by file path patterns (regex, any may match)
+    #[serde(default)]
+    path_patterns: Vec<String>,
+    /// Exclude file path patterns (regex, any match excludes)
+    #[serde(default)]
+    exclude_patterns: Vec<String>,
     /// Minimum similarity score (0.0 to 1.0)
     pub min_score: Option<f32>,
+    /// Maximum similarity score (0.0 to 1.0)
+    pub max_score: Option<f32>,
     /// Maximum results to return
     pub max_results: Option<usize>,
+    /// Only chunks with this dominant content (code, comments or docs)
+    #[serde(default)]
+    pub content_kind: Option<ContentKind>,
+    /// Only files under this directory (relative to the index root)
+    #[serde(default)]
+    pub scope: Option<String>,
+    /// Identifier parts every kept chunk must contain (lowercased)
+    #[serde(default)]
+    pub symbols: Vec<String>,
+    /// Only files modified at or after this time (Unix seconds)
+    #[serde(default)]
+    pub modified_after: Option<u64>,
+    /// Only files modified at or before this time (Unix seconds)
+    #[serde(default)]
+    pub modified_before: Option<u64>,
+    /// Only files of at least this many bytes
+    #[serde(default)]
+    pub min_file_bytes: Option<u64>,
+    /// Only files of at most this many bytes
+    #[serde(default)]
+    pub max_file_bytes: Option<u64>,
+    /// Path patterns compiled on first use
+    #[serde(skip)]
+    compiled: OnceLock<std::result::Result<PathPatterns, String>>,
+}
+
+/// Compiled include and exclude path patterns
+#[derive(Debug, Clone)]
+struct PathPatterns {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
 }
 
 impl SearchFilter {
@@ -41,32 +79,180 @@ impl SearchFilter {
         self
     }
 
-    /// Set path pattern filter
+    /// Add a path pattern filter
     pub fn with_path_pattern(mut self, pattern: String) -> Self {
-        self.path_pattern = Some(pattern);
+        self.path_patterns.push(pattern);
+        self.compiled = OnceLock::new();
+        self
+    }
+
+    /// Set path pattern filters (a path matching any of them is kept)
+    pub fn with_path_patterns(mut self, patterns: Vec<String>) -> Self {
+        self.path_patterns = patterns;
+        self.compiled = OnceLock::new();
         self
     }
 
-    /// Set exclude pattern filter
+    /// Add an exclude pattern filter
     pub fn with_exclude_pattern(mut self, pattern: String) -> Self {
-        self.exclude_pattern = Some(pattern);
+        self.exclude_patterns.push(pattern);
+        self.compiled = OnceLock::new();
         self
     }
 
+    /// Set exclude pattern filters (a path matching any of them is dropped)
+    pub fn with_exclude_patterns(mut self, patterns: Vec<String>) -> Self {
+        self.exclude_patterns = patterns;
+        self.compiled = OnceLock::new();
+        self
+    }
+
+    /// Path patterns a kept file must match one of
+    pub fn path_patterns(&self) -> &[String] {
+        &self.path_patterns
+    }
+
+    /// Path patterns that drop any file matching one of them
+    pub fn exclude_patterns(&self) -> &[String] {
+        &self.exclude_patterns
+    }
+
     /// Set minimum score threshold
     pub fn with_min_score(mut self, score: f32) -> Self {
         self.min_score = Some(score);
         self
     }
 
+    /// Set maximum score threshold
+    pub fn with_max_score(mut self, score: f32) -> Self {
+        self.max_score = Some(score);
+        self
+    }
+
     /// Set maximum results
     pub fn with_max_results(mut self, max: usize) -> Self {
         self.max_results = Some(max);
         self
     }
 
+    /// Only keep files under `dir`; `.` (or an empty path) means everywhere
+    pub fn with_scope(mut self, dir: &str) -> Self {
+        let dir = dir.trim_start_matches("./").trim_end_matches('/');
+        self.scope = (!dir.is_empty() && dir != ".").then(|| dir.to_string());
+        self
+    }
+
+    /// Only keep chunks whose dominant content is `kind`
+    pub fn with_content_kind(mut self, kind: ContentKind) -> Self {
+        self.content_kind = Some(kind);
+        self
+    }
+
+    /// Only keep chunks mentioning an identifier made of `name`'s parts
+    ///
+    /// `parseConfig`, `parse_config` and `PARSE_CONFIG` are equivalent here.
+    /// Chunks from indexes built before symbols were recorded never match.
+    pub fn with_symbol(mut self, name: &str) -> Self {
+        self.symbols.extend(split_identifier(name));
+        self
+    }
+
+    /// Only keep files modified at or after `time`
+    ///
+    /// Modification times are those recorded when the files were indexed.
+    /// Files without one (indexes built before they were recorded) are dropped.
+    pub fn with_modified_after(mut self, time: SystemTime) -> Self {
+        self.modified_after = Some(unix_seconds(time));
+        self
+    }
+
+    /// Only keep files modified at or before `time`
+    pub fn with_modified_before(mut self, time: SystemTime) -> Self {
+        self.modified_before = Some(unix_seconds(time));
+        self
+    }
+
+    /// Only keep files of at least `bytes`
+    ///
+    /// Sizes are those recorded when the files were indexed.
+    /// Files without one (indexes built before they were recorded) are dropped.
+    pub fn with_min_file_bytes(mut self, bytes: u64) -> Self {
+        self.min_file_bytes = Some(bytes);
+        self
+    }
+
+    /// Only keep files of at most `bytes`
+    pub fn with_max_file_bytes(mut self, bytes: u64) -> Self {
+        self.max_file_bytes = Some(bytes);
+        self
+    }
+
+    /// Check that the filter criteria are consistent
+    pub fn validate(&self) -> Result<()> {
+        if let (Some(min), Some(max)) = (self.min_score, self.max_score) {
+            if min > max {
+                return Err(LgrepError::Config(format!(
+                    "Minimum score ({}) is greater than maximum score ({})",
+                    min, max
+                )));
+            }
+        }
+
+        if let (Some(after), Some(before)) = (self.modified_after, self.modified_before) {
+            if after > before {
+                return Err(LgrepError::Config(
+                    "Modified-after time is later than modified-before time".to_string(),
+                ));
+            }
+        }
+
+        if let (Some(min), Some(max)) = (self.min_file_bytes, self.max_file_bytes) {
+            if min > max {
+                return Err(LgrepError::Config(format!(
+                    "Minimum file size ({} bytes) is greater than maximum file size ({} bytes)",
+                    min, max
+                )));
+            }
+        }
+
+        self.path_regexes()
+            .map(|_| ())
+            .map_err(|e| LgrepError::Config(e.clone()))
+    }
+
     /// Check if a chunk matches the filter criteria
     pub fn matches(&self, chunk: &Chunk, score: f32) -> bool {
+        self.matches_score(score) && self.matches_chunk(chunk)
+    }
+
+    /// Check only the modification time bounds against a file's `mtime`
+    pub fn matches_modified(&self, mtime: Option<u64>) -> bool {
+        if self.modified_after.is_none() && self.modified_before.is_none() {
+            return true;
+        }
+        let Some(mtime) = mtime else {
+            return false;
+        };
+
+        self.modified_after.map_or(true, |after| mtime >= after)
+            && self.modified_before.map_or(true, |before| mtime <= before)
+    }
+
+    /// Check only the file size bounds against a file's `size` in bytes
+    pub fn matches_file_size(&self, size: Option<u64>) -> bool {
+        if self.min_file_bytes.is_none() && self.max_file_bytes.is_none() {
+            return true;
+        }
+        let Some(size) = size else {
+            return false;
+        };
+
+        self.min_file_bytes.map_or(true, |min| size >= min)
+            && self.max_file_bytes.map_or(true, |max| size <= max)
+    }
+
+    /// Check only the score bounds
+    pub fn matches_score(&self, score: f32) -> bool {
         // Check minimum score
         if let Some(min_score) = self.min_score {
             if score < min_score {
@@ -74,6 +260,29 @@ impl SearchFilter {
             }
         }
 
+        // Check maximum score
+        if let Some(max_score) = self.max_score {
+            if score > max_score {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Check only the chunk's metadata (extension, language, path patterns)
+    pub fn matches_chunk(&self, chunk: &Chunk) -> bool {
+        // Check scope first: a plain prefix test on whole path components
+        if let Some(ref scope) = self.scope {
+            let inside = chunk
+                .file_path
+                .strip_prefix(scope.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+            if !inside {
+                return false;
+            }
+        }
+
         // Check file extension
         if let Some(ref extensions) = self.extensions {
             let file_ext = std::path::Path::new(&chunk.file_path)
@@ -103,26 +312,148 @@ impl SearchFilter {
             }
         }
 
-        // Check path pattern
-        if let Some(ref pattern) = self.path_pattern {
-            if let Ok(regex) = Regex::new(pattern) {
-                if !regex.is_match(&chunk.file_path) {
-                    return false;
-                }
-            }
+        // Check content kind
+        if self
+            .content_kind
+            .is_some_and(|kind| kind != chunk.content_kind)
+        {
+            return false;
         }
 
-        // Check exclude pattern
-        if let Some(ref pattern) = self.exclude_pattern {
-            if let Ok(regex) = Regex::new(pattern) {
-                if regex.is_match(&chunk.file_path) {
-                    return false;
-                }
-            }
+        // Check symbol parts (all must be present)
+        if !self.symbols.iter().all(|s| chunk.symbols.contains(s)) {
+            return false;
+        }
+
+        // Check path patterns; an invalid one keeps nothing (`validate` reports it)
+        let Ok(patterns) = self.path_regexes() else {
+            return false;
+        };
+
+        // Any include pattern may match
+        if !patterns.include.is_empty()
+            && !patterns
+                .include
+                .iter()
+                .any(|r| r.is_match(&chunk.file_path))
+        {
+            return false;
+        }
+
+        // Any exclude pattern match excludes
+        if patterns
+            .exclude
+            .iter()
+            .any(|r| r.is_match(&chunk.file_path))
+        {
+            return false;
         }
 
         true
     }
+
+    /// The include and exclude patterns, compiled once per filter
+    ///
+    /// The pattern fields are private and every setter resets the cache,
+    /// so it can't outlive the patterns it was compiled from.
+    fn path_regexes(&self) -> std::result::Result<&PathPatterns, &String> {
+        self.compiled
+            .get_or_init(|| {
+                Ok(PathPatterns {
+                    include: compile_patterns(&self.path_patterns)?,
+                    exclude: compile_patterns(&self.exclude_patterns)?,
+                })
+            })
+            .as_ref()
+    }
+}
+
+/// Parse a point in time: a relative age like `7d` or `2w` (before `now`),
+/// a date (`2024-05-01`, midnight UTC) or an RFC 3339 timestamp
+///
+/// Relative units are `m` (minutes), `h`, `d`, `w`, and `y` (365 days).
+pub fn parse_time(spec: &str, now: SystemTime) -> Result<SystemTime> {
+    let spec = spec.trim();
+    let invalid = || {
+        LgrepError::Config(format!(
+            "Invalid time: {}. Use an age like 7d or 2w, a date like 2024-05-01, \
+             or an RFC 3339 timestamp",
+            spec
+        ))
+    };
+
+    if let Some(unit) = spec.chars().last().filter(|c| c.is_ascii_alphabetic()) {
+        if let Ok(count) = spec[..spec.len() - 1].parse::<u64>() {
+            let seconds = match unit {
+                'm' => 60,
+                'h' => 60 * 60,
+                'd' => 24 * 60 * 60,
+                'w' => 7 * 24 * 60 * 60,
+                'y' => 365 * 24 * 60 * 60,
+                _ => return Err(invalid()),
+            };
+            let age = Duration::from_secs(count.saturating_mul(seconds));
+            return Ok(now.checked_sub(age).unwrap_or(UNIX_EPOCH));
+        }
+    }
+
+    let timestamp = if let Ok(date) = chrono::NaiveDate::parse_from_str(spec, "%Y-%m-%d") {
+        date.and_hms_opt(0, 0, 0)
+            .ok_or_else(invalid)?
+            .and_utc()
+            .timestamp()
+    } else {
+        chrono::DateTime::parse_from_rfc3339(spec)
+            .map_err(|_| invalid())?
+            .timestamp()
+    };
+    let seconds = u64::try_from(timestamp).map_err(|_| invalid())?;
+    Ok(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// Parse a byte size: a plain count or one with a `k`, `M` or `G` suffix
+/// (binary multiples, optionally followed by `b`), such as `500k` or `1.5M`
+pub fn parse_size(spec: &str) -> Result<u64> {
+    let spec = spec.trim();
+    let invalid = || {
+        LgrepError::Config(format!(
+            "Invalid size: {}. Use a byte count or a size like 500k or 2M",
+            spec
+        ))
+    };
+
+    let lower = spec.to_ascii_lowercase();
+    let digits = lower.strip_suffix('b').unwrap_or(&lower);
+    let (number, multiplier) = match digits.chars().last() {
+        Some('k') => (&digits[..digits.len() - 1], 1u64 << 10),
+        Some('m') => (&digits[..digits.len() - 1], 1 << 20),
+        Some('g') => (&digits[..digits.len() - 1], 1 << 30),
+        _ => (digits, 1),
+    };
+    let number = number.trim();
+
+    if let Ok(count) = number.parse::<u64>() {
+        return count.checked_mul(multiplier).ok_or_else(invalid);
+    }
+    match number.parse::<f64>() {
+        Ok(count) if count.is_finite() && count >= 0.0 => {
+            Ok((count * multiplier as f64).round() as u64)
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Whole seconds since the Unix epoch (0 for earlier times)
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// Compile path patterns, failing on the first that isn't a valid regex
+fn compile_patterns(patterns: &[String]) -> std::result::Result<Vec<Regex>, String> {
+    patterns
+        .iter()
+        .map(|p| Regex::new(p).map_err(|e| format!("Invalid path pattern '{}': {}", p, e)))
+        .collect()
 }
 
 #[cfg(test)]
@@ -138,6 +469,10 @@ mod tests {
             end_line: 1,
             file_hash: "hash".to_string(),
             language,
+            overlap_with_prev: 0,
+            content_kind: ContentKind::Code,
+            text_hash: String::new(),
+            symbols: Vec::new(),
         }
     }
 
@@ -163,6 +498,40 @@ mod tests {
         assert!(!filter.matches(&chunk_py, 0.8));
     }
 
+    #[test]
+    fn test_content_kind_filter() {
+        use crate::chunker::Chunker;
+
+        let text = "// Connection pooling: connections are reused\n\
+                    // across requests and closed after idling\n\
+                    // for longer than the configured timeout.\n\
+                    let pool = Pool::new();";
+        let chunk = Chunker::new(512, 0).chunk_text(text, "src/db.rs", "hash", 0)[0].clone();
+        assert_eq!(chunk.content_kind, ContentKind::Comments);
+
+        let code_only = SearchFilter::new().with_content_kind("code".parse().unwrap());
+        assert!(!code_only.matches(&chunk, 0.8));
+
+        let comments_only = SearchFilter::new().with_content_kind(ContentKind::Comments);
+        assert!(comments_only.matches(&chunk, 0.8));
+    }
+
+    #[test]
+    fn test_scope_filter() {
+        let filter = SearchFilter::new().with_scope("src/api");
+
+        assert!(filter.matches(&create_test_chunk("src/api/x.rs", None), 0.8));
+        assert!(!filter.matches(&create_test_chunk("src/apiv2/y.rs", None), 0.8));
+        assert!(!filter.matches(&create_test_chunk("lib/src/api/z.rs", None), 0.8));
+
+        let filter = SearchFilter::new().with_scope("./src/api/");
+        assert_eq!(filter.scope.as_deref(), Some("src/api"));
+
+        let filter = SearchFilter::new().with_scope(".");
+        assert!(filter.scope.is_none());
+        assert!(filter.matches(&create_test_chunk("src/apiv2/y.rs", None), 0.8));
+    }
+
     #[test]
     fn test_min_score_filter() {
         let filter = SearchFilter::new().with_min_score(0.7);
@@ -173,6 +542,25 @@ mod tests {
         assert!(!filter.matches(&chunk, 0.6));
     }
 
+    #[test]
+    fn test_score_range_filter() {
+        let filter = SearchFilter::new().with_min_score(0.5).with_max_score(0.9);
+
+        let chunk = create_test_chunk("test.rs", Some("rust".to_string()));
+
+        assert!(filter.matches(&chunk, 0.7));
+        assert!(filter.matches(&chunk, 0.9));
+        assert!(!filter.matches(&chunk, 0.95));
+        assert!(!filter.matches(&chunk, 0.4));
+        assert!(filter.validate().is_ok());
+    }
+
+    #[test]
+    fn test_inverted_score_range_rejected() {
+        let filter = SearchFilter::new().with_min_score(0.9).with_max_score(0.5);
+        assert!(filter.validate().is_err());
+    }
+
     #[test]
     fn test_path_pattern_filter() {
         let filter = SearchFilter::new().with_path_pattern("src/.*".to_string());
@@ -195,6 +583,56 @@ mod tests {
         assert!(!filter.matches(&chunk_test, 0.8));
     }
 
+    #[test]
+    fn test_multiple_path_patterns() {
+        let filter =
+            SearchFilter::new().with_path_patterns(vec!["^src/".to_string(), "^lib/".to_string()]);
+
+        let chunk_src = create_test_chunk("src/main.rs", Some("rust".to_string()));
+        let chunk_lib = create_test_chunk("lib/util.rs", Some("rust".to_string()));
+        let chunk_test = create_test_chunk("tests/test.rs", Some("rust".to_string()));
+
+        assert!(filter.matches(&chunk_src, 0.8));
+        assert!(filter.matches(&chunk_lib, 0.8));
+        assert!(!filter.matches(&chunk_test, 0.8));
+    }
+
+    #[test]
+    fn test_multiple_exclude_patterns() {
+        let filter = SearchFilter::new()
+            .with_exclude_patterns(vec!["^tests/".to_string(), "_test\\.rs$".to_string()]);
+
+        let chunk_src = create_test_chunk("src/main.rs", Some("rust".to_string()));
+        let chunk_tests = create_test_chunk("tests/test.rs", Some("rust".to_string()));
+        let chunk_unit = create_test_chunk("src/main_test.rs", Some("rust".to_string()));
+
+        assert!(filter.matches(&chunk_src, 0.8));
+        assert!(!filter.matches(&chunk_tests, 0.8));
+        assert!(!filter.matches(&chunk_unit, 0.8));
+    }
+
+    #[test]
+    fn test_invalid_pattern_rejected() {
+        let filter = SearchFilter::new().with_path_pattern("src/(".to_string());
+        assert!(filter.validate().is_err());
+
+        // An invalid pattern keeps nothing rather than filtering nothing
+        let chunk = create_test_chunk("src/main.rs", Some("rust".to_string()));
+        assert!(!filter.matches(&chunk, 0.8));
+    }
+
+    #[test]
+    fn test_pattern_setters_recompile() {
+        let chunk = create_test_chunk("lib/util.rs", Some("rust".to_string()));
+        let filter = SearchFilter::new().with_path_pattern("^src/".to_string());
+        assert!(!filter.matches(&chunk, 0.8));
+
+        // Patterns compiled for the first match don't stick to the filter
+        let filter = filter.with_path_pattern("^lib/".to_string());
+        assert_eq!(filter.path_patterns(), ["^src/", "^lib/"]);
+        assert!(filter.matches(&chunk, 0.8));
+    }
+
     #[test]
     fn test_combined_filters() {
         let filter = SearchFilter::new()
@@ -211,4 +649,76 @@ mod tests {
         assert!(!filter.matches(&chunk_wrong_lang, 0.8));
         assert!(!filter.matches(&chunk_match, 0.6)); // Low score
     }
+
+    #[test]
+    fn test_modified_time_filter() {
+        let day = Duration::from_secs(24 * 60 * 60);
+        let now = UNIX_EPOCH + 1000 * day;
+        let filter = SearchFilter::new()
+            .with_modified_after(parse_time("7d", now).unwrap())
+            .with_modified_before(parse_time("1d", now).unwrap());
+        filter.validate().unwrap();
+
+        let secs = |days: u64| days * day.as_secs();
+        assert!(filter.matches_modified(Some(secs(995))));
+        assert!(!filter.matches_modified(Some(secs(992))));
+        assert!(!filter.matches_modified(Some(secs(1000))));
+        assert!(!filter.matches_modified(None));
+        assert!(SearchFilter::new().matches_modified(None));
+
+        let swapped = SearchFilter::new()
+            .with_modified_after(now)
+            .with_modified_before(now - day);
+        assert!(swapped.validate().is_err());
+    }
+
+    #[test]
+    fn test_file_size_filter() {
+        let filter = SearchFilter::new()
+            .with_min_file_bytes(100)
+            .with_max_file_bytes(parse_size("2k").unwrap());
+        filter.validate().unwrap();
+
+        assert!(filter.matches_file_size(Some(100)));
+        assert!(filter.matches_file_size(Some(2048)));
+        assert!(!filter.matches_file_size(Some(99)));
+        assert!(!filter.matches_file_size(Some(2049)));
+        assert!(!filter.matches_file_size(None));
+        assert!(SearchFilter::new().matches_file_size(None));
+
+        let swapped = SearchFilter::new()
+            .with_min_file_bytes(10)
+            .with_max_file_bytes(5);
+        assert!(swapped.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("123").unwrap(), 123);
+        assert_eq!(parse_size("500k").unwrap(), 500 * 1024);
+        assert_eq!(parse_size("2M").unwrap(), 2 * 1024 * 1024);
+        assert_eq!(parse_size("2MB").unwrap(), 2 * 1024 * 1024);
+        assert_eq!(parse_size("1g").unwrap(), 1 << 30);
+        assert_eq!(parse_size("1.5k").unwrap(), 1536);
+        assert!(parse_size("").is_err());
+        assert!(parse_size("-1k").is_err());
+        assert!(parse_size("2T").is_err());
+        assert!(parse_size("k").is_err());
+    }
+
+    #[test]
+    fn test_parse_time() {
+        let now = UNIX_EPOCH + Duration::from_secs(2_000_000_000);
+        let ago = |spec: &str| now.duration_since(parse_time(spec, now).unwrap()).unwrap();
+        assert_eq!(ago("2w"), Duration::from_secs(14 * 24 * 60 * 60));
+        assert_eq!(ago("36h"), Duration::from_secs(36 * 60 * 60));
+
+        let date = parse_time("2024-05-01", now).unwrap();
+        assert_eq!(unix_seconds(date), 1_714_521_600);
+        let timestamp = parse_time("2024-05-01T02:00:00+02:00", now).unwrap();
+        assert_eq!(timestamp, date);
+
+        assert!(parse_time("7x", now).is_err());
+        assert!(parse_time("yesterday", now).is_err());
+    }
 }
\ No newline at end of file
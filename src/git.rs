@@ -0,0 +1,138 @@
+//! Change detection through git
+//!
+//! When the indexed root is inside a git repository, the files that changed
+//! since the last index can be read from `git diff` instead of walking and
+//! hashing the whole tree. Every function returns `None` when git is not
+//! available or the directory is not in a repository, so callers can fall
+//! back to the full walk.
+
+use std::path::Path;
+use std::process::Command;
+
+/// Commit checked out in `dir`
+pub fn head_commit(dir: &Path) -> Option<String> {
+    let output = git(dir, &["rev-parse", "--verify", "HEAD"])?;
+    Some(output.trim().to_string())
+}
+
+/// Files under `dir` that differ from `commit`, relative to `dir`
+///
+/// Covers committed and uncommitted changes (including deletions) plus
+/// untracked files that are not ignored. Returns `None` if `commit` is
+/// unknown to the repository.
+pub fn changed_since(dir: &Path, commit: &str) -> Option<Vec<String>> {
+    let diff = git(
+        dir,
+        &[
+            "diff",
+            "--name-only",
+            "--no-renames",
+            "--relative",
+            "-z",
+            commit,
+        ],
+    )?;
+    let untracked = git(dir, &["ls-files", "--others", "--exclude-standard", "-z"])?;
+
+    let mut files: Vec<String> = diff
+        .split('\0')
+        .chain(untracked.split('\0'))
+        .filter(|path| !path.is_empty())
+        .map(String::from)
+        .collect();
+    files.sort();
+    files.dedup();
+
+    Some(files)
+}
+
+/// When lines `start..=end` of `file` were last changed, as a Unix timestamp
+///
+/// Takes the newest author time `git blame` reports for the range; lines
+/// that are not committed yet count as changed now. Returns `None` outside a
+/// repository, for untracked files, or if the range is out of bounds.
+pub fn lines_last_changed(dir: &Path, file: &str, start: usize, end: usize) -> Option<i64> {
+    let range = format!("{},{}", start, end.max(start));
+    let output = git(dir, &["blame", "--porcelain", "-L", &range, "--", file])?;
+
+    output
+        .lines()
+        .filter_map(|line| line.strip_prefix("author-time "))
+        .filter_map(|time| time.trim().parse().ok())
+        .max()
+}
+
+/// Run git in `dir`, returning stdout if it succeeded
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8(output.stdout).ok()
+}
+
+/// Run git in `dir` with a throwaway identity, panicking on failure
+#[cfg(test)]
+pub(crate) fn run_git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args([
+            "-c",
+            "user.name=lgrep",
+            "-c",
+            "user.email=lgrep@example.com",
+        ])
+        .args(args)
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_changed_since() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("a.rs"), "fn a() {}").unwrap();
+        std::fs::write(root.join("b.rs"), "fn b() {}").unwrap();
+        run_git(root, &["init", "-q"]);
+        run_git(root, &["add", "."]);
+        run_git(root, &["commit", "-q", "-m", "initial"]);
+        let commit = head_commit(root).unwrap();
+
+        assert_eq!(changed_since(root, &commit), Some(Vec::new()));
+
+        std::fs::write(root.join("a.rs"), "fn a() { changed() }").unwrap();
+        std::fs::remove_file(root.join("b.rs")).unwrap();
+        std::fs::write(root.join("c.rs"), "fn c() {}").unwrap();
+
+        assert_eq!(
+            changed_since(root, &commit),
+            Some(vec![
+                "a.rs".to_string(),
+                "b.rs".to_string(),
+                "c.rs".to_string()
+            ])
+        );
+        assert_eq!(changed_since(root, "0000000"), None);
+    }
+
+    #[test]
+    fn test_not_a_repository() {
+        let dir = tempdir().unwrap();
+        assert_eq!(head_commit(dir.path()), None);
+    }
+}
@@ -0,0 +1,197 @@
+//! `.gitattributes` linguist hints
+//!
+//! Reads the `linguist-generated` and `linguist-vendored` markers GitHub
+//! uses to classify files, so generated and vendored code can be kept out
+//! of the index. Nested `.gitattributes` files override their parents, and
+//! within a file later lines override earlier ones, as in git.
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// One `.gitattributes` line that touches a linguist attribute
+struct Rule {
+    matcher: Gitignore,
+    generated: Option<bool>,
+    vendored: Option<bool>,
+}
+
+/// Linguist attributes for files under a root, with per-directory caching
+pub struct LinguistAttributes {
+    root: PathBuf,
+    rules: HashMap<PathBuf, Vec<Rule>>,
+}
+
+impl LinguistAttributes {
+    /// Create a matcher for files under `root`
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            rules: HashMap::new(),
+        }
+    }
+
+    /// Check whether a file is marked generated or vendored
+    pub fn is_excluded(&mut self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+
+        // Directories from the root down, so deeper files take precedence
+        let mut dirs = vec![self.root.clone()];
+        if let Some(parent) = relative.parent() {
+            let mut dir = self.root.clone();
+            for component in parent.components() {
+                dir.push(component);
+                dirs.push(dir.clone());
+            }
+        }
+
+        let mut generated = false;
+        let mut vendored = false;
+
+        for dir in dirs {
+            let rules = self
+                .rules
+                .entry(dir.clone())
+                .or_insert_with(|| load_rules(&dir));
+
+            for rule in rules.iter() {
+                if rule.matcher.matched(path, false).is_ignore() {
+                    generated = rule.generated.unwrap_or(generated);
+                    vendored = rule.vendored.unwrap_or(vendored);
+                }
+            }
+        }
+
+        generated || vendored
+    }
+}
+
+/// Parse the linguist rules of `dir/.gitattributes`, if present
+fn load_rules(dir: &Path) -> Vec<Rule> {
+    let Ok(content) = std::fs::read_to_string(dir.join(".gitattributes")) else {
+        return Vec::new();
+    };
+
+    content
+        .lines()
+        .filter_map(|line| parse_line(dir, line))
+        .collect()
+}
+
+/// Parse a single `.gitattributes` line, keeping only linguist attributes
+fn parse_line(dir: &Path, line: &str) -> Option<Rule> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let mut parts = line.split_whitespace();
+    let pattern = parts.next()?;
+
+    // Negative patterns are not allowed in .gitattributes
+    if pattern.starts_with('!') {
+        return None;
+    }
+
+    let mut generated = None;
+    let mut vendored = None;
+    for attr in parts {
+        if let Some(value) = attribute_value(attr, "linguist-generated") {
+            generated = Some(value);
+        } else if let Some(value) = attribute_value(attr, "linguist-vendored") {
+            vendored = Some(value);
+        }
+    }
+
+    if generated.is_none() && vendored.is_none() {
+        return None;
+    }
+
+    let mut builder = GitignoreBuilder::new(dir);
+    builder.add_line(None, pattern).ok()?;
+    let matcher = builder.build().ok()?;
+
+    Some(Rule {
+        matcher,
+        generated,
+        vendored,
+    })
+}
+
+/// Value of a boolean attribute: `name`, `name=true`, `-name`, `!name`, `name=false`
+fn attribute_value(attr: &str, name: &str) -> Option<bool> {
+    if attr == name {
+        return Some(true);
+    }
+    if let Some(rest) = attr.strip_prefix('-').or_else(|| attr.strip_prefix('!')) {
+        return (rest == name).then_some(false);
+    }
+
+    match attr.strip_prefix(name)?.strip_prefix('=')? {
+        "false" | "0" => Some(false),
+        _ => Some(true),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_generated_file_excluded() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("src/proto")).unwrap();
+        std::fs::write(
+            root.join(".gitattributes"),
+            "*.pb.rs linguist-generated\nthird_party/** linguist-vendored=true\n",
+        )
+        .unwrap();
+
+        let mut attributes = LinguistAttributes::new(root.clone());
+        assert!(attributes.is_excluded(&root.join("src/proto/api.pb.rs")));
+        assert!(attributes.is_excluded(&root.join("third_party/lib/x.c")));
+        assert!(!attributes.is_excluded(&root.join("src/main.rs")));
+    }
+
+    #[test]
+    fn test_nested_gitattributes_override() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("keep")).unwrap();
+        std::fs::write(root.join(".gitattributes"), "*.gen.rs linguist-generated\n").unwrap();
+        std::fs::write(
+            root.join("keep/.gitattributes"),
+            "*.gen.rs -linguist-generated\n",
+        )
+        .unwrap();
+
+        let mut attributes = LinguistAttributes::new(root.clone());
+        assert!(attributes.is_excluded(&root.join("a.gen.rs")));
+        assert!(!attributes.is_excluded(&root.join("keep/b.gen.rs")));
+    }
+
+    #[test]
+    fn test_attribute_value() {
+        assert_eq!(
+            attribute_value("linguist-generated", "linguist-generated"),
+            Some(true)
+        );
+        assert_eq!(
+            attribute_value("linguist-generated=false", "linguist-generated"),
+            Some(false)
+        );
+        assert_eq!(
+            attribute_value("-linguist-generated", "linguist-generated"),
+            Some(false)
+        );
+        assert_eq!(attribute_value("text", "linguist-generated"), None);
+        assert_eq!(
+            attribute_value("linguist-generated-ish", "linguist-generated"),
+            None
+        );
+    }
+}
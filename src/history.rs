@@ -5,7 +5,7 @@
 use crate::error::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 const MAX_HISTORY_SIZE: usize = 100;
 
@@ -34,7 +34,7 @@ pub struct QueryHistory {
 
 impl QueryHistory {
     /// Create or load query history
-    pub fn load(index_dir: &PathBuf) -> Result<Self> {
+    pub fn load(index_dir: &Path) -> Result<Self> {
         let history_path = index_dir.join("history.json");
 
         if history_path.exists() {
@@ -132,7 +132,7 @@ impl QueryHistory {
         }
 
         let mut queries: Vec<(String, usize)> = frequency.into_iter().collect();
-        queries.sort_by(|a, b| b.1.cmp(&a.1));
+        queries.sort_by_key(|q| std::cmp::Reverse(q.1));
         queries.truncate(limit);
         queries
     }
@@ -162,7 +162,7 @@ mod tests {
     #[test]
     fn test_add_query() {
         let dir = tempdir().unwrap();
-        let mut history = QueryHistory::load(&dir.path().to_path_buf()).unwrap();
+        let mut history = QueryHistory::load(dir.path()).unwrap();
 
         history.add_query("test query".to_string(), 5, None).unwrap();
         assert_eq!(history.len(), 1);
@@ -179,7 +179,7 @@ mod tests {
     #[test]
     fn test_recent_queries() {
         let dir = tempdir().unwrap();
-        let mut history = QueryHistory::load(&dir.path().to_path_buf()).unwrap();
+        let mut history = QueryHistory::load(dir.path()).unwrap();
 
         history.add_query("query 1".to_string(), 5, None).unwrap();
         history.add_query("query 2".to_string(), 3, None).unwrap();
@@ -194,7 +194,7 @@ mod tests {
     #[test]
     fn test_suggestions() {
         let dir = tempdir().unwrap();
-        let mut history = QueryHistory::load(&dir.path().to_path_buf()).unwrap();
+        let mut history = QueryHistory::load(dir.path()).unwrap();
 
         history.add_query("authentication".to_string(), 5, None).unwrap();
         history.add_query("authorization".to_string(), 3, None).unwrap();
@@ -209,7 +209,7 @@ mod tests {
     #[test]
     fn test_top_queries() {
         let dir = tempdir().unwrap();
-        let mut history = QueryHistory::load(&dir.path().to_path_buf()).unwrap();
+        let mut history = QueryHistory::load(dir.path()).unwrap();
 
         history.add_query("common query".to_string(), 5, None).unwrap();
         history.add_query("rare query".to_string(), 3, None).unwrap();
@@ -232,7 +232,7 @@ mod tests {
     #[test]
     fn test_max_history_size() {
         let dir = tempdir().unwrap();
-        let mut history = QueryHistory::load(&dir.path().to_path_buf()).unwrap();
+        let mut history = QueryHistory::load(dir.path()).unwrap();
 
         // Add more than MAX_HISTORY_SIZE queries
         for i in 0..150 {
@@ -3,17 +3,37 @@
 //! Provides fast approximate nearest neighbor search for semantic queries.
 //! Uses cosine similarity for comparing embeddings.
 
-use crate::chunker::{Chunk, IndexMetadata};
+use crate::chunker::{Chunk, IndexMetadata, SavedFiles, FORMAT_VERSION};
 use crate::config::Config;
+use crate::embedder::cosine_similarity;
 use crate::error::{LgrepError, Result};
+use crate::texts::ChunkTexts;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use tracing::{debug, info};
 use usearch::{Index, IndexOptions, MetricKind, ScalarKind};
 
+/// Stored (chunk ID, vector) pairs keyed by chunk text hash
+pub type ChunkVectors = HashMap<String, Vec<(u64, Vec<f32>)>>;
+
+#[cfg(test)]
+thread_local! {
+    /// Number of indexes read by `VectorIndex::load` on this thread
+    pub(crate) static LOADS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
 /// Vector index for semantic search
 pub struct VectorIndex {
     index: Index,
     metadata: IndexMetadata,
     config: Config,
+    /// Mean chunk embedding per file (only with `config.file_embeddings`)
+    file_vectors: HashMap<String, Vec<f32>>,
+    /// Format version the metadata was read in (older ones migrate on save)
+    format_version: u32,
+    /// Chunk text, kept out of `metadata.chunks` and read on demand
+    texts: ChunkTexts,
 }
 
 impl VectorIndex {
@@ -21,29 +41,53 @@ impl VectorIndex {
     pub fn new(config: Config) -> Result<Self> {
         let dimension = config.model.dimension();
 
-        let options = IndexOptions {
-            dimensions: dimension,
-            metric: MetricKind::Cos, // Cosine similarity
-            quantization: ScalarKind::F32,
-            connectivity: 16,       // M parameter for HNSW
-            expansion_add: 128,     // ef_construction
-            expansion_search: 64,   // ef
-            multi: false,
-        };
-
-        let index = Index::new(&options).map_err(|e| LgrepError::Index(e.to_string()))?;
+        let mut metadata = IndexMetadata::new(config.model.model_name().to_string(), dimension);
+        metadata.hnsw_connectivity = config.hnsw_connectivity;
+        metadata.hnsw_expansion_add = config.hnsw_expansion_add;
+        metadata.hnsw_expansion_search = config.hnsw_expansion_search;
+        metadata.model_prefixes = config.model.uses_prefixes();
 
-        let metadata = IndexMetadata::new(config.model.model_name().to_string(), dimension);
+        let index =
+            Index::new(&hnsw_options(&metadata)).map_err(|e| LgrepError::Index(e.to_string()))?;
 
         Ok(Self {
             index,
             metadata,
             config,
+            file_vectors: HashMap::new(),
+            format_version: FORMAT_VERSION,
+            texts: ChunkTexts::default(),
         })
     }
 
+    /// Create an index from chunks and their precomputed embeddings
+    ///
+    /// Every embedding must have the dimension of `config.model`. No model is
+    /// loaded, so an index can be moved between machines (e.g. into CI)
+    /// without downloading the model there.
+    pub fn from_chunks_and_embeddings(
+        config: Config,
+        chunks: Vec<Chunk>,
+        embeddings: Vec<Vec<f32>>,
+    ) -> Result<Self> {
+        let dimension = config.model.dimension();
+        if let Some(embedding) = embeddings.iter().find(|e| e.len() != dimension) {
+            return Err(LgrepError::DimensionMismatch {
+                expected: dimension,
+                got: embedding.len(),
+            });
+        }
+
+        let mut index = Self::new(config)?;
+        index.add_chunks(chunks, embeddings)?;
+        Ok(index)
+    }
+
     /// Load existing index from disk
     pub fn load(config: Config) -> Result<Self> {
+        #[cfg(test)]
+        LOADS.with(|n| n.set(n.get() + 1));
+
         let index_path = config.index_path();
         let metadata_path = config.metadata_path();
 
@@ -55,20 +99,22 @@ impl VectorIndex {
 
         // Load metadata first to get dimension
         let metadata_bytes = std::fs::read(&metadata_path)?;
-        let metadata: IndexMetadata = bincode::deserialize(&metadata_bytes)?;
-
-        // Create index with correct options
-        let options = IndexOptions {
-            dimensions: metadata.dimension,
-            metric: MetricKind::Cos,
-            quantization: ScalarKind::F32,
-            connectivity: 16,
-            expansion_add: 128,
-            expansion_search: 64,
-            multi: false,
-        };
+        let (mut metadata, saved) = IndexMetadata::from_bytes_with_files(&metadata_bytes)?;
+        check_model(&config, &metadata)?;
+        if let Some(saved) = saved {
+            check_saved_files(&config, &saved)?;
+        }
+        let version = IndexMetadata::format_version(&metadata_bytes);
+        if version < FORMAT_VERSION {
+            info!(
+                "Index uses format version {}; it will be migrated to version {} on next save",
+                version, FORMAT_VERSION
+            );
+        }
 
-        let index = Index::new(&options).map_err(|e| LgrepError::Index(e.to_string()))?;
+        // Create index with the options it was built with
+        let index =
+            Index::new(&hnsw_options(&metadata)).map_err(|e| LgrepError::Index(e.to_string()))?;
 
         // Load the index data
         index
@@ -81,30 +127,65 @@ impl VectorIndex {
             metadata.chunks.len()
         );
 
+        let file_vectors_path = config.file_vectors_path();
+        let file_vectors = if config.file_embeddings && file_vectors_path.exists() {
+            bincode::deserialize(&std::fs::read(&file_vectors_path)?)?
+        } else {
+            HashMap::new()
+        };
+
+        // Before version 9 the text was part of the metadata
+        let texts = if version >= 9 {
+            ChunkTexts::open(&config.texts_path())?
+        } else {
+            let texts = metadata
+                .chunks
+                .iter_mut()
+                .map(|chunk| (chunk.id, std::mem::take(&mut chunk.text)))
+                .collect::<HashMap<_, _>>();
+            ChunkTexts::from(texts)
+        };
+
         Ok(Self {
             index,
             metadata,
             config,
+            file_vectors,
+            format_version: version,
+            texts,
         })
     }
 
     /// Save index to disk
+    ///
+    /// Each file is written next to its final path with a `.tmp` suffix and
+    /// only renamed into place once all of them were written, so a crash or
+    /// full disk mid-save leaves the previous index loadable. The metadata
+    /// is renamed last and records the sizes of the other files, so a load
+    /// refuses files left from two different saves by a crash between the
+    /// renames.
     pub fn save(&self) -> Result<()> {
         std::fs::create_dir_all(&self.config.index_dir)?;
 
         let index_path = self.config.index_path();
-        let metadata_path = self.config.metadata_path();
+        let mut paths = vec![index_path.clone(), self.config.texts_path()];
+        if self.config.file_embeddings {
+            paths.push(self.config.file_vectors_path());
+        }
+        paths.push(self.config.metadata_path());
+        let temp_paths: Vec<PathBuf> = paths.iter().map(|path| temp_path(path)).collect();
 
         info!("Saving index to {:?}", index_path);
 
-        // Save vector index
-        self.index
-            .save(index_path.to_str().unwrap())
-            .map_err(|e| LgrepError::Index(e.to_string()))?;
-
-        // Save metadata
-        let metadata_bytes = bincode::serialize(&self.metadata)?;
-        std::fs::write(&metadata_path, metadata_bytes)?;
+        if let Err(e) = self.write_files(&temp_paths) {
+            for path in &temp_paths {
+                let _ = std::fs::remove_file(path);
+            }
+            return Err(e);
+        }
+        for (temp, path) in temp_paths.iter().zip(&paths) {
+            std::fs::rename(temp, path)?;
+        }
 
         // Save config
         self.config.save()?;
@@ -118,6 +199,31 @@ impl VectorIndex {
         Ok(())
     }
 
+    /// Write the vectors, the texts, the file vectors (if saved) and then
+    /// the metadata to `paths`, in that order
+    fn write_files(&self, paths: &[PathBuf]) -> Result<()> {
+        let (metadata_path, paths) = paths.split_last().unwrap();
+
+        self.index
+            .save(paths[0].to_str().unwrap())
+            .map_err(|e| LgrepError::Index(e.to_string()))?;
+        self.texts
+            .write(&paths[1], self.metadata.chunks.iter().map(|c| c.id))?;
+        let mut files = SavedFiles {
+            vectors: std::fs::metadata(&paths[0])?.len(),
+            file_vectors: None,
+            texts: Some(std::fs::metadata(&paths[1])?.len()),
+        };
+        if let Some(path) = paths.get(2) {
+            let bytes = bincode::serialize(&self.file_vectors)?;
+            std::fs::write(path, &bytes)?;
+            files.file_vectors = Some(bytes.len() as u64);
+        }
+
+        std::fs::write(metadata_path, self.metadata.to_bytes(&files)?)?;
+        Ok(())
+    }
+
     /// Add chunks with their embeddings to the index
     pub fn add_chunks(&mut self, chunks: Vec<Chunk>, embeddings: Vec<Vec<f32>>) -> Result<()> {
         if chunks.len() != embeddings.len() {
@@ -125,6 +231,9 @@ impl VectorIndex {
                 "Chunks and embeddings count mismatch".to_string(),
             ));
         }
+        for embedding in &embeddings {
+            self.check_dimension(embedding)?;
+        }
 
         // Reserve space
         let current_size = self.index.size();
@@ -142,8 +251,28 @@ impl VectorIndex {
             debug!("Added chunk {} from {}", chunk.id, chunk.file_path);
         }
 
-        // Update metadata
-        for chunk in chunks {
+        // Per-file embeddings: a file's chunks are always added together
+        if self.config.file_embeddings {
+            let mut sums: HashMap<&str, (Vec<f32>, usize)> = HashMap::new();
+            for (chunk, embedding) in chunks.iter().zip(embeddings.iter()) {
+                let (sum, count) = sums
+                    .entry(chunk.file_path.as_str())
+                    .or_insert_with(|| (vec![0.0; embedding.len()], 0));
+                for (s, e) in sum.iter_mut().zip(embedding) {
+                    *s += e;
+                }
+                *count += 1;
+            }
+
+            for (file_path, (sum, count)) in sums {
+                let mean = sum.into_iter().map(|s| s / count as f32).collect();
+                self.file_vectors.insert(file_path.to_string(), mean);
+            }
+        }
+
+        // Update metadata, keeping the text apart
+        for mut chunk in chunks {
+            self.texts.insert(chunk.id, std::mem::take(&mut chunk.text));
             let file_path = chunk.file_path.clone();
             let file_hash = chunk.file_hash.clone();
             self.metadata.chunks.push(chunk);
@@ -176,19 +305,133 @@ impl VectorIndex {
         // Remove from index (ignore errors for missing keys)
         for id in &removed_ids {
             let _ = self.index.remove(*id);
+            self.texts.remove(*id);
         }
 
         // Remove from metadata
         self.metadata.chunks.retain(|c| c.file_path != file_path);
         self.metadata.file_hashes.remove(file_path);
+        self.metadata.file_mtimes.remove(file_path);
+        self.metadata.file_sizes.remove(file_path);
+        self.file_vectors.remove(file_path);
 
         debug!("Removed {} chunks from {}", removed_ids.len(), file_path);
 
         Ok(removed_ids)
     }
 
+    /// Move a file's chunks and recorded details to a new path
+    ///
+    /// For a file renamed without changes: its stored embeddings still apply.
+    pub fn rename_file(&mut self, from: &str, to: &str) {
+        for chunk in self
+            .metadata
+            .chunks
+            .iter_mut()
+            .filter(|c| c.file_path == from)
+        {
+            chunk.file_path = to.to_string();
+        }
+
+        let metadata = &mut self.metadata;
+        if let Some(hash) = metadata.file_hashes.remove(from) {
+            metadata.file_hashes.insert(to.to_string(), hash);
+        }
+        if let Some(mtime) = metadata.file_mtimes.remove(from) {
+            metadata.file_mtimes.insert(to.to_string(), mtime);
+        }
+        if let Some(size) = metadata.file_sizes.remove(from) {
+            metadata.file_sizes.insert(to.to_string(), size);
+        }
+        if let Some(vector) = self.file_vectors.remove(from) {
+            self.file_vectors.insert(to.to_string(), vector);
+        }
+
+        debug!("Renamed {} to {}", from, to);
+    }
+
+    /// Remove a file, returning its chunks' IDs and vectors keyed by text hash
+    ///
+    /// Lets an update reuse the embeddings of chunks whose text did not
+    /// change. Chunks without a text hash (older indexes) are not returned.
+    pub fn take_file_vectors(&mut self, file_path: &str) -> Result<ChunkVectors> {
+        let mut vectors = ChunkVectors::new();
+        for chunk in self
+            .metadata
+            .chunks
+            .iter()
+            .filter(|c| c.file_path == file_path && !c.text_hash.is_empty())
+        {
+            if let Some(vector) = self.get_vector(chunk.id)? {
+                vectors
+                    .entry(chunk.text_hash.clone())
+                    .or_default()
+                    .push((chunk.id, vector));
+            }
+        }
+
+        self.remove_file(file_path)?;
+        Ok(vectors)
+    }
+
+    /// Rebuild the vector index from the live chunks and save it
+    ///
+    /// Removed chunks leave tombstones in the HNSW graph and gaps in the chunk
+    /// IDs, so an index updated for a long time (e.g. by `lgrep watch`) grows
+    /// past its contents. This re-adds the stored vector of every live chunk
+    /// to a fresh graph under contiguous IDs from 0, and drops file entries
+    /// that no longer have chunks.
+    pub fn compact(&mut self) -> Result<()> {
+        let index = Index::new(&hnsw_options(&self.metadata))
+            .map_err(|e| LgrepError::Index(e.to_string()))?;
+        index
+            .reserve(self.metadata.chunks.len())
+            .map_err(|e| LgrepError::Index(e.to_string()))?;
+
+        let mut chunks = std::mem::take(&mut self.metadata.chunks);
+        let mut texts = HashMap::with_capacity(chunks.len());
+        for (id, chunk) in (0u64..).zip(chunks.iter_mut()) {
+            let vector = self.get_vector(chunk.id)?.ok_or_else(|| {
+                LgrepError::Index(format!("No vector stored for chunk {}", chunk.id))
+            })?;
+            index
+                .add(id, &vector)
+                .map_err(|e| LgrepError::Index(e.to_string()))?;
+            texts.insert(id, self.texts.get(chunk.id)?.unwrap_or_default());
+            chunk.id = id;
+        }
+
+        let live: std::collections::HashSet<String> =
+            chunks.iter().map(|c| c.file_path.clone()).collect();
+        let metadata = &mut self.metadata;
+        metadata.file_hashes.retain(|path, _| live.contains(path));
+        metadata.file_mtimes.retain(|path, _| live.contains(path));
+        metadata.file_sizes.retain(|path, _| live.contains(path));
+        self.file_vectors.retain(|path, _| live.contains(path));
+
+        info!("Compacted index to {} vectors", chunks.len());
+        self.metadata.next_id = chunks.len() as u64;
+        self.metadata.chunks = chunks;
+        self.index = index;
+        self.texts = ChunkTexts::from(texts);
+
+        self.save()
+    }
+
     /// Search for similar chunks
     pub fn search(&self, query_embedding: &[f32], top_k: usize) -> Result<Vec<SearchResult>> {
+        let mut results = self.search_without_text(query_embedding, top_k)?;
+        self.fill_text(&mut results)?;
+        Ok(results)
+    }
+
+    /// Nearest chunks, with their text left empty
+    fn search_without_text(
+        &self,
+        query_embedding: &[f32],
+        top_k: usize,
+    ) -> Result<Vec<SearchResult>> {
+        self.check_dimension(query_embedding)?;
         if self.index.size() == 0 {
             return Ok(vec![]);
         }
@@ -206,10 +449,10 @@ impl VectorIndex {
                 // Convert distance to similarity score (cosine distance -> similarity)
                 let score = 1.0 - distance;
 
-                search_results.push(SearchResult {
-                    chunk: chunk.clone(),
-                    score,
-                });
+                let mut result = SearchResult::new(chunk.clone(), score);
+                result.distance = *distance;
+                result.root = self.config.root_of(&chunk.file_path);
+                search_results.push(result);
             }
         }
 
@@ -219,21 +462,263 @@ impl VectorIndex {
         Ok(search_results)
     }
 
+    /// Refuse an embedding whose dimension isn't the index's
+    fn check_dimension(&self, embedding: &[f32]) -> Result<()> {
+        if embedding.len() != self.metadata.dimension {
+            return Err(LgrepError::DimensionMismatch {
+                expected: self.metadata.dimension,
+                got: embedding.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Search for similar chunks, keeping only those accepted by `predicate`
+    ///
+    /// Oversamples from HNSW and keeps widening the candidate pool until
+    /// `top_k` chunks pass or the index is exhausted.
+    pub fn search_filtered<F>(
+        &self,
+        query_embedding: &[f32],
+        top_k: usize,
+        predicate: F,
+    ) -> Result<Vec<SearchResult>>
+    where
+        F: Fn(&Chunk) -> bool,
+    {
+        let size = self.index.size();
+        if size == 0 || top_k == 0 {
+            return Ok(vec![]);
+        }
+
+        let mut fetch = (top_k * 2).min(size);
+        loop {
+            let mut results = self.search_without_text(query_embedding, fetch)?;
+            results.retain(|r| predicate(&r.chunk));
+
+            if results.len() >= top_k || fetch >= size {
+                results.truncate(top_k);
+                self.fill_text(&mut results)?;
+                return Ok(results);
+            }
+
+            debug!(
+                "{} of {} candidates passed, widening search",
+                results.len(),
+                fetch
+            );
+            fetch = (fetch * 2).min(size);
+        }
+    }
+
+    /// Rank files by similarity of their per-file embedding to the query
+    pub fn search_files(&self, query_embedding: &[f32], top_k: usize) -> Result<Vec<FileResult>> {
+        if !self.config.file_embeddings {
+            return Err(LgrepError::Config(
+                "File embeddings are not enabled for this index (re-index with --file-embeddings)"
+                    .to_string(),
+            ));
+        }
+
+        let mut results: Vec<FileResult> = self
+            .file_vectors
+            .iter()
+            .map(|(file_path, vector)| FileResult {
+                file_path: file_path.clone(),
+                // The mean of unit vectors isn't unit length
+                score: cosine(query_embedding, vector),
+                root: self.config.root_of(file_path),
+            })
+            .collect();
+
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        results.truncate(top_k);
+
+        Ok(results)
+    }
+
+    /// Rank the chunks of a single file by exact cosine similarity
+    ///
+    /// Scores every chunk of the file instead of going through HNSW, so
+    /// all of its chunks are considered, not just global nearest neighbors.
+    pub fn search_within_file(
+        &self,
+        query_embedding: &[f32],
+        file_path: &str,
+        top_k: usize,
+    ) -> Result<Vec<SearchResult>> {
+        if !self.metadata.file_hashes.contains_key(file_path) {
+            return Err(LgrepError::InvalidPath(format!(
+                "{} is not in the index",
+                file_path
+            )));
+        }
+
+        let mut results = Vec::new();
+        for chunk in self
+            .metadata
+            .chunks
+            .iter()
+            .filter(|c| c.file_path == file_path)
+        {
+            if let Some(vector) = self.get_vector(chunk.id)? {
+                let mut result = SearchResult::new(chunk.clone(), cosine(query_embedding, &vector));
+                result.root = self.config.root_of(&chunk.file_path);
+                results.push(result);
+            }
+        }
+
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        results.truncate(top_k);
+        self.fill_text(&mut results)?;
+
+        Ok(results)
+    }
+
+    /// Text of a chunk, read from disk for a loaded index
+    ///
+    /// The chunks returned by [`chunk_metadata`](Self::chunk_metadata) and
+    /// [`chunk_at`](Self::chunk_at) don't carry their text; search results do.
+    pub fn chunk_text(&self, id: u64) -> Result<Option<String>> {
+        self.texts.get(id)
+    }
+
+    /// Copy of `chunk` with its text filled in
+    pub fn with_text(&self, chunk: &Chunk) -> Result<Chunk> {
+        let mut chunk = chunk.clone();
+        chunk.text = self.texts.get(chunk.id)?.unwrap_or_default();
+        Ok(chunk)
+    }
+
+    /// Length in bytes of a chunk's text, without reading it
+    pub fn chunk_text_len(&self, id: u64) -> Option<usize> {
+        self.texts.text_len(id)
+    }
+
+    /// Read the text of the chunks in `results`
+    fn fill_text(&self, results: &mut [SearchResult]) -> Result<()> {
+        for result in results {
+            if let Some(text) = self.texts.get(result.chunk.id)? {
+                result.chunk.text = text;
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the stored embedding of a chunk, if present
+    pub fn get_vector(&self, id: u64) -> Result<Option<Vec<f32>>> {
+        let mut vector = Vec::new();
+        let found = self
+            .index
+            .export(id, &mut vector)
+            .map_err(|e| LgrepError::Index(e.to_string()))?;
+
+        Ok((found > 0).then(|| {
+            vector.truncate(self.index.dimensions());
+            vector
+        }))
+    }
+
+    /// Iterate over all chunks with their stored embeddings
+    ///
+    /// Vectors are read from the graph one chunk at a time, so walking the
+    /// whole index doesn't hold a copy of every embedding.
+    pub fn iter_with_embeddings(&self) -> impl Iterator<Item = Result<(Chunk, Vec<f32>)>> + '_ {
+        self.metadata.chunks.iter().map(|chunk| {
+            let vector = self.get_vector(chunk.id)?.ok_or_else(|| {
+                LgrepError::Index(format!("No vector stored for chunk {}", chunk.id))
+            })?;
+            Ok((self.with_text(chunk)?, vector))
+        })
+    }
+
+    /// Get the chunk of `file_path` covering a line (1-indexed)
+    ///
+    /// Where overlapping chunks both cover the line, the later one wins,
+    /// since the line is part of its own content rather than its overlap.
+    pub fn chunk_at(&self, file_path: &str, line: usize) -> Option<&Chunk> {
+        self.metadata
+            .chunks
+            .iter()
+            .filter(|c| c.file_path == file_path && c.start_line <= line && line <= c.end_line)
+            .max_by_key(|c| c.start_line)
+    }
+
+    /// Get the stored per-file embedding, if any
+    pub fn file_vector(&self, file_path: &str) -> Option<&Vec<f32>> {
+        self.file_vectors.get(file_path)
+    }
+
+    /// Get all indexed chunks, reading each one's text from disk
+    pub fn chunks(&self) -> impl Iterator<Item = Result<Chunk>> + '_ {
+        self.metadata.chunks.iter().map(|c| self.with_text(c))
+    }
+
+    /// Get all indexed chunks without their text, which is left empty
+    ///
+    /// Cheap, for looking at paths, lines and other metadata; see
+    /// [`chunks`](Self::chunks) for the text too.
+    pub fn chunk_metadata(&self) -> &[Chunk] {
+        &self.metadata.chunks
+    }
+
     /// Get file hash if file is indexed
     pub fn get_file_hash(&self, file_path: &str) -> Option<&String> {
         self.metadata.file_hashes.get(file_path)
     }
 
+    /// Record when a file was last modified, as of reading it for indexing
+    pub fn set_file_mtime(&mut self, file_path: &str, mtime: Option<u64>) {
+        let mtimes = &mut self.metadata.file_mtimes;
+        match mtime {
+            Some(mtime) => mtimes.insert(file_path.to_string(), mtime),
+            None => mtimes.remove(file_path),
+        };
+    }
+
+    /// Modification time (Unix seconds) of an indexed file, if recorded
+    pub fn file_mtime(&self, file_path: &str) -> Option<u64> {
+        self.metadata.file_mtimes.get(file_path).copied()
+    }
+
+    /// Record the size of a file, as of reading it for indexing
+    pub fn set_file_size(&mut self, file_path: &str, size: u64) {
+        self.metadata.file_sizes.insert(file_path.to_string(), size);
+    }
+
+    /// Size in bytes of an indexed file, if recorded
+    pub fn file_size(&self, file_path: &str) -> Option<u64> {
+        self.metadata.file_sizes.get(file_path).copied()
+    }
+
     /// Get all indexed file paths
     pub fn indexed_files(&self) -> Vec<&String> {
         self.metadata.file_hashes.keys().collect()
     }
 
+    /// Format version of the index as it was loaded
+    ///
+    /// Below [`FORMAT_VERSION`] for an index written by an older lgrep and
+    /// not saved since.
+    pub fn format_version(&self) -> u32 {
+        self.format_version
+    }
+
+    /// Get the embedding dimension of the index
+    pub fn dimension(&self) -> usize {
+        self.metadata.dimension
+    }
+
     /// Get total number of chunks
     pub fn chunk_count(&self) -> usize {
         self.metadata.chunks.len()
     }
 
+    /// Whether the index holds no chunks at all
+    pub fn is_empty(&self) -> bool {
+        self.metadata.chunks.is_empty()
+    }
+
     /// Get total number of indexed files
     pub fn file_count(&self) -> usize {
         self.metadata.file_hashes.len()
@@ -244,17 +729,609 @@ impl VectorIndex {
         self.metadata.next_id
     }
 
+    /// Get the revision of the model the index was built with, if known
+    pub fn model_revision(&self) -> Option<&str> {
+        self.metadata.model_revision.as_deref()
+    }
+
+    /// Record the revision of the model used to build the index
+    pub fn set_model_revision(&mut self, revision: Option<String>) {
+        self.metadata.model_revision = revision;
+    }
+
+    /// Whether chunks were embedded with the model's instruction prefixes
+    ///
+    /// Fixed when the index is created, so incremental updates embed new
+    /// chunks the same way as the existing ones.
+    pub fn model_prefixes(&self) -> bool {
+        self.metadata.model_prefixes
+    }
+
+    /// Record whether chunks are embedded with the model's instruction prefixes
+    pub fn set_model_prefixes(&mut self, prefixes: bool) {
+        self.metadata.model_prefixes = prefixes;
+    }
+
+    /// Prefix for queries searched against this index
+    pub fn query_prefix(&self) -> &'static str {
+        if self.metadata.model_prefixes {
+            self.config.model.query_prefix()
+        } else {
+            ""
+        }
+    }
+
+    /// Prefix for chunk text embedded into this index
+    pub fn document_prefix(&self) -> &'static str {
+        if self.metadata.model_prefixes {
+            self.config.model.document_prefix()
+        } else {
+            ""
+        }
+    }
+
+    /// Get the git commit the index was last brought up to date with, if known
+    pub fn indexed_commit(&self) -> Option<&str> {
+        self.metadata.indexed_commit.as_deref()
+    }
+
+    /// Get the files that had uncommitted changes when the index was last updated
+    pub fn uncommitted_files(&self) -> &[String] {
+        &self.metadata.uncommitted_files
+    }
+
+    /// Record the git state the indexed files were read at
+    pub fn set_git_state(&mut self, commit: Option<String>, uncommitted: Vec<String>) {
+        self.metadata.indexed_commit = commit;
+        self.metadata.uncommitted_files = uncommitted;
+    }
+
     /// Get the configuration
     pub fn config(&self) -> &Config {
         &self.config
     }
 }
 
+/// Where `path` is written before being renamed into place
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".tmp");
+    PathBuf::from(name)
+}
+
+/// Refuse index files that weren't written by the same save as the metadata
+fn check_saved_files(config: &Config, saved: &SavedFiles) -> Result<()> {
+    let size = |path: PathBuf| std::fs::metadata(path).map(|m| m.len()).ok();
+    let vectors_match = size(config.index_path()) == Some(saved.vectors);
+    let file_vectors_match = saved
+        .file_vectors
+        .map_or(true, |bytes| size(config.file_vectors_path()) == Some(bytes));
+    let texts_match = saved
+        .texts
+        .map_or(true, |bytes| size(config.texts_path()) == Some(bytes));
+
+    if vectors_match && file_vectors_match && texts_match {
+        Ok(())
+    } else {
+        Err(LgrepError::Index(
+            "Index files come from different saves (was a save interrupted?); \
+             re-index with --force"
+                .to_string(),
+        ))
+    }
+}
+
+/// Refuse an index built with a different model than `config` asks for
+///
+/// Its vectors have another dimension or live in another model's embedding
+/// space, so searching them with this model would fail or return noise.
+fn check_model(config: &Config, metadata: &IndexMetadata) -> Result<()> {
+    let model = config.model.model_name();
+    let dimension = config.model.dimension();
+    // Very old indexes may not have recorded the model name
+    let other_model = !metadata.model_name.is_empty() && metadata.model_name != model;
+
+    if other_model || metadata.dimension != dimension {
+        return Err(LgrepError::Config(format!(
+            "Index was built with {} ({} dimensions) but {} ({} dimensions) was requested; \
+             use the same model, or rebuild with `lgrep index --force --model ...`",
+            metadata.model_name, metadata.dimension, model, dimension
+        )));
+    }
+    Ok(())
+}
+
+/// usearch options for an index described by `metadata`
+fn hnsw_options(metadata: &IndexMetadata) -> IndexOptions {
+    IndexOptions {
+        dimensions: metadata.dimension,
+        metric: MetricKind::Cos, // Cosine similarity
+        quantization: ScalarKind::F32,
+        connectivity: metadata.hnsw_connectivity, // M parameter for HNSW
+        expansion_add: metadata.hnsw_expansion_add, // ef_construction
+        expansion_search: metadata.hnsw_expansion_search, // ef
+        multi: false,
+    }
+}
+
+/// Cosine similarity that doesn't assume unit-length inputs
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denominator = norm(a) * norm(b);
+    if denominator > 0.0 {
+        cosine_similarity(a, b) / denominator
+    } else {
+        0.0
+    }
+}
+
+/// File-level search result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileResult {
+    /// Indexed file path
+    pub file_path: String,
+    /// Similarity of the file's mean embedding to the query
+    pub score: f32,
+    /// Workspace root the file came from (multi-root indexes only)
+    #[serde(default)]
+    pub root: Option<String>,
+}
+
 /// Search result with chunk and similarity score
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResult {
     /// The matched chunk
     pub chunk: Chunk,
     /// Similarity score (0.0 to 1.0, higher is better)
+    ///
+    /// For hybrid search this is `semantic + alpha * max(0, keyword - semantic)`,
+    /// with `keyword` in 0.0 to 1.0 rising with the pattern's matches per line.
     pub score: f32,
+    /// Raw cosine distance between the chunk and query embeddings
+    ///
+    /// Unlike `score`, never changed by keyword blending or re-ranking.
+    #[serde(default)]
+    pub distance: f32,
+    /// Pure semantic similarity before any keyword blending or re-ranking
+    pub semantic_score: Option<f32>,
+    /// Score gained from keyword matching, 0.0 for chunks without a match
+    /// (hybrid search only)
+    pub keyword_score: Option<f32>,
+    /// Byte ranges in the chunk text matched by the keyword pattern
+    pub keyword_spans: Vec<(usize, usize)>,
+    /// Other locations (`path:start-end`) with byte-identical content
+    #[serde(default)]
+    pub duplicates: Vec<String>,
+    /// Workspace root the chunk came from (multi-root indexes only)
+    #[serde(default)]
+    pub root: Option<String>,
+    /// Lines of the chunk (0-based) most similar to the query, if computed
+    #[serde(default)]
+    pub highlighted_lines: Vec<usize>,
+    /// Byte ranges of the chunk text shown highlighted in text output
+    #[serde(skip)]
+    pub highlighted_spans: Vec<(usize, usize)>,
+    /// Source lines just before the chunk, read from disk when requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub context_before: Vec<String>,
+    /// Source lines just after the chunk, read from disk when requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub context_after: Vec<String>,
+}
+
+impl SearchResult {
+    /// Create a result from a plain semantic match
+    pub fn new(chunk: Chunk, score: f32) -> Self {
+        Self {
+            chunk,
+            score,
+            distance: 1.0 - score,
+            semantic_score: Some(score),
+            keyword_score: None,
+            keyword_spans: Vec::new(),
+            duplicates: Vec::new(),
+            root: None,
+            highlighted_lines: Vec::new(),
+            highlighted_spans: Vec::new(),
+            context_before: Vec::new(),
+            context_after: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::ContentKind;
+    use crate::config::EmbeddingModel;
+    use std::path::PathBuf;
+
+    fn chunk(id: u64) -> Chunk {
+        Chunk {
+            id,
+            text: format!("chunk {}", id),
+            file_path: format!("file{}.rs", id),
+            start_line: 1,
+            end_line: 1,
+            file_hash: "hash".to_string(),
+            language: Some("rust".to_string()),
+            overlap_with_prev: 0,
+            content_kind: ContentKind::Code,
+            text_hash: String::new(),
+            symbols: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_chunk_at_prefers_chunk_owning_the_line() {
+        let config = Config::new(PathBuf::from("/nonexistent"));
+        let dimension = config.model.dimension();
+        let mut index = VectorIndex::new(config).unwrap();
+
+        // Second chunk overlaps the first on lines 8-10
+        let mut first = chunk(0);
+        first.end_line = 10;
+        let mut second = chunk(1);
+        second.file_path = "file0.rs".to_string();
+        second.start_line = 8;
+        second.end_line = 20;
+        index
+            .add_chunks(vec![first, second], vec![vec![1.0; dimension]; 2])
+            .unwrap();
+
+        assert_eq!(index.chunk_at("file0.rs", 3).unwrap().id, 0);
+        assert_eq!(index.chunk_at("file0.rs", 9).unwrap().id, 1);
+        assert_eq!(index.chunk_at("file0.rs", 20).unwrap().id, 1);
+        assert!(index.chunk_at("file0.rs", 21).is_none());
+        assert!(index.chunk_at("file1.rs", 3).is_none());
+    }
+
+    #[test]
+    fn test_wrong_dimension_is_typed() {
+        let config = Config::new(PathBuf::from("/nonexistent"));
+        let dimension = config.model.dimension();
+        let mut index = VectorIndex::new(config).unwrap();
+
+        let result = index.add_chunks(vec![chunk(0)], vec![vec![1.0; 3]]);
+        assert!(matches!(
+            result,
+            Err(LgrepError::DimensionMismatch { expected, got: 3 }) if expected == dimension
+        ));
+        assert_eq!(index.chunk_count(), 0);
+
+        let result = index.search(&[1.0; 3], 5);
+        assert!(matches!(
+            result,
+            Err(LgrepError::DimensionMismatch { got: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn test_from_chunks_and_embeddings_checks_dimension() {
+        let config = Config::new(PathBuf::from("/nonexistent"));
+        let dimension = config.model.dimension();
+
+        let index = VectorIndex::from_chunks_and_embeddings(
+            config.clone(),
+            vec![chunk(0), chunk(1)],
+            vec![vec![1.0; dimension]; 2],
+        )
+        .unwrap();
+        assert_eq!(index.chunk_count(), 2);
+        assert!(index.get_vector(1).unwrap().is_some());
+
+        let result = VectorIndex::from_chunks_and_embeddings(
+            config,
+            vec![chunk(0), chunk(1)],
+            vec![vec![1.0; dimension], vec![1.0; dimension / 2]],
+        );
+        assert!(matches!(
+            result,
+            Err(LgrepError::DimensionMismatch { expected, got })
+                if expected == dimension && got == dimension / 2
+        ));
+    }
+
+    #[test]
+    fn test_iter_with_embeddings() {
+        let config = Config::new(PathBuf::from("/nonexistent"));
+        let dimension = config.model.dimension();
+        let mut index = VectorIndex::new(config).unwrap();
+        index
+            .add_chunks(
+                vec![chunk(0), chunk(1)],
+                vec![vec![1.0; dimension], vec![0.5; dimension]],
+            )
+            .unwrap();
+
+        let pairs: Vec<(u64, Vec<f32>)> = index
+            .iter_with_embeddings()
+            .map(|pair| pair.map(|(chunk, vector)| (chunk.id, vector)))
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[0].0, 0);
+        assert_eq!(pairs[0].1.len(), dimension);
+        assert_eq!(pairs[1].0, 1);
+    }
+
+    #[test]
+    fn test_single_chunk_file_vector_equals_chunk_vector() {
+        let config = Config::new(PathBuf::from("/nonexistent")).with_file_embeddings(true);
+        let dimension = config.model.dimension();
+        let mut index = VectorIndex::new(config).unwrap();
+
+        let mut single = vec![0.0; dimension];
+        single[3] = 1.0;
+        let mut pair = chunk(2);
+        pair.file_path = "file1.rs".to_string();
+        let mut a = vec![0.0; dimension];
+        a[0] = 1.0;
+        let mut b = vec![0.0; dimension];
+        b[1] = 1.0;
+
+        index
+            .add_chunks(vec![chunk(0), chunk(1), pair], vec![single.clone(), a, b])
+            .unwrap();
+
+        assert_eq!(index.file_vector("file0.rs"), Some(&single));
+        let mean = index.file_vector("file1.rs").unwrap();
+        assert_eq!((mean[0], mean[1]), (0.5, 0.5));
+
+        index.remove_file("file0.rs").unwrap();
+        assert!(index.file_vector("file0.rs").is_none());
+    }
+
+    #[test]
+    fn test_search_filtered_fills_top_k() {
+        let config = Config::new(PathBuf::from("/nonexistent"));
+        let dimension = config.model.dimension();
+        let mut index = VectorIndex::new(config).unwrap();
+
+        // Chunk i leans further away from the query as i grows
+        let chunks: Vec<Chunk> = (0..40).map(chunk).collect();
+        let embeddings = (0..40)
+            .map(|i| {
+                let mut v = vec![0.0; dimension];
+                v[0] = 1.0;
+                v[1] = i as f32 * 0.05;
+                v
+            })
+            .collect();
+        index.add_chunks(chunks, embeddings).unwrap();
+
+        let mut query = vec![0.0; dimension];
+        query[0] = 1.0;
+
+        // Rejecting every other chunk still yields a full page
+        let results = index
+            .search_filtered(&query, 10, |c| c.id % 2 == 0)
+            .unwrap();
+        assert_eq!(results.len(), 10);
+        assert!(results.iter().all(|r| r.chunk.id % 2 == 0));
+
+        // A predicate that almost nothing passes exhausts the index
+        let results = index.search_filtered(&query, 10, |c| c.id == 37).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].chunk.id, 37);
+    }
+
+    #[test]
+    fn test_compact_reassigns_ids_and_keeps_search_working() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::new(PathBuf::from(dir.path()));
+        let dimension = config.model.dimension();
+        let mut index = VectorIndex::new(config.clone()).unwrap();
+
+        let embedding = |i: usize| {
+            let mut v = vec![0.0; dimension];
+            v[i] = 1.0;
+            v
+        };
+        let chunks: Vec<Chunk> = (0..20).map(chunk).collect();
+        index
+            .add_chunks(chunks, (0..20).map(embedding).collect())
+            .unwrap();
+        index.save().unwrap();
+        let saved_size = std::fs::metadata(config.index_path()).unwrap().len();
+
+        for i in (0..20).step_by(2) {
+            index.remove_file(&format!("file{}.rs", i)).unwrap();
+        }
+        // Removed vectors are hidden from size() but still take up space
+        assert_eq!(index.index.size(), 10);
+        let length_before = index.index.serialized_length();
+        index.compact().unwrap();
+
+        assert!(index.index.serialized_length() < length_before);
+        assert_eq!(index.index.size(), 10);
+        assert_eq!(index.chunk_count(), 10);
+        assert_eq!(index.file_count(), 10);
+        assert_eq!(index.next_id(), 10);
+        let ids: Vec<u64> = index.chunk_metadata().iter().map(|c| c.id).collect();
+        assert_eq!(ids, (0..10).collect::<Vec<_>>());
+        assert!(std::fs::metadata(config.index_path()).unwrap().len() < saved_size);
+
+        // Every surviving file is still found by its own vector
+        let reloaded = VectorIndex::load(config).unwrap();
+        for i in (1..20).step_by(2) {
+            let results = reloaded.search(&embedding(i), 1).unwrap();
+            assert_eq!(results[0].chunk.file_path, format!("file{}.rs", i));
+            assert!(results[0].score > 0.99);
+        }
+        let removed = reloaded.search(&embedding(4), 10).unwrap();
+        assert!(removed.iter().all(|r| r.score < 0.5));
+    }
+
+    #[test]
+    fn test_load_keeps_hnsw_parameters() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::new(PathBuf::from(dir.path())).with_hnsw(8, 64, 32);
+        let dimension = config.model.dimension();
+        let mut index = VectorIndex::new(config.clone()).unwrap();
+        assert_eq!(index.index.connectivity(), 8);
+        index
+            .add_chunks(vec![chunk(0)], vec![vec![1.0; dimension]])
+            .unwrap();
+        index.save().unwrap();
+
+        // Reloading with the default settings keeps the build parameters
+        let loaded = VectorIndex::load(Config::new(PathBuf::from(dir.path()))).unwrap();
+        assert_eq!(loaded.index.connectivity(), 8);
+        assert_eq!(loaded.index.expansion_add(), 64);
+        assert_eq!(loaded.index.expansion_search(), 32);
+        assert_eq!(loaded.metadata.hnsw_connectivity, 8);
+    }
+
+    #[test]
+    fn test_text_is_read_on_demand() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::new(PathBuf::from(dir.path()));
+        let dimension = config.model.dimension();
+        let embedding = |i: usize| {
+            let mut v = vec![0.0; dimension];
+            v[i] = 1.0;
+            v
+        };
+        let mut index = VectorIndex::new(config.clone()).unwrap();
+        index
+            .add_chunks((0..3).map(chunk).collect(), (0..3).map(embedding).collect())
+            .unwrap();
+        index.save().unwrap();
+
+        let mut loaded = VectorIndex::load(config.clone()).unwrap();
+        assert!(loaded.chunk_metadata().iter().all(|c| c.text.is_empty()));
+        assert_eq!(loaded.chunk_text(1).unwrap().as_deref(), Some("chunk 1"));
+        let texts: Vec<String> = loaded.chunks().map(|c| c.unwrap().text).collect();
+        assert_eq!(texts, ["chunk 0", "chunk 1", "chunk 2"]);
+        let results = loaded.search(&embedding(2), 1).unwrap();
+        assert_eq!(results[0].chunk.text, "chunk 2");
+
+        // Replaced and removed chunks survive the next save
+        loaded.remove_file("file0.rs").unwrap();
+        let mut changed = chunk(1);
+        changed.text = "changed".to_string();
+        loaded.remove_file("file1.rs").unwrap();
+        loaded
+            .add_chunks(vec![changed], vec![embedding(1)])
+            .unwrap();
+        loaded.save().unwrap();
+
+        let reloaded = VectorIndex::load(config).unwrap();
+        assert_eq!(reloaded.chunk_text(0).unwrap(), None);
+        assert_eq!(reloaded.chunk_text(1).unwrap().as_deref(), Some("changed"));
+        assert_eq!(reloaded.chunk_text(2).unwrap().as_deref(), Some("chunk 2"));
+    }
+
+    #[test]
+    fn test_failed_save_keeps_previous_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::new(PathBuf::from(dir.path()));
+        let dimension = config.model.dimension();
+        let mut index = VectorIndex::new(config.clone()).unwrap();
+        index
+            .add_chunks(vec![chunk(0)], vec![vec![1.0; dimension]])
+            .unwrap();
+        index.save().unwrap();
+
+        // The metadata can't be written, after the vectors already were
+        std::fs::create_dir(temp_path(&config.metadata_path())).unwrap();
+        index
+            .add_chunks(vec![chunk(1)], vec![vec![0.5; dimension]])
+            .unwrap();
+        assert!(index.save().is_err());
+        assert!(!temp_path(&config.index_path()).exists());
+
+        // A stray temporary file doesn't get in the way either
+        std::fs::write(temp_path(&config.index_path()), b"partial").unwrap();
+        let loaded = VectorIndex::load(config).unwrap();
+        assert_eq!(loaded.chunk_count(), 1);
+        assert_eq!(loaded.index.size(), 1);
+    }
+
+    #[test]
+    fn test_load_refuses_files_from_different_saves() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::new(PathBuf::from(dir.path()));
+        let dimension = config.model.dimension();
+        let mut index = VectorIndex::new(config.clone()).unwrap();
+        index
+            .add_chunks(vec![chunk(0)], vec![vec![1.0; dimension]])
+            .unwrap();
+        index.save().unwrap();
+        let old_metadata = std::fs::read(config.metadata_path()).unwrap();
+
+        // A save that stopped after renaming the vectors, before the metadata
+        index
+            .add_chunks(vec![chunk(1)], vec![vec![0.5; dimension]])
+            .unwrap();
+        index.save().unwrap();
+        std::fs::write(config.metadata_path(), &old_metadata).unwrap();
+
+        let err = VectorIndex::load(config.clone()).err().unwrap().to_string();
+        assert!(err.contains("different saves"), "{}", err);
+
+        // The next complete save pairs them up again
+        index.save().unwrap();
+        assert_eq!(VectorIndex::load(config).unwrap().chunk_count(), 2);
+    }
+
+    #[test]
+    fn test_load_refuses_other_model() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::new(PathBuf::from(dir.path()));
+        let dimension = config.model.dimension();
+        let mut index = VectorIndex::new(config.clone()).unwrap();
+        index
+            .add_chunks(vec![chunk(0)], vec![vec![1.0; dimension]])
+            .unwrap();
+        index.save().unwrap();
+
+        // Different dimension
+        let nomic = config.clone().with_model(EmbeddingModel::NomicEmbedTextV15);
+        let err = VectorIndex::load(nomic).err().unwrap();
+        assert!(matches!(err, LgrepError::Config(_)));
+        let message = err.to_string();
+        assert!(message.contains("384 dimensions"), "{}", message);
+        assert!(message.contains("768 dimensions"), "{}", message);
+        assert!(message.contains("--force"), "{}", message);
+
+        // Same dimension, different embedding space
+        let bge = config.clone().with_model(EmbeddingModel::BgeSmallEnV15);
+        let err = VectorIndex::load(bge).err().unwrap().to_string();
+        assert!(err.contains("all-MiniLM-L6-v2"), "{}", err);
+
+        assert_eq!(VectorIndex::load(config).unwrap().chunk_count(), 1);
+    }
+
+    #[test]
+    fn test_load_refuses_newer_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::new(PathBuf::from(dir.path()));
+        let dimension = config.model.dimension();
+        let mut index = VectorIndex::new(config.clone()).unwrap();
+        index
+            .add_chunks(vec![chunk(0)], vec![vec![1.0; dimension]])
+            .unwrap();
+        index.save().unwrap();
+
+        // Headerless metadata is the legacy format and is migrated on save
+        let bytes = std::fs::read(config.metadata_path()).unwrap();
+        assert_eq!(IndexMetadata::format_version(&bytes), FORMAT_VERSION);
+        std::fs::write(config.metadata_path(), &bytes[8..]).unwrap();
+        let legacy = VectorIndex::load(config.clone()).unwrap();
+        assert_eq!(legacy.chunk_count(), 1);
+        assert_eq!(legacy.format_version(), 1);
+        legacy.save().unwrap();
+        let bytes = std::fs::read(config.metadata_path()).unwrap();
+        assert_eq!(IndexMetadata::format_version(&bytes), FORMAT_VERSION);
+
+        let mut future = bytes.clone();
+        future[4..8].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        std::fs::write(config.metadata_path(), future).unwrap();
+
+        let err = VectorIndex::load(config).err().unwrap().to_string();
+        assert!(err.contains("newer lgrep"), "{}", err);
+        assert!(err.contains("upgrade"), "{}", err);
+    }
 }
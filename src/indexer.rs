@@ -3,48 +3,290 @@
 //! Walks directories respecting .gitignore, chunks files,
 //! generates embeddings, and builds the search index.
 
-use crate::chunker::Chunker;
-use crate::config::{should_index_file, Config};
-use crate::embedder::Embedder;
-use crate::error::Result;
+use crate::archive;
+use crate::chunker::{detect_language, Chunk, Chunker};
+use crate::config::{Config, WorkspaceRoot};
+use crate::embedder::{Embed, Embedder};
+use crate::error::{LgrepError, Result};
+use crate::git;
+use crate::gitattributes::LinguistAttributes;
 use crate::index::VectorIndex;
+use ignore::gitignore::Gitignore;
 use ignore::WalkBuilder;
 use indicatif::{ProgressBar, ProgressStyle};
 use rayon::prelude::*;
+use serde::Serialize;
 use sha2::{Digest, Sha256};
-use std::collections::HashSet;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
-use tracing::{debug, info};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::{debug, info, warn};
 
 /// File to be indexed with content and hash
 #[derive(Debug)]
-struct FileToIndex {
-    #[allow(dead_code)]
+pub(crate) struct FileToIndex {
     path: PathBuf,
-    relative_path: String,
-    content: String,
-    hash: String,
+    pub(crate) relative_path: String,
+    /// `None` for files above the stream threshold, chunked from `path`
+    pub(crate) content: Option<String>,
+    pub(crate) hash: String,
+    /// Modification time in seconds since the Unix epoch, if known
+    pub(crate) mtime: Option<u64>,
+    /// Size of the file in bytes
+    pub(crate) size: u64,
+}
+
+impl FileToIndex {
+    /// Chunk the file, reading it from disk if its content wasn't kept
+    pub(crate) fn chunk(&self, chunker: &Chunker, start_id: u64) -> Result<Vec<Chunk>> {
+        match &self.content {
+            Some(content) => {
+                Ok(chunker.chunk_text(content, &self.relative_path, &self.hash, start_id))
+            }
+            None => {
+                let reader = BufReader::new(File::open(&self.path)?);
+                chunker.chunk_reader(reader, &self.relative_path, &self.hash, start_id)
+            }
+        }
+    }
+}
+
+/// One step of indexing progress
+///
+/// Serializes as `{"phase":"discover","found":N}`,
+/// `{"phase":"chunk","done":D,"total":T}`,
+/// `{"phase":"download","model":M,"size_mb":S}` and
+/// `{"phase":"embed","done":D,"total":T}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "phase", rename_all = "lowercase")]
+pub enum Progress {
+    /// Walking the roots, with `found` indexable files so far
+    Discover { found: usize },
+    /// Fetching the embedding model before first use (about `size_mb` MB)
+    Download { model: String, size_mb: u64 },
+    /// Splitting files into chunks
+    Chunk { done: usize, total: usize },
+    /// Embedding new and changed chunks
+    Embed { done: usize, total: usize },
+}
+
+/// Sink for indexing progress
+pub trait ProgressReporter: Send + Sync {
+    /// Record progress within the current phase
+    fn report(&self, progress: Progress);
+
+    /// The current phase is over; `message` summarizes it for people
+    fn finish(&self, message: &str);
+}
+
+/// Draws indicatif bars on the terminal, one per phase
+#[derive(Default)]
+pub struct TerminalProgress {
+    bar: std::sync::Mutex<Option<ProgressBar>>,
+}
+
+impl TerminalProgress {
+    fn new_bar(progress: &Progress) -> ProgressBar {
+        let (bar, template) = match progress {
+            Progress::Discover { .. } => {
+                let bar = ProgressBar::new_spinner();
+                bar.set_style(
+                    ProgressStyle::default_spinner()
+                        .template("{spinner:.green} {msg}")
+                        .unwrap(),
+                );
+                return bar;
+            }
+            // fastembed draws its own download bar
+            Progress::Download { .. } => return ProgressBar::hidden(),
+            Progress::Chunk { total, .. } => (
+                ProgressBar::new(*total as u64),
+                "{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} files chunked",
+            ),
+            Progress::Embed { total, .. } => (
+                ProgressBar::new(*total as u64),
+                "{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} chunks embedded",
+            ),
+        };
+        bar.set_style(
+            ProgressStyle::default_bar()
+                .template(template)
+                .unwrap()
+                .progress_chars("=>-"),
+        );
+        bar
+    }
+}
+
+impl ProgressReporter for TerminalProgress {
+    fn report(&self, progress: Progress) {
+        if let Progress::Download { model, size_mb } = &progress {
+            eprintln!(
+                "Downloading model {} (~{}MB), this happens once",
+                model, size_mb
+            );
+            return;
+        }
+
+        let mut bar = self.bar.lock().unwrap_or_else(|e| e.into_inner());
+        let bar = bar.get_or_insert_with(|| Self::new_bar(&progress));
+        match progress {
+            Progress::Download { .. } => {}
+            Progress::Discover { found: 0 } => bar.set_message("Discovering files..."),
+            Progress::Discover { found } => {
+                bar.set_message(format!("Found {} files, reading...", found))
+            }
+            Progress::Chunk { done, .. } | Progress::Embed { done, .. } => {
+                bar.set_position(done as u64)
+            }
+        }
+    }
+
+    fn finish(&self, message: &str) {
+        let bar = self.bar.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(bar) = bar {
+            bar.finish_with_message(message.to_string());
+        }
+    }
+}
+
+/// Writes each event as a line of JSON to stderr, for editors and CI
+///
+/// Chunking reports every file, so only every hundredth and the last are
+/// written.
+#[derive(Debug, Default)]
+pub struct JsonProgress;
+
+impl ProgressReporter for JsonProgress {
+    fn report(&self, progress: Progress) {
+        if let Progress::Chunk { done, total } = progress {
+            if done % 100 != 0 && done != total {
+                return;
+            }
+        }
+        if let Ok(line) = serde_json::to_string(&progress) {
+            let _ = writeln!(std::io::stderr().lock(), "{}", line);
+        }
+    }
+
+    fn finish(&self, _message: &str) {}
+}
+
+/// How `lgrep index` shows its progress
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProgressFormat {
+    /// Terminal progress bars
+    #[default]
+    Bar,
+    /// Newline-delimited JSON events on stderr
+    Json,
+}
+
+impl ProgressFormat {
+    /// Reporter that writes in this format
+    pub fn reporter(self) -> Box<dyn ProgressReporter> {
+        match self {
+            Self::Bar => Box::new(TerminalProgress::default()),
+            Self::Json => Box::new(JsonProgress),
+        }
+    }
+}
+
+impl std::str::FromStr for ProgressFormat {
+    type Err = LgrepError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "bar" => Ok(Self::Bar),
+            "json" | "ndjson" | "jsonl" => Ok(Self::Json),
+            _ => Err(LgrepError::Config(format!(
+                "Unknown progress format: {}. Valid options: bar, json",
+                s
+            ))),
+        }
+    }
 }
 
 /// Indexer for building and updating the semantic index
 pub struct Indexer {
     config: Config,
-    embedder: Embedder,
+    /// Loaded on first embed, so updates with nothing to embed skip the model
+    embedder: OnceLock<Box<dyn Embed>>,
     chunker: Chunker,
+    progress: Box<dyn ProgressReporter>,
 }
 
 impl Indexer {
     /// Create a new indexer with the given configuration
+    ///
+    /// The model isn't loaded (or downloaded) until something needs
+    /// embedding.
     pub fn new(config: Config) -> Result<Self> {
-        let embedder = Embedder::new(&config.model)?;
-        let chunker = Chunker::new(config.chunk_size, config.chunk_overlap);
+        Ok(Self::with_chunker(config, OnceLock::new()))
+    }
+
+    /// Create an indexer with an already-constructed embedder
+    pub fn with_embedder(config: Config, embedder: Box<dyn Embed>) -> Self {
+        Self::with_chunker(config, OnceLock::from(embedder))
+    }
 
-        Ok(Self {
+    fn with_chunker(config: Config, embedder: OnceLock<Box<dyn Embed>>) -> Self {
+        let chunker = Chunker::from_config(&config);
+
+        Self {
             config,
             embedder,
             chunker,
-        })
+            progress: Box::new(TerminalProgress::default()),
+        }
+    }
+
+    /// The embedder, loading the configured model on first use
+    fn embedder(&self) -> Result<&dyn Embed> {
+        if let Some(embedder) = self.embedder.get() {
+            return Ok(embedder.as_ref());
+        }
+
+        let model = &self.config.model;
+        if let Some(size_mb) = model.download_size_mb() {
+            if !Embedder::is_cached(model) {
+                self.progress.report(Progress::Download {
+                    model: model.model_name().to_string(),
+                    size_mb,
+                });
+            }
+        }
+        let embedder = Embedder::new(model, self.config.execution_provider)?;
+        Ok(self.embedder.get_or_init(|| Box::new(embedder)).as_ref())
+    }
+
+    /// Revision of the model files, without loading the model if it isn't yet
+    fn model_revision(&self) -> Option<String> {
+        match self.embedder.get() {
+            Some(embedder) => embedder.revision().map(String::from),
+            None => Embedder::cached_revision(&self.config.model),
+        }
+    }
+
+    /// Send progress to `progress` instead of terminal bars
+    pub fn with_progress(mut self, progress: Box<dyn ProgressReporter>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Give up the indexer, keeping its embedder (e.g. for a [`Searcher`])
+    ///
+    /// Loads the model if nothing was embedded yet.
+    ///
+    /// [`Searcher`]: crate::searcher::Searcher
+    pub fn into_embedder(self) -> Result<Box<dyn Embed>> {
+        self.embedder()?;
+        Ok(self
+            .embedder
+            .into_inner()
+            .expect("embedder was just loaded"))
     }
 
     /// Build a fresh index from scratch
@@ -52,14 +294,43 @@ impl Indexer {
         info!("Building fresh index for {:?}", self.config.root_path);
 
         let mut index = VectorIndex::new(self.config.clone())?;
-        let files = self.discover_files()?;
+        let (commit, uncommitted) = git_state(&self.config);
+        index.set_git_state(commit, uncommitted);
+        let files = discover_files_with(&self.config, self.progress.as_ref())?;
 
         if files.is_empty() {
             info!("No files to index");
+            index.set_model_revision(self.model_revision());
             return Ok(index);
         }
 
         self.index_files(&mut index, files)?;
+        // Known for sure once the model is loaded (downloaded, on a first run)
+        index.set_model_revision(self.model_revision());
+        index.save()?;
+
+        Ok(index)
+    }
+
+    /// Build a fresh index from exactly the given files (no directory walk)
+    ///
+    /// For file lists produced by other tools (`fd`, `git ls-files`).
+    /// Relative paths are taken from the first root, and paths outside every
+    /// root are skipped with a warning. With `filter`, files lgrep wouldn't
+    /// index itself (by extension or size) are skipped too; ignore rules are
+    /// left to whatever produced the list.
+    pub fn build_index_from_paths(&self, paths: &[PathBuf], filter: bool) -> Result<VectorIndex> {
+        info!("Building index from {} listed paths", paths.len());
+
+        let mut index = VectorIndex::new(self.config.clone())?;
+        let (commit, uncommitted) = git_state(&self.config);
+        index.set_git_state(commit, uncommitted);
+        let files = listed_files(&self.config, paths, filter)?;
+
+        if !files.is_empty() {
+            self.index_files(&mut index, files)?;
+        }
+        index.set_model_revision(self.model_revision());
         index.save()?;
 
         Ok(index)
@@ -69,7 +340,49 @@ impl Indexer {
     pub fn update_index(&self, index: &mut VectorIndex) -> Result<UpdateStats> {
         info!("Updating index for {:?}", self.config.root_path);
 
-        let files = self.discover_files()?;
+        if let (Some(built), Some(current)) = (index.model_revision(), self.model_revision()) {
+            if built != current {
+                warn!(
+                    "Model files changed since the index was built ({} -> {}); \
+                     re-index with --force for consistent results",
+                    built, current
+                );
+            }
+        }
+
+        if index.model_prefixes() != self.config.model.uses_prefixes() {
+            let built = if index.model_prefixes() {
+                "with"
+            } else {
+                "without"
+            };
+            warn!(
+                "Index was built {} the model's instruction prefixes; updates keep it that \
+                 way, re-index with --force to switch",
+                built
+            );
+        }
+
+        // Taken before reading any files, so later edits show up in the next diff
+        let (commit, uncommitted) = git_state(&self.config);
+
+        if let Some(paths) = git_changed_paths(&self.config, index) {
+            debug!("Checking {} paths changed according to git", paths.len());
+            let roots = self.config.roots()?;
+            let (files_to_add, mut stats) =
+                collect_path_changes(&roots, &self.config, index, &paths)?;
+            self.index_files(index, files_to_add)?;
+
+            stats.unchanged = index
+                .file_count()
+                .saturating_sub(stats.added + stats.updated);
+            index.set_git_state(commit, uncommitted);
+            index.save()?;
+
+            return Ok(stats);
+        }
+
+        let files = discover_files_with(&self.config, self.progress.as_ref())?;
         let mut stats = UpdateStats::default();
 
         // Find files that need updating
@@ -90,8 +403,7 @@ impl Indexer {
                     stats.unchanged += 1;
                     continue;
                 }
-                // File changed - remove old chunks
-                index.remove_file(&file.relative_path)?;
+                // File changed - old chunks are replaced when it is re-chunked
                 stats.updated += 1;
             } else {
                 stats.added += 1;
@@ -100,6 +412,8 @@ impl Indexer {
             files_to_add.push(file);
         }
 
+        apply_renames(index, &mut files_to_add, &mut files_to_remove, &mut stats);
+
         // Remove deleted files
         for file_path in &files_to_remove {
             index.remove_file(file_path)?;
@@ -111,83 +425,28 @@ impl Indexer {
             self.index_files(index, files_to_add)?;
         }
 
+        index.set_git_state(commit, uncommitted);
         index.save()?;
 
         Ok(stats)
     }
 
-    /// Discover all indexable files in the root directory
-    fn discover_files(&self) -> Result<Vec<FileToIndex>> {
-        let pb = ProgressBar::new_spinner();
-        pb.set_style(
-            ProgressStyle::default_spinner()
-                .template("{spinner:.green} {msg}")
-                .unwrap(),
-        );
-        pb.set_message("Discovering files...");
-
-        let root = self.config.root_path.canonicalize()?;
-        let files = Arc::new(Mutex::new(Vec::new()));
-
-        // Use ignore crate to respect .gitignore
-        let walker = WalkBuilder::new(&root)
-            .hidden(true)
-            .git_ignore(true)
-            .git_global(true)
-            .git_exclude(true)
-            .ignore(true)
-            .parents(true)
-            .add_custom_ignore_filename(".lgrepignore")
-            .build();
-
-        // Collect file paths first
-        let file_paths: Vec<PathBuf> = walker
-            .filter_map(|entry| entry.ok())
-            .filter(|entry| {
-                entry.file_type().map(|ft| ft.is_file()).unwrap_or(false)
-                    && should_index_file(entry.path())
-            })
-            .filter(|entry| {
-                entry
-                    .metadata()
-                    .map(|m| m.len() <= self.config.max_file_size)
-                    .unwrap_or(false)
-            })
-            .map(|entry| entry.path().to_path_buf())
-            .collect();
-
-        pb.set_message(format!("Found {} files, reading...", file_paths.len()));
-
-        // Read files in parallel
-        let root_clone = root.clone();
-
-        file_paths.par_iter().for_each(|path| {
-            if let Ok(content) = std::fs::read_to_string(path) {
-                let relative_path = path
-                    .strip_prefix(&root_clone)
-                    .unwrap_or(path)
-                    .to_string_lossy()
-                    .to_string();
-
-                let hash = compute_hash(&content);
-
-                let file = FileToIndex {
-                    path: path.clone(),
-                    relative_path,
-                    content,
-                    hash,
-                };
+    /// Update the index for a specific set of paths (targeted, no directory walk)
+    ///
+    /// Used by the watcher, which already knows which paths changed. Paths
+    /// that no longer exist (or are no longer indexable) are removed from
+    /// the index, including any indexed files below a deleted directory.
+    pub fn update_paths(&self, index: &mut VectorIndex, paths: &[PathBuf]) -> Result<UpdateStats> {
+        let roots = self.config.roots()?;
+        let (files_to_add, stats) = collect_path_changes(&roots, &self.config, index, paths)?;
 
-                files.lock().unwrap().push(file);
-            }
-        });
-
-        pb.finish_with_message("File discovery complete");
+        if !files_to_add.is_empty() {
+            self.index_files(index, files_to_add)?;
+        }
 
-        let result = Arc::try_unwrap(files).unwrap().into_inner().unwrap();
-        info!("Discovered {} indexable files", result.len());
+        index.save()?;
 
-        Ok(result)
+        Ok(stats)
     }
 
     /// Index a list of files
@@ -197,60 +456,74 @@ impl Indexer {
         }
 
         // Create chunks from all files
-        let pb = ProgressBar::new(files.len() as u64);
-        pb.set_style(
-            ProgressStyle::default_bar()
-                .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} files chunked")
-                .unwrap()
-                .progress_chars("=>-"),
-        );
+        let total = files.len();
+        self.progress.report(Progress::Chunk { done: 0, total });
 
+        let mut reused = Vec::new();
         let mut all_chunks = Vec::new();
         let mut next_id = index.next_id();
 
-        for file in &files {
-            let chunks =
-                self.chunker
-                    .chunk_text(&file.content, &file.relative_path, &file.hash, next_id);
+        for (done, file) in (1..).zip(&files) {
+            let chunks = match file.chunk(&self.chunker, next_id) {
+                Ok(chunks) => chunks,
+                Err(e) => {
+                    warn!("Skipping {}: {}", file.relative_path, e);
+                    self.progress.report(Progress::Chunk { done, total });
+                    continue;
+                }
+            };
 
             next_id += chunks.len() as u64;
-            all_chunks.extend(chunks);
-            pb.inc(1);
+            let (kept, changed) = reuse_unchanged_chunks(index, &file.relative_path, chunks)?;
+            index.set_file_mtime(&file.relative_path, file.mtime);
+            index.set_file_size(&file.relative_path, file.size);
+            reused.extend(kept);
+            all_chunks.extend(changed);
+            self.progress.report(Progress::Chunk { done, total });
         }
 
-        pb.finish_with_message(format!(
+        self.progress.finish(&format!(
             "Created {} chunks from {} files",
-            all_chunks.len(),
+            all_chunks.len() + reused.len(),
             files.len()
         ));
 
+        if !reused.is_empty() {
+            debug!("Reusing embeddings of {} unchanged chunks", reused.len());
+        }
+        let (mut chunks, mut vectors): (Vec<Chunk>, Vec<Vec<f32>>) = reused.into_iter().unzip();
+
         if all_chunks.is_empty() {
-            return Ok(());
+            return index.add_chunks(chunks, vectors);
         }
 
-        // Generate embeddings
-        let pb = ProgressBar::new(all_chunks.len() as u64);
-        pb.set_style(
-            ProgressStyle::default_bar()
-                .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} chunks embedded")
-                .unwrap()
-                .progress_chars("=>-"),
-        );
+        // Generate embeddings, loading the model before the bar is drawn
+        let embedder = self.embedder()?;
+        let total = all_chunks.len();
+        self.progress.report(Progress::Embed { done: 0, total });
 
-        let texts: Vec<String> = all_chunks.iter().map(|c| c.text.clone()).collect();
         let batch_size = 32;
+        let prefix = index.document_prefix();
+        let mut embeddings = Vec::with_capacity(all_chunks.len());
+        for batch in all_chunks.chunks(batch_size) {
+            let texts: Vec<String> = batch
+                .iter()
+                .map(|c| format!("{}{}", prefix, c.text))
+                .collect();
+            embeddings.extend(embedder.embed_batch(texts.iter().map(String::as_str).collect())?);
+            self.progress.report(Progress::Embed {
+                done: embeddings.len(),
+                total,
+            });
+        }
 
-        let embeddings =
-            self.embedder
-                .embed_batch_with_progress(texts, batch_size, |done, _total| {
-                    pb.set_position(done as u64);
-                })?;
-
-        pb.finish_with_message("Embeddings generated");
+        self.progress.finish("Embeddings generated");
 
-        // Add to index
-        info!("Adding {} chunks to index", all_chunks.len());
-        index.add_chunks(all_chunks, embeddings)?;
+        // Add to index, together with the reused chunks of the same files
+        chunks.extend(all_chunks);
+        vectors.extend(embeddings);
+        info!("Adding {} chunks to index", chunks.len());
+        index.add_chunks(chunks, vectors)?;
 
         Ok(())
     }
@@ -265,23 +538,1489 @@ pub struct UpdateStats {
     pub updated: usize,
     /// Number of files removed (deleted)
     pub removed: usize,
+    /// Number of files moved to a new path unchanged (embeddings kept)
+    pub renamed: usize,
     /// Number of unchanged files
     pub unchanged: usize,
 }
 
+impl UpdateStats {
+    /// Whether the update changed the index at all
+    pub fn changed(&self) -> bool {
+        self.added > 0 || self.updated > 0 || self.removed > 0 || self.renamed > 0
+    }
+}
+
 impl std::fmt::Display for UpdateStats {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Added: {}, Updated: {}, Removed: {}, Unchanged: {}",
-            self.added, self.updated, self.removed, self.unchanged
+            "Added: {}, Updated: {}, Renamed: {}, Removed: {}, Unchanged: {}",
+            self.added, self.updated, self.renamed, self.removed, self.unchanged
         )
     }
 }
 
+/// What indexing would cover, worked out without loading the model
+#[derive(Debug, Default)]
+pub struct IndexPlan {
+    /// Number of files that would be chunked
+    pub files: usize,
+    /// Their total size in bytes
+    pub total_bytes: u64,
+    /// Estimated number of chunks
+    pub estimated_chunks: usize,
+    /// Breakdown by detected language (`unknown` when none was detected)
+    pub by_language: BTreeMap<String, PlanGroup>,
+    /// Breakdown by lowercased file extension (`none` for files without one)
+    pub by_extension: BTreeMap<String, PlanGroup>,
+}
+
+/// Files, bytes and estimated chunks of one group in an [`IndexPlan`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PlanGroup {
+    pub files: usize,
+    pub bytes: u64,
+    pub estimated_chunks: usize,
+}
+
+impl PlanGroup {
+    fn add(&mut self, bytes: u64, chunks: usize) {
+        self.files += 1;
+        self.bytes += bytes;
+        self.estimated_chunks += chunks;
+    }
+}
+
+/// Discover the files `config` would index and estimate their chunks
+///
+/// Walks (or reads the archive) exactly like indexing does, but never
+/// constructs an embedder. Chunk counts are estimated from file sizes and the
+/// (per-language) chunk size and overlap, so they are approximate.
+pub fn plan_index(config: &Config) -> Result<IndexPlan> {
+    let mut plan = IndexPlan::default();
+
+    for file in discover_files(config)? {
+        let language = detect_language(&file.relative_path);
+        let chunk_size = language
+            .as_ref()
+            .and_then(|l| config.language_chunk_sizes.get(l))
+            .copied()
+            .unwrap_or(config.chunk_size);
+        let chunks = estimate_chunks(file.size, chunk_size, config.chunk_overlap);
+        let extension = Path::new(&file.relative_path)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_else(|| "none".to_string());
+
+        plan.files += 1;
+        plan.total_bytes += file.size;
+        plan.estimated_chunks += chunks;
+        plan.by_language
+            .entry(language.unwrap_or_else(|| "unknown".to_string()))
+            .or_default()
+            .add(file.size, chunks);
+        plan.by_extension
+            .entry(extension)
+            .or_default()
+            .add(file.size, chunks);
+    }
+
+    Ok(plan)
+}
+
+/// Rough chunk count for a file of `bytes`: windows of `chunk_size` that
+/// advance by `chunk_size - overlap`
+fn estimate_chunks(bytes: u64, chunk_size: usize, overlap: usize) -> usize {
+    if bytes == 0 {
+        return 0;
+    }
+    let stride = chunk_size.saturating_sub(overlap).max(1) as u64;
+    bytes.saturating_sub(overlap as u64).div_ceil(stride).max(1) as usize
+}
+
+/// Discover all indexable files under each of the config's roots
+pub(crate) fn discover_files(config: &Config) -> Result<Vec<FileToIndex>> {
+    discover_files_with(config, &TerminalProgress::default())
+}
+
+/// [`discover_files`], reporting the running count to `progress`
+fn discover_files_with(
+    config: &Config,
+    progress: &dyn ProgressReporter,
+) -> Result<Vec<FileToIndex>> {
+    if let Some(archive) = &config.archive {
+        let files = discover_archive_files(archive, config)?;
+        progress.report(Progress::Discover { found: files.len() });
+        progress.finish("File discovery complete");
+        return Ok(files);
+    }
+
+    progress.report(Progress::Discover { found: 0 });
+
+    let mut files = Vec::new();
+
+    for root in config.roots()? {
+        // Use ignore crate to respect .gitignore
+        let respect_ignores = !config.no_ignore;
+        let mut walker = WalkBuilder::new(&root.path);
+        walker
+            .hidden(!config.include_hidden)
+            .git_ignore(respect_ignores)
+            .git_global(respect_ignores)
+            .git_exclude(respect_ignores)
+            .ignore(respect_ignores)
+            .parents(respect_ignores)
+            .filter_entry({
+                let index_dir = config.index_dir.clone();
+                move |entry| !is_internal_dir(&index_dir, entry.path())
+            });
+        if respect_ignores {
+            walker.add_custom_ignore_filename(&config.ignore_filename);
+        }
+        let walker = walker.build();
+        let patterns = config.ignore_matcher(&root.path)?;
+        let mut attributes = config
+            .respect_gitattributes
+            .then(|| LinguistAttributes::new(root.path.clone()));
+
+        // Collect file paths first
+        let file_paths: Vec<PathBuf> = walker
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                entry.file_type().map(|ft| ft.is_file()).unwrap_or(false)
+                    && config.should_index(entry.path())
+                    && !patterns
+                        .matched_path_or_any_parents(entry.path(), false)
+                        .is_ignore()
+            })
+            .filter(|entry| {
+                attributes
+                    .as_mut()
+                    .map(|a| !a.is_excluded(entry.path()))
+                    .unwrap_or(true)
+            })
+            .filter(|entry| {
+                entry
+                    .metadata()
+                    .map(|m| m.len() <= config.max_file_size)
+                    .unwrap_or(false)
+            })
+            .map(|entry| entry.path().to_path_buf())
+            .collect();
+
+        progress.report(Progress::Discover {
+            found: files.len() + file_paths.len(),
+        });
+
+        files.extend(read_files(&root, &file_paths, config));
+    }
+
+    progress.report(Progress::Discover { found: files.len() });
+    progress.finish("File discovery complete");
+
+    // Walk order depends on the filesystem. usearch takes no construction
+    // seed, so a fixed insertion order is what makes identical content build
+    // an identical graph (and chunk ids).
+    files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+
+    info!("Discovered {} indexable files", files.len());
+
+    Ok(files)
+}
+
+/// Read the indexable files of an archive, keyed by their in-archive paths
+fn discover_archive_files(archive: &Path, config: &Config) -> Result<Vec<FileToIndex>> {
+    // Entries count as modified when the archive was
+    let mtime = file_mtime(archive);
+    let files: Vec<FileToIndex> = archive::read_archive(archive, config)?
+        .into_iter()
+        .map(|entry| FileToIndex {
+            path: archive.join(&entry.path),
+            hash: file_hash(&entry.content, config),
+            relative_path: entry.path,
+            size: entry.content.len() as u64,
+            content: Some(entry.content),
+            mtime,
+        })
+        .collect();
+    info!("Read {} indexable files from {:?}", files.len(), archive);
+
+    Ok(files)
+}
+
+/// Read the listed `paths` that are under one of the roots
+fn listed_files(config: &Config, paths: &[PathBuf], filter: bool) -> Result<Vec<FileToIndex>> {
+    let roots = config.roots()?;
+    let mut by_root: Vec<Vec<PathBuf>> = vec![Vec::new(); roots.len()];
+    let mut seen = HashSet::new();
+    let mut outside = 0;
+
+    for path in paths {
+        let mut path = if path.is_absolute() {
+            path.clone()
+        } else {
+            roots[0].path.join(path)
+        };
+        if !roots.iter().any(|r| path.starts_with(&r.path)) {
+            path = canonicalize_lenient(&path).unwrap_or(path);
+        }
+        let Some(i) = roots.iter().position(|r| path.starts_with(&r.path)) else {
+            debug!("Skipping listed path outside the indexed roots: {:?}", path);
+            outside += 1;
+            continue;
+        };
+
+        let indexable = path.is_file()
+            && (!filter
+                || config.should_index(&path)
+                    && std::fs::metadata(&path)
+                        .map(|m| m.len() <= config.max_file_size)
+                        .unwrap_or(false));
+        if indexable && seen.insert(path.clone()) {
+            by_root[i].push(path);
+        }
+    }
+
+    if outside > 0 {
+        warn!("Skipped {} listed paths outside the indexed roots", outside);
+    }
+
+    let mut files: Vec<FileToIndex> = roots
+        .iter()
+        .zip(&by_root)
+        .flat_map(|(root, paths)| read_files(root, paths, config))
+        .collect();
+    files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+
+    Ok(files)
+}
+
+/// Split a list of paths separated by NULs (`-print0`) or newlines
+pub fn parse_path_list(input: &str) -> Vec<PathBuf> {
+    let separator = if input.contains('\0') { '\0' } else { '\n' };
+    input
+        .split(separator)
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Read `paths` under `root` in parallel
+///
+/// Files can be deleted or replaced between the walk and the read; those are
+/// skipped and reported instead of failing the whole run.
+fn read_files(root: &WorkspaceRoot, paths: &[PathBuf], config: &Config) -> Vec<FileToIndex> {
+    let results: Vec<std::io::Result<Option<FileToIndex>>> = paths
+        .par_iter()
+        .map(|path| read_file(root, path, config))
+        .collect();
+
+    let mut files = Vec::with_capacity(results.len());
+    let mut skipped = 0;
+    for (path, result) in paths.iter().zip(results) {
+        match result {
+            Ok(Some(file)) => files.push(file),
+            Ok(None) => {}
+            Err(e) => {
+                debug!("Skipping {:?}: {}", path, e);
+                skipped += 1;
+            }
+        }
+    }
+
+    if skipped > 0 {
+        warn!(
+            "Skipped {} files that disappeared or became unreadable during indexing",
+            skipped
+        );
+    }
+
+    files
+}
+
+/// Read a file into a `FileToIndex`
+///
+/// Returns `Ok(None)` for files that aren't text or aren't under `root`,
+/// and an error if the file could not be read (e.g. it was just deleted).
+fn read_file(
+    root: &WorkspaceRoot,
+    path: &Path,
+    config: &Config,
+) -> std::io::Result<Option<FileToIndex>> {
+    #[cfg(test)]
+    tests::FILES_READ.with(|n| n.set(n.get() + 1));
+
+    // Large files are hashed now and chunked from disk later
+    let metadata = std::fs::metadata(path)?;
+    let streamed = metadata.len() > config.stream_threshold;
+    let read = if streamed {
+        hash_file(path, config).map(|hash| (None, hash))
+    } else {
+        std::fs::read_to_string(path).map(|content| {
+            let hash = file_hash(&content, config);
+            (Some(content), hash)
+        })
+    };
+    let (content, hash) = match read {
+        Ok(read) => read,
+        Err(e) if e.kind() == std::io::ErrorKind::InvalidData => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let Some(relative) = relative_path(&root.path, path) else {
+        warn!("Skipping {:?}: not under {:?}", path, root.path);
+        return Ok(None);
+    };
+
+    Ok(Some(FileToIndex {
+        path: path.to_path_buf(),
+        relative_path: root.stored_path(&relative),
+        content,
+        hash,
+        mtime: unix_seconds(metadata.modified().ok()),
+        size: metadata.len(),
+    }))
+}
+
+/// Modification time of `path` in seconds since the Unix epoch
+fn file_mtime(path: &Path) -> Option<u64> {
+    unix_seconds(std::fs::metadata(path).and_then(|m| m.modified()).ok())
+}
+
+/// Seconds since the Unix epoch, for times after it
+fn unix_seconds(time: Option<std::time::SystemTime>) -> Option<u64> {
+    let since_epoch = time?.duration_since(std::time::UNIX_EPOCH).ok()?;
+    Some(since_epoch.as_secs())
+}
+
+/// Path relative to the index root, as stored in chunk metadata
+///
+/// `root` is canonical. A `path` reached through a symlink or another
+/// spelling of the root is canonicalized before giving up, so stored paths
+/// are always relative and `/`-separated. Returns `None` for paths outside
+/// `root`.
+pub fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let relative = match path.strip_prefix(root) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => canonicalize_lenient(path)?
+            .strip_prefix(root)
+            .ok()?
+            .to_path_buf(),
+    };
+
+    let parts: Vec<String> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    Some(parts.join("/"))
+}
+
+/// Canonicalize a path that may no longer exist, via its parent directory
+fn canonicalize_lenient(path: &Path) -> Option<PathBuf> {
+    path.canonicalize().ok().or_else(|| {
+        let parent = path.parent()?.canonicalize().ok()?;
+        Some(parent.join(path.file_name()?))
+    })
+}
+
+/// Work out which of `paths` need (re-)indexing, removing stale entries as we go
+///
+/// Only the given paths are read and hashed. Returns the files that must be
+/// chunked and embedded along with the resulting stats.
+fn collect_path_changes(
+    roots: &[WorkspaceRoot],
+    config: &Config,
+    index: &mut VectorIndex,
+    paths: &[PathBuf],
+) -> Result<(Vec<FileToIndex>, UpdateStats)> {
+    let mut stats = UpdateStats::default();
+    let mut files_to_add = Vec::new();
+    let mut seen = HashSet::new();
+    let mut removed = HashSet::new();
+
+    for path in paths {
+        let mut path = if path.is_absolute() {
+            path.clone()
+        } else {
+            roots[0].path.join(path)
+        };
+        // Roots are canonical; events may arrive through a symlink
+        if !roots.iter().any(|r| path.starts_with(&r.path)) {
+            path = canonicalize_lenient(&path).unwrap_or(path);
+        }
+        let Some(root) = roots.iter().find(|r| path.starts_with(&r.path)) else {
+            debug!("Skipping path outside the indexed roots: {:?}", path);
+            continue;
+        };
+        let Some(relative) = relative_path(&root.path, &path) else {
+            continue;
+        };
+        let relative = root.stored_path(&relative);
+        if !seen.insert(relative.clone()) {
+            continue;
+        }
+
+        let indexable = path.is_file()
+            && config.should_index(&path)
+            && !is_ignored(&root.path, &path, config)
+            && !config
+                .ignore_matcher(&root.path)?
+                .matched_path_or_any_parents(&path, false)
+                .is_ignore()
+            && !(config.respect_gitattributes
+                && LinguistAttributes::new(root.path.clone()).is_excluded(&path))
+            && std::fs::metadata(&path)
+                .map(|m| m.len() <= config.max_file_size)
+                .unwrap_or(false);
+
+        // A read error means the file went away after the checks above
+        let file = if indexable {
+            read_file(root, &path, config).ok().flatten()
+        } else {
+            None
+        };
+
+        let Some(file) = file else {
+            // Gone (or no longer indexable): drop the file itself and, for a
+            // deleted directory, everything that was indexed beneath it
+            let prefix = format!("{}/", relative);
+            removed.extend(
+                index
+                    .indexed_files()
+                    .into_iter()
+                    .filter(|f| **f == relative || f.starts_with(&prefix))
+                    .cloned(),
+            );
+            continue;
+        };
+
+        match index.get_file_hash(&file.relative_path) {
+            Some(existing_hash) if existing_hash == &file.hash => {
+                debug!("Skipping unchanged file: {}", file.relative_path);
+                stats.unchanged += 1;
+                continue;
+            }
+            Some(_) => stats.updated += 1,
+            None => stats.added += 1,
+        }
+
+        files_to_add.push(file);
+    }
+
+    // Removals wait until every path is read, so a move within the batch
+    // can keep its embeddings
+    apply_renames(index, &mut files_to_add, &mut removed, &mut stats);
+    for file_path in &removed {
+        index.remove_file(file_path)?;
+        stats.removed += 1;
+    }
+
+    Ok((files_to_add, stats))
+}
+
+/// Move removed files whose content reappears at an added path
+///
+/// A rename (or `git mv`) shows up as a removed and an added path with the
+/// same hash. Re-keying the stored chunks to the new path saves embedding
+/// identical content again. The detected language must match too, since it
+/// decides how a file is chunked.
+fn apply_renames(
+    index: &mut VectorIndex,
+    files_to_add: &mut Vec<FileToIndex>,
+    removed: &mut HashSet<String>,
+    stats: &mut UpdateStats,
+) {
+    if removed.is_empty() {
+        return;
+    }
+
+    files_to_add.retain(|file| {
+        if index.get_file_hash(&file.relative_path).is_some() {
+            return true;
+        }
+        let language = detect_language(&file.relative_path);
+        let Some(from) = removed
+            .iter()
+            .find(|old| {
+                index.get_file_hash(old) == Some(&file.hash) && detect_language(old) == language
+            })
+            .cloned()
+        else {
+            return true;
+        };
+
+        removed.remove(&from);
+        index.rename_file(&from, &file.relative_path);
+        index.set_file_mtime(&file.relative_path, file.mtime);
+        index.set_file_size(&file.relative_path, file.size);
+        stats.added -= 1;
+        stats.renamed += 1;
+        false
+    });
+}
+
+/// A chunk with its embedding
+type EmbeddedChunk = (Chunk, Vec<f32>);
+
+/// Replace a file's indexed chunks, reusing vectors of chunks whose text is unchanged
+///
+/// Removes the file's old chunks from the index. New chunks whose
+/// `text_hash` matches an old chunk take over its ID and vector and are
+/// returned with it; the rest are returned as needing embedding.
+fn reuse_unchanged_chunks(
+    index: &mut VectorIndex,
+    file_path: &str,
+    chunks: Vec<Chunk>,
+) -> Result<(Vec<EmbeddedChunk>, Vec<Chunk>)> {
+    if index.get_file_hash(file_path).is_none() {
+        return Ok((Vec::new(), chunks));
+    }
+
+    let mut old = index.take_file_vectors(file_path)?;
+    let mut reused = Vec::new();
+    let mut changed = Vec::new();
+
+    for mut chunk in chunks {
+        match old.get_mut(&chunk.text_hash).and_then(|v| v.pop()) {
+            Some((id, vector)) => {
+                chunk.id = id;
+                reused.push((chunk, vector));
+            }
+            None => changed.push(chunk),
+        }
+    }
+
+    Ok((reused, changed))
+}
+
+/// Current git state of a single-root index: HEAD and the files differing from it
+fn git_state(config: &Config) -> (Option<String>, Vec<String>) {
+    if config.archive.is_some() || !config.root_paths.is_empty() {
+        return (None, Vec::new());
+    }
+
+    let state = git::head_commit(&config.root_path).and_then(|commit| {
+        git::changed_since(&config.root_path, &commit).map(|files| (commit, files))
+    });
+
+    match state {
+        Some((commit, files)) => (Some(commit), files),
+        None => (None, Vec::new()),
+    }
+}
+
+/// Paths that may have changed since the index's recorded git state
+///
+/// Returns `None` when the full walk is needed instead: no recorded commit,
+/// not a git repository, a commit git no longer knows, or changed ignore
+/// rules (which can affect files git reports as unchanged).
+fn git_changed_paths(config: &Config, index: &VectorIndex) -> Option<Vec<PathBuf>> {
+    // git doesn't report changes to the ignored files `no_ignore` indexes
+    if config.archive.is_some() || !config.root_paths.is_empty() || config.no_ignore {
+        return None;
+    }
+
+    let changed = git::changed_since(&config.root_path, index.indexed_commit()?)?;
+    let rules_changed = changed.iter().any(|path| {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        [".gitignore", ".ignore", ".gitattributes"].contains(&name)
+            || name == config.ignore_filename
+    });
+    if rules_changed {
+        return None;
+    }
+
+    // Files that were dirty when last indexed may since have been reverted
+    let paths = changed
+        .iter()
+        .chain(index.uncommitted_files())
+        .map(PathBuf::from)
+        .collect();
+
+    Some(paths)
+}
+
+/// Check whether a path is hidden or excluded by an ignore file between it and the root
+///
+/// Mirrors the rules used by `discover_files` (hidden files, `.gitignore`,
+/// `.ignore` and `ignore_filename`, unless `include_hidden` or `no_ignore`
+/// turn them off) for a single path without walking the tree.
+fn is_ignored(root: &Path, path: &Path, config: &Config) -> bool {
+    let Ok(relative) = path.strip_prefix(root) else {
+        return true;
+    };
+
+    if path
+        .ancestors()
+        .take(relative.components().count())
+        .any(|p| is_internal_dir(&config.index_dir, p))
+    {
+        return true;
+    }
+
+    if !config.include_hidden
+        && relative
+            .components()
+            .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+    {
+        return true;
+    }
+
+    if config.no_ignore {
+        return false;
+    }
+
+    let mut dir = path.parent();
+    while let Some(current) = dir {
+        for name in [".gitignore", ".ignore", &config.ignore_filename] {
+            let ignore_file = current.join(name);
+            if ignore_file.is_file() {
+                let (matcher, _) = Gitignore::new(&ignore_file);
+                if matcher.matched_path_or_any_parents(path, false).is_ignore() {
+                    return true;
+                }
+            }
+        }
+
+        if current == root {
+            break;
+        }
+        dir = current.parent();
+    }
+
+    false
+}
+
+/// Check for directories that are never indexed, even with `include_hidden`:
+/// lgrep's own index (here or in a nested project) and git's object store
+fn is_internal_dir(index_dir: &Path, path: &Path) -> bool {
+    path == index_dir
+        || path
+            .file_name()
+            .is_some_and(|name| name == ".git" || name == ".lgrep")
+}
+
+/// Hash used to detect that a file changed
+///
+/// With `ignore_whitespace_changes`, runs of whitespace are collapsed first
+/// so reformatting a file doesn't count as a change.
+fn file_hash(content: &str, config: &Config) -> String {
+    if config.ignore_whitespace_changes {
+        compute_hash(&content.split_whitespace().collect::<Vec<_>>().join(" "))
+    } else {
+        compute_hash(content)
+    }
+}
+
+/// [`file_hash`] of a file's content, read line by line
+///
+/// Fails with `InvalidData` if the file isn't UTF-8, like `read_to_string`.
+fn hash_file(path: &Path, config: &Config) -> std::io::Result<String> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut buf = Vec::new();
+    let mut first_word = true;
+
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        let line = std::str::from_utf8(&buf)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+        if config.ignore_whitespace_changes {
+            for word in line.split_whitespace() {
+                if !first_word {
+                    hasher.update(b" ");
+                }
+                hasher.update(word.as_bytes());
+                first_word = false;
+            }
+        } else {
+            hasher.update(line.as_bytes());
+        }
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
 /// Compute SHA-256 hash of content
 fn compute_hash(content: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(content.as_bytes());
     hex::encode(hasher.finalize())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::ContentKind;
+    use crate::config::EmbeddingModel;
+    use crate::embedder::{Embed, StubEmbedder};
+    use crate::searcher::Searcher;
+    use std::cell::Cell;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use tempfile::tempdir;
+
+    thread_local! {
+        /// Number of files read by `read_file` on this thread
+        pub(super) static FILES_READ: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// Build an index containing one chunk per file with its current hash
+    fn index_with_files(config: &Config, root: &Path, names: &[&str]) -> VectorIndex {
+        let mut index = VectorIndex::new(config.clone()).unwrap();
+        let dimension = config.model.dimension();
+
+        let chunks: Vec<Chunk> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let content = std::fs::read_to_string(root.join(name)).unwrap();
+                Chunk {
+                    id: i as u64,
+                    text: content.clone(),
+                    file_path: name.to_string(),
+                    start_line: 1,
+                    end_line: 1,
+                    file_hash: file_hash(&content, config),
+                    language: Some("rust".to_string()),
+                    overlap_with_prev: 0,
+                    content_kind: ContentKind::Code,
+                    text_hash: String::new(),
+                    symbols: Vec::new(),
+                }
+            })
+            .collect();
+        let embeddings = vec![vec![1.0; dimension]; chunks.len()];
+
+        index.add_chunks(chunks, embeddings).unwrap();
+        index
+    }
+
+    #[test]
+    fn test_update_paths_reads_only_changed_file() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("a.rs"), "fn a() {}").unwrap();
+        std::fs::write(root.join("b.rs"), "fn b() {}").unwrap();
+
+        let config = Config::new(root.clone());
+        let mut index = index_with_files(&config, &root, &["a.rs", "b.rs"]);
+
+        std::fs::write(root.join("a.rs"), "fn a() { changed() }").unwrap();
+        FILES_READ.with(|n| n.set(0));
+
+        let roots = config.roots().unwrap();
+        let (files, stats) =
+            collect_path_changes(&roots, &config, &mut index, &[root.join("a.rs")]).unwrap();
+
+        assert_eq!(FILES_READ.with(|n| n.get()), 1);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].relative_path, "a.rs");
+        assert_eq!(stats.updated, 1);
+        assert_eq!(stats.added, 0);
+        assert!(index.get_file_hash("b.rs").is_some());
+    }
+
+    #[test]
+    fn test_whitespace_only_change_is_not_reembedded() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("a.rs"), "fn a() {\n    b();\n}\n").unwrap();
+
+        let config = Config::new(root.clone()).with_ignore_whitespace_changes(true);
+        let mut index = index_with_files(&config, &root, &["a.rs"]);
+        let roots = config.roots().unwrap();
+
+        std::fs::write(root.join("a.rs"), "fn a() { b(); }   \n\n").unwrap();
+        let (files, stats) =
+            collect_path_changes(&roots, &config, &mut index, &[root.join("a.rs")]).unwrap();
+        assert!(files.is_empty());
+        assert_eq!(stats.unchanged, 1);
+
+        std::fs::write(root.join("a.rs"), "fn a() { c(); }").unwrap();
+        let (files, stats) =
+            collect_path_changes(&roots, &config, &mut index, &[root.join("a.rs")]).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(stats.updated, 1);
+    }
+
+    #[test]
+    fn test_identical_content_builds_identical_index() {
+        let search = |names: Vec<usize>| {
+            let dir = tempdir().unwrap();
+            let root = dir.path().canonicalize().unwrap();
+            for i in names {
+                let content = format!("fn handler_{}() {{ route_{}(); }}", i, i % 7);
+                std::fs::write(root.join(format!("f{}.rs", i)), content).unwrap();
+            }
+
+            let config = Config::new(root);
+            let stub = || {
+                Box::new(StubEmbedder {
+                    dimension: config.model.dimension(),
+                })
+            };
+            let index = Indexer::with_embedder(config.clone(), stub())
+                .build_index()
+                .unwrap();
+            Searcher::with_embedder(index, stub())
+                .search("route_3 handler", 10)
+                .unwrap()
+                .into_iter()
+                .map(|r| (r.chunk.id, r.chunk.file_path, r.score.to_bits()))
+                .collect::<Vec<_>>()
+        };
+
+        let forward = search((0..40).collect());
+        let backward = search((0..40).rev().collect());
+        assert_eq!(forward.len(), 10);
+        assert_eq!(forward, backward);
+    }
+
+    /// Stub embedder recording the texts it embeds
+    struct RecordingEmbedder {
+        inner: StubEmbedder,
+        embedded: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl RecordingEmbedder {
+        fn boxed(config: &Config, embedded: &Arc<Mutex<Vec<String>>>) -> Box<Self> {
+            Box::new(Self {
+                inner: StubEmbedder {
+                    dimension: config.model.dimension(),
+                },
+                embedded: Arc::clone(embedded),
+            })
+        }
+    }
+
+    impl Embed for RecordingEmbedder {
+        fn dimension(&self) -> usize {
+            self.inner.dimension()
+        }
+
+        fn embed_one(&self, text: &str) -> Result<Vec<f32>> {
+            self.embed_batch(vec![text]).map(|mut v| v.remove(0))
+        }
+
+        fn embed_batch(&self, texts: Vec<&str>) -> Result<Vec<Vec<f32>>> {
+            let mut embedded = self.embedded.lock().unwrap();
+            embedded.extend(texts.iter().map(|t| t.to_string()));
+            self.inner.embed_batch(texts)
+        }
+    }
+
+    /// Keeps every reported event, with `None` marking a finished phase
+    #[derive(Default)]
+    struct RecordingProgress(Arc<Mutex<Vec<Option<Progress>>>>);
+
+    impl ProgressReporter for RecordingProgress {
+        fn report(&self, progress: Progress) {
+            self.0.lock().unwrap().push(Some(progress));
+        }
+
+        fn finish(&self, _message: &str) {
+            self.0.lock().unwrap().push(None);
+        }
+    }
+
+    #[test]
+    fn test_model_loads_only_when_embedding() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let missing_model = EmbeddingModel::Custom {
+            path: root.join("no-such-model"),
+            dimension: 8,
+        };
+        let config = Config::new(root.clone()).with_model(missing_model);
+
+        // Nothing to embed: the missing model is never opened
+        let indexer = Indexer::new(config).unwrap();
+        let mut index = indexer.build_index().unwrap();
+        assert!(!indexer.update_index(&mut index).unwrap().changed());
+
+        std::fs::write(root.join("a.rs"), "fn a() {}").unwrap();
+        assert!(matches!(
+            indexer.update_index(&mut index),
+            Err(LgrepError::Embedding(_))
+        ));
+    }
+
+    #[test]
+    fn test_progress_reports_each_phase() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("a.rs"), "fn a() {}").unwrap();
+        std::fs::write(root.join("b.rs"), "fn b() {}").unwrap();
+
+        let config = Config::new(root);
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let stub = Box::new(StubEmbedder {
+            dimension: config.model.dimension(),
+        });
+        Indexer::with_embedder(config, stub)
+            .with_progress(Box::new(RecordingProgress(events.clone())))
+            .build_index()
+            .unwrap();
+
+        let events = events.lock().unwrap();
+        assert_eq!(events[0], Some(Progress::Discover { found: 0 }));
+        assert!(events.contains(&Some(Progress::Discover { found: 2 })));
+        assert!(events.contains(&Some(Progress::Chunk { done: 2, total: 2 })));
+        assert_eq!(
+            events[events.len() - 2..],
+            [Some(Progress::Embed { done: 2, total: 2 }), None]
+        );
+        assert_eq!(events.iter().filter(|e| e.is_none()).count(), 3);
+        assert_eq!(
+            serde_json::to_string(&Progress::Embed { done: 1, total: 2 }).unwrap(),
+            r#"{"phase":"embed","done":1,"total":2}"#
+        );
+    }
+
+    #[test]
+    fn test_rename_keeps_embeddings() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("src")).unwrap();
+        std::fs::write(root.join("src/auth.rs"), "fn verify_password() {}").unwrap();
+        std::fs::write(root.join("src/db.rs"), "fn open_pool() {}").unwrap();
+
+        let config = Config::new(root.clone());
+        let embedded = Arc::new(Mutex::new(Vec::new()));
+        let indexer =
+            Indexer::with_embedder(config.clone(), RecordingEmbedder::boxed(&config, &embedded));
+        let mut index = indexer.build_index().unwrap();
+        let ids: Vec<u64> = index.chunk_metadata().iter().map(|c| c.id).collect();
+
+        std::fs::create_dir(root.join("lib")).unwrap();
+        std::fs::rename(root.join("src/auth.rs"), root.join("lib/login.rs")).unwrap();
+        embedded.lock().unwrap().clear();
+
+        let stats = indexer.update_index(&mut index).unwrap();
+        assert!(embedded.lock().unwrap().is_empty());
+        assert_eq!(stats.renamed, 1);
+        assert_eq!((stats.added, stats.removed, stats.unchanged), (0, 0, 1));
+        assert!(index.get_file_hash("src/auth.rs").is_none());
+        assert!(index.get_file_hash("lib/login.rs").is_some());
+        assert_eq!(
+            index
+                .chunk_metadata()
+                .iter()
+                .map(|c| c.id)
+                .collect::<Vec<_>>(),
+            ids
+        );
+
+        let searcher = Searcher::with_embedder(
+            index,
+            Box::new(StubEmbedder {
+                dimension: config.model.dimension(),
+            }),
+        );
+        let results = searcher.search("verify_password", 1).unwrap();
+        assert_eq!(results[0].chunk.file_path, "lib/login.rs");
+    }
+
+    #[test]
+    fn test_document_prefix_follows_the_index() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("a.rs"), "fn a() {}").unwrap();
+
+        let config = Config::new(root.clone()).with_model(EmbeddingModel::NomicEmbedTextV15);
+        let embedded = Arc::new(Mutex::new(Vec::new()));
+        let indexer =
+            Indexer::with_embedder(config.clone(), RecordingEmbedder::boxed(&config, &embedded));
+
+        let mut index = indexer.build_index().unwrap();
+        assert!(index.model_prefixes());
+        assert_eq!(*embedded.lock().unwrap(), ["search_document: fn a() {}"]);
+
+        // An index built before prefixes keeps embedding raw text
+        index.set_model_prefixes(false);
+        embedded.lock().unwrap().clear();
+        std::fs::write(root.join("b.rs"), "fn b() {}").unwrap();
+        indexer.update_index(&mut index).unwrap();
+        assert_eq!(*embedded.lock().unwrap(), ["fn b() {}"]);
+    }
+
+    #[test]
+    fn test_moved_path_batch_is_a_rename() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("a.rs"), "fn a() {}").unwrap();
+
+        let config = Config::new(root.clone());
+        let mut index = index_with_files(&config, &root, &["a.rs"]);
+        std::fs::rename(root.join("a.rs"), root.join("b.rs")).unwrap();
+
+        // As the watcher reports a move: the old path, then the new one
+        let roots = config.roots().unwrap();
+        let paths = [root.join("a.rs"), root.join("b.rs")];
+        let (files, stats) = collect_path_changes(&roots, &config, &mut index, &paths).unwrap();
+        assert!(files.is_empty());
+        assert_eq!((stats.renamed, stats.added, stats.removed), (1, 0, 0));
+        assert_eq!(index.chunk_metadata()[0].file_path, "b.rs");
+    }
+
+    #[test]
+    fn test_build_index_from_path_list() {
+        let dir = tempdir().unwrap();
+        let outside = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        for name in ["a.rs", "b.rs", "c.rs", "notes.bin"] {
+            std::fs::write(root.join(name), format!("fn {}() {{}}", name)).unwrap();
+        }
+        std::fs::write(outside.path().join("d.rs"), "fn d() {}").unwrap();
+
+        let list = format!(
+            "a.rs\0{}\0notes.bin\0",
+            outside.path().join("d.rs").display()
+        );
+        let paths = parse_path_list(&list);
+        assert_eq!(paths.len(), 3);
+        assert_eq!(parse_path_list("a.rs\r\nc.rs\n\n").len(), 2);
+
+        let config = Config::new(root.clone());
+        let indexer = Indexer::with_embedder(
+            config.clone(),
+            Box::new(StubEmbedder {
+                dimension: config.model.dimension(),
+            }),
+        );
+        let index = indexer.build_index_from_paths(&paths, true).unwrap();
+        let files: Vec<&String> = index.indexed_files();
+        assert_eq!(files, vec!["a.rs"]);
+
+        let index = indexer.build_index_from_paths(&paths, false).unwrap();
+        let mut files = index.indexed_files();
+        files.sort();
+        assert_eq!(files, vec!["a.rs", "notes.bin"]);
+    }
+
+    #[test]
+    fn test_large_file_is_streamed() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let content = (1..=50)
+            .map(|i| format!("fn handler_{}() {{\n    route({});\n}}\n", i, i))
+            .collect::<String>();
+        std::fs::write(root.join("big.rs"), &content).unwrap();
+
+        for ignore_whitespace in [false, true] {
+            let config = Config::new(root.clone())
+                .with_stream_threshold(100)
+                .with_ignore_whitespace_changes(ignore_whitespace);
+            let roots = config.roots().unwrap();
+            let file = read_file(&roots[0], &root.join("big.rs"), &config)
+                .unwrap()
+                .unwrap();
+
+            assert!(file.content.is_none());
+            assert_eq!(file.hash, file_hash(&content, &config));
+
+            let chunker = Chunker::from_config(&config);
+            let streamed: Vec<(String, usize)> = file
+                .chunk(&chunker, 0)
+                .unwrap()
+                .into_iter()
+                .map(|c| (c.text, c.start_line))
+                .collect();
+            let read: Vec<(String, usize)> = chunker
+                .chunk_text(&content, "big.rs", &file.hash, 0)
+                .into_iter()
+                .map(|c| (c.text, c.start_line))
+                .collect();
+            assert_eq!(streamed, read);
+        }
+    }
+
+    #[test]
+    fn test_vanished_file_is_skipped() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("a.rs"), "fn a() {}").unwrap();
+
+        let config = Config::new(root.clone());
+        let roots = config.roots().unwrap();
+        let paths = vec![root.join("a.rs"), root.join("gone.rs")];
+
+        let files = read_files(&roots[0], &paths, &config);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].relative_path, "a.rs");
+
+        let searcher = stub_searcher(&config, &files);
+        assert_eq!(searcher.stats().files, 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_relative_path_through_symlink() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("real")).unwrap();
+        std::fs::write(root.join("real/a.rs"), "fn a() {}").unwrap();
+        std::os::unix::fs::symlink(root.join("real"), root.join("link")).unwrap();
+
+        let real = root.join("real");
+        assert_eq!(
+            relative_path(&real, &root.join("link/a.rs")),
+            Some("a.rs".to_string())
+        );
+        assert_eq!(
+            relative_path(&real, &root.join("link/deleted.rs")),
+            Some("deleted.rs".to_string())
+        );
+        assert_eq!(relative_path(&real, Path::new("/elsewhere/a.rs")), None);
+    }
+
+    #[test]
+    fn test_update_paths_handles_deletions() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("sub")).unwrap();
+        std::fs::write(root.join("a.rs"), "fn a() {}").unwrap();
+        std::fs::write(root.join("sub/b.rs"), "fn b() {}").unwrap();
+
+        let config = Config::new(root.clone());
+        let mut index = index_with_files(&config, &root, &["a.rs", "sub/b.rs"]);
+
+        std::fs::remove_dir_all(root.join("sub")).unwrap();
+        FILES_READ.with(|n| n.set(0));
+
+        let roots = config.roots().unwrap();
+        let (files, stats) =
+            collect_path_changes(&roots, &config, &mut index, &[root.join("sub")]).unwrap();
+
+        assert_eq!(FILES_READ.with(|n| n.get()), 0);
+        assert!(files.is_empty());
+        assert_eq!(stats.removed, 1);
+        assert_eq!(index.file_count(), 1);
+    }
+
+    /// Chunk and embed discovered files with the stub embedder
+    fn stub_searcher(config: &Config, files: &[FileToIndex]) -> Searcher {
+        let chunker = Chunker::from_config(config);
+        let chunks: Vec<Chunk> = files
+            .iter()
+            .enumerate()
+            .flat_map(|(i, f)| f.chunk(&chunker, i as u64).unwrap())
+            .collect();
+        let embedder = StubEmbedder {
+            dimension: config.model.dimension(),
+        };
+        let embeddings = chunks
+            .iter()
+            .map(|c| embedder.embed_one(&c.text).unwrap())
+            .collect();
+        let mut index = VectorIndex::new(config.clone()).unwrap();
+        index.add_chunks(chunks, embeddings).unwrap();
+
+        Searcher::with_embedder(index, Box::new(embedder))
+    }
+
+    #[test]
+    fn test_multi_root_index_searches_both() {
+        let a = tempdir().unwrap();
+        let b = tempdir().unwrap();
+        let home = tempdir().unwrap();
+        std::fs::write(a.path().join("db.rs"), "fn open_database_connection() {}").unwrap();
+        std::fs::write(b.path().join("auth.rs"), "fn verify_password_hash() {}").unwrap();
+
+        // The repeated root is deduped rather than indexed twice
+        let config = Config::new(home.path().to_path_buf()).with_root_paths(vec![
+            a.path().to_path_buf(),
+            b.path().to_path_buf(),
+            a.path().to_path_buf(),
+        ]);
+        let roots = config.roots().unwrap();
+        assert_eq!(roots.len(), 2);
+
+        let files = discover_files(&config).unwrap();
+        assert_eq!(files.len(), 2);
+
+        let searcher = stub_searcher(&config, &files);
+
+        let results = searcher.search("database connection", 10).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].root.as_deref(), Some(roots[0].label.as_str()));
+        assert_eq!(
+            results[0].chunk.file_path,
+            format!("{}/db.rs", roots[0].label)
+        );
+
+        let results = searcher.search("verify password", 10).unwrap();
+        assert_eq!(results[0].root.as_deref(), Some(roots[1].label.as_str()));
+    }
+
+    #[test]
+    fn test_discover_respects_gitattributes() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(
+            root.join(".gitattributes"),
+            "schema.rs linguist-generated\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("schema.rs"), "pub struct Generated;").unwrap();
+        std::fs::write(root.join("main.rs"), "fn main() {}").unwrap();
+
+        let config = Config::new(root.clone());
+        assert_eq!(discover_files(&config).unwrap().len(), 2);
+
+        let config = config.with_respect_gitattributes(true);
+        let files = discover_files(&config).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].relative_path, "main.rs");
+    }
+
+    #[test]
+    fn test_discover_skips_ignore_patterns() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("generated")).unwrap();
+        std::fs::write(root.join("generated/api.rs"), "pub struct Api;").unwrap();
+        std::fs::write(root.join("main.rs"), "fn main() {}").unwrap();
+
+        let config = Config::new(root).with_ignore_patterns(vec!["generated/".to_string()]);
+        let files = discover_files(&config).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].relative_path, "main.rs");
+    }
+
+    #[test]
+    fn test_discover_reads_configured_ignore_filename() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("migrations")).unwrap();
+        std::fs::write(root.join("migrations/0001.rs"), "fn up() {}").unwrap();
+        std::fs::write(root.join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(root.join(".searchignore"), "migrations/\n").unwrap();
+
+        let config = Config::new(root);
+        assert_eq!(discover_files(&config).unwrap().len(), 2);
+
+        let config = config.with_ignore_filename(".searchignore");
+        let files = discover_files(&config).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].relative_path, "main.rs");
+    }
+
+    #[test]
+    fn test_plan_index_without_embedder() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("main.rs"), "x".repeat(1000)).unwrap();
+        std::fs::write(root.join("lib.rs"), "fn lib() {}").unwrap();
+        std::fs::write(root.join("README.md"), "# Title").unwrap();
+
+        let mut config = Config::new(root).with_chunk_size(500);
+        config.chunk_overlap = 100;
+        let plan = plan_index(&config).unwrap();
+
+        assert_eq!(plan.files, 3);
+        assert_eq!(plan.total_bytes, 1000 + 11 + 7);
+        // 900 bytes past the first overlap, in steps of 400
+        assert_eq!(plan.estimated_chunks, 3 + 1 + 1);
+        assert_eq!(
+            plan.by_language["rust"],
+            PlanGroup {
+                files: 2,
+                bytes: 1011,
+                estimated_chunks: 4
+            }
+        );
+        assert_eq!(plan.by_extension["md"].files, 1);
+        assert_eq!(estimate_chunks(0, 500, 100), 0);
+        assert_eq!(estimate_chunks(50, 500, 100), 1);
+    }
+
+    #[test]
+    fn test_all_text_skips_binary_files() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("Makefile"), "build:\n\tcargo build\n").unwrap();
+        std::fs::write(root.join("logo.bin"), [0xff, 0xfe, 0x00, 0x80]).unwrap();
+
+        let config = Config::new(root.clone());
+        assert!(discover_files(&config).unwrap().is_empty());
+
+        let config = config.with_all_text(true);
+        let files = discover_files(&config).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].relative_path, "Makefile");
+    }
+
+    #[test]
+    fn test_index_zip_archive() {
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, content) in [
+            ("project/src/db.rs", "fn open_database_connection() {}"),
+            ("project/src/auth.rs", "fn verify_password_hash() {}"),
+        ] {
+            zip.start_file(name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        let bytes = zip.finish().unwrap().into_inner();
+
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join("project.zip");
+        std::fs::write(&archive, bytes).unwrap();
+
+        let config = Config::new(dir.path().to_path_buf()).with_archive(archive);
+        let files = discover_files(&config).unwrap();
+        assert_eq!(files.len(), 2);
+
+        let searcher = stub_searcher(&config, &files);
+        let results = searcher.search("verify password", 10).unwrap();
+        assert_eq!(results[0].chunk.file_path, "project/src/auth.rs");
+    }
+
+    #[test]
+    fn test_git_diff_reindexes_only_committed_change() {
+        use crate::git::run_git;
+
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("a.rs"), "fn a() {}").unwrap();
+        std::fs::write(root.join("b.rs"), "fn b() {}").unwrap();
+        run_git(&root, &["init", "-q"]);
+        run_git(&root, &["add", "."]);
+        run_git(&root, &["commit", "-q", "-m", "initial"]);
+
+        let config = Config::new(root.clone());
+        let mut index = index_with_files(&config, &root, &["a.rs", "b.rs"]);
+        assert!(git_changed_paths(&config, &index).is_none());
+
+        let (commit, uncommitted) = git_state(&config);
+        assert!(commit.is_some() && uncommitted.is_empty());
+        index.set_git_state(commit, uncommitted);
+
+        std::fs::write(root.join("a.rs"), "fn a() { changed() }").unwrap();
+        run_git(&root, &["commit", "-q", "-am", "change a"]);
+        FILES_READ.with(|n| n.set(0));
+
+        let paths = git_changed_paths(&config, &index).unwrap();
+        assert_eq!(paths, vec![PathBuf::from("a.rs")]);
+
+        let roots = config.roots().unwrap();
+        let (files, stats) = collect_path_changes(&roots, &config, &mut index, &paths).unwrap();
+        assert_eq!(FILES_READ.with(|n| n.get()), 1);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].relative_path, "a.rs");
+        assert_eq!(stats.updated, 1);
+    }
+
+    #[test]
+    fn test_edit_reembeds_only_changed_chunk() {
+        let config = Config::new(PathBuf::from("/nonexistent"));
+        let chunker = Chunker::new(60, 0);
+        let embedder = StubEmbedder {
+            dimension: config.model.dimension(),
+        };
+        let embed = |chunks: &[Chunk]| -> Vec<Vec<f32>> {
+            chunks
+                .iter()
+                .map(|c| embedder.embed_one(&c.text).unwrap())
+                .collect()
+        };
+
+        let functions = [
+            "fn open_connection() {\n    connect();\n}\n",
+            "fn close_connection() {\n    disconnect();\n}\n",
+            "fn verify_password() {\n    check_hash();\n}\n",
+        ];
+        let original = functions.concat();
+        let mut index = VectorIndex::new(config.clone()).unwrap();
+        let chunks = chunker.chunk_text(&original, "src/db.rs", "v1", 0);
+        assert_eq!(chunks.len(), 3);
+        let vectors = embed(&chunks);
+        let original_ids: Vec<u64> = chunks.iter().map(|c| c.id).collect();
+        index.add_chunks(chunks, vectors).unwrap();
+
+        let edited = original.replace("disconnect();", "disconnect_all(true);");
+        let chunks = chunker.chunk_text(&edited, "src/db.rs", "v2", index.next_id());
+        let (reused, changed) = reuse_unchanged_chunks(&mut index, "src/db.rs", chunks).unwrap();
+
+        assert_eq!(changed.len(), 1);
+        assert!(changed[0].text.contains("disconnect_all"));
+        let reused_ids: Vec<u64> = reused.iter().map(|(c, _)| c.id).collect();
+        assert_eq!(reused_ids, vec![original_ids[0], original_ids[2]]);
+        assert_eq!(index.chunk_count(), 0);
+
+        let vectors = embed(&changed);
+        let (mut chunks, mut all_vectors): (Vec<Chunk>, Vec<Vec<f32>>) = reused.into_iter().unzip();
+        chunks.extend(changed);
+        all_vectors.extend(vectors);
+        index.add_chunks(chunks, all_vectors).unwrap();
+
+        assert_eq!(index.chunk_count(), 3);
+        assert_eq!(index.get_file_hash("src/db.rs").unwrap(), "v2");
+        let query = embedder.embed_one("verify password").unwrap();
+        assert_eq!(
+            index.search(&query, 1).unwrap()[0].chunk.id,
+            original_ids[2]
+        );
+    }
+
+    #[test]
+    fn test_is_ignored() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(root.join(".searchignore"), "fixtures/\n").unwrap();
+
+        let config = Config::new(root.clone()).with_ignore_filename(".searchignore");
+        let ignored = |config: &Config, path: &str| is_ignored(&root, &root.join(path), config);
+        assert!(ignored(&config, "target/debug/build.rs"));
+        assert!(ignored(&config, ".hidden/a.rs"));
+        assert!(ignored(&config, "fixtures/a.rs"));
+        assert!(!ignored(&config, "src/main.rs"));
+
+        let config = config.with_no_ignore(true).with_include_hidden(true);
+        assert!(!ignored(&config, "target/debug/build.rs"));
+        assert!(!ignored(&config, ".hidden/a.rs"));
+        assert!(ignored(&config, ".git/config"));
+        assert!(ignored(&config, ".lgrep/config.json"));
+    }
+
+    #[test]
+    fn test_discover_no_ignore_and_hidden() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        for dir in ["dist", ".github", ".git", ".lgrep"] {
+            std::fs::create_dir(root.join(dir)).unwrap();
+        }
+        std::fs::write(root.join(".gitignore"), "dist/\n").unwrap();
+        std::fs::write(root.join("dist/bundle.js"), "let x = 1;").unwrap();
+        std::fs::write(root.join(".github/build.sh"), "make").unwrap();
+        std::fs::write(root.join(".git/hook.sh"), "exit 0").unwrap();
+        std::fs::write(root.join(".lgrep/notes.md"), "index data").unwrap();
+        std::fs::write(root.join("main.rs"), "fn main() {}").unwrap();
+
+        let paths = |config: &Config| -> Vec<String> {
+            discover_files(config)
+                .unwrap()
+                .into_iter()
+                .map(|f| f.relative_path)
+                .collect()
+        };
+        let config = Config::new(root);
+        assert_eq!(paths(&config), ["main.rs"]);
+        assert_eq!(
+            paths(&config.clone().with_no_ignore(true)),
+            ["dist/bundle.js", "main.rs"]
+        );
+        assert_eq!(
+            paths(&config.with_include_hidden(true)),
+            [".github/build.sh", "main.rs"]
+        );
+    }
+}
@@ -39,25 +39,50 @@
 //! }
 //! ```
 
+pub mod archive;
+pub mod bench;
+pub mod bookmarks;
+pub mod bundle;
 pub mod chunker;
 pub mod config;
+pub mod editor;
 pub mod embedder;
 pub mod error;
 pub mod filter;
+pub mod git;
+pub mod gitattributes;
 pub mod history;
 pub mod index;
 pub mod indexer;
+pub mod mcp;
 pub mod searcher;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod texts;
 pub mod watcher;
 
 // Re-export commonly used types
-pub use chunker::{Chunk, Chunker, IndexMetadata};
-pub use config::{Config, EmbeddingModel};
-pub use embedder::Embedder;
-pub use error::{LgrepError, Result};
+pub use bookmarks::{Bookmark, Bookmarks};
+pub use chunker::{Chunk, Chunker, ContentKind, IndexMetadata, SavedFiles, FORMAT_VERSION};
+pub use config::{
+    ChunkStrategy, Config, ConfigBuilder, EmbeddingModel, ExecutionProvider, WorkspaceRoot,
+};
+pub use embedder::{Embed, Embedder};
+pub use error::{ErrorReport, LgrepError, Result};
 pub use filter::SearchFilter;
 pub use history::{QueryEntry, QueryHistory};
-pub use index::{SearchResult, VectorIndex};
-pub use indexer::{Indexer, UpdateStats};
-pub use searcher::{format_results, format_results_json, IndexStats, Searcher};
+pub use index::{FileResult, SearchResult, VectorIndex};
+pub use indexer::{
+    plan_index, IndexPlan, Indexer, JsonProgress, PlanGroup, Progress, ProgressFormat,
+    ProgressReporter, TerminalProgress, UpdateStats,
+};
+pub use searcher::{
+    add_context, cut_at_knee, dedupe_results, filter_recent, format_results,
+    format_results_compact, format_results_csv, format_results_github, format_results_grouped,
+    format_results_json, format_results_sarif, format_summary, highlight_matches, is_short_query,
+    keyword_regex, related_queries, resolve_max_count, sort_results, terminal_width,
+    write_results_jsonl, IndexStats, MultiSearcher, OutputFormat, Searcher, SearcherHandle,
+    SortOrder, DEFAULT_HYBRID_ALPHA, DEFAULT_MAX_COUNT_CEILING, DEFAULT_MMR_LAMBDA,
+    DEFAULT_SHORT_QUERY_CHARS, KEYWORD_MATCH_LINES, LITERAL_OVERFETCH, MMR_OVERFETCH,
+};
 pub use watcher::IndexWatcher;
@@ -5,11 +5,23 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use colored::*;
+use lgrep::bookmarks::parse_location;
+use lgrep::filter::{parse_size, parse_time};
+use lgrep::indexer::{parse_path_list, relative_path};
 use lgrep::{
-    format_results, format_results_json, Config, EmbeddingModel, IndexWatcher, Indexer,
-    QueryHistory, SearchFilter, Searcher, VectorIndex,
+    add_context, cut_at_knee, dedupe_results, filter_recent, format_results,
+    format_results_compact, format_results_csv, format_results_github, format_results_grouped,
+    format_results_json, format_results_sarif, format_summary, highlight_matches, is_short_query,
+    keyword_regex, related_queries, resolve_max_count, sort_results, terminal_width,
+    write_results_jsonl, Bookmarks, Config, Embedder, EmbeddingModel, ErrorReport,
+    ExecutionProvider, IndexStats, IndexWatcher, Indexer, LgrepError, OutputFormat, ProgressFormat,
+    QueryHistory, SearchFilter, SearchResult, Searcher, SortOrder, VectorIndex, WorkspaceRoot,
+    DEFAULT_HYBRID_ALPHA, DEFAULT_MAX_COUNT_CEILING, DEFAULT_MMR_LAMBDA, DEFAULT_SHORT_QUERY_CHARS,
+    MMR_OVERFETCH,
 };
+use std::io::BufRead;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tracing_subscriber::EnvFilter;
 
 #[derive(Parser, Debug)]
@@ -19,22 +31,132 @@ struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
 
+    /// How errors are printed: text, or json (one object on stderr, for scripts)
+    #[arg(
+        long,
+        global = true,
+        default_value = "text",
+        value_parser = ["text", "json"],
+        env = "LGREP_ERROR_FORMAT"
+    )]
+    error_format: String,
+
     /// Search query (when no subcommand is provided)
     #[arg(trailing_var_arg = true)]
     query: Vec<String>,
 
-    /// Maximum number of results
-    #[arg(short = 'm', long, default_value = "10", env = "LGREP_MAX_COUNT")]
-    max_count: usize,
+    /// Maximum number of results [default: 10, or LGREP_MAX_COUNT]
+    #[arg(short = 'm', long)]
+    max_count: Option<usize>,
+
+    /// Upper bound on --max-count
+    #[arg(long, default_value_t = DEFAULT_MAX_COUNT_CEILING, env = "LGREP_MAX_COUNT_CEILING")]
+    max_count_ceiling: usize,
+
+    /// Skip this many of the best results, for paging (with -m as page size)
+    #[arg(long, default_value_t = 0)]
+    offset: usize,
 
     /// Show content of results
     #[arg(short = 'c', long, env = "LGREP_CONTENT")]
     content: bool,
 
+    /// Also read N source lines around each result from disk (shown with -c and in JSON)
+    #[arg(short = 'C', long, value_name = "N", default_value_t = 0)]
+    context: usize,
+
     /// Output as JSON
     #[arg(long, env = "LGREP_JSON")]
     json: bool,
 
+    /// Output format: text, json, jsonl (streamed), github (Actions annotations), sarif, or csv
+    #[arg(long, default_value = "text")]
+    format: String,
+
+    /// Explain why each result matched (score breakdown)
+    #[arg(long)]
+    explain: bool,
+
+    /// Result order: score, path, or recent
+    #[arg(long, default_value = "score")]
+    sort: String,
+
+    /// Print a compact ranked list of all hits before the details
+    #[arg(long)]
+    summary: bool,
+
+    /// Print one aligned `score% | lang | file:line` row per result instead of details
+    #[arg(long)]
+    compact: bool,
+
+    /// List each file once with its matching line ranges beneath it
+    #[arg(long)]
+    group: bool,
+
+    /// Cut results at the largest score drop, keeping the clearly relevant cluster
+    #[arg(long)]
+    auto_threshold: bool,
+
+    /// Rerank for variety (MMR) so near-identical chunks don't fill the results
+    #[arg(long)]
+    diverse: bool,
+
+    /// Relevance weight for --diverse, from 0.0 (most varied) to 1.0 (plain ranking)
+    #[arg(long, value_name = "LAMBDA", default_value_t = DEFAULT_MMR_LAMBDA)]
+    diversity_lambda: f32,
+
+    /// Rank the chunks of a single indexed file instead of the whole index
+    #[arg(long, value_name = "FILE")]
+    in_file: Option<PathBuf>,
+
+    /// Truncate content lines to this many columns (default: terminal width)
+    #[arg(long)]
+    width: Option<usize>,
+
+    /// Bold the line of each shown result that best matches the query (slower)
+    #[arg(long, conflicts_with = "no_highlight")]
+    highlight_lines: bool,
+
+    /// Don't highlight keyword matches or the best-matching line in content
+    #[arg(long)]
+    no_highlight: bool,
+
+    /// Open the Nth result (as numbered in the output) in the editor instead of printing
+    #[arg(long, value_name = "N", conflicts_with = "group")]
+    open: Option<usize>,
+
+    /// Editor for --open (default: $VISUAL or $EDITOR), e.g. "code -w"
+    #[arg(long, value_name = "CMD")]
+    editor: Option<String>,
+
+    /// Decimal places of the percentage score in text output
+    #[arg(long, default_value = "0")]
+    score_decimals: usize,
+
+    /// Show the raw embedding distance next to each score
+    #[arg(long)]
+    show_distance: bool,
+
+    /// Don't suggest related searches after the results
+    #[arg(long)]
+    no_suggestions: bool,
+
+    /// Don't blend literal matches into the results of short queries
+    #[arg(long)]
+    no_auto_hybrid: bool,
+
+    /// Queries up to this many characters also match chunk text literally
+    #[arg(long, default_value_t = DEFAULT_SHORT_QUERY_CHARS)]
+    short_query_chars: usize,
+
+    /// Keyword weight for short queries, from 0.0 (semantic only) to 1.0 (matches only)
+    #[arg(long, value_name = "ALPHA", default_value_t = DEFAULT_HYBRID_ALPHA)]
+    alpha: f32,
+
+    /// Only keep results whose lines changed in the last N days (git blame, else mtime)
+    #[arg(long, value_name = "N")]
+    recent_days: Option<u64>,
+
     /// Sync index before searching
     #[arg(short = 's', long, env = "LGREP_SYNC")]
     sync: bool,
@@ -52,21 +174,103 @@ struct Cli {
     verbose: bool,
 }
 
+// Parsed once per run; boxing the larger variants buys nothing
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Build or rebuild the search index
     Index {
-        /// Path to index
+        /// Path to index (a directory, or a .zip/.tar/.tar.gz archive)
         #[arg(default_value = ".")]
         path: PathBuf,
 
-        /// Embedding model to use
-        #[arg(long, default_value = "minilm")]
-        model: String,
+        /// Embedding model to use (default: `model` in .lgrep.toml, or minilm)
+        #[arg(long)]
+        model: Option<String>,
+
+        /// Compute embeddings on: cpu, cuda or coreml (falls back to cpu)
+        #[arg(long, default_value = "cpu")]
+        device: String,
+
+        /// Embedding dimension of a `custom:PATH` model
+        #[arg(long, value_name = "N")]
+        model_dimension: Option<usize>,
 
         /// Force rebuild even if index exists
         #[arg(short, long)]
         force: bool,
+
+        /// Index this workspace root instead of PATH (repeatable; the index stays in PATH)
+        #[arg(long = "root")]
+        roots: Vec<PathBuf>,
+
+        /// Skip files marked linguist-generated or linguist-vendored in .gitattributes
+        #[arg(long)]
+        respect_gitattributes: bool,
+
+        /// Also index files excluded by .gitignore, .ignore and .lgrepignore
+        #[arg(long)]
+        no_ignore: bool,
+
+        /// Also index hidden files and directories (.git and .lgrep are still skipped)
+        #[arg(long)]
+        hidden: bool,
+
+        /// Also store per-file embeddings (enables `lgrep files --search`)
+        #[arg(long)]
+        file_embeddings: bool,
+
+        /// Only index programming-language files (skip docs, config and data)
+        #[arg(long)]
+        code_only: bool,
+
+        /// Don't re-index files whose only changes are whitespace (e.g. after a formatter)
+        #[arg(long)]
+        ignore_whitespace_changes: bool,
+
+        /// Also index files with these extensions (comma-separated, e.g. "proto,graphql")
+        #[arg(long, value_name = "EXTS", value_delimiter = ',')]
+        include_ext: Vec<String>,
+
+        /// Stop indexing files with these extensions (comma-separated)
+        #[arg(long, value_name = "EXTS", value_delimiter = ',')]
+        exclude_ext: Vec<String>,
+
+        /// Index every UTF-8 text file, whatever its extension
+        #[arg(long)]
+        all_text: bool,
+
+        /// Progress display: bar, or json for one event per line on stderr
+        #[arg(long, value_name = "FORMAT", default_value = "bar")]
+        progress: String,
+
+        /// Index exactly the files listed on stdin (newline or NUL separated)
+        #[arg(long, conflicts_with = "files_from")]
+        from_stdin: bool,
+
+        /// Index exactly the files listed in FILE (newline or NUL separated)
+        #[arg(long, value_name = "FILE")]
+        files_from: Option<PathBuf>,
+
+        /// Also index listed files lgrep would skip by extension or size
+        #[arg(long)]
+        no_filter: bool,
+
+        /// Show what would be indexed (files, size, estimated chunks) without indexing
+        #[arg(long, conflicts_with_all = ["from_stdin", "files_from"])]
+        dry_run: bool,
+
+        /// HNSW graph degree; higher improves recall on large indexes (default: 16)
+        #[arg(long, value_name = "M")]
+        hnsw_connectivity: Option<usize>,
+
+        /// HNSW candidates considered while building (default: 128)
+        #[arg(long, value_name = "N")]
+        hnsw_expansion_add: Option<usize>,
+
+        /// HNSW candidates considered while searching (default: 64)
+        #[arg(long, value_name = "N")]
+        hnsw_expansion_search: Option<usize>,
     },
 
     /// Watch for file changes and update index automatically
@@ -75,32 +279,60 @@ enum Commands {
         #[arg(default_value = ".")]
         path: PathBuf,
 
-        /// Embedding model to use
-        #[arg(long, default_value = "minilm")]
-        model: String,
+        /// Embedding model to use (default: `model` in .lgrep.toml, or minilm)
+        #[arg(long)]
+        model: Option<String>,
+
+        /// Compute embeddings on: cpu, cuda or coreml (falls back to cpu)
+        #[arg(long, default_value = "cpu")]
+        device: String,
+
+        /// Embedding dimension of a `custom:PATH` model
+        #[arg(long, value_name = "N")]
+        model_dimension: Option<usize>,
     },
 
     /// Search the index
     Search {
-        /// Search query
+        /// Search query (`-` reads one query per line from stdin, printing JSON Lines)
         query: String,
 
         /// Path to search in
         #[arg(default_value = ".")]
         path: PathBuf,
 
-        /// Maximum number of results
-        #[arg(short = 'm', long, default_value = "10")]
-        max_count: usize,
+        /// Maximum number of results [default: top-level -m, LGREP_MAX_COUNT, or 10]
+        #[arg(short = 'm', long)]
+        max_count: Option<usize>,
+
+        /// Skip this many of the best results, for paging (with -m as page size)
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
 
         /// Show content of results
         #[arg(short = 'c', long)]
         content: bool,
 
+        /// Also read N source lines around each result from disk (shown with -c and in JSON)
+        #[arg(short = 'C', long, value_name = "N", default_value_t = 0)]
+        context: usize,
+
         /// Output as JSON
         #[arg(long)]
         json: bool,
 
+        /// Output as CSV (file, lines, score, language; plus content with -c)
+        #[arg(long, conflicts_with = "json")]
+        csv: bool,
+
+        /// Output a SARIF 2.1.0 log for code scanning uploads (same as --format sarif)
+        #[arg(long, conflicts_with_all = ["json", "csv"])]
+        sarif: bool,
+
+        /// Output format: text, json, jsonl (streamed), github (Actions annotations), sarif, or csv
+        #[arg(long, default_value = "text")]
+        format: String,
+
         /// Sync index before searching
         #[arg(short = 's', long)]
         sync: bool,
@@ -113,21 +345,206 @@ enum Commands {
         #[arg(long)]
         lang: Option<String>,
 
-        /// Filter by path pattern (regex)
+        /// Only search files under this directory (relative to the index root)
+        #[arg(long, value_name = "DIR")]
+        scope: Option<String>,
+
+        /// Filter by path pattern (regex, repeatable: matches any)
         #[arg(long)]
-        path_pattern: Option<String>,
+        path_pattern: Vec<String>,
 
-        /// Exclude path pattern (regex)
+        /// Exclude path pattern (regex, repeatable: excludes any)
         #[arg(long)]
-        exclude: Option<String>,
+        exclude: Vec<String>,
+
+        /// Only chunks that are mostly code, comments, or docs
+        #[arg(long, value_name = "KIND")]
+        only: Option<String>,
 
         /// Minimum similarity score (0.0 to 1.0)
         #[arg(long)]
         min_score: Option<f32>,
 
+        /// Maximum similarity score (0.0 to 1.0)
+        #[arg(long)]
+        max_score: Option<f32>,
+
+        /// Only files modified since TIME when indexed (e.g. 7d, 2w, 2024-05-01)
+        #[arg(long, value_name = "TIME")]
+        since: Option<String>,
+
+        /// Only files modified before TIME when indexed (e.g. 1d, 2024-05-01)
+        #[arg(long, value_name = "TIME")]
+        until: Option<String>,
+
+        /// Only files of at most SIZE when indexed (e.g. 500k, 2M)
+        #[arg(long, value_name = "SIZE")]
+        max_filesize: Option<String>,
+
+        /// Only files of at least SIZE when indexed (e.g. 1k)
+        #[arg(long, value_name = "SIZE")]
+        min_filesize: Option<String>,
+
         /// Keyword pattern for hybrid search (regex)
         #[arg(short = 'k', long)]
         keyword: Option<String>,
+
+        /// Keyword weight for --keyword and short queries, from 0.0 (semantic only) to 1.0 (matches only)
+        #[arg(long, value_name = "ALPHA", default_value_t = DEFAULT_HYBRID_ALPHA)]
+        alpha: f32,
+
+        /// Explain why each result matched (score breakdown)
+        #[arg(long)]
+        explain: bool,
+
+        /// Result order: score, path, or recent
+        #[arg(long, default_value = "score")]
+        sort: String,
+
+        /// Collapse results with identical content, listing other locations
+        #[arg(long)]
+        dedupe_content: bool,
+
+        /// Print a compact ranked list of all hits before the details
+        #[arg(long)]
+        summary: bool,
+
+        /// Print one aligned `score% | lang | file:line` row per result instead of details
+        #[arg(long)]
+        compact: bool,
+
+        /// List each file once with its matching line ranges beneath it
+        #[arg(long)]
+        group: bool,
+
+        /// Cut results at the largest score drop, keeping the clearly relevant cluster
+        #[arg(long)]
+        auto_threshold: bool,
+
+        /// Rerank for variety (MMR) so near-identical chunks don't fill the results
+        #[arg(long)]
+        diverse: bool,
+
+        /// Relevance weight for --diverse, from 0.0 (most varied) to 1.0 (plain ranking)
+        #[arg(long, value_name = "LAMBDA", default_value_t = DEFAULT_MMR_LAMBDA)]
+        diversity_lambda: f32,
+
+        /// Rank the chunks of a single indexed file instead of the whole index
+        #[arg(long, value_name = "FILE")]
+        in_file: Option<PathBuf>,
+
+        /// Truncate content lines to this many columns (default: terminal width)
+        #[arg(long)]
+        width: Option<usize>,
+
+        /// Bold the line of each shown result that best matches the query (slower)
+        #[arg(long, conflicts_with = "no_highlight")]
+        highlight_lines: bool,
+
+        /// Don't highlight keyword matches or the best-matching line in content
+        #[arg(long)]
+        no_highlight: bool,
+
+        /// Open the Nth result (as numbered in the output) in the editor instead of printing
+        #[arg(long, value_name = "N", conflicts_with = "group")]
+        open: Option<usize>,
+
+        /// Editor for --open (default: $VISUAL or $EDITOR), e.g. "code -w"
+        #[arg(long, value_name = "CMD")]
+        editor: Option<String>,
+
+        /// Decimal places of the percentage score in text output
+        #[arg(long, default_value = "0")]
+        score_decimals: usize,
+
+        /// Show the raw embedding distance next to each score
+        #[arg(long)]
+        show_distance: bool,
+
+        /// Don't suggest related searches after the results
+        #[arg(long)]
+        no_suggestions: bool,
+
+        /// Don't blend literal matches into the results of short queries
+        #[arg(long)]
+        no_auto_hybrid: bool,
+
+        /// Queries up to this many characters also match chunk text literally
+        #[arg(long, default_value_t = DEFAULT_SHORT_QUERY_CHARS)]
+        short_query_chars: usize,
+
+        /// Only keep results whose lines changed in the last N days (git blame, else mtime)
+        #[arg(long, value_name = "N")]
+        recent_days: Option<u64>,
+
+        /// Search the indexes of these directories together instead of PATH (repeatable)
+        #[arg(
+            short = 'p',
+            long = "project",
+            value_name = "DIR",
+            conflicts_with_all = ["sync", "in_file", "open", "recent_days", "diverse", "highlight_lines"]
+        )]
+        projects: Vec<PathBuf>,
+    },
+
+    /// Serve searches over HTTP, keeping the index and model loaded
+    #[cfg(feature = "server")]
+    Serve {
+        /// Path to index
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Port to listen on (localhost only)
+        #[arg(long, default_value = "7070")]
+        port: u16,
+    },
+
+    /// Serve searches to AI assistants over the Model Context Protocol (stdio)
+    Mcp {
+        /// Path to index
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+
+    /// List indexed files, or rank them by relevance to a query
+    Files {
+        /// Path to index
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Rank files by similarity to this query (needs --file-embeddings at index time)
+        #[arg(short, long)]
+        search: Option<String>,
+
+        /// Maximum number of files to show when searching [default: 10]
+        #[arg(short = 'm', long)]
+        max_count: Option<usize>,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Find code similar to the chunk at a location
+    Similar {
+        /// Location of the chunk as file:line (e.g. src/auth.rs:42)
+        location: String,
+
+        /// Path to index
+        #[arg(short = 'p', long, default_value = ".")]
+        path: PathBuf,
+
+        /// Maximum number of results [default: 10]
+        #[arg(short = 'm', long)]
+        max_count: Option<usize>,
+
+        /// Show content of results
+        #[arg(short = 'c', long)]
+        content: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
     },
 
     /// Show index statistics
@@ -137,9 +554,62 @@ enum Commands {
         path: PathBuf,
     },
 
+    /// Rebuild the vector index without removed chunks to reclaim space
+    Compact {
+        /// Path to index
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+
+    /// Export the index as a portable JSON Lines bundle
+    Export {
+        /// Bundle file to write
+        file: PathBuf,
+
+        /// Path to index
+        #[arg(short = 'p', long, default_value = ".")]
+        path: PathBuf,
+
+        /// Leave out the embeddings (a smaller bundle, re-embedded on import)
+        #[arg(long)]
+        no_vectors: bool,
+    },
+
+    /// Rebuild an index from a bundle written by `lgrep export`
+    Import {
+        /// Bundle file to read
+        file: PathBuf,
+
+        /// Directory the index is for
+        #[arg(short = 'p', long, default_value = ".")]
+        path: PathBuf,
+
+        /// Embedding model to use (must match the bundle's embeddings, if any)
+        #[arg(long, default_value = "minilm")]
+        model: String,
+    },
+
     /// List available embedding models
     Models,
 
+    /// Load (and download if needed) an embedding model without indexing
+    Warmup {
+        /// Embedding model to use
+        #[arg(long, default_value = "minilm")]
+        model: String,
+    },
+
+    /// Benchmark the pipeline on a generated corpus to check the setup
+    Bench {
+        /// Embedding model to use
+        #[arg(long, default_value = "minilm")]
+        model: String,
+
+        /// Number of files to generate
+        #[arg(long, default_value = "200")]
+        files: usize,
+    },
+
     /// Show query history
     History {
         /// Path to index
@@ -158,11 +628,89 @@ enum Commands {
         #[arg(long)]
         clear: bool,
     },
+
+    /// Save and recall bookmarked locations
+    Bookmark {
+        #[command(subcommand)]
+        action: BookmarkAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum BookmarkAction {
+    /// Bookmark a location given as file:line
+    Add {
+        /// Location to bookmark (e.g. src/auth.rs:42)
+        location: String,
+
+        /// Note to remember why the location matters
+        #[arg(long)]
+        note: Option<String>,
+
+        /// Path to index
+        #[arg(short = 'p', long, default_value = ".")]
+        path: PathBuf,
+    },
+
+    /// List bookmarks, flagging ones whose file is gone
+    List {
+        /// Path to index
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+
+    /// Search only within bookmarked files
+    Search {
+        /// Search query
+        query: String,
+
+        /// Path to index
+        #[arg(short = 'p', long, default_value = ".")]
+        path: PathBuf,
+
+        /// Maximum number of results
+        #[arg(short = 'm', long)]
+        max_count: Option<usize>,
+
+        /// Show content snippets
+        #[arg(short = 'c', long)]
+        content: bool,
+    },
 }
 
-fn main() -> Result<()> {
+fn main() {
     let cli = Cli::parse();
+    let json_errors = cli.error_format == "json";
+
+    if let Err(err) = run(cli) {
+        report_error(&err, json_errors);
+        std::process::exit(1);
+    }
+}
+
+/// Print an error to stderr, as a single JSON object with `--error-format json`
+fn report_error(err: &anyhow::Error, json: bool) {
+    if !json {
+        eprintln!("{} {:#}", "Error:".red().bold(), err);
+        return;
+    }
+
+    let report = match err.downcast_ref::<LgrepError>() {
+        Some(err) => ErrorReport::from(err),
+        None => ErrorReport {
+            error: if err.is::<std::io::Error>() {
+                "Io"
+            } else {
+                "Other"
+            },
+            message: format!("{:#}", err),
+            hint: None,
+        },
+    };
+    eprintln!("{}", serde_json::to_string(&report).unwrap_or_default());
+}
 
+fn run(cli: Cli) -> Result<()> {
     // Initialize logging
     let filter = if cli.verbose {
         EnvFilter::new("debug")
@@ -173,46 +721,225 @@ fn main() -> Result<()> {
     tracing_subscriber::fmt()
         .with_env_filter(filter)
         .with_target(false)
+        .with_writer(std::io::stderr)
         .init();
 
+    let env_max_count = std::env::var("LGREP_MAX_COUNT").ok();
+    let resolve_count = |subcommand: Option<usize>| {
+        resolve_max_count(
+            subcommand,
+            cli.max_count,
+            env_max_count.as_deref(),
+            cli.max_count_ceiling,
+        )
+    };
+
     match cli.command {
-        Some(Commands::Index { path, model, force }) => cmd_index(path, model, force),
-        Some(Commands::Watch { path, model }) => cmd_watch(path, model),
+        Some(Commands::Index {
+            path,
+            model,
+            device,
+            model_dimension,
+            force,
+            roots,
+            respect_gitattributes,
+            no_ignore,
+            hidden,
+            file_embeddings,
+            code_only,
+            ignore_whitespace_changes,
+            include_ext,
+            exclude_ext,
+            all_text,
+            progress,
+            from_stdin,
+            files_from,
+            no_filter,
+            dry_run,
+            hnsw_connectivity,
+            hnsw_expansion_add,
+            hnsw_expansion_search,
+        }) => {
+            let file_list = if from_stdin {
+                Some(std::io::read_to_string(std::io::stdin())?)
+            } else {
+                files_from.map(std::fs::read_to_string).transpose()?
+            };
+            cmd_index(
+                path,
+                (model, model_dimension),
+                device,
+                force,
+                roots,
+                IndexOptions {
+                    respect_gitattributes,
+                    no_ignore,
+                    hidden,
+                    file_embeddings,
+                    code_only,
+                    ignore_whitespace_changes,
+                    extensions: (include_ext, exclude_ext),
+                    all_text,
+                    progress,
+                    dry_run,
+                    hnsw: [hnsw_connectivity, hnsw_expansion_add, hnsw_expansion_search],
+                },
+                file_list.map(|list| (list, !no_filter)),
+            )
+        }
+        Some(Commands::Watch {
+            path,
+            model,
+            device,
+            model_dimension,
+        }) => cmd_watch(path, (model, model_dimension), device, cli.verbose),
         Some(Commands::Search {
             query,
             path,
             max_count,
+            offset,
             content,
+            context,
             json,
+            csv,
+            sarif,
+            format,
             sync,
             ext,
             lang,
+            scope,
             path_pattern,
             exclude,
+            only,
             min_score,
+            max_score,
+            since,
+            until,
+            max_filesize,
+            min_filesize,
             keyword,
+            alpha,
+            explain,
+            sort,
+            dedupe_content,
+            summary,
+            compact,
+            group,
+            auto_threshold,
+            diverse,
+            diversity_lambda,
+            in_file,
+            width,
+            highlight_lines,
+            no_highlight,
+            open,
+            editor,
+            score_decimals,
+            show_distance,
+            no_suggestions,
+            no_auto_hybrid,
+            short_query_chars,
+            recent_days,
+            projects,
         }) => cmd_search(
             query,
             path,
+            SearchOptions {
+                max_count: resolve_count(max_count)?,
+                max_count_ceiling: cli.max_count_ceiling,
+                offset,
+                content,
+                context,
+                json,
+                csv,
+                sarif,
+                format,
+                sync,
+                ext,
+                lang,
+                scope,
+                path_pattern,
+                exclude,
+                only,
+                min_score,
+                max_score,
+                since,
+                until,
+                max_filesize,
+                min_filesize,
+                keyword,
+                alpha,
+                explain,
+                sort,
+                dedupe_content,
+                summary,
+                compact,
+                group,
+                auto_threshold,
+                diverse,
+                diversity_lambda,
+                in_file,
+                width,
+                highlight_lines,
+                no_highlight,
+                open,
+                editor,
+                score_decimals,
+                show_distance,
+                no_suggestions,
+                no_auto_hybrid,
+                short_query_chars,
+                recent_days,
+                projects,
+            },
+        ),
+        #[cfg(feature = "server")]
+        Some(Commands::Serve { path, port }) => cmd_serve(path, port),
+        Some(Commands::Mcp { path }) => cmd_mcp(path),
+        Some(Commands::Files {
+            path,
+            search,
+            max_count,
+            json,
+        }) => cmd_files(path, search, resolve_count(max_count)?, json),
+        Some(Commands::Similar {
+            location,
+            path,
             max_count,
             content,
             json,
-            sync,
-            ext,
-            lang,
-            path_pattern,
-            exclude,
-            min_score,
-            keyword,
-        ),
+        }) => cmd_similar(location, path, resolve_count(max_count)?, content, json),
         Some(Commands::Stats { path }) => cmd_stats(path),
+        Some(Commands::Compact { path }) => cmd_compact(path),
+        Some(Commands::Export {
+            file,
+            path,
+            no_vectors,
+        }) => cmd_export(file, path, !no_vectors),
+        Some(Commands::Import { file, path, model }) => cmd_import(file, path, model),
         Some(Commands::Models) => cmd_models(),
+        Some(Commands::Warmup { model }) => cmd_warmup(model),
+        Some(Commands::Bench { model, files }) => cmd_bench(model, files),
         Some(Commands::History {
             path,
             limit,
             top,
             clear,
         }) => cmd_history(path, limit, top, clear),
+        Some(Commands::Bookmark { action }) => match action {
+            BookmarkAction::Add {
+                location,
+                note,
+                path,
+            } => cmd_bookmark_add(location, note, path),
+            BookmarkAction::List { path } => cmd_bookmark_list(path),
+            BookmarkAction::Search {
+                query,
+                path,
+                max_count,
+                content,
+            } => cmd_bookmark_search(query, path, resolve_count(max_count)?, content),
+        },
         None => {
             // Default: search if query provided, otherwise show help
             if cli.query.is_empty() {
@@ -239,38 +966,206 @@ fn main() -> Result<()> {
             cmd_search(
                 query,
                 cli.path,
-                cli.max_count,
-                cli.content,
-                cli.json,
-                cli.sync,
-                None,
-                None,
-                None,
-                None,
-                None,
-                None,
+                SearchOptions {
+                    max_count: resolve_count(None)?,
+                    max_count_ceiling: cli.max_count_ceiling,
+                    offset: cli.offset,
+                    content: cli.content,
+                    context: cli.context,
+                    json: cli.json,
+                    format: cli.format,
+                    sync: cli.sync,
+                    explain: cli.explain,
+                    sort: cli.sort,
+                    summary: cli.summary,
+                    compact: cli.compact,
+                    group: cli.group,
+                    auto_threshold: cli.auto_threshold,
+                    diverse: cli.diverse,
+                    diversity_lambda: cli.diversity_lambda,
+                    in_file: cli.in_file,
+                    width: cli.width,
+                    highlight_lines: cli.highlight_lines,
+                    no_highlight: cli.no_highlight,
+                    open: cli.open,
+                    editor: cli.editor,
+                    score_decimals: cli.score_decimals,
+                    show_distance: cli.show_distance,
+                    no_suggestions: cli.no_suggestions,
+                    no_auto_hybrid: cli.no_auto_hybrid,
+                    short_query_chars: cli.short_query_chars,
+                    alpha: cli.alpha,
+                    recent_days: cli.recent_days,
+                    ..Default::default()
+                },
             )
         }
     }
 }
 
-fn cmd_index(path: PathBuf, model: String, force: bool) -> Result<()> {
+/// Index settings, most of which stick to an existing index once enabled
+#[derive(Debug, Default)]
+struct IndexOptions {
+    respect_gitattributes: bool,
+    no_ignore: bool,
+    hidden: bool,
+    file_embeddings: bool,
+    code_only: bool,
+    ignore_whitespace_changes: bool,
+    /// Extensions to add to and remove from the indexed set
+    extensions: (Vec<String>, Vec<String>),
+    all_text: bool,
+    /// Progress display, `bar` or `json`
+    progress: String,
+    /// Only report what would be indexed
+    dry_run: bool,
+    /// HNSW connectivity, expansion_add and expansion_search overrides
+    hnsw: [Option<usize>; 3],
+}
+
+fn cmd_index(
+    path: PathBuf,
+    model: (Option<String>, Option<usize>),
+    device: String,
+    force: bool,
+    roots: Vec<PathBuf>,
+    options: IndexOptions,
+    file_list: Option<(String, bool)>,
+) -> Result<()> {
+    let IndexOptions {
+        respect_gitattributes,
+        no_ignore,
+        hidden,
+        file_embeddings,
+        code_only,
+        ignore_whitespace_changes,
+        extensions: (include_ext, exclude_ext),
+        all_text,
+        progress,
+        dry_run,
+        hnsw,
+    } = options;
+
     let path = path.canonicalize()?;
-    println!("{} {:?}", "Indexing".cyan().bold(), path);
+    let action = if dry_run { "Dry run for" } else { "Indexing" };
+    println!("{} {:?}", action.cyan().bold(), path);
+
+    let device: ExecutionProvider = device.parse()?;
+    let progress: ProgressFormat = progress.parse()?;
+
+    // An archive is indexed by its in-archive paths, with the index kept
+    // next to it
+    let archive = lgrep::archive::is_archive(&path).then(|| path.clone());
+    let root = match &archive {
+        Some(archive) => archive.parent().unwrap_or(&path).to_path_buf(),
+        None => path.clone(),
+    };
 
-    let model: EmbeddingModel = model.parse()?;
-    let config = Config::new(path.clone()).with_model(model);
+    let mut config = project_config(root, model)?.with_execution_provider(device);
+    if let Some(archive) = archive {
+        config = config.with_archive(archive);
+    }
+    // The flags below are added to the settings of an existing index
+    let existing = Config::load(&config.index_dir).ok();
+    if let Some(existing) = &existing {
+        config = keep_index_settings(config, existing);
+    }
 
-    if !force && config.index_path().exists() {
-        println!("Index already exists. Updating...");
-        let indexer = Indexer::new(config.clone())?;
-        let mut index = VectorIndex::load(config)?;
-        let stats = indexer.update_index(&mut index)?;
-        println!("\n{} {}", "✓".green(), stats);
-    } else {
-        let indexer = Indexer::new(config)?;
-        let index = indexer.build_index()?;
-        println!(
+    if !roots.is_empty() {
+        config = config.with_root_paths(roots);
+        for root in config.roots()? {
+            println!("  {} {:?}", root.label.dimmed(), root.path);
+        }
+    }
+
+    let respect_gitattributes = respect_gitattributes || config.respect_gitattributes;
+    config = config.with_respect_gitattributes(respect_gitattributes);
+    let no_ignore = no_ignore || config.no_ignore;
+    let hidden = hidden || config.include_hidden;
+    config = config.with_no_ignore(no_ignore).with_include_hidden(hidden);
+
+    // File vectors are only computed for (re-)embedded files, so turning
+    // them on for an existing index needs a full rebuild
+    let had_file_embeddings = config.file_embeddings;
+    let force = force || (file_embeddings && !had_file_embeddings);
+    config = config.with_file_embeddings(file_embeddings || had_file_embeddings);
+
+    // Files that are no longer indexable are only dropped by a full walk
+    let had_code_only = config.code_only;
+    let force = force || (code_only && !had_code_only);
+    config = config.with_code_only(code_only || had_code_only);
+
+    let ignore_whitespace_changes = ignore_whitespace_changes || config.ignore_whitespace_changes;
+    config = config.with_ignore_whitespace_changes(ignore_whitespace_changes);
+
+    // Extension changes apply on top of the ones the index was built with;
+    // files that lose their extension are only dropped by a full walk
+    let before = config.extensions.clone();
+    config = config.with_extensions(&include_ext, &exclude_ext);
+    let dropped_ext = before.iter().any(|ext| !config.extensions.contains(ext));
+    let force = force || (existing.is_some() && dropped_ext);
+
+    let had_all_text = config.all_text;
+    config = config.with_all_text(all_text || had_all_text);
+
+    // The graph is built with these, so changing them needs a full rebuild
+    let previous = [
+        config.hnsw_connectivity,
+        config.hnsw_expansion_add,
+        config.hnsw_expansion_search,
+    ];
+    let [connectivity, expansion_add, expansion_search] =
+        [0, 1, 2].map(|i| hnsw[i].unwrap_or(previous[i]));
+    let force = force
+        || (existing.is_some() && [connectivity, expansion_add, expansion_search] != previous);
+    config = config.with_hnsw(connectivity, expansion_add, expansion_search);
+    config.validate()?;
+
+    if dry_run {
+        print_index_plan(&lgrep::plan_index(&config)?);
+        return Ok(());
+    }
+
+    let load_indexer = |config: Config| -> Result<Indexer> {
+        Ok(Indexer::new(config)?.with_progress(progress.reporter()))
+    };
+
+    // A file list replaces the walk, so the index holds exactly those files
+    if let Some((list, filter)) = file_list {
+        if config.archive.is_some() {
+            return Err(LgrepError::Config(
+                "A file list can't be combined with indexing an archive".into(),
+            )
+            .into());
+        }
+        let cwd = std::env::current_dir()?;
+        let paths: Vec<PathBuf> = parse_path_list(&list)
+            .into_iter()
+            .map(|p| cwd.join(p))
+            .collect();
+
+        let indexer = load_indexer(config)?;
+        let index = indexer.build_index_from_paths(&paths, filter)?;
+        println!(
+            "\n{} Indexed {} of {} listed files, {} chunks",
+            "✓".green(),
+            index.file_count(),
+            paths.len(),
+            index.chunk_count()
+        );
+        return Ok(());
+    }
+
+    if !force && config.index_path().exists() {
+        println!("Index already exists. Updating...");
+        let indexer = load_indexer(config.clone())?;
+        let mut index = VectorIndex::load(config)?;
+        let stats = indexer.update_index(&mut index)?;
+        println!("\n{} {}", "✓".green(), stats);
+    } else {
+        let indexer = load_indexer(config)?;
+        let index = indexer.build_index()?;
+        println!(
             "\n{} Indexed {} files, {} chunks",
             "✓".green(),
             index.file_count(),
@@ -281,55 +1176,272 @@ fn cmd_index(path: PathBuf, model: String, force: bool) -> Result<()> {
     Ok(())
 }
 
-fn cmd_watch(path: PathBuf, model: String) -> Result<()> {
+fn cmd_watch(
+    path: PathBuf,
+    model: (Option<String>, Option<usize>),
+    device: String,
+    verbose: bool,
+) -> Result<()> {
     let path = path.canonicalize()?;
     println!("{} {:?}", "Watching".cyan().bold(), path);
 
-    let model: EmbeddingModel = model.parse()?;
-    let config = Config::new(path).with_model(model);
+    let device: ExecutionProvider = device.parse()?;
+    let mut config = project_config(path, model)?.with_execution_provider(device);
+    if let Ok(existing) = Config::load(&config.index_dir) {
+        config = keep_index_settings(config, &existing);
+    }
+
+    // Verbose log lines would be drawn over the live status line
+    let mut watcher = IndexWatcher::new(config)?.with_status_line(!verbose);
+
+    // Ctrl+C keeps exiting right away until the index is in sync; nothing
+    // is saved until the sync completes, so there is nothing to lose
+    watcher.sync()?;
 
-    let mut watcher = IndexWatcher::new(config)?;
-    watcher.watch()?;
+    // From here the first Ctrl+C stops watching cleanly, and a second one
+    // exits without waiting for the final save
+    let (stop_tx, stop_rx) = std::sync::mpsc::channel();
+    let interrupted = AtomicBool::new(false);
+    ctrlc::set_handler(move || {
+        if interrupted.swap(true, Ordering::SeqCst) {
+            std::process::exit(130);
+        }
+        let _ = stop_tx.send(());
+    })
+    .map_err(|e| LgrepError::Watch(e.to_string()))?;
+    watcher.watch(stop_rx)?;
 
     Ok(())
 }
 
-fn cmd_search(
-    query: String,
-    path: PathBuf,
+/// Carry over the settings an existing index was built with
+///
+/// Used by both `index` and `watch`, so an update never drops one: each
+/// decides which files are indexed or what is stored for them.
+fn keep_index_settings(mut config: Config, existing: &Config) -> Config {
+    config.extensions = existing.extensions.clone();
+    config
+        .with_root_paths(existing.root_paths.clone())
+        .with_respect_gitattributes(existing.respect_gitattributes)
+        .with_no_ignore(existing.no_ignore)
+        .with_include_hidden(existing.include_hidden)
+        .with_file_embeddings(existing.file_embeddings)
+        .with_code_only(existing.code_only)
+        .with_ignore_whitespace_changes(existing.ignore_whitespace_changes)
+        .with_all_text(existing.all_text)
+        .with_hnsw(
+            existing.hnsw_connectivity,
+            existing.hnsw_expansion_add,
+            existing.hnsw_expansion_search,
+        )
+}
+
+/// Config for `root` from its `.lgrep.toml`, if any, with `--model` on top
+fn project_config(
+    root: PathBuf,
+    (name, dimension): (Option<String>, Option<usize>),
+) -> Result<Config> {
+    let config = match Config::from_project_file(&root)? {
+        Some(config) => config,
+        None => Config::new(root),
+    };
+    Ok(match (name, dimension) {
+        (Some(name), dimension) => config.with_model(parse_model((name, dimension))?),
+        (None, Some(dimension)) => {
+            let model = config.model.clone().with_dimension(dimension);
+            config.with_model(model)
+        }
+        (None, None) => config,
+    })
+}
+
+/// Parse `--model`, applying `--model-dimension` to a custom model
+fn parse_model((name, dimension): (String, Option<usize>)) -> Result<EmbeddingModel> {
+    let model: EmbeddingModel = name.parse()?;
+    Ok(match dimension {
+        Some(dimension) => model.with_dimension(dimension),
+        None => model,
+    })
+}
+
+/// Print the result of `lgrep index --dry-run`
+fn print_index_plan(plan: &lgrep::IndexPlan) {
+    println!("\n{}", "Would index (nothing written)".cyan().bold());
+    println!("  Files:  {}", plan.files.to_string().green());
+    println!("  Size:   {}", format_bytes(plan.total_bytes).green());
+    println!("  Chunks: ~{}", plan.estimated_chunks.to_string().green());
+
+    for (title, groups) in [
+        ("By language:", &plan.by_language),
+        ("By extension:", &plan.by_extension),
+    ] {
+        if groups.is_empty() {
+            continue;
+        }
+        let mut groups: Vec<_> = groups.iter().collect();
+        groups.sort_by(|a, b| b.1.files.cmp(&a.1.files).then(a.0.cmp(b.0)));
+        println!("  {}", title);
+        for (name, group) in groups {
+            println!(
+                "    {:<12} {:>7} files {:>10} {}",
+                name,
+                group.files,
+                format_bytes(group.bytes),
+                format!("(~{} chunks)", group.estimated_chunks).dimmed()
+            );
+        }
+    }
+}
+
+/// Run `load`, first explaining the wait if it has to download `model`
+///
+/// fastembed only shows a bare progress bar, which on a first run looks like
+/// lgrep hanging. Indexing reports the same through [`Progress::Download`].
+///
+/// [`Progress::Download`]: lgrep::Progress::Download
+fn load_model<T>(
+    model: &EmbeddingModel,
+    load: impl FnOnce() -> std::result::Result<T, LgrepError>,
+) -> Result<T> {
+    let Some(size_mb) = model.download_size_mb() else {
+        return Ok(load()?);
+    };
+    if Embedder::is_cached(model) {
+        return Ok(load()?);
+    }
+
+    eprintln!(
+        "{} model {} (~{}MB), this happens once",
+        "Downloading".cyan().bold(),
+        model.model_name(),
+        size_mb
+    );
+    let loaded = load()?;
+    if let Some(dir) = Embedder::cache_dir(model) {
+        eprintln!("{} Model cached in {}", "✓".green(), dir.display());
+    }
+    Ok(loaded)
+}
+
+/// Options shared by the default search and the `search` subcommand
+#[derive(Debug, Default)]
+struct SearchOptions {
     max_count: usize,
+    max_count_ceiling: usize,
+    offset: usize,
     content: bool,
+    context: usize,
     json: bool,
+    csv: bool,
+    sarif: bool,
+    format: String,
     sync: bool,
     ext: Option<String>,
     lang: Option<String>,
-    path_pattern: Option<String>,
-    exclude: Option<String>,
+    scope: Option<String>,
+    path_pattern: Vec<String>,
+    exclude: Vec<String>,
+    only: Option<String>,
     min_score: Option<f32>,
+    max_score: Option<f32>,
+    since: Option<String>,
+    until: Option<String>,
+    max_filesize: Option<String>,
+    min_filesize: Option<String>,
     keyword: Option<String>,
-) -> Result<()> {
+    alpha: f32,
+    explain: bool,
+    sort: String,
+    dedupe_content: bool,
+    summary: bool,
+    compact: bool,
+    group: bool,
+    auto_threshold: bool,
+    diverse: bool,
+    diversity_lambda: f32,
+    in_file: Option<PathBuf>,
+    width: Option<usize>,
+    highlight_lines: bool,
+    no_highlight: bool,
+    /// Result to open in the editor (1-based) and the editor override
+    open: Option<usize>,
+    editor: Option<String>,
+    score_decimals: usize,
+    show_distance: bool,
+    no_suggestions: bool,
+    no_auto_hybrid: bool,
+    short_query_chars: usize,
+    recent_days: Option<u64>,
+    /// Directories whose indexes are searched together instead of `path`
+    projects: Vec<PathBuf>,
+}
+
+fn cmd_search(query: String, path: PathBuf, options: SearchOptions) -> Result<()> {
+    let SearchOptions {
+        max_count,
+        max_count_ceiling,
+        offset,
+        content,
+        context,
+        json,
+        csv,
+        sarif,
+        format,
+        sync,
+        ext,
+        lang,
+        scope,
+        path_pattern,
+        exclude,
+        only,
+        min_score,
+        max_score,
+        since,
+        until,
+        max_filesize,
+        min_filesize,
+        keyword,
+        alpha,
+        explain,
+        sort,
+        dedupe_content,
+        summary,
+        compact,
+        group,
+        auto_threshold,
+        diverse,
+        diversity_lambda,
+        in_file,
+        width,
+        highlight_lines: highlight,
+        no_highlight,
+        open,
+        editor,
+        score_decimals,
+        show_distance,
+        no_suggestions,
+        no_auto_hybrid,
+        short_query_chars,
+        recent_days,
+        projects,
+    } = options;
+
     let path = path.canonicalize()?;
+    let sort: SortOrder = sort.parse()?;
+    let format = if json {
+        OutputFormat::Json
+    } else if csv {
+        OutputFormat::Csv
+    } else if sarif {
+        OutputFormat::Sarif
+    } else {
+        format.parse()?
+    };
 
     // Check if index exists
     let index_dir = path.join(".lgrep");
-    if !index_dir.exists() {
-        eprintln!(
-            "{} No index found. Run {} first.",
-            "Error:".red().bold(),
-            "lgrep index".yellow()
-        );
-        std::process::exit(1);
-    }
-
-    // Sync if requested
-    if sync {
-        let config = Config::load(&index_dir)?;
-        let indexer = Indexer::new(config.clone())?;
-        let mut index = VectorIndex::load(config)?;
-        let stats = indexer.update_index(&mut index)?;
-        if stats.added > 0 || stats.updated > 0 || stats.removed > 0 {
-            eprintln!("Synced: {}", stats);
-        }
+    if projects.is_empty() && !index_dir.exists() {
+        return Err(LgrepError::NoIndex.into());
     }
 
     // Build filter from options
@@ -346,13 +1458,23 @@ fn cmd_search(
         has_filter = true;
     }
 
-    if let Some(ref pattern) = path_pattern {
-        filter = filter.with_path_pattern(pattern.clone());
+    if let Some(ref dir) = scope {
+        filter = filter.with_scope(dir);
+        has_filter |= filter.scope.is_some();
+    }
+
+    if !path_pattern.is_empty() {
+        filter = filter.with_path_patterns(path_pattern.clone());
+        has_filter = true;
+    }
+
+    if !exclude.is_empty() {
+        filter = filter.with_exclude_patterns(exclude);
         has_filter = true;
     }
 
-    if let Some(ref pattern) = exclude {
-        filter = filter.with_exclude_pattern(pattern.clone());
+    if let Some(ref kind) = only {
+        filter = filter.with_content_kind(kind.parse()?);
         has_filter = true;
     }
 
@@ -361,23 +1483,212 @@ fn cmd_search(
         has_filter = true;
     }
 
+    if let Some(score) = max_score {
+        filter = filter.with_max_score(score);
+        has_filter = true;
+    }
+
+    let now = std::time::SystemTime::now();
+    if let Some(ref time) = since {
+        filter = filter.with_modified_after(parse_time(time, now)?);
+        has_filter = true;
+    }
+
+    if let Some(ref time) = until {
+        filter = filter.with_modified_before(parse_time(time, now)?);
+        has_filter = true;
+    }
+
+    if let Some(ref size) = max_filesize {
+        filter = filter.with_max_file_bytes(parse_size(size)?);
+        has_filter = true;
+    }
+
+    if let Some(ref size) = min_filesize {
+        filter = filter.with_min_file_bytes(parse_size(size)?);
+        has_filter = true;
+    }
+
+    filter.validate()?;
+
+    // Rather than quietly searching without the keyword
+    if let Some(ref pattern) = keyword {
+        keyword_regex(pattern)?;
+    }
+
     let filter_opt = if has_filter { Some(&filter) } else { None };
 
-    // Search
-    let searcher = Searcher::load(&path)?;
-    let results = if let Some(kw) = keyword.as_deref() {
-        // Hybrid search with keyword
-        searcher.hybrid_search(&query, Some(kw), max_count, filter_opt)?
-    } else if has_filter {
-        // Semantic search with filters
-        searcher.search_with_filter(&query, max_count, filter_opt)?
+    // Fails early when the indexes were built with different models
+    let multi = if projects.is_empty() {
+        None
     } else {
-        // Basic semantic search
-        searcher.search(&query, max_count)?
+        Some(Searcher::load_many(&projects)?)
     };
 
-    // Save to history
-    if let Ok(mut history) = QueryHistory::load(&index_dir) {
+    // `-` reads one query per line from stdin and streams JSON Lines
+    if query == "-" {
+        if multi.is_some() {
+            return Err(LgrepError::Config(
+                "Queries from stdin search a single index; drop --project".to_string(),
+            )
+            .into());
+        }
+        let searcher = Searcher::load(&path)?;
+        let queries = std::io::stdin()
+            .lock()
+            .lines()
+            .map(|line| line.map_err(LgrepError::from));
+        let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+        searcher.search_batch(queries, max_count, filter_opt, &mut out)?;
+        return Ok(());
+    }
+
+    // A page is the best `offset + max_count` results with the first
+    // `offset` dropped at the end, so every ranking step sees the same list
+    let window = max_count + offset;
+
+    // Over-fetch when collapsing duplicates or reranking for variety so we
+    // can still fill the window, without going past the ceiling
+    let fetch_count = if dedupe_content || diverse {
+        (window * MMR_OVERFETCH).min(max_count_ceiling).max(window)
+    } else {
+        window
+    };
+
+    // Within-file ranking always runs locally against the stored vectors
+    let in_file = in_file.map(|file| {
+        file.canonicalize()
+            .ok()
+            .and_then(|abs| abs.strip_prefix(&path).ok().map(|p| p.to_path_buf()))
+            .unwrap_or(file)
+            .to_string_lossy()
+            .to_string()
+    });
+
+    // Short queries embed poorly, so they also match chunk text literally
+    let auto_hybrid =
+        !no_auto_hybrid && keyword.is_none() && is_short_query(&query, short_query_chars);
+
+    // Prefer a running daemon, which already has the model loaded
+    // Diversity reranking needs the stored vectors and line highlighting
+    // the model, so they also stay local (both conflict with --project, so
+    // the one index is loaded below)
+    let local_only =
+        in_file.is_some() || auto_hybrid || diverse || (highlight && content) || multi.is_some();
+    let remote = if local_only {
+        None
+    } else {
+        search_via_server(
+            &index_dir,
+            sync,
+            &query,
+            fetch_count,
+            filter_opt,
+            keyword.as_deref(),
+            alpha,
+        )
+    };
+
+    // Search one index (each of them, with --project)
+    let search = |searcher: &Searcher| {
+        if let Some(file) = in_file.as_deref() {
+            // Rank chunks of a single file
+            searcher.search_within_file(&query, file, fetch_count)
+        } else if let Some(kw) = keyword.as_deref() {
+            // Hybrid search with keyword
+            searcher.hybrid_search_with_alpha(&query, Some(kw), fetch_count, alpha, filter_opt)
+        } else if auto_hybrid {
+            searcher.auto_hybrid_search_with_alpha(&query, fetch_count, alpha, filter_opt)
+        } else if has_filter {
+            // Semantic search with filters
+            searcher.search_with_filter(&query, fetch_count, filter_opt)
+        } else {
+            // Basic semantic search
+            searcher.search(&query, fetch_count)
+        }
+    };
+
+    // The index searched locally, kept for diversity reranking and line
+    // highlighting
+    let mut local = None;
+    let results = match (remote, &multi) {
+        (Some(results), _) => results,
+        (None, Some(multi)) => multi.search_each(fetch_count, search)?,
+        (None, None) => {
+            // Sync if requested, searching the index that was just updated
+            let searcher = if sync {
+                let (searcher, stats) = Searcher::load_synced(&path)?;
+                if stats.changed() {
+                    eprintln!("Synced: {}", stats);
+                }
+                searcher
+            } else {
+                Searcher::load(&path)?
+            };
+
+            let results = search(&searcher)?;
+            local = Some(searcher);
+            results
+        }
+    };
+
+    // Diversity reranking picks its results from the whole candidate pool
+    let results = if dedupe_content {
+        let mut results = dedupe_results(results);
+        if !diverse {
+            results.truncate(window);
+        }
+        results
+    } else {
+        results
+    };
+
+    // Results are still in score order here, before any diversity reranking
+    let results = if auto_threshold {
+        cut_at_knee(results)
+    } else {
+        results
+    };
+
+    let results = match &local {
+        Some(searcher) if diverse => searcher.rerank_diverse(results, window, diversity_lambda)?,
+        _ => results,
+    };
+
+    // Blame is slow, so recency is only checked for the results kept so far
+    let results = match recent_days {
+        Some(days) => filter_recent(results, &source_roots(&index_dir)?, days),
+        None => results,
+    };
+    let mut results: Vec<SearchResult> = results.into_iter().skip(offset).collect();
+
+    // Reorder for display (filtering above always uses similarity)
+    let roots = match (&multi, sort) {
+        (_, SortOrder::Score | SortOrder::Path) => Vec::new(),
+        (Some(multi), SortOrder::Recent) => multi.roots(),
+        (None, SortOrder::Recent) => source_roots(&index_dir)?,
+    };
+    sort_results(&mut results, sort, &roots);
+
+    // Numbered as in the text output
+    if let Some(n) = open {
+        return open_result(&results, n, &index_dir, editor.as_deref());
+    }
+
+    if context > 0 {
+        match &multi {
+            Some(multi) => multi.add_context(&mut results, context),
+            None => add_context(&mut results, &Config::load(&index_dir)?, context),
+        }
+    }
+
+    // Save to history (of the one index searched)
+    let mut history = if multi.is_some() {
+        None
+    } else {
+        QueryHistory::load(&index_dir).ok()
+    };
+    if let Some(history) = history.as_mut() {
         let filter_desc = if has_filter {
             Some(format!(
                 "ext:{:?} lang:{:?} path:{:?}",
@@ -389,35 +1700,432 @@ fn cmd_search(
         let _ = history.add_query(query.clone(), results.len(), filter_desc);
     }
 
+    // Machine-readable formats stay valid (and CI annotations empty) without results
+    match format {
+        OutputFormat::Github => {
+            print!("{}", format_results_github(&results, &query));
+            return Ok(());
+        }
+        OutputFormat::Sarif => {
+            println!("{}", format_results_sarif(&results, &query)?);
+            return Ok(());
+        }
+        OutputFormat::Jsonl => {
+            let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+            write_results_jsonl(&results, &query, &mut out)?;
+            return Ok(());
+        }
+        OutputFormat::Csv => {
+            print!("{}", format_results_csv(&results, content)?);
+            return Ok(());
+        }
+        OutputFormat::Text | OutputFormat::Json => {}
+    }
+
     if results.is_empty() {
         println!("No results found for: {}", query.yellow());
         return Ok(());
     }
 
     // Output results
-    if json {
+    if format == OutputFormat::Json {
         println!("{}", format_results_json(&results)?);
+    } else if compact {
+        print!(
+            "{}",
+            format_results_compact(&results, width.or_else(terminal_width))
+        );
     } else {
         println!(
             "\n{} results for \"{}\":\n",
             results.len().to_string().green().bold(),
             query.cyan()
         );
-        print!("{}", format_results(&results, content, &path));
+        if summary {
+            print!("{}", format_summary(&results));
+        }
+        if let Some(searcher) = local.as_ref().filter(|_| highlight && content) {
+            searcher.highlight_lines(&query, &mut results)?;
+        }
+        if content && !no_highlight {
+            highlight_matches(&query, &mut results);
+        }
+        let width = width.or_else(terminal_width);
+        if group {
+            print!(
+                "{}",
+                format_results_grouped(&results, content, explain, width, score_decimals)
+            );
+        } else {
+            print!(
+                "{}",
+                format_results(
+                    &results,
+                    content,
+                    explain,
+                    width,
+                    score_decimals,
+                    show_distance,
+                    &path
+                )
+            );
+        }
+
+        let suggestions = if no_suggestions {
+            Vec::new()
+        } else {
+            related_queries(&query, &results, history.as_ref())
+        };
+        if !suggestions.is_empty() {
+            println!("\n{}", "Related searches:".dimmed());
+            for suggestion in suggestions {
+                println!("  {} \"{}\"", "lgrep".green(), suggestion);
+            }
+        }
     }
 
     Ok(())
 }
 
-fn cmd_stats(path: PathBuf) -> Result<()> {
+/// Directories holding the files of an index, none for an archive
+fn source_roots(index_dir: &std::path::Path) -> Result<Vec<WorkspaceRoot>> {
+    let config = Config::load(index_dir)?;
+    if config.archive.is_some() {
+        return Ok(Vec::new());
+    }
+    Ok(config.roots()?)
+}
+
+/// Open the `n`th result (1-based) in the editor
+fn open_result(
+    results: &[SearchResult],
+    n: usize,
+    index_dir: &std::path::Path,
+    editor: Option<&str>,
+) -> Result<()> {
+    use lgrep::editor::{open_in_editor, resolve_editor, result_file};
+
+    let result = n
+        .checked_sub(1)
+        .and_then(|i| results.get(i))
+        .ok_or_else(|| {
+            LgrepError::Config(format!(
+                "No result [{}] to open; the search returned {}",
+                n,
+                results.len()
+            ))
+        })?;
+    let editor = resolve_editor(editor).ok_or_else(|| {
+        LgrepError::Config("No editor set; set $EDITOR or pass --editor".to_string())
+    })?;
+    let file = result_file(&Config::load(index_dir)?, result)?;
+
+    eprintln!(
+        "Opening {}:{}",
+        result.chunk.file_path, result.chunk.start_line
+    );
+    open_in_editor(&editor, &file, result.chunk.start_line)?;
+    Ok(())
+}
+
+/// Search through a running `lgrep serve` daemon, if there is one
+///
+/// Returns `None` (so the caller searches locally) when no daemon answers,
+/// or when it fails or times out.
+#[cfg(feature = "server")]
+fn search_via_server(
+    index_dir: &std::path::Path,
+    sync: bool,
+    query: &str,
+    max_count: usize,
+    filter: Option<&SearchFilter>,
+    keyword: Option<&str>,
+    alpha: f32,
+) -> Option<Vec<SearchResult>> {
+    use lgrep::server::{find_server, remote_reindex, remote_search, SearchRequest};
+
+    let addr = find_server(index_dir)?;
+
+    if sync {
+        match remote_reindex(addr) {
+            Ok(stats) if stats.changed() => {
+                eprintln!("Synced: {}", stats);
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::warn!("Daemon reindex failed, searching locally: {}", e);
+                return None;
+            }
+        }
+    }
+
+    let request = SearchRequest {
+        query: query.to_string(),
+        max_count,
+        filters: filter.cloned(),
+        keyword: keyword.map(|k| k.to_string()),
+        alpha,
+        full_results: true,
+    };
+
+    match remote_search(addr, &request) {
+        Ok(results) => Some(results),
+        Err(e) => {
+            tracing::warn!("Daemon search failed, searching locally: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "server"))]
+fn search_via_server(
+    _index_dir: &std::path::Path,
+    _sync: bool,
+    _query: &str,
+    _max_count: usize,
+    _filter: Option<&SearchFilter>,
+    _keyword: Option<&str>,
+    _alpha: f32,
+) -> Option<Vec<SearchResult>> {
+    None
+}
+
+#[cfg(feature = "server")]
+fn cmd_serve(path: PathBuf, port: u16) -> Result<()> {
     let path = path.canonicalize()?;
     let searcher = Searcher::load(&path)?;
-    let stats = searcher.stats();
+
+    let mut server = lgrep::server::Server::bind(("127.0.0.1", port), searcher)?;
+    println!(
+        "{} on http://{} (POST /search, POST /reindex)",
+        "Serving".cyan().bold(),
+        server.local_addr()?
+    );
+    println!("  Press Ctrl+C to stop.\n");
+
+    server.serve()?;
+
+    Ok(())
+}
+
+fn cmd_files(path: PathBuf, search: Option<String>, max_count: usize, json: bool) -> Result<()> {
+    let path = path.canonicalize()?;
+
+    let Some(query) = search else {
+        // Plain listing needs no embedding model
+        let config = Config::load(&path.join(".lgrep"))?;
+        let index = VectorIndex::load(config)?;
+        let mut files = index.indexed_files();
+        files.sort();
+
+        if json {
+            println!("{}", serde_json::to_string_pretty(&files)?);
+        } else {
+            for file in files {
+                println!("{}", file.green());
+            }
+        }
+        return Ok(());
+    };
+
+    let searcher = Searcher::load(&path)?;
+    let files = searcher.search_files(&query, max_count)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&files)?);
+        return Ok(());
+    }
+
+    for file in &files {
+        println!(
+            "{} {}",
+            format!("{:>3}%", (file.score * 100.0).round() as i32).dimmed(),
+            file.file_path.green()
+        );
+    }
+
+    Ok(())
+}
+
+fn cmd_similar(
+    location: String,
+    path: PathBuf,
+    max_count: usize,
+    content: bool,
+    json: bool,
+) -> Result<()> {
+    let path = path.canonicalize()?;
+    let (file, line) = parse_location(&location)?;
+    let file = std::env::current_dir()?.join(file);
+    let file = relative_path(&path, &file).ok_or_else(|| {
+        LgrepError::InvalidPath(format!("{} is outside {}", file.display(), path.display()))
+    })?;
+
+    let searcher = Searcher::load(&path)?;
+    let seed = searcher.index().chunk_at(&file, line).ok_or_else(|| {
+        LgrepError::InvalidPath(format!("No indexed chunk covers {}:{}", file, line))
+    })?;
+    let results = searcher.search_similar_to_chunk(seed.id, max_count)?;
+
+    if json {
+        println!("{}", format_results_json(&results)?);
+        return Ok(());
+    }
+
+    println!(
+        "{} {}:{}-{}\n",
+        "Similar to".cyan().bold(),
+        seed.file_path,
+        seed.start_line,
+        seed.end_line
+    );
+    if results.is_empty() {
+        println!("No similar code found.");
+        return Ok(());
+    }
+    print!(
+        "{}",
+        format_results(&results, content, false, terminal_width(), 0, false, &path)
+    );
+
+    Ok(())
+}
+
+fn cmd_stats(path: PathBuf) -> Result<()> {
+    let path = path.canonicalize()?;
+    // Stats only need the index, not the model
+    let config = Config::load(&path.join(".lgrep"))?;
+    let stats = IndexStats::of(&VectorIndex::load(config)?);
 
     println!("{}", "Index Statistics".cyan().bold());
     println!("  Files:  {}", stats.files.to_string().green());
     println!("  Chunks: {}", stats.chunks.to_string().green());
     println!("  Model:  {}", stats.model.yellow());
+    let revision = stats.model_revision.as_deref().unwrap_or("unknown");
+    println!("  Revision: {}", revision.dimmed());
+    if stats.format_version < lgrep::FORMAT_VERSION {
+        println!(
+            "  Format: {} {}",
+            stats.format_version,
+            format!("(upgraded to {} on next update)", lgrep::FORMAT_VERSION).dimmed()
+        );
+    } else {
+        println!("  Format: {}", stats.format_version);
+    }
+    println!("  Size:   {}", format_bytes(stats.total_bytes).green());
+    println!("  Average chunk: {} bytes", stats.avg_chunk_len);
+    if !stats.by_language.is_empty() {
+        let mut languages: Vec<_> = stats.by_language.iter().collect();
+        languages.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        println!("  Chunks by language:");
+        for (language, count) in languages {
+            let share = *count as f64 * 100.0 / stats.chunks as f64;
+            println!(
+                "    {:<12} {:>7} {}",
+                language,
+                count,
+                format!("({:.1}%)", share).dimmed()
+            );
+        }
+    }
+    if stats.chunks == 0 {
+        println!("\n{}", LgrepError::EmptyIndex.to_string().yellow());
+    }
+
+    Ok(())
+}
+
+/// `bytes` in the largest unit that keeps it at least 1, e.g. `12.3 MiB`
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+fn cmd_mcp(path: PathBuf) -> Result<()> {
+    let path = path.canonicalize()?;
+    let searcher = Searcher::load(&path)?;
+    tracing::info!("MCP server ready for {}", path.display());
+
+    let server = lgrep::mcp::McpServer::new(searcher);
+    server.serve(std::io::stdin().lock(), std::io::stdout().lock())?;
+
+    Ok(())
+}
+
+fn cmd_compact(path: PathBuf) -> Result<()> {
+    let path = path.canonicalize()?;
+    let config = Config::load(&path.join(".lgrep"))?;
+    let index_path = config.index_path();
+    let mut index = VectorIndex::load(config)?;
+
+    let size_before = std::fs::metadata(&index_path)?.len();
+    index.compact()?;
+    let size_after = std::fs::metadata(&index_path)?.len();
+
+    println!(
+        "{} Compacted {} chunks from {} files ({} -> {} bytes)",
+        "✓".green(),
+        index.chunk_count(),
+        index.file_count(),
+        size_before,
+        size_after
+    );
+
+    Ok(())
+}
+
+fn cmd_export(file: PathBuf, path: PathBuf, embeddings: bool) -> Result<()> {
+    let path = path.canonicalize()?;
+    let config = Config::load(&path.join(".lgrep"))?;
+    let index = VectorIndex::load(config)?;
+
+    let writer = std::io::BufWriter::new(std::fs::File::create(&file)?);
+    let count = lgrep::bundle::export_bundle(&index, writer, embeddings)?;
+    println!("{} Exported {} chunks to {:?}", "✓".green(), count, file);
+
+    Ok(())
+}
+
+fn cmd_import(file: PathBuf, path: PathBuf, model: String) -> Result<()> {
+    use lgrep::bundle::{import_bundle, read_bundle};
+
+    let path = path.canonicalize()?;
+    let model: EmbeddingModel = model.parse()?;
+
+    let reader = std::io::BufReader::new(std::fs::File::open(&file)?);
+    let (header, chunks) = read_bundle(reader)?;
+
+    // Only bundles without vectors need the model
+    let embedder = if header.embeddings {
+        None
+    } else {
+        Some(Embedder::new(&model, ExecutionProvider::Cpu)?)
+    };
+
+    let config = Config::new(path).with_model(model);
+    let index = import_bundle(
+        &header,
+        chunks,
+        config,
+        embedder.as_ref().map(|e| e as &dyn lgrep::Embed),
+    )?;
+    index.save()?;
+
+    println!(
+        "{} Imported {} files, {} chunks",
+        "✓".green(),
+        index.file_count(),
+        index.chunk_count()
+    );
 
     Ok(())
 }
@@ -441,22 +2149,57 @@ fn cmd_models() -> Result<()> {
     println!("    Supports 100+ languages (384 dims, ~470MB)");
     println!("    Best for: Multi-language codebases");
     println!();
+    println!("  {}", "custom:PATH".green().bold());
+    println!("    Your own ONNX model and tokenizer files in PATH (needs --model-dimension)");
+    println!("    Best for: Fine-tuned or domain-specific models");
+    println!();
     println!("Usage: {} --model nomic", "lgrep index".yellow());
 
     Ok(())
 }
 
+fn cmd_warmup(model: String) -> Result<()> {
+    let model: EmbeddingModel = model.parse()?;
+    println!("{} {}", "Loading".cyan().bold(), model.model_name());
+
+    let start = std::time::Instant::now();
+    let embedder = load_model(&model, || Embedder::new(&model, ExecutionProvider::Cpu))?;
+
+    println!(
+        "\n{} Model ready in {:.2}s (dimension: {})",
+        "✓".green(),
+        start.elapsed().as_secs_f64(),
+        embedder.dimension()
+    );
+
+    Ok(())
+}
+
+fn cmd_bench(model: String, files: usize) -> Result<()> {
+    let model: EmbeddingModel = model.parse()?;
+    println!(
+        "{} {} on {} generated files",
+        "Benchmarking".cyan().bold(),
+        model.model_name(),
+        files
+    );
+
+    let start = std::time::Instant::now();
+    let embedder = Embedder::new(&model, ExecutionProvider::Cpu)?;
+    println!("Model load: {:.2?}", start.elapsed());
+
+    let report = lgrep::bench::run_bench(model, Box::new(embedder), files)?;
+    println!("{}", report);
+
+    Ok(())
+}
+
 fn cmd_history(path: PathBuf, limit: usize, top: bool, clear: bool) -> Result<()> {
     let path = path.canonicalize()?;
     let index_dir = path.join(".lgrep");
 
     if !index_dir.exists() {
-        eprintln!(
-            "{} No index found. Run {} first.",
-            "Error:".red().bold(),
-            "lgrep index".yellow()
-        );
-        std::process::exit(1);
+        return Err(LgrepError::NoIndex.into());
     }
 
     let mut history = QueryHistory::load(&index_dir)?;
@@ -517,3 +2260,78 @@ fn cmd_history(path: PathBuf, limit: usize, top: bool, clear: bool) -> Result<()
 
     Ok(())
 }
+
+fn cmd_bookmark_add(location: String, note: Option<String>, path: PathBuf) -> Result<()> {
+    let path = path.canonicalize()?;
+    let (file, line) = parse_location(&location)?;
+    let file = std::env::current_dir()?.join(file);
+
+    let mut bookmarks = Bookmarks::load(&path.join(".lgrep"))?;
+    bookmarks.add(&path, &file, line, note)?;
+    println!("{} Bookmarked {}", "✓".green(), location.cyan());
+
+    Ok(())
+}
+
+fn cmd_bookmark_list(path: PathBuf) -> Result<()> {
+    let path = path.canonicalize()?;
+    let bookmarks = Bookmarks::load(&path.join(".lgrep"))?;
+
+    if bookmarks.is_empty() {
+        println!("No bookmarks yet.");
+        return Ok(());
+    }
+
+    println!("{}", "Bookmarks".cyan().bold());
+    println!();
+    for (i, bookmark) in bookmarks.all().iter().enumerate() {
+        let location = format!("{}:{}", bookmark.file, bookmark.line);
+        let stale = if bookmark.is_stale(&path) {
+            format!(" {}", "(stale: file no longer exists)".red())
+        } else {
+            String::new()
+        };
+        println!(
+            "  {} {}{}",
+            format!("[{}]", i + 1).dimmed(),
+            location.green(),
+            stale
+        );
+        if let Some(ref note) = bookmark.note {
+            println!("      {}", note.dimmed());
+        }
+    }
+
+    Ok(())
+}
+
+fn cmd_bookmark_search(
+    query: String,
+    path: PathBuf,
+    max_count: usize,
+    content: bool,
+) -> Result<()> {
+    let path = path.canonicalize()?;
+    let bookmarks = Bookmarks::load(&path.join(".lgrep"))?;
+
+    if bookmarks.is_empty() {
+        println!("No bookmarks yet.");
+        return Ok(());
+    }
+
+    let searcher = Searcher::load(&path)?;
+    let results =
+        searcher.search_with_filter(&query, max_count, Some(&bookmarks.search_filter()))?;
+
+    if results.is_empty() {
+        println!("No results found for: {}", query.yellow());
+        return Ok(());
+    }
+
+    print!(
+        "{}",
+        format_results(&results, content, false, terminal_width(), 0, false, &path)
+    );
+
+    Ok(())
+}
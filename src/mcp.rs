@@ -0,0 +1,376 @@
+//! Model Context Protocol server over stdio
+//!
+//! Lets AI assistants search the index as a tool. Speaks newline-delimited
+//! JSON-RPC 2.0 on stdin/stdout and exposes two tools: `semantic_search`
+//! (a query with optional filters, answered in the `lgrep --json` shape)
+//! and `index_stats`.
+//!
+//! Nothing but protocol messages may be written to stdout while serving;
+//! logs go to stderr.
+
+use crate::error::Result;
+use crate::filter::SearchFilter;
+use crate::searcher::{format_results_json, Searcher};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+use tracing::debug;
+
+/// MCP protocol revision this server implements
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// A JSON-RPC request or notification (notifications have no `id`)
+#[derive(Debug, Deserialize)]
+struct RpcRequest {
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// Arguments of the `semantic_search` tool
+#[derive(Debug, Deserialize)]
+struct SearchArgs {
+    query: String,
+    #[serde(default = "default_max_count", alias = "top_k")]
+    max_count: usize,
+    #[serde(default)]
+    filters: Option<SearchFilter>,
+}
+
+fn default_max_count() -> usize {
+    10
+}
+
+/// MCP server answering tool calls from a loaded index
+pub struct McpServer {
+    searcher: Searcher,
+}
+
+impl McpServer {
+    /// Create a server over a loaded searcher
+    pub fn new(searcher: Searcher) -> Self {
+        Self { searcher }
+    }
+
+    /// Answer requests read from `input` until it is closed
+    pub fn serve<R: BufRead, W: Write>(&self, input: R, mut output: W) -> Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle(&line) {
+                writeln!(output, "{}", response)?;
+                output.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle one JSON-RPC message, returning the response to send, if any
+    pub fn handle(&self, line: &str) -> Option<String> {
+        let request: RpcRequest = match serde_json::from_str::<Value>(line) {
+            Err(e) => return Some(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+            Ok(value) => match serde_json::from_value(value) {
+                Ok(request) => request,
+                Err(e) => {
+                    return Some(error_response(Value::Null, INVALID_REQUEST, &e.to_string()))
+                }
+            },
+        };
+        debug!("MCP {}", request.method);
+
+        // Notifications (e.g. `notifications/initialized`) get no reply
+        let id = request.id?;
+
+        let result = match request.method.as_str() {
+            "initialize" => Ok(json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "lgrep", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+            "tools/call" => self.call_tool(&request.params),
+            _ => Err((
+                METHOD_NOT_FOUND,
+                format!("Unknown method: {}", request.method),
+            )),
+        };
+
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }).to_string(),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+
+    /// Handle `tools/call`
+    ///
+    /// Failures inside a tool are reported in the result with `isError`, so
+    /// the assistant sees them; only malformed calls are protocol errors.
+    fn call_tool(&self, params: &Value) -> std::result::Result<Value, (i64, String)> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or((INVALID_PARAMS, "Missing tool name".to_string()))?;
+        let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+
+        let output = match name {
+            "semantic_search" => {
+                let args: SearchArgs = serde_json::from_value(arguments)
+                    .map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+                self.searcher
+                    .search_with_filter(&args.query, args.max_count, args.filters.as_ref())
+                    .and_then(|results| format_results_json(&results))
+            }
+            "index_stats" => {
+                let stats = self.searcher.stats();
+                Ok(json!({
+                    "files": stats.files,
+                    "chunks": stats.chunks,
+                    "model": stats.model,
+                    "model_revision": stats.model_revision,
+                    "format_version": stats.format_version,
+                    "total_bytes": stats.total_bytes,
+                    "avg_chunk_len": stats.avg_chunk_len,
+                    "by_language": stats.by_language,
+                })
+                .to_string())
+            }
+            _ => return Err((INVALID_PARAMS, format!("Unknown tool: {}", name))),
+        };
+
+        Ok(match output {
+            Ok(text) => json!({ "content": [{ "type": "text", "text": text }] }),
+            Err(e) => json!({
+                "content": [{ "type": "text", "text": e.to_string() }],
+                "isError": true,
+            }),
+        })
+    }
+}
+
+/// Tools advertised by `tools/list`
+fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": "semantic_search",
+            "description": "Search the indexed code base by meaning. Returns matching chunks \
+                            with file, line range, score and content, best first.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "What to look for, in natural language",
+                    },
+                    "max_count": {
+                        "type": "integer",
+                        "description": "Maximum number of results (default 10)",
+                        "minimum": 1,
+                    },
+                    "filters": {
+                        "type": "object",
+                        "description": "Optional filters on the results",
+                        "properties": {
+                            "extensions": { "type": "array", "items": { "type": "string" } },
+                            "languages": { "type": "array", "items": { "type": "string" } },
+                            "path_patterns": { "type": "array", "items": { "type": "string" } },
+                            "exclude_patterns": { "type": "array", "items": { "type": "string" } },
+                            "symbols": { "type": "array", "items": { "type": "string" } },
+                            "min_score": { "type": "number" },
+                        },
+                    },
+                },
+                "required": ["query"],
+            },
+        },
+        {
+            "name": "index_stats",
+            "description": "Number of indexed files and chunks, and the embedding model.",
+            "inputSchema": { "type": "object", "properties": {} },
+        },
+    ])
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::{Chunk, ContentKind};
+    use crate::config::Config;
+    use crate::embedder::{Embed, StubEmbedder};
+    use crate::index::VectorIndex;
+
+    fn test_server() -> McpServer {
+        let config = Config::new(std::path::PathBuf::from("/nonexistent"));
+        let embedder = StubEmbedder {
+            dimension: config.model.dimension(),
+        };
+        let chunks: Vec<Chunk> = [
+            ("src/db.rs", "open database connection pool"),
+            ("src/auth.rs", "verify user password"),
+            ("docs/db.md", "database connection pool"),
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, (path, text))| Chunk {
+            id: i as u64,
+            text: text.to_string(),
+            file_path: path.to_string(),
+            start_line: 1,
+            end_line: 1,
+            file_hash: "hash".to_string(),
+            language: None,
+            overlap_with_prev: 0,
+            content_kind: ContentKind::Code,
+            text_hash: String::new(),
+            symbols: Vec::new(),
+        })
+        .collect();
+        let embeddings = chunks
+            .iter()
+            .map(|c| embedder.embed_one(&c.text).unwrap())
+            .collect();
+        let mut index = VectorIndex::new(config).unwrap();
+        index.add_chunks(chunks, embeddings).unwrap();
+
+        McpServer::new(Searcher::with_embedder(index, Box::new(embedder)))
+    }
+
+    fn call(server: &McpServer, request: Value) -> Value {
+        serde_json::from_str(&server.handle(&request.to_string()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_initialize_and_list_tools() {
+        let server = test_server();
+
+        let response = call(
+            &server,
+            json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+        );
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"]["protocolVersion"], PROTOCOL_VERSION);
+        assert_eq!(response["result"]["serverInfo"]["name"], "lgrep");
+
+        let notification = json!({"jsonrpc": "2.0", "method": "notifications/initialized"});
+        assert!(server.handle(&notification.to_string()).is_none());
+
+        let response = call(
+            &server,
+            json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"}),
+        );
+        let names: Vec<&str> = response["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["semantic_search", "index_stats"]);
+    }
+
+    #[test]
+    fn test_semantic_search_tool() {
+        let server = test_server();
+
+        let response = call(
+            &server,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 3,
+                "method": "tools/call",
+                "params": {
+                    "name": "semantic_search",
+                    "arguments": {
+                        "query": "database connection",
+                        "max_count": 5,
+                        "filters": {"extensions": ["rs"]},
+                    },
+                },
+            }),
+        );
+        assert!(response["result"].get("isError").is_none());
+        let text = response["result"]["content"][0]["text"].as_str().unwrap();
+        let results: Value = serde_json::from_str(text).unwrap();
+        let results = results.as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["file"], "src/db.rs");
+    }
+
+    #[test]
+    fn test_index_stats_tool() {
+        let server = test_server();
+
+        let response = call(
+            &server,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 4,
+                "method": "tools/call",
+                "params": {"name": "index_stats"},
+            }),
+        );
+        let text = response["result"]["content"][0]["text"].as_str().unwrap();
+        let stats: Value = serde_json::from_str(text).unwrap();
+        assert_eq!(stats["files"], 3);
+        assert_eq!(stats["chunks"], 3);
+    }
+
+    #[test]
+    fn test_errors() {
+        let server = test_server();
+
+        let response: Value = serde_json::from_str(&server.handle("{not json").unwrap()).unwrap();
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+
+        let response = call(
+            &server,
+            json!({"jsonrpc": "2.0", "id": 5, "method": "resources/list"}),
+        );
+        assert_eq!(response["id"], 5);
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+
+        let response = call(
+            &server,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 6,
+                "method": "tools/call",
+                "params": {"name": "semantic_search", "arguments": {}},
+            }),
+        );
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+
+        // Tool failures are reported to the assistant, not as protocol errors
+        let response = call(
+            &server,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 7,
+                "method": "tools/call",
+                "params": {
+                    "name": "semantic_search",
+                    "arguments": {"query": "x", "filters": {"min_score": 0.9, "max_score": 0.1}},
+                },
+            }),
+        );
+        assert_eq!(response["result"]["isError"], true);
+    }
+}
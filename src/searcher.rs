@@ -3,19 +3,27 @@
 //! Provides semantic search over the index and formats results
 //! for terminal display or JSON output.
 
-use crate::config::Config;
-use crate::embedder::Embedder;
-use crate::error::Result;
+use crate::chunker::{extract_symbols, Chunk};
+use crate::config::{root_label, Config, WorkspaceRoot};
+use crate::embedder::{cosine_similarity, Embed, Embedder};
+use crate::error::{LgrepError, Result};
 use crate::filter::SearchFilter;
-use crate::index::{SearchResult, VectorIndex};
+use crate::history::QueryHistory;
+use crate::index::{FileResult, SearchResult, VectorIndex};
+use crate::indexer::{Indexer, UpdateStats};
 use colored::*;
 use regex::Regex;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
 
 /// Semantic searcher with filtering and hybrid search support
 pub struct Searcher {
     index: VectorIndex,
-    embedder: Embedder,
+    embedder: Box<dyn Embed>,
 }
 
 impl Searcher {
@@ -24,15 +32,92 @@ impl Searcher {
         let index_dir = root_path.join(".lgrep");
         let config = Config::load(&index_dir)?;
         let index = VectorIndex::load(config.clone())?;
-        let embedder = Embedder::new(&config.model)?;
+        let embedder = Embedder::new(&config.model, config.execution_provider)?;
+        check_embedder(&index, &embedder)?;
 
-        Ok(Self { index, embedder })
+        Ok(Self::with_embedder(index, Box::new(embedder)))
+    }
+
+    /// Load the indexes under several roots to search them as one
+    ///
+    /// All indexes must be built with the same model; they share a single
+    /// loaded embedder. See [`MultiSearcher`] for how results are labeled.
+    pub fn load_many(roots: &[PathBuf]) -> Result<MultiSearcher> {
+        let mut configs = Vec::with_capacity(roots.len());
+        for root in roots {
+            let root = root.canonicalize()?;
+            let index_dir = root.join(".lgrep");
+            if !index_dir.exists() {
+                return Err(LgrepError::InvalidPath(format!(
+                    "{} has no index; run `lgrep index` there first",
+                    root.display()
+                )));
+            }
+            configs.push((root, Config::load(&index_dir)?));
+        }
+
+        let Some((first_root, first)) = configs.first() else {
+            return Err(LgrepError::Config("No indexes to search".to_string()));
+        };
+        for (root, config) in &configs[1..] {
+            if config.model.model_name() != first.model.model_name()
+                || config.model.dimension() != first.model.dimension()
+            {
+                return Err(LgrepError::Config(format!(
+                    "Indexes use different models: {} uses {}, {} uses {}; \
+                     re-index them with the same --model to search them together",
+                    first_root.display(),
+                    first.model.model_name(),
+                    root.display(),
+                    config.model.model_name()
+                )));
+            }
+        }
+
+        let embedder: Arc<dyn Embed> =
+            Arc::new(Embedder::new(&first.model, first.execution_provider)?);
+        let mut labels = HashSet::new();
+        let mut searchers = Vec::with_capacity(configs.len());
+        for (root, config) in configs {
+            let index = VectorIndex::load(config)?;
+            check_embedder(&index, embedder.as_ref())?;
+            let embedder = Box::new(SharedEmbedder(Arc::clone(&embedder)));
+            searchers.push((
+                root_label(&root, &mut labels),
+                Self::with_embedder(index, embedder),
+            ));
+        }
+
+        Ok(MultiSearcher { searchers })
+    }
+
+    /// Load the index under `root_path`, bring it up to date, and search it
+    ///
+    /// The index is read from disk once and updated in memory, and the
+    /// indexer's embedder is reused for queries.
+    pub fn load_synced(root_path: &Path) -> Result<(Self, UpdateStats)> {
+        let config = Config::load(&root_path.join(".lgrep"))?;
+        let indexer = Indexer::new(config.clone())?;
+        Self::sync_with(indexer, config)
+    }
+
+    /// Load the index for `config` and update it with `indexer`
+    pub(crate) fn sync_with(indexer: Indexer, config: Config) -> Result<(Self, UpdateStats)> {
+        let mut index = VectorIndex::load(config)?;
+        let stats = indexer.update_index(&mut index)?;
+        Ok((Self::with_embedder(index, indexer.into_embedder()?), stats))
     }
 
     /// Create a searcher from an existing index
     pub fn from_index(index: VectorIndex) -> Result<Self> {
-        let embedder = Embedder::new(&index.config().model)?;
-        Ok(Self { index, embedder })
+        let embedder = Embedder::new(&index.config().model, index.config().execution_provider)?;
+        check_embedder(&index, &embedder)?;
+        Ok(Self::with_embedder(index, Box::new(embedder)))
+    }
+
+    /// Create a searcher from an index and an already-constructed embedder
+    pub fn with_embedder(index: VectorIndex, embedder: Box<dyn Embed>) -> Self {
+        Self { index, embedder }
     }
 
     /// Search for chunks matching the query
@@ -40,42 +125,168 @@ impl Searcher {
         self.search_with_filter(query, top_k, None)
     }
 
+    /// Search with a precomputed query embedding, bypassing the embedder
+    ///
+    /// For callers with their own embedding pipeline. The vector must come
+    /// from the same model as the index; one of another dimension fails with
+    /// [`LgrepError::DimensionMismatch`].
+    pub fn search_vector(&self, embedding: &[f32], top_k: usize) -> Result<Vec<SearchResult>> {
+        self.index.search(embedding, top_k)
+    }
+
+    /// Search with a latency budget
+    ///
+    /// Fails with [`LgrepError::Timeout`] if embedding the query uses up the
+    /// budget. If the HNSW lookup itself overruns, the results it produced
+    /// are still returned since they're already available.
+    pub fn search_deadline(
+        &self,
+        query: &str,
+        top_k: usize,
+        budget: Duration,
+    ) -> Result<Vec<SearchResult>> {
+        let start = Instant::now();
+
+        let query_embedding = self.embed_query(query)?;
+        if start.elapsed() > budget {
+            return Err(LgrepError::Timeout(budget));
+        }
+
+        let results = self.index.search(&query_embedding, top_k)?;
+        if start.elapsed() > budget {
+            warn!(
+                "Search exceeded {:?} budget ({:?})",
+                budget,
+                start.elapsed()
+            );
+        }
+
+        Ok(results)
+    }
+
     /// Search with optional filters
+    ///
+    /// Fails with [`LgrepError::EmptyIndex`] when nothing was indexed, so that
+    /// case isn't mistaken for a query with no matches.
     pub fn search_with_filter(
         &self,
         query: &str,
         top_k: usize,
         filter: Option<&SearchFilter>,
     ) -> Result<Vec<SearchResult>> {
-        let query_embedding = self.embedder.embed_one(query)?;
-        
-        // Get more results than needed to account for filtering
-        let fetch_count = if filter.is_some() {
-            (top_k * 3).max(100) // Fetch 3x more when filtering
-        } else {
-            top_k
-        };
-        
-        let mut results = self.index.search(&query_embedding, fetch_count)?;
-
-        // Apply filters if provided
+        if self.index.is_empty() {
+            return Err(LgrepError::EmptyIndex);
+        }
         if let Some(filter) = filter {
-            results.retain(|r| filter.matches(&r.chunk, r.score));
-            
-            // Apply max_results limit from filter
-            if let Some(max) = filter.max_results {
-                results.truncate(max);
-            } else {
-                results.truncate(top_k);
-            }
-        } else {
-            results.truncate(top_k);
+            filter.validate()?;
         }
 
+        let query_embedding = self.embed_query(query)?;
+
+        // Apply max_results limit from filter
+        let limit = filter.and_then(|f| f.max_results).unwrap_or(top_k);
+
+        let mut results = match filter {
+            Some(filter) => {
+                // Score bounds are only known after scoring, so leave headroom
+                // for results above max_score being dropped
+                let mut fetch_count = if filter.max_score.is_some() {
+                    (limit * 3).max(100)
+                } else {
+                    limit
+                };
+
+                loop {
+                    let mut results =
+                        self.index
+                            .search_filtered(&query_embedding, fetch_count, |chunk| {
+                                self.filter_keeps(filter, chunk)
+                            })?;
+                    // Candidates come best first, so once they fall below
+                    // min_score (or run out) a wider search finds nothing new
+                    let exhausted = results.len() < fetch_count
+                        || results
+                            .last()
+                            .zip(filter.min_score)
+                            .is_some_and(|(r, min)| r.score < min);
+                    results.retain(|r| filter.matches_score(r.score));
+                    if results.len() >= limit || exhausted {
+                        break results;
+                    }
+
+                    debug!(
+                        "{} of {} candidates within the score bounds, widening search",
+                        results.len(),
+                        fetch_count
+                    );
+                    fetch_count *= 2;
+                }
+            }
+            None => self.index.search(&query_embedding, limit)?,
+        };
+
+        results.truncate(limit);
         Ok(results)
     }
 
+    /// Search for one page of results: `limit` results after skipping `offset`
+    ///
+    /// Fetches `offset + limit` results, so pages are consistent with
+    /// [`search_with_filter`](Self::search_with_filter) for the same filter.
+    /// A page past the last result is empty.
+    pub fn search_paged(
+        &self,
+        query: &str,
+        offset: usize,
+        limit: usize,
+        filter: Option<&SearchFilter>,
+    ) -> Result<Vec<SearchResult>> {
+        let results = self.search_with_filter(query, offset + limit, filter)?;
+        Ok(results.into_iter().skip(offset).collect())
+    }
+
+    /// Find chunks similar to an indexed chunk ("more like this")
+    ///
+    /// Searches with the chunk's stored embedding, so nothing is embedded.
+    /// The chunk itself is left out of the results.
+    pub fn search_similar_to_chunk(
+        &self,
+        chunk_id: u64,
+        top_k: usize,
+    ) -> Result<Vec<SearchResult>> {
+        let embedding = self
+            .index
+            .get_vector(chunk_id)?
+            .ok_or_else(|| LgrepError::Index(format!("No chunk with ID {}", chunk_id)))?;
+
+        self.index
+            .search_filtered(&embedding, top_k, |chunk| chunk.id != chunk_id)
+    }
+
+    /// Rank whole files by similarity to the query
+    ///
+    /// Requires an index built with per-file embeddings.
+    pub fn search_files(&self, query: &str, top_k: usize) -> Result<Vec<FileResult>> {
+        let query_embedding = self.embed_query(query)?;
+        self.index.search_files(&query_embedding, top_k)
+    }
+
+    /// Rank the chunks of one file by relevance to the query
+    pub fn search_within_file(
+        &self,
+        query: &str,
+        file_path: &str,
+        top_k: usize,
+    ) -> Result<Vec<SearchResult>> {
+        let query_embedding = self.embed_query(query)?;
+        self.index
+            .search_within_file(&query_embedding, file_path, top_k)
+    }
+
     /// Hybrid search: combines semantic search with keyword/regex matching
+    ///
+    /// Blends the two scores with [`DEFAULT_HYBRID_ALPHA`]; see
+    /// [`hybrid_search_with_alpha`](Self::hybrid_search_with_alpha).
     pub fn hybrid_search(
         &self,
         semantic_query: &str,
@@ -83,138 +294,2990 @@ impl Searcher {
         top_k: usize,
         filter: Option<&SearchFilter>,
     ) -> Result<Vec<SearchResult>> {
-        // First do semantic search
-        let mut results = self.search_with_filter(semantic_query, top_k * 2, filter)?;
-
-        // If keyword pattern provided, boost matching results
-        if let Some(pattern) = keyword_pattern {
-            if let Ok(regex) = Regex::new(pattern) {
-                // Score boost for keyword matches
-                const KEYWORD_BOOST: f32 = 0.2;
-
-                for result in &mut results {
-                    if regex.is_match(&result.chunk.text) {
-                        // Boost score but cap at 1.0
-                        result.score = (result.score + KEYWORD_BOOST).min(1.0);
-                    }
-                }
+        self.hybrid_search_with_alpha(
+            semantic_query,
+            keyword_pattern,
+            top_k,
+            DEFAULT_HYBRID_ALPHA,
+            filter,
+        )
+    }
 
-                // Re-sort by boosted scores
-                results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
-            }
+    /// Hybrid search with an explicit keyword weight
+    ///
+    /// Each result scores `semantic + alpha * max(0, keyword - semantic)`:
+    /// a keyword score above the semantic one lifts the result `alpha` of
+    /// the way to it, where `keyword` grows with the pattern's matches per
+    /// line of the chunk (one match every [`KEYWORD_MATCH_LINES`] lines or
+    /// more scores 1.0). Chunks without a match keep their semantic score,
+    /// except at `alpha` 1.0, where only matching chunks are returned. The
+    /// filter's score bounds apply to the blended scores.
+    pub fn hybrid_search_with_alpha(
+        &self,
+        semantic_query: &str,
+        keyword_pattern: Option<&str>,
+        top_k: usize,
+        alpha: f32,
+        filter: Option<&SearchFilter>,
+    ) -> Result<Vec<SearchResult>> {
+        let regex = keyword_pattern.map(keyword_regex).transpose()?;
+        self.blended_search(
+            semantic_query,
+            regex.as_ref(),
+            top_k * 2,
+            top_k,
+            alpha,
+            filter,
+        )
+    }
+
+    /// Search a short query, blending in chunks that contain it literally
+    ///
+    /// Blends with [`DEFAULT_HYBRID_ALPHA`]; see
+    /// [`auto_hybrid_search_with_alpha`](Self::auto_hybrid_search_with_alpha).
+    pub fn auto_hybrid_search(
+        &self,
+        query: &str,
+        top_k: usize,
+        filter: Option<&SearchFilter>,
+    ) -> Result<Vec<SearchResult>> {
+        self.auto_hybrid_search_with_alpha(query, top_k, DEFAULT_HYBRID_ALPHA, filter)
+    }
+
+    /// Search a short query with an explicit keyword weight
+    ///
+    /// Short queries ("db", "auth") embed poorly, so semantic search alone
+    /// tends to rank the chunks a grep would find too low. This fetches
+    /// [`LITERAL_OVERFETCH`] times `top_k` semantic candidates and scores
+    /// them as `--keyword` results for the query (ignoring case), so literal
+    /// matches further down rise to the top without scanning the whole
+    /// index. See [`hybrid_search_with_alpha`](Self::hybrid_search_with_alpha)
+    /// for the scoring.
+    pub fn auto_hybrid_search_with_alpha(
+        &self,
+        query: &str,
+        top_k: usize,
+        alpha: f32,
+        filter: Option<&SearchFilter>,
+    ) -> Result<Vec<SearchResult>> {
+        let literal = query.trim();
+        let regex = Regex::new(&format!("(?i){}", regex::escape(literal)))
+            .map_err(|e| LgrepError::Config(e.to_string()))?;
+
+        self.blended_search(
+            literal,
+            Some(&regex),
+            top_k.saturating_mul(LITERAL_OVERFETCH),
+            top_k,
+            alpha,
+            filter,
+        )
+    }
+
+    /// Fetch `candidates` semantic results and keep the `top_k` best after
+    /// blending in keyword scores for `regex`
+    ///
+    /// The score bounds of `filter` are applied after the blend, since they
+    /// describe the scores that are shown.
+    fn blended_search(
+        &self,
+        query: &str,
+        regex: Option<&Regex>,
+        candidates: usize,
+        top_k: usize,
+        alpha: f32,
+        filter: Option<&SearchFilter>,
+    ) -> Result<Vec<SearchResult>> {
+        if !(0.0..=1.0).contains(&alpha) {
+            return Err(LgrepError::Config(format!(
+                "Hybrid alpha must be between 0.0 and 1.0, got {}",
+                alpha
+            )));
+        }
+        if let Some(filter) = filter {
+            filter.validate()?;
+        }
+
+        let unbounded = filter.map(|filter| {
+            let mut filter = filter.clone();
+            filter.min_score = None;
+            filter.max_score = None;
+            filter
+        });
+        let mut results = self.search_with_filter(query, candidates, unbounded.as_ref())?;
+
+        if let Some(regex) = regex {
+            blend_keyword_matches(&mut results, regex, alpha);
+        }
+        if let Some(filter) = filter {
+            results.retain(|r| filter.matches_score(r.score));
         }
 
         results.truncate(top_k);
         Ok(results)
     }
 
+    /// Search, then rerank with Maximal Marginal Relevance
+    ///
+    /// Fetches [`MMR_OVERFETCH`] times `top_k` candidates; see
+    /// [`rerank_diverse`](Self::rerank_diverse).
+    pub fn search_diverse(
+        &self,
+        query: &str,
+        top_k: usize,
+        lambda: f32,
+    ) -> Result<Vec<SearchResult>> {
+        let candidates = self.search(query, top_k * MMR_OVERFETCH)?;
+        self.rerank_diverse(candidates, top_k, lambda)
+    }
+
+    /// Pick `top_k` of `candidates`, trading relevance for variety
+    ///
+    /// Greedily takes the candidate with the best `lambda * score` minus
+    /// `(1 - lambda) * its highest similarity to those already taken`,
+    /// comparing stored chunk embeddings, so overlapping chunks of one
+    /// function don't crowd out everything else. Results come back in
+    /// selection order.
+    pub fn rerank_diverse(
+        &self,
+        candidates: Vec<SearchResult>,
+        top_k: usize,
+        lambda: f32,
+    ) -> Result<Vec<SearchResult>> {
+        if !(0.0..=1.0).contains(&lambda) {
+            return Err(LgrepError::Config(format!(
+                "MMR lambda must be between 0.0 and 1.0, got {}",
+                lambda
+            )));
+        }
+
+        let mut remaining = candidates
+            .into_iter()
+            .map(|r| Ok((self.index.get_vector(r.chunk.id)?, r)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut selected: Vec<(Option<Vec<f32>>, SearchResult)> = Vec::new();
+
+        while selected.len() < top_k && !remaining.is_empty() {
+            let mmr = |(vector, result): &(Option<Vec<f32>>, SearchResult)| {
+                let redundancy = vector.as_ref().map_or(0.0, |v| {
+                    selected
+                        .iter()
+                        .filter_map(|(s, _)| s.as_ref())
+                        .map(|s| cosine_similarity(v, s))
+                        .fold(0.0, f32::max)
+                });
+                lambda * result.score - (1.0 - lambda) * redundancy
+            };
+            // First best candidate, so ties keep the incoming order
+            let best = remaining
+                .iter()
+                .enumerate()
+                .min_by(|(_, a), (_, b)| mmr(b).total_cmp(&mmr(a)))
+                .map(|(i, _)| i)
+                .unwrap();
+            selected.push(remaining.remove(best));
+        }
+
+        Ok(selected.into_iter().map(|(_, r)| r).collect())
+    }
+
+    /// Search, adding up to `context_lines` source lines around each result
+    ///
+    /// See [`add_context`].
+    pub fn search_with_context(
+        &self,
+        query: &str,
+        top_k: usize,
+        context_lines: usize,
+    ) -> Result<Vec<SearchResult>> {
+        let mut results = self.search(query, top_k)?;
+        add_context(&mut results, self.config(), context_lines);
+        Ok(results)
+    }
+
+    /// Record which line of each result is most similar to the query
+    ///
+    /// Embeds each non-blank line that [`format_results`] would display, as
+    /// a document, and stores the best one in `highlighted_lines`, which is
+    /// then shown in bold. A chunk with a single non-blank line gets that
+    /// line without embedding. Embeds every displayed line, so only call
+    /// this on the results that will actually be shown.
+    pub fn highlight_lines(&self, query: &str, results: &mut [SearchResult]) -> Result<()> {
+        let query_embedding = self.embed_query(query)?;
+        let prefix = self.index.document_prefix();
+
+        for result in results {
+            let lines: Vec<(usize, String)> = result
+                .chunk
+                .text
+                .lines()
+                .take(MAX_CONTENT_LINES)
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(i, line)| (i, format!("{}{}", prefix, line)))
+                .collect();
+
+            if lines.len() <= 1 {
+                result.highlighted_lines = lines.iter().map(|(i, _)| *i).collect();
+                continue;
+            }
+
+            let embeddings = self
+                .embedder
+                .embed_batch(lines.iter().map(|(_, line)| line.as_str()).collect())?;
+            result.highlighted_lines = lines
+                .iter()
+                .zip(&embeddings)
+                .map(|((i, _), embedding)| (*i, cosine_similarity(&query_embedding, embedding)))
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(i, _)| i)
+                .into_iter()
+                .collect();
+        }
+
+        Ok(())
+    }
+
+    /// Run one search per query, streaming the results as JSON Lines
+    ///
+    /// Each query's results are written and flushed before the next query
+    /// is read, so a consumer sees them as soon as they are ready. Blank
+    /// queries are skipped. Returns the number of queries searched.
+    pub fn search_batch<I, W>(
+        &self,
+        queries: I,
+        top_k: usize,
+        filter: Option<&SearchFilter>,
+        writer: &mut W,
+    ) -> Result<usize>
+    where
+        I: IntoIterator<Item = Result<String>>,
+        W: Write,
+    {
+        let mut count = 0;
+        for query in queries {
+            let query = query?;
+            let query = query.trim();
+            if query.is_empty() {
+                continue;
+            }
+
+            let results = self.search_with_filter(query, top_k, filter)?;
+            write_results_jsonl(&results, query, writer)?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Suggest follow-up searches from the query history and `results`
+    ///
+    /// See [`related_queries`]; the history is read from the index directory.
+    pub fn related_queries(&self, query: &str, results: &[SearchResult]) -> Vec<String> {
+        let history = QueryHistory::load(&self.index.config().index_dir).ok();
+        related_queries(query, results, history.as_ref())
+    }
+
+    /// Check a chunk against a filter, including its file's recorded
+    /// modification time and size
+    fn filter_keeps(&self, filter: &SearchFilter, chunk: &Chunk) -> bool {
+        filter.matches_chunk(chunk)
+            && filter.matches_modified(self.index.file_mtime(&chunk.file_path))
+            && filter.matches_file_size(self.index.file_size(&chunk.file_path))
+    }
+
+    /// Get the configuration of the loaded index
+    pub fn config(&self) -> &Config {
+        self.index.config()
+    }
+
+    /// Embed a query, with the instruction prefix the index expects
+    fn embed_query(&self, query: &str) -> Result<Vec<f32>> {
+        let prefix = self.index.query_prefix();
+        if prefix.is_empty() {
+            return self.embedder.embed_one(query);
+        }
+        self.embedder.embed_one(&format!("{}{}", prefix, query))
+    }
+
+    /// Get the loaded index
+    pub fn index(&self) -> &VectorIndex {
+        &self.index
+    }
+
+    /// Get mutable access to the loaded index (e.g. for incremental updates)
+    pub fn index_mut(&mut self) -> &mut VectorIndex {
+        &mut self.index
+    }
+
     /// Get index statistics
     pub fn stats(&self) -> IndexStats {
-        IndexStats {
-            files: self.index.file_count(),
-            chunks: self.index.chunk_count(),
-            model: self.index.config().model.model_name().to_string(),
-        }
+        IndexStats::of(&self.index)
+    }
+
+    /// Share the loaded searcher between threads
+    ///
+    /// Searching only reads the index and embedder, so clones of the handle
+    /// can search concurrently without reloading either.
+    pub fn handle(self) -> SearcherHandle {
+        SearcherHandle(Arc::new(self))
     }
 }
 
-/// Index statistics
-pub struct IndexStats {
-    /// Number of indexed files
-    pub files: usize,
-    /// Number of chunks
-    pub chunks: usize,
-    /// Model name used for embeddings
-    pub model: String,
+/// Cheaply cloneable, thread-safe reference to a loaded [`Searcher`]
+///
+/// Dereferences to the searcher, so every `&self` method is available.
+#[derive(Clone)]
+pub struct SearcherHandle(Arc<Searcher>);
+
+impl std::ops::Deref for SearcherHandle {
+    type Target = Searcher;
+
+    fn deref(&self) -> &Searcher {
+        &self.0
+    }
 }
 
-/// Format search results for terminal display
-pub fn format_results(results: &[SearchResult], show_content: bool, _root_path: &Path) -> String {
-    let mut output = String::new();
+impl From<Searcher> for SearcherHandle {
+    fn from(searcher: Searcher) -> Self {
+        searcher.handle()
+    }
+}
 
-    for (i, result) in results.iter().enumerate() {
-        // File path and line range
-        let file_display = format!(
-            "{}:{}",
-            result.chunk.file_path,
-            if result.chunk.start_line == result.chunk.end_line {
-                format!("{}", result.chunk.start_line)
-            } else {
-                format!("{}-{}", result.chunk.start_line, result.chunk.end_line)
-            }
-        );
+/// Several indexes searched as one, from [`Searcher::load_many`]
+///
+/// Each index is labeled with its root's directory name (`-2`, `-3`, ...
+/// added for repeats). Results carry the label in `root` and as a prefix of
+/// `chunk.file_path` (`api/src/main.rs`), as in a multi-root index.
+pub struct MultiSearcher {
+    searchers: Vec<(String, Searcher)>,
+}
 
-        // Score indicator with color
-        let score_pct = (result.score * 100.0) as u32;
-        let score_color = if score_pct >= 80 {
-            "green"
-        } else if score_pct >= 60 {
-            "yellow"
-        } else {
-            "red"
-        };
+impl MultiSearcher {
+    /// The labeled searchers, one per index
+    pub fn searchers(&self) -> &[(String, Searcher)] {
+        &self.searchers
+    }
 
-        output.push_str(&format!(
-            "\n{} {} ({}%)\n",
-            format!("[{}]", i + 1).dimmed(),
-            file_display.cyan().bold(),
-            format!("{}", score_pct).color(score_color)
-        ));
+    /// Search every index for the query
+    pub fn search(&self, query: &str, top_k: usize) -> Result<Vec<SearchResult>> {
+        self.search_with_filter(query, top_k, None)
+    }
 
-        if show_content {
-            output.push_str(&format!("{}\n", "─".repeat(60).dimmed()));
+    /// Search every index with optional filters
+    pub fn search_with_filter(
+        &self,
+        query: &str,
+        top_k: usize,
+        filter: Option<&SearchFilter>,
+    ) -> Result<Vec<SearchResult>> {
+        self.search_each(top_k, |searcher| {
+            searcher.search_with_filter(query, top_k, filter)
+        })
+    }
 
-            // Show content with line numbers
-            let lines: Vec<&str> = result.chunk.text.lines().collect();
-            let max_lines = 15;
-            let show_lines = if lines.len() > max_lines {
-                &lines[..max_lines]
-            } else {
-                &lines
+    /// Run `search` on every index and keep the `top_k` best results overall
+    ///
+    /// Empty indexes are skipped; [`LgrepError::EmptyIndex`] is only returned
+    /// when all of them are empty.
+    pub fn search_each<F>(&self, top_k: usize, search: F) -> Result<Vec<SearchResult>>
+    where
+        F: Fn(&Searcher) -> Result<Vec<SearchResult>>,
+    {
+        let mut results = Vec::new();
+        let mut searched = 0;
+        for (label, searcher) in &self.searchers {
+            let found = match search(searcher) {
+                Err(LgrepError::EmptyIndex) => continue,
+                found => found?,
             };
+            searched += 1;
+            results.extend(found.into_iter().map(|mut result| {
+                result.chunk.file_path = format!("{}/{}", label, result.chunk.file_path);
+                result.root = Some(label.clone());
+                result
+            }));
+        }
+        if searched == 0 {
+            return Err(LgrepError::EmptyIndex);
+        }
 
-            for (j, line) in show_lines.iter().enumerate() {
-                let line_num = result.chunk.start_line + j;
-                output.push_str(&format!("{} {}\n", format!("{:4}", line_num).dimmed(), line));
-            }
+        results.sort_by(|a, b| b.score.total_cmp(&a.score));
+        results.truncate(top_k);
+        Ok(results)
+    }
 
-            if lines.len() > max_lines {
-                output.push_str(&format!(
-                    "{}\n",
-                    format!("     ... ({} more lines)", lines.len() - max_lines).dimmed()
-                ));
-            }
-        }
+    /// Fill in the source lines around each result, like [`add_context`]
+    pub fn add_context(&self, results: &mut [SearchResult], context_lines: usize) {
+        add_context_from(results, &self.roots(), context_lines);
     }
 
-    output
+    /// Workspace roots of every index, labeled as its results are
+    ///
+    /// For finding results on disk with [`source_path`]. Archives have none.
+    pub fn roots(&self) -> Vec<WorkspaceRoot> {
+        self.searchers
+            .iter()
+            .filter(|(_, searcher)| searcher.config().archive.is_none())
+            .flat_map(|(label, searcher)| {
+                let roots = searcher.config().roots().unwrap_or_default();
+                roots.into_iter().map(move |root| WorkspaceRoot {
+                    label: if root.label.is_empty() {
+                        label.clone()
+                    } else {
+                        format!("{}/{}", label, root.label)
+                    },
+                    path: root.path,
+                })
+            })
+            .collect()
+    }
 }
 
-/// Format results as JSON
-pub fn format_results_json(results: &[SearchResult]) -> Result<String> {
-    use serde::Serialize;
+/// One embedder used by several searchers
+struct SharedEmbedder(Arc<dyn Embed>);
 
-    #[derive(Serialize)]
-    struct JsonResult {
-        file: String,
-        start_line: usize,
-        end_line: usize,
-        score: f32,
-        content: String,
-        language: Option<String>,
+impl Embed for SharedEmbedder {
+    fn dimension(&self) -> usize {
+        self.0.dimension()
     }
 
-    let json_results: Vec<JsonResult> = results
-        .iter()
-        .map(|r| JsonResult {
-            file: r.chunk.file_path.clone(),
-            start_line: r.chunk.start_line,
-            end_line: r.chunk.end_line,
-            score: r.score,
-            content: r.chunk.text.clone(),
-            language: r.chunk.language.clone(),
-        })
-        .collect();
+    fn embed_one(&self, text: &str) -> Result<Vec<f32>> {
+        self.0.embed_one(text)
+    }
 
-    Ok(serde_json::to_string_pretty(&json_results)?)
+    fn embed_batch(&self, texts: Vec<&str>) -> Result<Vec<Vec<f32>>> {
+        self.0.embed_batch(texts)
+    }
+
+    fn revision(&self) -> Option<&str> {
+        self.0.revision()
+    }
+}
+
+/// Default weight of the keyword score in hybrid search
+pub const DEFAULT_HYBRID_ALPHA: f32 = 0.5;
+
+/// Lines per keyword match at which a chunk gets the full keyword score
+pub const KEYWORD_MATCH_LINES: f32 = 10.0;
+
+/// Queries up to this many characters get literal matches blended in
+pub const DEFAULT_SHORT_QUERY_CHARS: usize = 4;
+
+/// Candidates fetched per wanted result when blending in literal matches
+pub const LITERAL_OVERFETCH: usize = 10;
+
+/// Default MMR trade-off: 1.0 is pure relevance, 0.0 pure diversity
+pub const DEFAULT_MMR_LAMBDA: f32 = 0.7;
+
+/// Candidates fetched per wanted result before MMR reranking
+pub const MMR_OVERFETCH: usize = 3;
+
+/// Compile a `--keyword` pattern, failing on an invalid regex
+pub fn keyword_regex(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern)
+        .map_err(|e| LgrepError::Config(format!("Invalid keyword pattern '{}': {}", pattern, e)))
+}
+
+/// Whether a query is short enough for [`Searcher::auto_hybrid_search`]
+pub fn is_short_query(query: &str, max_chars: usize) -> bool {
+    let query = query.trim();
+    !query.is_empty() && query.chars().count() <= max_chars
+}
+
+/// Blend keyword scores for `regex` into `results`, recording the matched
+/// spans, and re-sort
+///
+/// A chunk whose text doesn't match still counts once when one of its symbol
+/// parts matches ignoring case, so `config` finds `ConfigLoader`. Each
+/// result's `keyword_score` is the change the blend made to its score, which
+/// is never negative.
+fn blend_keyword_matches(results: &mut Vec<SearchResult>, regex: &Regex, alpha: f32) {
+    let folded = Regex::new(&format!("(?i){}", regex.as_str())).ok();
+    results.retain_mut(|result| {
+        result.keyword_spans = regex
+            .find_iter(&result.chunk.text)
+            .map(|m| (m.start(), m.end()))
+            .collect();
+
+        let symbol_match = folded
+            .as_ref()
+            .is_some_and(|re| result.chunk.symbols.iter().any(|s| re.is_match(s)));
+        if result.keyword_spans.is_empty() && symbol_match {
+            if let Some(re) = &folded {
+                result.keyword_spans = re
+                    .find_iter(&result.chunk.text)
+                    .map(|m| (m.start(), m.end()))
+                    .collect();
+            }
+        }
+
+        let matches = result.keyword_spans.len().max(usize::from(symbol_match));
+        let lines = result.chunk.text.lines().count().max(1);
+        let keyword = (matches as f32 * KEYWORD_MATCH_LINES / lines as f32).min(1.0);
+
+        let semantic = result.score;
+        result.score = semantic + alpha * (keyword - semantic).max(0.0);
+        result.keyword_score = Some(result.score - semantic);
+
+        // Pure keyword ranking has nothing to say about chunks without a match
+        alpha < 1.0 || matches > 0
+    });
+
+    // Re-sort by blended scores
+    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+}
+
+/// Refuse an embedder whose vectors don't fit the index
+fn check_embedder(index: &VectorIndex, embedder: &dyn Embed) -> Result<()> {
+    if embedder.dimension() != index.dimension() {
+        return Err(LgrepError::Config(format!(
+            "Model produces {}-dimensional embeddings but the index holds {}; \
+             rebuild with `lgrep index --force`",
+            embedder.dimension(),
+            index.dimension()
+        )));
+    }
+    Ok(())
+}
+
+/// Index statistics
+pub struct IndexStats {
+    /// Number of indexed files
+    pub files: usize,
+    /// Number of chunks
+    pub chunks: usize,
+    /// Model name used for embeddings
+    pub model: String,
+    /// Revision of the model files, if recorded (older indexes don't have it)
+    pub model_revision: Option<String>,
+    /// On-disk format version of the index
+    pub format_version: u32,
+    /// Size of the index files on disk, in bytes
+    pub total_bytes: u64,
+    /// Mean chunk text length, in bytes
+    pub avg_chunk_len: usize,
+    /// Number of chunks per language (`unknown` when none was detected)
+    pub by_language: HashMap<String, usize>,
+}
+
+impl IndexStats {
+    /// Statistics of a loaded index
+    pub fn of(index: &VectorIndex) -> Self {
+        let config = index.config();
+        // Files that weren't saved yet (or aren't used) count as empty
+        let total_bytes = [
+            config.index_path(),
+            config.metadata_path(),
+            config.texts_path(),
+            config.file_vectors_path(),
+        ]
+        .iter()
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .map(|m| m.len())
+        .sum();
+
+        let chunks = index.chunk_metadata();
+        let text_bytes: usize = chunks
+            .iter()
+            .filter_map(|c| index.chunk_text_len(c.id))
+            .sum();
+        let mut by_language: HashMap<String, usize> = HashMap::new();
+        for chunk in chunks {
+            let language = chunk.language.as_deref().unwrap_or("unknown");
+            *by_language.entry(language.to_string()).or_default() += 1;
+        }
+
+        IndexStats {
+            files: index.file_count(),
+            chunks: index.chunk_count(),
+            model: config.model.model_name().to_string(),
+            model_revision: index.model_revision().map(String::from),
+            format_version: index.format_version(),
+            total_bytes,
+            avg_chunk_len: text_bytes.checked_div(chunks.len()).unwrap_or(0),
+            by_language,
+        }
+    }
+}
+
+/// Display order for search results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Highest similarity first
+    #[default]
+    Score,
+    /// Alphabetically by file path, then line
+    Path,
+    /// Most recently modified files first
+    Recent,
+}
+
+impl std::str::FromStr for SortOrder {
+    type Err = LgrepError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "score" => Ok(Self::Score),
+            "path" => Ok(Self::Path),
+            "recent" | "mtime" => Ok(Self::Recent),
+            _ => Err(LgrepError::Config(format!(
+                "Unknown sort order: {}. Valid options: score, path, recent",
+                s
+            ))),
+        }
+    }
+}
+
+/// Output format for search results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable terminal output
+    #[default]
+    Text,
+    /// JSON array of results
+    Json,
+    /// JSON Lines, one result per line, written as it is produced
+    Jsonl,
+    /// GitHub Actions annotation commands
+    Github,
+    /// SARIF 2.1.0 log, for code scanning uploads
+    Sarif,
+    /// CSV table with a header row
+    Csv,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = LgrepError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "jsonl" | "ndjson" => Ok(Self::Jsonl),
+            "github" => Ok(Self::Github),
+            "sarif" => Ok(Self::Sarif),
+            "csv" => Ok(Self::Csv),
+            _ => Err(LgrepError::Config(format!(
+                "Unknown output format: {}. Valid options: text, json, jsonl, github, sarif, csv",
+                s
+            ))),
+        }
+    }
+}
+
+/// Reorder results for display
+///
+/// Only changes presentation order; `Recent` reads each result's file
+/// modification time once, finding the file through `roots` (see
+/// [`source_path`]), and falls back to score order for ties and for files
+/// that can't be found.
+pub fn sort_results(results: &mut [SearchResult], order: SortOrder, roots: &[WorkspaceRoot]) {
+    match order {
+        SortOrder::Score => {
+            results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        }
+        SortOrder::Path => {
+            results.sort_by(|a, b| {
+                a.chunk
+                    .file_path
+                    .cmp(&b.chunk.file_path)
+                    .then(a.chunk.start_line.cmp(&b.chunk.start_line))
+            });
+        }
+        SortOrder::Recent => {
+            let mtimes: HashMap<String, std::time::SystemTime> = results
+                .iter()
+                .filter_map(|r| {
+                    let path = source_path(roots, &r.chunk.file_path)?;
+                    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+                    Some((r.chunk.file_path.clone(), modified))
+                })
+                .collect();
+            let mtime = |r: &SearchResult| mtimes.get(&r.chunk.file_path).copied();
+            results.sort_by(|a, b| {
+                mtime(b)
+                    .cmp(&mtime(a))
+                    .then(b.score.partial_cmp(&a.score).unwrap())
+            });
+        }
+    }
+}
+
+/// Keep the results whose lines changed within the last `days` days
+///
+/// Each result's line range is checked with `git blame` in the workspace
+/// root its file belongs to (see [`source_path`]); outside a repository, or
+/// for untracked files, the file's modification time is used instead.
+/// Results whose file can't be found are dropped. Every result costs a
+/// blame, so only apply this to the results that will be shown.
+pub fn filter_recent(
+    results: Vec<SearchResult>,
+    roots: &[WorkspaceRoot],
+    days: u64,
+) -> Vec<SearchResult> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    // Huge spans (say `--recent-days` of a million years) reach back to the epoch
+    let span = i64::try_from(days.saturating_mul(24 * 60 * 60)).unwrap_or(i64::MAX);
+    let cutoff = now.saturating_sub(span);
+
+    let last_changed = |r: &SearchResult| {
+        let (root, relative) = locate(roots, &r.chunk.file_path)?;
+        crate::git::lines_last_changed(&root.path, relative, r.chunk.start_line, r.chunk.end_line)
+            .or_else(|| {
+                let modified = std::fs::metadata(root.path.join(relative))
+                    .and_then(|m| m.modified())
+                    .ok()?;
+                let since_epoch = modified.duration_since(std::time::UNIX_EPOCH).ok()?;
+                Some(since_epoch.as_secs() as i64)
+            })
+    };
+
+    results
+        .into_iter()
+        .filter(|r| last_changed(r).is_some_and(|time| time >= cutoff))
+        .collect()
+}
+
+/// Fill in the source lines around each result from the files on disk
+///
+/// Files are found through the index's workspace roots. A result keeps just
+/// its chunk text when its file is gone, or no longer has the chunk's text
+/// at the chunk's lines; archives are never read.
+pub fn add_context(results: &mut [SearchResult], config: &Config, context_lines: usize) {
+    if context_lines == 0 || config.archive.is_some() {
+        return;
+    }
+    let Ok(roots) = config.roots() else {
+        return;
+    };
+    add_context_from(results, &roots, context_lines);
+}
+
+/// [`add_context`] for files under `roots`
+fn add_context_from(results: &mut [SearchResult], roots: &[WorkspaceRoot], context_lines: usize) {
+    if context_lines == 0 {
+        return;
+    }
+
+    for result in results.iter_mut() {
+        let chunk = &result.chunk;
+        let Some(path) = source_path(roots, &chunk.file_path) else {
+            continue;
+        };
+        let Ok(source) = std::fs::read_to_string(path) else {
+            continue;
+        };
+
+        let lines: Vec<&str> = source.lines().collect();
+        let start = chunk.start_line.saturating_sub(1);
+        let end = chunk.end_line;
+        let unchanged = end <= lines.len()
+            && end - start == chunk.text.lines().count()
+            && lines[start..end]
+                .iter()
+                .zip(chunk.text.lines())
+                .all(|(disk, indexed)| disk.trim_end() == indexed.trim_end());
+        if !unchanged {
+            continue;
+        }
+
+        let owned = |lines: &[&str]| lines.iter().map(|l| l.to_string()).collect();
+        result.context_before = owned(&lines[start.saturating_sub(context_lines)..start]);
+        result.context_after = owned(&lines[end..(end + context_lines).min(lines.len())]);
+    }
+}
+
+/// File on disk for a stored chunk path
+pub fn source_path(roots: &[WorkspaceRoot], stored: &str) -> Option<PathBuf> {
+    locate(roots, stored).map(|(root, relative)| root.path.join(relative))
+}
+
+/// Workspace root of a stored chunk path, and the path relative to it
+fn locate<'a, 'p>(
+    roots: &'a [WorkspaceRoot],
+    stored: &'p str,
+) -> Option<(&'a WorkspaceRoot, &'p str)> {
+    roots.iter().find_map(|root| {
+        if root.label.is_empty() {
+            return Some((root, stored));
+        }
+        let relative = stored
+            .strip_prefix(root.label.as_str())?
+            .strip_prefix('/')?;
+        Some((root, relative))
+    })
+}
+
+/// Results shown when no count is given
+pub const DEFAULT_MAX_COUNT: usize = 10;
+
+/// Default upper bound on the number of results
+pub const DEFAULT_MAX_COUNT_CEILING: usize = 1000;
+
+/// Resolve the result count from its possible sources
+///
+/// Precedence is the subcommand's `-m`, then the top-level `-m`, then
+/// `LGREP_MAX_COUNT` (`env`), then [`DEFAULT_MAX_COUNT`]. The result is
+/// capped at `ceiling` so a stray `-m 1000000` cannot fetch the whole index.
+pub fn resolve_max_count(
+    subcommand: Option<usize>,
+    top_level: Option<usize>,
+    env: Option<&str>,
+    ceiling: usize,
+) -> Result<usize> {
+    let env = env
+        .map(|value| {
+            value
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| LgrepError::Config(format!("Invalid LGREP_MAX_COUNT: {}", value)))
+        })
+        .transpose()?;
+
+    let max_count = subcommand
+        .or(top_level)
+        .or(env)
+        .unwrap_or(DEFAULT_MAX_COUNT);
+
+    if max_count > ceiling {
+        warn!(
+            "Limiting results to {} (requested {}); raise --max-count-ceiling to allow more",
+            ceiling, max_count
+        );
+        return Ok(ceiling);
+    }
+
+    Ok(max_count)
+}
+
+/// Follow-up searches suggested after a search
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Past queries among the suggestions, at most
+const MAX_HISTORY_SUGGESTIONS: usize = 2;
+
+/// Top results whose text is mined for suggestion terms
+const SUGGESTION_RESULTS: usize = 5;
+
+/// Words too common in code or prose to make a useful follow-up search
+const STOP_WORDS: &[&str] = &[
+    "and", "are", "as", "but", "can", "const", "def", "else", "false", "fn", "for", "from", "func",
+    "function", "has", "have", "if", "impl", "import", "into", "let", "mut", "new", "none", "not",
+    "null", "pub", "return", "self", "some", "str", "string", "that", "the", "this", "true", "use",
+    "var", "was", "when", "with", "you",
+];
+
+/// Suggest up to five follow-up searches
+///
+/// Past queries that share a term with `query` come first, then `query`
+/// extended by the terms that occur most often in the top results. Returns
+/// nothing when there is neither history nor result text to draw on.
+pub fn related_queries(
+    query: &str,
+    results: &[SearchResult],
+    history: Option<&QueryHistory>,
+) -> Vec<String> {
+    let query_terms: HashSet<String> = terms(query).collect();
+    let mut suggestions: Vec<String> = Vec::new();
+
+    if let Some(history) = history {
+        for entry in history.all() {
+            if suggestions.len() >= MAX_HISTORY_SUGGESTIONS {
+                break;
+            }
+            let past = entry.query.trim();
+            let related = !past.eq_ignore_ascii_case(query.trim())
+                && terms(past).any(|t| query_terms.contains(&t));
+            if related && !suggestions.iter().any(|s| s == past) {
+                suggestions.push(past.to_string());
+            }
+        }
+    }
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for result in results.iter().take(SUGGESTION_RESULTS) {
+        for term in terms(&result.chunk.text) {
+            if !query_terms.contains(&term) {
+                *counts.entry(term).or_insert(0) += 1;
+            }
+        }
+    }
+    let mut ranked: Vec<(String, usize)> = counts.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    for (term, _) in ranked {
+        if suggestions.len() >= MAX_SUGGESTIONS {
+            break;
+        }
+        suggestions.push(format!("{} {}", query.trim(), term));
+    }
+
+    suggestions
+}
+
+/// Lowercased words of `text` worth searching for
+fn terms(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.len() >= 3 && !w.chars().all(|c| c.is_ascii_digit()))
+        .map(str::to_lowercase)
+        .filter(|w| !STOP_WORDS.contains(&w.as_str()))
+}
+
+/// Smallest score drop treated as a knee
+const MIN_KNEE_DROP: f32 = 0.05;
+
+/// Number of scores before the "knee" of a descending score list
+///
+/// The knee is the largest drop between neighbouring scores. It only counts
+/// when it is at least `MIN_KNEE_DROP` and twice the average of the other
+/// drops; otherwise the distribution is treated as flat and every score is
+/// kept.
+pub fn knee_index(scores: &[f32]) -> usize {
+    if scores.len() < 2 {
+        return scores.len();
+    }
+
+    let drops: Vec<f32> = scores.windows(2).map(|w| w[0] - w[1]).collect();
+    let (knee, largest) = drops
+        .iter()
+        .copied()
+        .enumerate()
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+        .unwrap();
+
+    let others = drops.len() - 1;
+    let average_other = if others == 0 {
+        0.0
+    } else {
+        (drops.iter().sum::<f32>() - largest) / others as f32
+    };
+
+    if largest < MIN_KNEE_DROP || largest < 2.0 * average_other {
+        return scores.len();
+    }
+
+    knee + 1
+}
+
+/// Keep only the results above the knee of their (descending) scores
+pub fn cut_at_knee(mut results: Vec<SearchResult>) -> Vec<SearchResult> {
+    let scores: Vec<f32> = results.iter().map(|r| r.score).collect();
+    results.truncate(knee_index(&scores));
+    results
+}
+
+/// Collapse results whose chunk text is byte-identical (e.g. vendored copies)
+///
+/// The best-scoring copy is kept and the other locations are recorded in
+/// its `duplicates`. Near-identical chunks are left alone.
+pub fn dedupe_results(results: Vec<SearchResult>) -> Vec<SearchResult> {
+    use std::hash::{Hash, Hasher};
+
+    let mut deduped: Vec<SearchResult> = Vec::with_capacity(results.len());
+    let mut seen: HashMap<u64, Vec<usize>> = HashMap::new();
+
+    for result in results {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        result.chunk.text.hash(&mut hasher);
+        let candidates = seen.entry(hasher.finish()).or_default();
+
+        // Compare text too, so a hash collision never merges different chunks
+        let existing = candidates
+            .iter()
+            .copied()
+            .find(|&i| deduped[i].chunk.text == result.chunk.text);
+
+        match existing {
+            Some(i) => {
+                let location = format_location(&result);
+                deduped[i].duplicates.push(location);
+            }
+            None => {
+                candidates.push(deduped.len());
+                deduped.push(result);
+            }
+        }
+    }
+
+    deduped
+}
+
+/// Format a result's location as `path:line` or `path:start-end`
+fn format_location(result: &SearchResult) -> String {
+    if result.chunk.start_line == result.chunk.end_line {
+        format!("{}:{}", result.chunk.file_path, result.chunk.start_line)
+    } else {
+        format!(
+            "{}:{}-{}",
+            result.chunk.file_path, result.chunk.start_line, result.chunk.end_line
+        )
+    }
+}
+
+/// Format a compact, relevance-ranked list of `file:line (score%)` entries
+///
+/// Meant to precede the detailed output so the hit list can be scanned first.
+pub fn format_summary(results: &[SearchResult]) -> String {
+    let mut ranked: Vec<&SearchResult> = results.iter().collect();
+    ranked.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+
+    let mut output = String::new();
+    for (i, result) in ranked.iter().enumerate() {
+        output.push_str(&format!(
+            "  {} {} ({}%)\n",
+            format!("{:>3}.", i + 1).dimmed(),
+            format_location(result).cyan(),
+            score_percent(result.score)
+        ));
+    }
+
+    output
+}
+
+/// Columns given to the location when the output width is unknown (piped)
+const COMPACT_LOCATION_WIDTH: usize = 60;
+
+/// Fewest columns the location is squeezed into on a narrow terminal
+const COMPACT_MIN_LOCATION_WIDTH: usize = 20;
+
+/// Format results as an aligned `score% | lang | file:line` table, one per line
+///
+/// Column widths come from the result set. The location column fills the
+/// remaining `width` (or a fixed width when piped) and long paths are cut
+/// from the left so the file name stays visible.
+pub fn format_results_compact(results: &[SearchResult], width: Option<usize>) -> String {
+    let language = |result: &SearchResult| result.chunk.language.clone().unwrap_or("-".into());
+
+    let score_width = results
+        .iter()
+        .map(|r| format!("{}%", score_percent(r.score)).len())
+        .max()
+        .unwrap_or(0);
+    let lang_width = results
+        .iter()
+        .map(|r| language(r).chars().count())
+        .max()
+        .unwrap_or(0);
+    let location_width = match width {
+        Some(width) => width
+            .saturating_sub(score_width + lang_width + 6)
+            .max(COMPACT_MIN_LOCATION_WIDTH),
+        None => COMPACT_LOCATION_WIDTH,
+    };
+
+    let mut output = String::new();
+    for result in results {
+        let score = format!("{}%", score_percent(result.score));
+        let location = truncate_left(&format_location(result), location_width);
+        output.push_str(&format!(
+            "{} {} {} {} {}\n",
+            format!("{:>score_width$}", score).green(),
+            "|".dimmed(),
+            format!("{:<lang_width$}", language(result)).yellow(),
+            "|".dimmed(),
+            location.cyan()
+        ));
+    }
+
+    output
+}
+
+/// Cut a line to its last `width` columns, marking the cut with a leading `…`
+fn truncate_left(line: &str, width: usize) -> String {
+    let count = line.chars().count();
+    if count <= width {
+        return line.to_string();
+    }
+
+    let mut truncated = String::from("…");
+    truncated.extend(line.chars().skip(count - width.saturating_sub(1)));
+    truncated
+}
+
+/// Score as a whole percentage, rounded
+fn score_percent(score: f32) -> u32 {
+    (score * 100.0).round().max(0.0) as u32
+}
+
+/// Content lines shown per result in text output
+const MAX_CONTENT_LINES: usize = 15;
+
+/// Mark what to highlight in each result's content
+///
+/// Results with keyword matches (hybrid search) get the matched text
+/// highlighted. Others without highlighted lines get the displayed line
+/// sharing the most identifier parts with the query, a cheap stand-in for
+/// [`Searcher::highlight_lines`]; lines sharing none are left plain.
+pub fn highlight_matches(query: &str, results: &mut [SearchResult]) {
+    let query_parts: Vec<String> = extract_symbols(query, None)
+        .into_iter()
+        .filter(|part| !STOP_WORDS.contains(&part.as_str()))
+        .collect();
+
+    for result in results {
+        if !result.keyword_spans.is_empty() {
+            result.highlighted_spans = result.keyword_spans.clone();
+            continue;
+        }
+        if !result.highlighted_lines.is_empty() || query_parts.is_empty() {
+            continue;
+        }
+
+        let mut best: Option<(usize, usize)> = None;
+        for (i, line) in result
+            .chunk
+            .text
+            .lines()
+            .take(MAX_CONTENT_LINES)
+            .enumerate()
+        {
+            let overlap = extract_symbols(line, None)
+                .iter()
+                .filter(|part| query_parts.contains(part))
+                .count();
+            if overlap > best.map_or(0, |(_, most)| most) {
+                best = Some((i, overlap));
+            }
+        }
+        result.highlighted_lines = best.map(|(i, _)| i).into_iter().collect();
+    }
+}
+
+/// Format search results for terminal display
+///
+/// With `explain`, each result is followed by its score breakdown. With
+/// `width`, content lines are truncated to fit that many columns. Scores are
+/// shown as percentages with `score_decimals` decimal places, followed by
+/// the raw embedding distance with `show_distance`.
+pub fn format_results(
+    results: &[SearchResult],
+    show_content: bool,
+    explain: bool,
+    width: Option<usize>,
+    score_decimals: usize,
+    show_distance: bool,
+    _root_path: &Path,
+) -> String {
+    let mut output = String::new();
+
+    for (i, result) in results.iter().enumerate() {
+        // File path and line range
+        let file_display = format_location(result);
+
+        let distance = if show_distance {
+            format!(", distance {:.4}", result.distance)
+        } else {
+            String::new()
+        };
+        output.push_str(&format!(
+            "\n{} {} ({}%{})\n",
+            format!("[{}]", i + 1).dimmed(),
+            file_display.cyan().bold(),
+            format_score(result.score, score_decimals),
+            distance
+        ));
+
+        push_details(&mut output, result, show_content, explain, width);
+    }
+
+    output
+}
+
+/// Format search results grouped by file
+///
+/// Each file is listed once, in the order of its first result, with its
+/// best score; its matching line ranges follow in line order. Options are
+/// as for [`format_results`].
+pub fn format_results_grouped(
+    results: &[SearchResult],
+    show_content: bool,
+    explain: bool,
+    width: Option<usize>,
+    score_decimals: usize,
+) -> String {
+    let mut groups: Vec<(&str, Vec<&SearchResult>)> = Vec::new();
+    for result in results {
+        let path = result.chunk.file_path.as_str();
+        match groups.iter_mut().find(|(p, _)| *p == path) {
+            Some((_, members)) => members.push(result),
+            None => groups.push((path, vec![result])),
+        }
+    }
+
+    let mut output = String::new();
+    for (i, (path, mut members)) in groups.into_iter().enumerate() {
+        let best = members.iter().map(|r| r.score).fold(f32::MIN, f32::max);
+        output.push_str(&format!(
+            "\n{} {} ({}%)\n",
+            format!("[{}]", i + 1).dimmed(),
+            path.cyan().bold(),
+            format_score(best, score_decimals)
+        ));
+
+        members.sort_by_key(|r| r.chunk.start_line);
+        for result in members {
+            let lines = if result.chunk.start_line == result.chunk.end_line {
+                format!("line {}", result.chunk.start_line)
+            } else {
+                format!(
+                    "lines {}-{}",
+                    result.chunk.start_line, result.chunk.end_line
+                )
+            };
+            output.push_str(&format!(
+                "    {} ({}%)\n",
+                lines.cyan(),
+                format_score(result.score, score_decimals)
+            ));
+            push_details(&mut output, result, show_content, explain, width);
+        }
+    }
+
+    output
+}
+
+/// Percentage score colored by strength
+fn format_score(score: f32, score_decimals: usize) -> ColoredString {
+    let score_color = match score_percent(score) {
+        80.. => "green",
+        60.. => "yellow",
+        _ => "red",
+    };
+    format!("{:.*}", score_decimals, score * 100.0).color(score_color)
+}
+
+/// Append what follows a result's heading: duplicates, explanation, content
+fn push_details(
+    output: &mut String,
+    result: &SearchResult,
+    show_content: bool,
+    explain: bool,
+    width: Option<usize>,
+) {
+    if !result.duplicates.is_empty() {
+        output.push_str(&format!(
+            "    {}\n",
+            format!("also in: {}", result.duplicates.join(", ")).dimmed()
+        ));
+    }
+
+    if explain {
+        output.push_str(&format_explanation(result));
+    }
+
+    if !show_content {
+        return;
+    }
+
+    let rule_width = width.map_or(60, |w| w.min(60));
+    output.push_str(&format!("{}\n", "─".repeat(rule_width).dimmed()));
+
+    // Show content with line numbers
+    let lines: Vec<&str> = result.chunk.text.lines().collect();
+    let mut line_start = 0;
+    let show_lines = if lines.len() > MAX_CONTENT_LINES {
+        &lines[..MAX_CONTENT_LINES]
+    } else {
+        &lines
+    };
+
+    // Gutter fits the largest line number shown (at least 4 wide)
+    let last_line = match result.context_after.len() {
+        0 => result.chunk.start_line + show_lines.len().saturating_sub(1),
+        after => result.chunk.end_line + after,
+    };
+    let gutter = last_line.to_string().len().max(4);
+    let context_line = |line_num: usize, line: &str| {
+        let line = match width {
+            Some(w) => truncate_line(line, w.saturating_sub(gutter + 1)),
+            None => line.to_string(),
+        };
+        format!("{}\n", format!("{:>gutter$} {}", line_num, line).dimmed())
+    };
+
+    let first_context = result
+        .chunk
+        .start_line
+        .saturating_sub(result.context_before.len());
+    for (j, line) in result.context_before.iter().enumerate() {
+        output.push_str(&context_line(first_context + j, line));
+    }
+
+    for (j, line) in show_lines.iter().enumerate() {
+        let line_num = result.chunk.start_line + j;
+        let spans = line_spans(&result.highlighted_spans, line_start, line.len());
+        line_start += result.chunk.text[line_start..]
+            .find('\n')
+            .map_or(line.len(), |end| end + 1);
+
+        let shown = match width {
+            Some(w) => truncate_line(line, w.saturating_sub(gutter + 1)),
+            None => line.to_string(),
+        };
+        let line = if !spans.is_empty() {
+            mark_spans(line, &shown, &spans)
+        } else if result.highlighted_lines.contains(&j) {
+            shown.bold().to_string()
+        } else {
+            shown
+        };
+        output.push_str(&format!(
+            "{} {}\n",
+            format!("{:>gutter$}", line_num).dimmed(),
+            line
+        ));
+    }
+
+    if lines.len() > MAX_CONTENT_LINES {
+        output.push_str(&format!(
+            "{}\n",
+            format!(
+                "{:gutter$} ... ({} more lines)",
+                "",
+                lines.len() - MAX_CONTENT_LINES
+            )
+            .dimmed()
+        ));
+    }
+
+    for (j, line) in result.context_after.iter().enumerate() {
+        output.push_str(&context_line(result.chunk.end_line + 1 + j, line));
+    }
+}
+
+/// Parts of `spans` (byte ranges of a chunk) that fall on the line starting
+/// at byte `start`, relative to that line
+fn line_spans(spans: &[(usize, usize)], start: usize, len: usize) -> Vec<(usize, usize)> {
+    spans
+        .iter()
+        .map(|&(from, to)| {
+            (
+                from.max(start) - start,
+                to.min(start + len).saturating_sub(start),
+            )
+        })
+        .filter(|(from, to)| from < to)
+        .collect()
+}
+
+/// Color the byte ranges `spans` of `line`, displayed as `shown`
+fn mark_spans(line: &str, shown: &str, spans: &[(usize, usize)]) -> String {
+    // A cut line keeps a prefix of the original, then `…`
+    let visible = if shown == line {
+        line.len()
+    } else {
+        shown.len() - '…'.len_utf8()
+    };
+
+    let mut output = String::new();
+    let mut pos = 0;
+    for &(from, to) in spans {
+        let (from, to) = (from.clamp(pos, visible), to.min(visible));
+        if from >= to {
+            continue;
+        }
+        output.push_str(&shown[pos..from]);
+        output.push_str(&shown[from..to].yellow().bold().to_string());
+        pos = to;
+    }
+    output.push_str(&shown[pos..]);
+    output
+}
+
+/// Cut a line to at most `width` columns, marking the cut with `…`
+fn truncate_line(line: &str, width: usize) -> String {
+    if line.chars().count() <= width {
+        return line.to_string();
+    }
+
+    let mut truncated: String = line.chars().take(width.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Width of the terminal stdout is attached to, or `None` when piped
+pub fn terminal_width() -> Option<usize> {
+    use std::io::IsTerminal;
+
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    terminal_size::terminal_size().map(|(w, _)| w.0 as usize)
+}
+
+/// Format the score breakdown for a single result
+fn format_explanation(result: &SearchResult) -> String {
+    let mut parts = Vec::new();
+
+    let semantic = result.semantic_score.unwrap_or(result.score);
+    parts.push(format!("semantic: {:.3}", semantic));
+
+    if let Some(keyword) = result.keyword_score {
+        parts.push(format!("keyword: {:+.3}", keyword));
+    }
+
+    // Anything not accounted for by the components came from re-ranking
+    let delta = result.score - semantic - result.keyword_score.unwrap_or(0.0);
+    if delta.abs() > 1e-4 {
+        parts.push(format!("rerank: {:+.3}", delta));
+    }
+
+    parts.push(format!(
+        "language: {}",
+        result.chunk.language.as_deref().unwrap_or("unknown")
+    ));
+
+    let mut output = format!("    {}\n", parts.join("  ").dimmed());
+
+    if !result.keyword_spans.is_empty() {
+        let matches: Vec<String> = result
+            .keyword_spans
+            .iter()
+            .take(5)
+            .map(|&(start, end)| {
+                let line =
+                    result.chunk.start_line + result.chunk.text[..start].matches('\n').count();
+                format!("\"{}\" (line {})", &result.chunk.text[start..end], line)
+            })
+            .collect();
+
+        let more = result.keyword_spans.len().saturating_sub(matches.len());
+        let suffix = if more > 0 {
+            format!(" +{} more", more)
+        } else {
+            String::new()
+        };
+
+        output.push_str(&format!(
+            "    {}\n",
+            format!("keyword matches: {}{}", matches.join(", "), suffix).dimmed()
+        ));
+    }
+
+    output
+}
+
+/// A search result as written in JSON output
+#[derive(serde::Serialize)]
+struct JsonResult<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query: Option<&'a str>,
+    file: &'a str,
+    start_line: usize,
+    end_line: usize,
+    score: f32,
+    score_pct: u32,
+    distance: f32,
+    content: &'a str,
+    language: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    root: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    duplicates: &'a [String],
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    context_before: &'a [String],
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    context_after: &'a [String],
+}
+
+impl<'a> JsonResult<'a> {
+    fn new(result: &'a SearchResult, query: Option<&'a str>) -> Self {
+        Self {
+            query,
+            file: &result.chunk.file_path,
+            start_line: result.chunk.start_line,
+            end_line: result.chunk.end_line,
+            score: result.score,
+            score_pct: score_percent(result.score),
+            distance: result.distance,
+            content: &result.chunk.text,
+            language: result.chunk.language.as_deref(),
+            root: result.root.as_deref(),
+            duplicates: &result.duplicates,
+            context_before: &result.context_before,
+            context_after: &result.context_after,
+        }
+    }
+}
+
+/// Format results as JSON
+pub fn format_results_json(results: &[SearchResult]) -> Result<String> {
+    let json_results: Vec<JsonResult> = results.iter().map(|r| JsonResult::new(r, None)).collect();
+
+    Ok(serde_json::to_string_pretty(&json_results)?)
+}
+
+/// Format results as CSV: `file,start_line,end_line,score,language`
+///
+/// With `content`, a final `content` column holds the chunk text, so cells
+/// may span lines. Fields are quoted only when they need it (RFC 4180).
+pub fn format_results_csv(results: &[SearchResult], content: bool) -> Result<String> {
+    let mut output = String::from("file,start_line,end_line,score,language");
+    if content {
+        output.push_str(",content");
+    }
+    output.push('\n');
+
+    for result in results {
+        let chunk = &result.chunk;
+        output.push_str(&format!(
+            "{},{},{},{},{}",
+            csv_field(&chunk.file_path),
+            chunk.start_line,
+            chunk.end_line,
+            result.score,
+            csv_field(chunk.language.as_deref().unwrap_or_default())
+        ));
+        if content {
+            output.push(',');
+            output.push_str(&csv_field(&chunk.text));
+        }
+        output.push('\n');
+    }
+
+    Ok(output)
+}
+
+/// Quote a CSV field if it contains a delimiter, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Write results as JSON Lines, one object per result, flushing after each
+///
+/// Every line carries the `query` it answers, so the output of several
+/// searches can be concatenated.
+pub fn write_results_jsonl<W: Write>(
+    results: &[SearchResult],
+    query: &str,
+    writer: &mut W,
+) -> Result<()> {
+    for result in results {
+        serde_json::to_writer(&mut *writer, &JsonResult::new(result, Some(query)))?;
+        writeln!(writer)?;
+        writer.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Format results as GitHub Actions `::notice` annotations, one per line
+pub fn format_results_github(results: &[SearchResult], query: &str) -> String {
+    results
+        .iter()
+        .map(|r| {
+            format!(
+                "::notice file={},line={},endLine={},title=lgrep::{}\n",
+                escape_property(&r.chunk.file_path),
+                r.chunk.start_line,
+                r.chunk.end_line,
+                escape_data(&match_message(r, query))
+            )
+        })
+        .collect()
+}
+
+/// Format results as a minimal SARIF 2.1.0 log with one result per chunk
+pub fn format_results_sarif(results: &[SearchResult], query: &str) -> Result<String> {
+    let sarif_results: Vec<serde_json::Value> = results
+        .iter()
+        .map(|r| {
+            serde_json::json!({
+                "ruleId": "semantic-match",
+                "level": "note",
+                "message": { "text": match_message(r, query) },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": r.chunk.file_path },
+                        "region": {
+                            "startLine": r.chunk.start_line,
+                            "endLine": r.chunk.end_line,
+                        },
+                    },
+                }],
+                "properties": {
+                    "score": r.score,
+                    "scorePct": score_percent(r.score),
+                    "query": query,
+                },
+            })
+        })
+        .collect();
+
+    let log = serde_json::json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "lgrep",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": [{
+                        "id": "semantic-match",
+                        "shortDescription": { "text": "Code semantically similar to a query" },
+                        "fullDescription": {
+                            "text": format!("Code semantically similar to \"{}\"", query),
+                        },
+                    }],
+                },
+            },
+            "results": sarif_results,
+        }],
+    });
+
+    Ok(serde_json::to_string_pretty(&log)?)
+}
+
+/// Annotation text for a result
+fn match_message(result: &SearchResult, query: &str) -> String {
+    format!("Matches \"{}\" (score {:.2})", query, result.score)
+}
+
+/// Escape a workflow command message
+fn escape_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a workflow command property value
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::{Chunk, ContentKind};
+    use crate::config::EmbeddingModel;
+    use crate::embedder::StubEmbedder;
+
+    fn create_test_result(file_path: &str, text: &str, score: f32) -> SearchResult {
+        let chunk = Chunk {
+            id: 0,
+            text: text.to_string(),
+            file_path: file_path.to_string(),
+            start_line: 10,
+            end_line: 12,
+            file_hash: "hash".to_string(),
+            language: Some("rust".to_string()),
+            overlap_with_prev: 0,
+            content_kind: ContentKind::Code,
+            text_hash: String::new(),
+            symbols: Vec::new(),
+        };
+        SearchResult::new(chunk, score)
+    }
+
+    /// Build a searcher over (path, text) chunks using the stub embedder
+    fn create_test_searcher(files: &[(&str, &str)]) -> Searcher {
+        let config = Config::new(std::path::PathBuf::from("/nonexistent"));
+        let embedder = StubEmbedder {
+            dimension: config.model.dimension(),
+        };
+        let mut index = VectorIndex::new(config).unwrap();
+
+        let chunks: Vec<Chunk> = files
+            .iter()
+            .enumerate()
+            .map(|(i, (path, text))| {
+                let language = crate::chunker::detect_language(path);
+                Chunk {
+                    id: i as u64,
+                    text: text.to_string(),
+                    file_path: path.to_string(),
+                    start_line: 1,
+                    end_line: 1,
+                    file_hash: "hash".to_string(),
+                    symbols: crate::chunker::extract_symbols(text, language.as_deref()),
+                    language,
+                    overlap_with_prev: 0,
+                    content_kind: ContentKind::Code,
+                    text_hash: String::new(),
+                }
+            })
+            .collect();
+        let embeddings = chunks
+            .iter()
+            .map(|c| embedder.embed_one(&c.text).unwrap())
+            .collect();
+        index.add_chunks(chunks, embeddings).unwrap();
+
+        Searcher::with_embedder(index, Box::new(embedder))
+    }
+
+    fn sample_searcher() -> Searcher {
+        create_test_searcher(&[
+            ("src/auth.rs", "verify user password login"),
+            ("src/token.rs", "verify user session login jwt"),
+            ("app/auth.py", "verify user password login"),
+            ("src/db.rs", "database connection pool"),
+        ])
+    }
+
+    #[test]
+    fn test_search() {
+        let searcher = sample_searcher();
+        let results = searcher.search("database connection pool", 2).unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].chunk.file_path, "src/db.rs");
+        assert!(results[0].score >= results[1].score);
+    }
+
+    #[test]
+    fn test_search_diverse_skips_near_duplicates() {
+        let searcher = create_test_searcher(&[
+            ("src/a.rs", "retry request backoff"),
+            ("src/b.rs", "retry request backoff"),
+            ("src/c.rs", "retry request backoff"),
+            ("src/d.rs", "retry connection timeout"),
+        ]);
+        let query = "retry request backoff timeout";
+
+        let plain = searcher.search(query, 2).unwrap();
+        assert!(plain.iter().all(|r| r.chunk.file_path != "src/d.rs"));
+
+        let diverse = searcher.search_diverse(query, 2, 0.5).unwrap();
+        assert_eq!(diverse.len(), 2);
+        assert_eq!(diverse[0].chunk.file_path, plain[0].chunk.file_path);
+        assert_eq!(diverse[1].chunk.file_path, "src/d.rs");
+
+        // Pure relevance keeps the plain order
+        let relevant = searcher.search_diverse(query, 2, 1.0).unwrap();
+        assert_eq!(relevant[1].chunk.file_path, plain[1].chunk.file_path);
+
+        assert!(searcher.search_diverse(query, 2, 1.5).is_err());
+    }
+
+    #[test]
+    fn test_rerank_diverse_tolerates_nan_scores() {
+        let searcher = sample_searcher();
+        let mut candidates = searcher.search("connect to the database", 3).unwrap();
+        candidates[0].score = f32::NAN;
+
+        let reranked = searcher.rerank_diverse(candidates, 3, 0.5).unwrap();
+        assert_eq!(reranked.len(), 3);
+    }
+
+    #[test]
+    fn test_search_filters_by_recorded_mtime() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("old.rs"), "fn open_session() {}").unwrap();
+        std::fs::write(root.join("new.rs"), "fn close_session() {}").unwrap();
+        let month_ago = std::time::SystemTime::now() - Duration::from_secs(30 * 24 * 60 * 60);
+        std::fs::File::options()
+            .write(true)
+            .open(root.join("old.rs"))
+            .unwrap()
+            .set_modified(month_ago)
+            .unwrap();
+
+        let config = Config::new(root.clone());
+        let stub = || {
+            Box::new(StubEmbedder {
+                dimension: config.model.dimension(),
+            })
+        };
+        let index = Indexer::with_embedder(config.clone(), stub())
+            .build_index()
+            .unwrap();
+        let searcher = Searcher::with_embedder(index, stub());
+
+        let week = crate::filter::parse_time("7d", std::time::SystemTime::now()).unwrap();
+        let filter = SearchFilter::new().with_modified_after(week);
+        let results = searcher
+            .search_with_filter("session", 10, Some(&filter))
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].chunk.file_path, "new.rs");
+
+        let filter = SearchFilter::new().with_modified_before(week);
+        let results = searcher
+            .search_with_filter("session", 10, Some(&filter))
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].chunk.file_path, "old.rs");
+    }
+
+    #[test]
+    fn test_search_filters_by_recorded_file_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("small.rs"), "fn open_session() {}").unwrap();
+        let padding = "// session padding\n".repeat(100);
+        std::fs::write(
+            root.join("large.rs"),
+            format!("fn close_session() {{}}\n{}", padding),
+        )
+        .unwrap();
+
+        let config = Config::new(root.clone());
+        let stub = || {
+            Box::new(StubEmbedder {
+                dimension: config.model.dimension(),
+            })
+        };
+        let index = Indexer::with_embedder(config.clone(), stub())
+            .build_index()
+            .unwrap();
+        let searcher = Searcher::with_embedder(index, stub());
+
+        let filter = SearchFilter::new().with_max_file_bytes(1024);
+        let results = searcher
+            .search_with_filter("session", 10, Some(&filter))
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].chunk.file_path, "small.rs");
+
+        let filter = SearchFilter::new().with_min_file_bytes(1024);
+        let results = searcher
+            .search_with_filter("session", 10, Some(&filter))
+            .unwrap();
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|r| r.chunk.file_path == "large.rs"));
+    }
+
+    #[test]
+    fn test_search_with_context_reads_surrounding_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let lines: Vec<String> = (1..=12)
+            .map(|i| format!("let value{} = {};", i, i))
+            .collect();
+        std::fs::write(root.join("lib.rs"), lines.join("\n")).unwrap();
+
+        let mut config = Config::new(root.clone());
+        config.chunk_size = 60;
+        config.chunk_overlap = 0;
+        let stub = || {
+            Box::new(StubEmbedder {
+                dimension: config.model.dimension(),
+            })
+        };
+        Indexer::with_embedder(config.clone(), stub())
+            .build_index()
+            .unwrap();
+        let searcher = Searcher::with_embedder(VectorIndex::load(config.clone()).unwrap(), stub());
+
+        let results = searcher.search_with_context("value", 10, 2).unwrap();
+        assert!(results.len() > 2);
+        for result in &results {
+            let (start, end) = (result.chunk.start_line, result.chunk.end_line);
+            let before = &lines[start.saturating_sub(3)..start - 1];
+            let after = &lines[end..(end + 2).min(lines.len())];
+            assert_eq!(result.context_before, before);
+            assert_eq!(result.context_after, after);
+        }
+        let first = results.iter().find(|r| r.chunk.start_line == 1).unwrap();
+        let text = format_results(
+            std::slice::from_ref(first),
+            true,
+            false,
+            None,
+            0,
+            false,
+            &root,
+        );
+        assert!(text.contains(&lines[first.chunk.end_line]));
+        let json = format_results_json(std::slice::from_ref(first)).unwrap();
+        assert!(json.contains("\"context_after\""));
+        assert!(!json.contains("\"context_before\""));
+
+        // Once the lines move, the chunk text is all there is
+        std::fs::write(
+            root.join("lib.rs"),
+            format!("// moved\n{}", lines.join("\n")),
+        )
+        .unwrap();
+        let results = searcher.search_with_context("value", 10, 2).unwrap();
+        assert!(results
+            .iter()
+            .all(|r| r.context_before.is_empty() && r.context_after.is_empty()));
+
+        std::fs::remove_file(root.join("lib.rs")).unwrap();
+        let results = searcher.search_with_context("value", 10, 2).unwrap();
+        assert!(results.iter().all(|r| r.context_before.is_empty()));
+    }
+
+    #[test]
+    fn test_sync_loads_index_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("db.rs"), "fn open_database_connection() {}").unwrap();
+
+        let config = Config::new(root.clone());
+        let stub = || {
+            Box::new(StubEmbedder {
+                dimension: config.model.dimension(),
+            })
+        };
+        Indexer::with_embedder(config.clone(), stub())
+            .build_index()
+            .unwrap();
+        std::fs::write(root.join("auth.rs"), "fn verify_password() {}").unwrap();
+
+        crate::index::LOADS.with(|n| n.set(0));
+        let indexer = Indexer::with_embedder(config.clone(), stub());
+        let (searcher, stats) = Searcher::sync_with(indexer, config).unwrap();
+
+        assert_eq!(crate::index::LOADS.with(|n| n.get()), 1);
+        assert_eq!(stats.added, 1);
+        let results = searcher.search("verify password", 1).unwrap();
+        assert_eq!(results[0].chunk.file_path, "auth.rs");
+    }
+
+    #[test]
+    fn test_search_vector() {
+        let searcher = sample_searcher();
+        let db_chunk = searcher
+            .index
+            .search(&searcher.embedder.embed_one("database").unwrap(), 1)
+            .unwrap()[0]
+            .chunk
+            .id;
+        let embedding = searcher.index.get_vector(db_chunk).unwrap().unwrap();
+
+        let results = searcher.search_vector(&embedding, 3).unwrap();
+        assert_eq!(results[0].chunk.id, db_chunk);
+        assert_eq!(results[0].chunk.file_path, "src/db.rs");
+
+        let short = &embedding[..embedding.len() - 1];
+        assert!(matches!(
+            searcher.search_vector(short, 3),
+            Err(LgrepError::DimensionMismatch { got, .. }) if got == embedding.len() - 1
+        ));
+    }
+
+    #[test]
+    fn test_query_prefix_follows_the_index() {
+        let config = Config::new(std::path::PathBuf::from("/nonexistent"))
+            .with_model(EmbeddingModel::MultilingualE5Small);
+        let stub = StubEmbedder {
+            dimension: config.model.dimension(),
+        };
+        let prefixed = stub.embed_one("query: open database").unwrap();
+        let raw = stub.embed_one("open database").unwrap();
+
+        let mut index = VectorIndex::new(config).unwrap();
+        let dimension = index.dimension();
+        let searcher = Searcher::with_embedder(index, Box::new(StubEmbedder { dimension }));
+        assert_eq!(searcher.embed_query("open database").unwrap(), prefixed);
+
+        index = searcher.index;
+        index.set_model_prefixes(false);
+        let searcher = Searcher::with_embedder(index, Box::new(StubEmbedder { dimension }));
+        assert_eq!(searcher.embed_query("open database").unwrap(), raw);
+    }
+
+    #[test]
+    fn test_search_similar_to_chunk() {
+        let searcher = sample_searcher();
+        let seed = searcher.index().chunk_at("src/auth.rs", 1).unwrap().id;
+
+        let results = searcher.search_similar_to_chunk(seed, 2).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.chunk.id != seed));
+        assert_eq!(results[0].chunk.file_path, "app/auth.py");
+
+        assert!(matches!(
+            searcher.search_similar_to_chunk(999, 2),
+            Err(LgrepError::Index(_))
+        ));
+    }
+
+    #[test]
+    fn test_search_files_ranks_topical_file_first() {
+        let config =
+            Config::new(std::path::PathBuf::from("/nonexistent")).with_file_embeddings(true);
+        let embedder = StubEmbedder {
+            dimension: config.model.dimension(),
+        };
+        let mut index = VectorIndex::new(config).unwrap();
+
+        let files = [
+            ("src/db.rs", "open database connection"),
+            ("src/db.rs", "database connection pool size"),
+            ("src/auth.rs", "verify password hash"),
+            ("src/auth.rs", "database lookup of user"),
+            ("src/ui.rs", "render button widget"),
+        ];
+        let chunks: Vec<Chunk> = files
+            .iter()
+            .enumerate()
+            .map(|(i, (path, text))| Chunk {
+                id: i as u64,
+                text: text.to_string(),
+                file_path: path.to_string(),
+                start_line: 1,
+                end_line: 1,
+                file_hash: "hash".to_string(),
+                language: Some("rust".to_string()),
+                overlap_with_prev: 0,
+                content_kind: ContentKind::Code,
+                text_hash: String::new(),
+                symbols: Vec::new(),
+            })
+            .collect();
+        let embeddings = chunks
+            .iter()
+            .map(|c| embedder.embed_one(&c.text).unwrap())
+            .collect();
+        index.add_chunks(chunks, embeddings).unwrap();
+
+        let searcher = Searcher::with_embedder(index, Box::new(embedder));
+        let results = searcher.search_files("database connection", 10).unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].file_path, "src/db.rs");
+        assert_eq!(results[1].file_path, "src/auth.rs");
+
+        // Plain indexes have no file vectors to search
+        assert!(sample_searcher().search_files("database", 10).is_err());
+    }
+
+    #[test]
+    fn test_search_within_file() {
+        let searcher = create_test_searcher(&[
+            ("src/big.rs", "render the main window"),
+            ("src/big.rs", "open database connection pool"),
+            ("src/big.rs", "parse command line flags"),
+            ("src/db.rs", "database connection pool"),
+        ]);
+
+        let results = searcher
+            .search_within_file("database connection", "src/big.rs", 10)
+            .unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|r| r.chunk.file_path == "src/big.rs"));
+        assert_eq!(results[0].chunk.text, "open database connection pool");
+        assert!(results[0].score > results[1].score);
+
+        let err = searcher
+            .search_within_file("database", "src/missing.rs", 10)
+            .unwrap_err();
+        assert!(matches!(err, LgrepError::InvalidPath(_)));
+    }
+
+    #[test]
+    fn test_multi_searcher_merges_by_score() {
+        let multi = MultiSearcher {
+            searchers: vec![
+                (
+                    "web".to_string(),
+                    create_test_searcher(&[
+                        ("src/db.rs", "database connection pool"),
+                        ("src/ui.rs", "render button widget"),
+                    ]),
+                ),
+                ("empty".to_string(), create_test_searcher(&[])),
+                (
+                    "api".to_string(),
+                    create_test_searcher(&[("src/pool.rs", "database connection pool retry")]),
+                ),
+            ],
+        };
+
+        let results = multi.search("database connection pool", 2).unwrap();
+        let paths: Vec<&str> = results.iter().map(|r| r.chunk.file_path.as_str()).collect();
+        assert_eq!(paths, ["web/src/db.rs", "api/src/pool.rs"]);
+        assert_eq!(results[1].root.as_deref(), Some("api"));
+        assert!(results[0].score >= results[1].score);
+
+        let empty = MultiSearcher {
+            searchers: vec![("empty".to_string(), create_test_searcher(&[]))],
+        };
+        assert!(matches!(
+            empty.search("anything", 5),
+            Err(LgrepError::EmptyIndex)
+        ));
+    }
+
+    #[test]
+    fn test_load_many_refuses_mixed_models() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut roots = Vec::new();
+        for (name, model) in [
+            ("one", EmbeddingModel::AllMiniLmL6V2),
+            ("two", EmbeddingModel::NomicEmbedTextV15),
+        ] {
+            let root = dir.path().join(name);
+            std::fs::create_dir(&root).unwrap();
+            std::fs::write(root.join("a.rs"), "fn a() {}").unwrap();
+            let config = Config::new(root.clone()).with_model(model);
+            let stub = StubEmbedder {
+                dimension: config.model.dimension(),
+            };
+            Indexer::with_embedder(config, Box::new(stub))
+                .build_index()
+                .unwrap();
+            roots.push(root);
+        }
+
+        let err = Searcher::load_many(&roots).err().unwrap();
+        assert!(matches!(err, LgrepError::Config(ref m) if m.contains("different models")));
+
+        let err = Searcher::load_many(&[dir.path().to_path_buf()])
+            .err()
+            .unwrap();
+        assert!(matches!(err, LgrepError::InvalidPath(_)));
+    }
+
+    #[test]
+    fn test_handle_searches_from_several_threads() {
+        fn assert_shareable<T: Clone + Send + Sync>() {}
+        assert_shareable::<SearcherHandle>();
+
+        let handle = sample_searcher().handle();
+        let expected: Vec<u64> = handle
+            .search("verify password", 3)
+            .unwrap()
+            .iter()
+            .map(|r| r.chunk.id)
+            .collect();
+
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let handle = handle.clone();
+                std::thread::spawn(move || {
+                    (0..10)
+                        .map(|_| {
+                            let results = handle.search("verify password", 3).unwrap();
+                            results.iter().map(|r| r.chunk.id).collect::<Vec<_>>()
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        for thread in threads {
+            for ids in thread.join().unwrap() {
+                assert_eq!(ids, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stats_break_down_languages() {
+        let searcher = create_test_searcher(&[
+            ("src/a.rs", "fn a() {}"),
+            ("src/b.rs", "fn b() { c() }"),
+            ("app/c.py", "def c(): pass"),
+            ("NOTES", "todo"),
+        ]);
+        let stats = searcher.stats();
+
+        assert_eq!(stats.by_language["rust"], 2);
+        assert_eq!(stats.by_language["python"], 1);
+        assert_eq!(stats.by_language["unknown"], 1);
+        assert_eq!(stats.avg_chunk_len, (9 + 14 + 13 + 4) / 4);
+        // Never saved
+        assert_eq!(stats.total_bytes, 0);
+    }
+
+    #[test]
+    fn test_model_revision_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::new(dir.path().to_path_buf());
+        let dimension = config.model.dimension();
+
+        let mut index = VectorIndex::new(config.clone()).unwrap();
+        index.set_model_revision(Some("stub-rev-1234".to_string()));
+        index.save().unwrap();
+
+        let loaded = VectorIndex::load(config).unwrap();
+        let searcher = Searcher::with_embedder(loaded, Box::new(StubEmbedder { dimension }));
+        let stats = searcher.stats();
+
+        assert_eq!(stats.model, "sentence-transformers/all-MiniLM-L6-v2");
+        assert_eq!(stats.model_revision.as_deref(), Some("stub-rev-1234"));
+        assert_eq!(stats.format_version, crate::chunker::FORMAT_VERSION);
+        assert!(stats.total_bytes > 0);
+    }
+
+    #[test]
+    fn test_search_empty_index() {
+        let searcher = create_test_searcher(&[]);
+        assert_eq!(searcher.stats().chunks, 0);
+
+        let err = searcher.search("anything", 10).unwrap_err();
+        assert!(matches!(err, LgrepError::EmptyIndex));
+        assert!(err.to_string().contains("nothing was indexed"), "{}", err);
+    }
+
+    #[test]
+    fn test_auto_hybrid_surfaces_literal_matches() {
+        let searcher = create_test_searcher(&[
+            ("a.rs", "}"),
+            ("b.rs", "})"),
+            ("c.rs", "();"),
+            ("d.rs", "{}"),
+            ("e.rs", "};"),
+            ("store.rs", "let conn = dbpool.get();"),
+        ]);
+        assert!(is_short_query(" db ", DEFAULT_SHORT_QUERY_CHARS));
+        assert!(!is_short_query("retry", DEFAULT_SHORT_QUERY_CHARS));
+
+        let semantic = searcher.search("db", 3).unwrap();
+        assert!(semantic.iter().all(|r| r.chunk.file_path != "store.rs"));
+
+        let results = searcher.auto_hybrid_search("DB", 3, None).unwrap();
+        assert_eq!(results[0].chunk.file_path, "store.rs");
+        assert_eq!(results[0].keyword_spans, vec![(11, 13)]);
+    }
+
+    #[test]
+    fn test_search_with_filter() {
+        let searcher = sample_searcher();
+        let filter = SearchFilter::new().with_extensions(vec!["py".to_string()]);
+        let results = searcher
+            .search_with_filter("verify user password", 10, Some(&filter))
+            .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].chunk.file_path, "app/auth.py");
+
+        let filter = SearchFilter::new().with_min_score(0.99);
+        let results = searcher
+            .search_with_filter("verify user password login", 10, Some(&filter))
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.score >= 0.99));
+    }
+
+    #[test]
+    fn test_search_paged() {
+        let searcher = sample_searcher();
+        let query = "verify user password login";
+        let ids = |results: Vec<SearchResult>| -> Vec<u64> {
+            results.into_iter().map(|r| r.chunk.id).collect()
+        };
+
+        let all = ids(searcher.search(query, 4).unwrap());
+        let first = ids(searcher.search_paged(query, 0, 2, None).unwrap());
+        let second = ids(searcher.search_paged(query, 2, 2, None).unwrap());
+        assert_eq!([first, second].concat(), all);
+        assert!(searcher.search_paged(query, 4, 2, None).unwrap().is_empty());
+
+        let filter = SearchFilter::new().with_extensions(vec!["rs".to_string()]);
+        let page = searcher.search_paged(query, 1, 5, Some(&filter)).unwrap();
+        assert_eq!(page.len(), 2);
+        assert!(page.iter().all(|r| r.chunk.file_path.ends_with(".rs")));
+    }
+
+    #[test]
+    fn test_hybrid_search_boosts_keyword_match() {
+        let searcher = sample_searcher();
+
+        let semantic = searcher.search("verify user login", 4).unwrap();
+        assert_ne!(semantic[0].chunk.file_path, "src/token.rs");
+
+        let results = searcher
+            .hybrid_search("verify user login", Some("jwt"), 4, None)
+            .unwrap();
+        assert_eq!(results[0].chunk.file_path, "src/token.rs");
+        assert!(results[0].keyword_score.unwrap() > 0.0);
+        assert!(!results[0].keyword_spans.is_empty());
+        assert_eq!(results[1].keyword_score, Some(0.0));
+        assert_eq!(results[1].score, results[1].semantic_score.unwrap());
+    }
+
+    #[test]
+    fn test_hybrid_search_refuses_invalid_keyword() {
+        let searcher = sample_searcher();
+        assert!(matches!(
+            searcher.hybrid_search("verify user login", Some("jwt("), 4, None),
+            Err(LgrepError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_hybrid_search_alpha_weights_keyword_score() {
+        let searcher = sample_searcher();
+        let semantic = searcher.search("verify user login", 4).unwrap();
+
+        // Alpha 0.0 keeps the semantic ranking and scores
+        let results = searcher
+            .hybrid_search_with_alpha("verify user login", Some("jwt"), 4, 0.0, None)
+            .unwrap();
+        assert_eq!(results.len(), semantic.len());
+        for (result, plain) in results.iter().zip(&semantic) {
+            assert_eq!(result.chunk.id, plain.chunk.id);
+            assert!((result.score - plain.score).abs() < 1e-6);
+        }
+
+        // Alpha 1.0 scores by keyword alone, so only matches remain
+        let results = searcher
+            .hybrid_search_with_alpha("verify user login", Some("jwt"), 4, 1.0, None)
+            .unwrap();
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|r| !r.keyword_spans.is_empty()));
+
+        // Otherwise chunks without a match keep their semantic score
+        let results = searcher
+            .hybrid_search_with_alpha("verify user login", Some("jwt"), 4, 0.5, None)
+            .unwrap();
+        let unmatched = results.iter().find(|r| r.keyword_spans.is_empty()).unwrap();
+        assert_eq!(unmatched.score, unmatched.semantic_score.unwrap());
+
+        // Score bounds apply to the blended score
+        let token_semantic = semantic
+            .iter()
+            .find(|r| r.chunk.file_path == "src/token.rs")
+            .unwrap()
+            .score;
+        let min_score = token_semantic + 1e-3;
+        let filter = SearchFilter::new().with_min_score(min_score);
+        let results = searcher
+            .hybrid_search_with_alpha("verify user login", Some("jwt"), 4, 0.5, Some(&filter))
+            .unwrap();
+        assert!(results.iter().any(|r| r.chunk.file_path == "src/token.rs"));
+        assert!(results.iter().all(|r| r.score >= min_score));
+
+        assert!(searcher
+            .hybrid_search_with_alpha("verify user login", Some("jwt"), 4, 1.5, None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_hybrid_search_matches_symbol_parts_ignoring_case() {
+        let searcher = create_test_searcher(&[
+            ("src/disk.rs", "load settings from disk"),
+            ("src/loader.rs", "let loader = ConfigLoader::new();"),
+        ]);
+
+        let results = searcher
+            .hybrid_search("load settings", Some("config"), 2, None)
+            .unwrap();
+        let loader = results
+            .iter()
+            .find(|r| r.chunk.file_path == "src/loader.rs")
+            .unwrap();
+        assert!(loader.keyword_score.unwrap() > 0.0);
+        assert_eq!(loader.keyword_spans, vec![(13, 19)]);
+
+        let filter = SearchFilter::new().with_symbol("config_loader");
+        let results = searcher
+            .search_with_filter("load settings", 2, Some(&filter))
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].chunk.file_path, "src/loader.rs");
+    }
+
+    #[test]
+    fn test_hybrid_search_with_filter() {
+        let searcher = sample_searcher();
+        let filter = SearchFilter::new().with_languages(vec!["rust".to_string()]);
+        let results = searcher
+            .hybrid_search("verify user login", Some("jwt"), 10, Some(&filter))
+            .unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|r| r.chunk.file_path.ends_with(".rs")));
+    }
+
+    /// Embedder that takes longer than any reasonable search budget
+    struct SlowEmbedder(StubEmbedder);
+
+    impl Embed for SlowEmbedder {
+        fn dimension(&self) -> usize {
+            self.0.dimension()
+        }
+
+        fn embed_one(&self, text: &str) -> Result<Vec<f32>> {
+            std::thread::sleep(Duration::from_millis(50));
+            self.0.embed_one(text)
+        }
+
+        fn embed_batch(&self, texts: Vec<&str>) -> Result<Vec<Vec<f32>>> {
+            self.0.embed_batch(texts)
+        }
+    }
+
+    #[test]
+    fn test_search_deadline() {
+        let searcher = sample_searcher();
+        let results = searcher
+            .search_deadline("database connection", 2, Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(results[0].chunk.file_path, "src/db.rs");
+    }
+
+    #[test]
+    fn test_search_deadline_times_out() {
+        let Searcher { index, .. } = sample_searcher();
+        let dimension = index.config().model.dimension();
+        let searcher =
+            Searcher::with_embedder(index, Box::new(SlowEmbedder(StubEmbedder { dimension })));
+
+        let result = searcher.search_deadline("database", 2, Duration::from_millis(10));
+        assert!(matches!(result, Err(LgrepError::Timeout(_))));
+    }
+
+    #[test]
+    fn test_sort_by_path() {
+        let mut results = vec![
+            create_test_result("src/z.rs", "a", 0.9),
+            create_test_result("lib/b.rs", "b", 0.8),
+            create_test_result("src/a.rs", "c", 0.7),
+        ];
+
+        sort_results(&mut results, SortOrder::Path, &[]);
+        let paths: Vec<&str> = results.iter().map(|r| r.chunk.file_path.as_str()).collect();
+        assert_eq!(paths, vec!["lib/b.rs", "src/a.rs", "src/z.rs"]);
+    }
+
+    #[test]
+    fn test_sort_by_recent_finds_files_through_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = |name: &str| {
+            let path = dir.path().join(name);
+            std::fs::create_dir(&path).unwrap();
+            WorkspaceRoot {
+                label: name.to_string(),
+                path,
+            }
+        };
+        let roots = [root("api"), root("web")];
+        let week_ago = std::time::SystemTime::now() - Duration::from_secs(7 * 24 * 60 * 60);
+        for (root, name, modified) in [
+            (&roots[0], "old.rs", week_ago),
+            (&roots[1], "new.rs", std::time::SystemTime::now()),
+        ] {
+            let file = std::fs::File::create(root.path.join(name)).unwrap();
+            file.set_modified(modified).unwrap();
+        }
+
+        let mut results = vec![
+            create_test_result("api/old.rs", "a", 0.9),
+            create_test_result("gone.rs", "b", 0.8),
+            create_test_result("web/new.rs", "c", 0.7),
+        ];
+
+        sort_results(&mut results, SortOrder::Recent, &roots);
+        let paths: Vec<&str> = results.iter().map(|r| r.chunk.file_path.as_str()).collect();
+        assert_eq!(paths, vec!["web/new.rs", "api/old.rs", "gone.rs"]);
+    }
+
+    #[test]
+    fn test_sort_by_score_is_unchanged() {
+        let mut results = vec![
+            create_test_result("src/z.rs", "a", 0.9),
+            create_test_result("lib/b.rs", "b", 0.8),
+            create_test_result("src/a.rs", "c", 0.7),
+        ];
+
+        sort_results(&mut results, SortOrder::Score, &[]);
+        let paths: Vec<&str> = results.iter().map(|r| r.chunk.file_path.as_str()).collect();
+        assert_eq!(paths, vec!["src/z.rs", "lib/b.rs", "src/a.rs"]);
+        assert!("bogus".parse::<SortOrder>().is_err());
+    }
+
+    #[test]
+    fn test_format_results_csv() {
+        let mut results = vec![
+            create_test_result("src/a,b.rs", "let s = \"x\";\nreturn s;", 0.5),
+            create_test_result("notes", "plain", 0.25),
+        ];
+        results[1].chunk.language = None;
+
+        let csv = format_results_csv(&results, false).unwrap();
+        assert_eq!(
+            csv,
+            "file,start_line,end_line,score,language\n\
+             \"src/a,b.rs\",10,12,0.5,rust\n\
+             notes,10,12,0.25,\n"
+        );
+
+        let csv = format_results_csv(&results, true).unwrap();
+        assert!(csv.starts_with("file,start_line,end_line,score,language,content\n"));
+        assert!(csv.contains(",rust,\"let s = \"\"x\"\";\nreturn s;\"\n"));
+        assert_eq!(format_results_csv(&[], false).unwrap().lines().count(), 1);
+    }
+
+    #[test]
+    fn test_score_representations() {
+        let results = vec![create_test_result("src/a.rs", "a", 0.8765)];
+
+        let json: serde_json::Value =
+            serde_json::from_str(&format_results_json(&results).unwrap()).unwrap();
+        assert!((json[0]["score"].as_f64().unwrap() - 0.8765).abs() < 1e-6);
+        assert_eq!(json[0]["score_pct"], 88);
+
+        let output = format_results(&results, false, false, None, 2, false, Path::new("."));
+        assert!(output.contains("87.65"), "{}", output);
+        let output = format_results(&results, false, false, None, 0, false, Path::new("."));
+        assert!(output.contains("88"), "{}", output);
+    }
+
+    #[test]
+    fn test_distance_is_kept_through_blending() {
+        colored::control::set_override(false);
+        let searcher = sample_searcher();
+
+        let results = searcher
+            .hybrid_search_with_alpha("verify login", Some("jwt"), 4, 0.5, None)
+            .unwrap();
+        for result in &results {
+            let semantic = result.semantic_score.unwrap();
+            assert!((result.distance - (1.0 - semantic)).abs() < 1e-5);
+        }
+        assert!(results.iter().any(|r| r.score != 1.0 - r.distance));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&format_results_json(&results).unwrap()).unwrap();
+        assert!((json[0]["distance"].as_f64().unwrap() - results[0].distance as f64).abs() < 1e-6);
+
+        let output = format_results(&results[..1], false, false, None, 0, true, Path::new("."));
+        let expected = format!(", distance {:.4})", results[0].distance);
+        assert!(output.contains(&expected), "{}", output);
+    }
+
+    #[test]
+    fn test_grouped_results_list_each_file_once() {
+        colored::control::set_override(false);
+
+        let mut late = create_test_result("src/auth.rs", "fn logout() {}", 0.6);
+        late.chunk.start_line = 40;
+        late.chunk.end_line = 40;
+        let results = vec![
+            late,
+            create_test_result("src/db.rs", "fn open() {}", 0.7),
+            create_test_result("src/auth.rs", "fn login() {}", 0.5),
+        ];
+
+        let output = format_results_grouped(&results, false, false, None, 0);
+        let lines: Vec<&str> = output.lines().filter(|l| !l.is_empty()).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "[1] src/auth.rs (60%)",
+                "    lines 10-12 (50%)",
+                "    line 40 (60%)",
+                "[2] src/db.rs (70%)",
+                "    lines 10-12 (70%)",
+            ]
+        );
+
+        let output = format_results_grouped(&results, true, false, None, 0);
+        assert!(output.contains("  40 fn logout() {}"));
+    }
+
+    #[test]
+    fn test_compact_table_alignment() {
+        let long = format!("{}/handler.rs", "deeply/nested".repeat(10));
+        let mut python = create_test_result("app.py", "a", 0.5);
+        python.chunk.language = Some("python".to_string());
+        let results = vec![
+            create_test_result("src/lib.rs", "a", 0.91),
+            python,
+            create_test_result(&long, "a", 0.07),
+        ];
+
+        // Drop any color escapes so columns can be compared as plain text
+        let strip = |output: String| -> String {
+            output
+                .split('\x1b')
+                .enumerate()
+                .map(|(i, part)| {
+                    if i == 0 {
+                        part
+                    } else {
+                        &part[part.find('m').unwrap() + 1..]
+                    }
+                })
+                .collect()
+        };
+
+        let plain = strip(format_results_compact(&results, Some(60)));
+        let lines: Vec<&str> = plain.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "91% | rust   | src/lib.rs:10-12");
+        assert_eq!(lines[1], "50% | python | app.py:10-12");
+        assert!(lines[2].starts_with(" 7% | rust   | …"), "{}", lines[2]);
+        assert!(lines[2].ends_with("handler.rs:10-12"), "{}", lines[2]);
+        assert_eq!(lines[2].chars().count(), 60);
+
+        let piped = strip(format_results_compact(&results, None));
+        assert!(piped.lines().all(|line| line.chars().count() <= 80));
+    }
+
+    #[test]
+    fn test_search_batch_flushes_each_query() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        /// Records each flush along with the lines written since the last one
+        struct LogWriter {
+            log: Rc<RefCell<Vec<String>>>,
+            pending: Vec<u8>,
+        }
+
+        impl Write for LogWriter {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.pending.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                let text = String::from_utf8(std::mem::take(&mut self.pending)).unwrap();
+                self.log.borrow_mut().push(format!("flush {}", text.trim()));
+                Ok(())
+            }
+        }
+
+        let searcher = create_test_searcher(&[
+            ("src/db.rs", "open database connection"),
+            ("src/auth.rs", "verify password hash"),
+        ]);
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut writer = LogWriter {
+            log: log.clone(),
+            pending: Vec::new(),
+        };
+        let queries = ["database connection", "", "password hash"]
+            .into_iter()
+            .map(|q| {
+                log.borrow_mut().push(format!("read {}", q));
+                Ok(q.to_string())
+            });
+
+        let count = searcher
+            .search_batch(queries, 1, None, &mut writer)
+            .unwrap();
+        assert_eq!(count, 2);
+
+        let log = log.borrow();
+        let position = |prefix: &str| log.iter().position(|e| e.starts_with(prefix)).unwrap();
+        let first_flush = position("flush");
+        assert!(log[first_flush].contains("\"query\":\"database connection\""));
+        assert!(log[first_flush].contains("src/db.rs"));
+        assert!(first_flush < position("read password hash"));
+    }
+
+    #[test]
+    fn test_format_results_github() {
+        let results = vec![
+            create_test_result("src/auth.rs", "a", 0.9),
+            create_test_result("src/a,b.rs", "b", 0.8),
+        ];
+
+        let output = format_results_github(&results, "hardcoded 100% secret");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "::notice file=src/auth.rs,line=10,endLine=12,title=lgrep::Matches \"hardcoded 100%25 secret\" (score 0.90)",
+                "::notice file=src/a%2Cb.rs,line=10,endLine=12,title=lgrep::Matches \"hardcoded 100%25 secret\" (score 0.80)",
+            ]
+        );
+        assert_eq!(
+            "github".parse::<OutputFormat>().unwrap(),
+            OutputFormat::Github
+        );
+    }
+
+    #[test]
+    fn test_format_results_sarif() {
+        let results = vec![
+            create_test_result("src/auth.rs", "a", 0.9),
+            create_test_result("src/db.rs", "b", 0.8),
+        ];
+
+        let output = format_results_sarif(&results, "secret").unwrap();
+        let log: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(log["version"], "2.1.0");
+
+        let sarif_results = log["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(sarif_results.len(), 2);
+        let location = &sarif_results[1]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/db.rs");
+        assert_eq!(location["region"]["startLine"], 10);
+        assert_eq!(sarif_results[1]["ruleId"], "semantic-match");
+        assert_eq!(sarif_results[1]["properties"]["scorePct"], 80);
+        assert_eq!(sarif_results[1]["properties"]["query"], "secret");
+        let rule = &log["runs"][0]["tool"]["driver"]["rules"][0];
+        assert!(rule["fullDescription"]["text"]
+            .as_str()
+            .unwrap()
+            .contains("\"secret\""));
+    }
+
+    #[test]
+    fn test_knee_cuts_after_sharp_drop() {
+        let scores = [0.91, 0.89, 0.86, 0.52, 0.50, 0.47, 0.45];
+        assert_eq!(knee_index(&scores), 3);
+
+        let results: Vec<SearchResult> = scores
+            .iter()
+            .map(|&score| create_test_result("src/a.rs", "a", score))
+            .collect();
+        assert_eq!(cut_at_knee(results).len(), 3);
+    }
+
+    #[test]
+    fn test_resolve_max_count_precedence_and_ceiling() {
+        let ceiling = DEFAULT_MAX_COUNT_CEILING;
+        assert_eq!(
+            resolve_max_count(Some(5), Some(7), Some("9"), ceiling).unwrap(),
+            5
+        );
+        assert_eq!(
+            resolve_max_count(None, Some(7), Some("9"), ceiling).unwrap(),
+            7
+        );
+        assert_eq!(
+            resolve_max_count(None, None, Some("9"), ceiling).unwrap(),
+            9
+        );
+        assert_eq!(
+            resolve_max_count(None, None, None, ceiling).unwrap(),
+            DEFAULT_MAX_COUNT
+        );
+
+        assert_eq!(
+            resolve_max_count(Some(1_000_000), None, None, 50).unwrap(),
+            50
+        );
+        assert_eq!(resolve_max_count(None, None, Some("200"), 50).unwrap(), 50);
+        assert!(resolve_max_count(None, None, Some("lots"), ceiling).is_err());
+    }
+
+    #[test]
+    fn test_related_queries() {
+        let results = vec![
+            create_test_result(
+                "src/pool.rs",
+                "fn acquire(pool: &Pool) { pool.checkout(); pool.release(); }",
+                0.9,
+            ),
+            create_test_result("src/db.rs", "let pool = Pool::new(config);", 0.8),
+        ];
+
+        let suggestions = related_queries("database connection", &results, None);
+        assert!(!suggestions.is_empty() && suggestions.len() <= MAX_SUGGESTIONS);
+        assert_eq!(suggestions[0], "database connection pool");
+
+        assert!(related_queries("database connection", &[], None).is_empty());
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = QueryHistory::load(dir.path()).unwrap();
+        history
+            .add_query("close database handles".to_string(), 3, None)
+            .unwrap();
+        history
+            .add_query("render html".to_string(), 3, None)
+            .unwrap();
+        let suggestions = related_queries("database connection", &results, Some(&history));
+        assert_eq!(suggestions[0], "close database handles");
+        assert_eq!(suggestions[1], "database connection pool");
+    }
+
+    #[test]
+    fn test_filter_recent_uses_blame() {
+        use crate::git::run_git;
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        run_git(root, &["init", "-q"]);
+        std::fs::write(root.join("old.rs"), "fn legacy() {}\n").unwrap();
+        run_git(root, &["add", "old.rs"]);
+        run_git(
+            root,
+            &["commit", "-q", "-m", "old", "--date", "2001-01-01T00:00:00"],
+        );
+        std::fs::write(root.join("new.rs"), "fn fresh() {}\n").unwrap();
+        run_git(root, &["add", "new.rs"]);
+        run_git(root, &["commit", "-q", "-m", "new"]);
+
+        let mut old = create_test_result("old.rs", "fn legacy() {}", 0.9);
+        let mut new = create_test_result("new.rs", "fn fresh() {}", 0.8);
+        for result in [&mut old, &mut new] {
+            result.chunk.start_line = 1;
+            result.chunk.end_line = 1;
+        }
+
+        // Both files were just written, so mtime alone would keep both
+        let roots = [WorkspaceRoot {
+            label: String::new(),
+            path: root.to_path_buf(),
+        }];
+        let recent = filter_recent(vec![old.clone(), new.clone()], &roots, 30);
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].chunk.file_path, "new.rs");
+
+        // A span past the epoch keeps everything rather than overflowing
+        assert_eq!(filter_recent(vec![old, new], &roots, u64::MAX).len(), 2);
+    }
+
+    #[test]
+    fn test_knee_keeps_flat_distribution() {
+        let scores = [0.80, 0.78, 0.76, 0.74, 0.72];
+        assert_eq!(knee_index(&scores), 5);
+        assert_eq!(knee_index(&[0.5]), 1);
+        assert_eq!(knee_index(&[]), 0);
+    }
+
+    #[test]
+    fn test_highlight_marks_most_similar_line() {
+        let searcher = sample_searcher();
+        let mut results = vec![
+            create_test_result(
+                "src/http.rs",
+                "fn send(client: &Client) {\n\n    retry the http request after a timeout\n}",
+                0.8,
+            ),
+            create_test_result("src/one.rs", "let x = 1;", 0.7),
+        ];
+
+        searcher
+            .highlight_lines("retry http request", &mut results)
+            .unwrap();
+        assert_eq!(results[0].highlighted_lines, vec![2]);
+        assert_eq!(results[1].highlighted_lines, vec![0]);
+    }
+
+    #[test]
+    fn test_highlight_matches() {
+        colored::control::set_override(false);
+
+        let mut keyword = create_test_result("src/auth.rs", "fn check_jwt(token: &str) {}", 0.9);
+        keyword.keyword_spans = vec![(9, 12)];
+        let mut results = vec![
+            keyword,
+            create_test_result(
+                "src/db.rs",
+                "fn open() {\n    let pool = ConnectionPool::new();\n}",
+                0.8,
+            ),
+            create_test_result("src/one.rs", "let x = 1;", 0.7),
+        ];
+
+        highlight_matches("database connection pool", &mut results);
+        assert_eq!(results[0].highlighted_spans, vec![(9, 12)]);
+        assert!(results[0].highlighted_lines.is_empty());
+        assert_eq!(results[1].highlighted_lines, vec![1]);
+        assert!(results[2].highlighted_lines.is_empty());
+
+        // Spans past the cut of a truncated line are dropped, not sliced
+        let output = format_results(
+            &results[..1],
+            true,
+            false,
+            Some(16),
+            0,
+            false,
+            Path::new("."),
+        );
+        assert!(output.contains(" fn check_j…\n"), "{}", output);
+    }
+
+    #[test]
+    fn test_dedupe_identical_chunks() {
+        let results = vec![
+            create_test_result("src/util.rs", "fn helper() {}", 0.9),
+            create_test_result("vendor/lib/util.rs", "fn helper() {}", 0.9),
+            create_test_result("src/other.rs", "fn helper() { }", 0.8),
+        ];
+
+        let deduped = dedupe_results(results);
+        assert_eq!(deduped.len(), 2);
+        assert_eq!(deduped[0].chunk.file_path, "src/util.rs");
+        assert_eq!(deduped[0].duplicates, vec!["vendor/lib/util.rs:10-12"]);
+        assert!(deduped[1].duplicates.is_empty());
+
+        let output = format_results(&deduped, false, false, None, 0, false, Path::new("."));
+        assert!(output.contains("src/util.rs:10-12"));
+        assert!(output.contains("also in: vendor/lib/util.rs:10-12"));
+    }
+
+    #[test]
+    fn test_summary_precedes_details() {
+        let mut results = vec![
+            create_test_result("src/b.rs", "b", 0.7),
+            create_test_result("src/a.rs", "a", 0.9),
+        ];
+        sort_results(&mut results, SortOrder::Path, &[]);
+
+        let summary = format_summary(&results);
+        let output = summary.clone()
+            + &format_results(&results, false, false, None, 0, false, Path::new("."));
+
+        let lines: Vec<&str> = summary.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("src/a.rs:10-12") && lines[0].contains("(90%)"));
+        assert!(lines[1].contains("src/b.rs:10-12") && lines[1].contains("(70%)"));
+
+        // Summary comes before the first detailed entry
+        let detail_start = output.find("[1]").unwrap();
+        assert!(output.find("src/a.rs").unwrap() < detail_start);
+        assert!(output.find("src/b.rs").unwrap() < detail_start);
+    }
+
+    #[test]
+    fn test_explain_hybrid_result() {
+        let mut result = create_test_result("src/auth.rs", "fn check() {\n    verify(jwt)\n}", 0.9);
+        result.semantic_score = Some(0.7);
+        result.keyword_score = Some(0.2);
+        result.keyword_spans = vec![(24, 27)];
+
+        let output = format_results(&[result], false, true, None, 0, false, Path::new("."));
+        assert!(output.contains("semantic: 0.700"));
+        assert!(output.contains("keyword: +0.200"));
+        assert!(output.contains("language: rust"));
+        assert!(output.contains("\"jwt\" (line 11)"));
+        assert!(!output.contains("rerank"));
+    }
+
+    #[test]
+    fn test_gutter_widens_and_lines_truncate() {
+        colored::control::set_override(false);
+
+        let mut result = create_test_result("src/big.rs", &"x".repeat(100), 0.9);
+        result.chunk.start_line = 12345;
+        result.chunk.end_line = 12345;
+
+        let output = format_results(
+            &[result.clone()],
+            true,
+            false,
+            Some(40),
+            0,
+            false,
+            Path::new("."),
+        );
+        let line = output.lines().find(|l| l.starts_with("12345")).unwrap();
+        assert_eq!(line.chars().count(), 40);
+        assert!(line.starts_with("12345 xxx"));
+        assert!(line.ends_with('…'));
+
+        // Short line numbers keep the 4-wide gutter; no width means no cut
+        result.chunk.start_line = 7;
+        let output = format_results(&[result], true, false, None, 0, false, Path::new("."));
+        let line = output.lines().find(|l| l.starts_with("   7 ")).unwrap();
+        assert_eq!(line.len(), 105);
+    }
+
+    #[test]
+    fn test_explain_semantic_only() {
+        let result = create_test_result("src/auth.rs", "fn check() {}", 0.8);
+
+        let output = format_results(&[result], false, true, None, 0, false, Path::new("."));
+        assert!(output.contains("semantic: 0.800"));
+        assert!(!output.contains("keyword"));
+
+        let plain = create_test_result("a.rs", "x", 0.8);
+        let output = format_results(&[plain], false, false, None, 0, false, Path::new("."));
+        assert!(!output.contains("semantic"));
+    }
 }
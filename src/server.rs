@@ -0,0 +1,309 @@
+//! Search daemon over HTTP
+//!
+//! Keeps the index and a warm embedder resident so repeated searches skip
+//! the model load. Answers `POST /search` and `POST /reindex` with JSON.
+//! Enabled with the `server` feature.
+//!
+//! Search responses use the same shape as `lgrep --json`, so editors and
+//! scripts can read either.
+
+use crate::error::{LgrepError, Result};
+use crate::filter::SearchFilter;
+use crate::index::SearchResult;
+use crate::indexer::{Indexer, UpdateStats};
+use crate::searcher::{format_results_json, Searcher, DEFAULT_HYBRID_ALPHA};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::Duration;
+use tiny_http::{Header, Method, Request, Response};
+use tracing::{debug, info, warn};
+
+/// How long the CLI waits when probing for a running daemon
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// How long the CLI waits for a daemon's search results before searching
+/// locally instead
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the CLI waits for a daemon to update its index
+const REINDEX_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Body of a `POST /search` request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchRequest {
+    /// Search query
+    pub query: String,
+    /// Maximum number of results
+    #[serde(default = "default_max_count", alias = "top_k")]
+    pub max_count: usize,
+    /// Optional metadata filters
+    #[serde(default)]
+    pub filters: Option<SearchFilter>,
+    /// Keyword pattern for hybrid search (regex)
+    #[serde(default)]
+    pub keyword: Option<String>,
+    /// Weight of the keyword score when `keyword` is set, 0.0 to 1.0
+    #[serde(default = "default_alpha")]
+    pub alpha: f32,
+    /// Answer with a [`SearchResponse`] of complete results (as the CLI
+    /// does) rather than the `--json` output shape
+    #[serde(default)]
+    pub full_results: bool,
+}
+
+fn default_max_count() -> usize {
+    10
+}
+
+fn default_alpha() -> f32 {
+    DEFAULT_HYBRID_ALPHA
+}
+
+/// Body of a successful `POST /search` response with `full_results`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchResponse {
+    /// Matching chunks, best first
+    pub results: Vec<SearchResult>,
+}
+
+/// Address of a running daemon, written to the index directory
+#[derive(Debug, Serialize, Deserialize)]
+struct ServerInfo {
+    addr: SocketAddr,
+    pid: u32,
+}
+
+/// HTTP daemon serving searches from a resident index
+pub struct Server {
+    http: tiny_http::Server,
+    searcher: Searcher,
+    indexer: Option<Indexer>,
+}
+
+impl Server {
+    /// Bind the daemon to an address (use port 0 for an ephemeral port)
+    pub fn bind<A: ToSocketAddrs>(addr: A, searcher: Searcher) -> Result<Self> {
+        let http = tiny_http::Server::http(addr).map_err(|e| LgrepError::Server(e.to_string()))?;
+
+        Ok(Self {
+            http,
+            searcher,
+            indexer: None,
+        })
+    }
+
+    /// Address the daemon is listening on
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.http
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| LgrepError::Server("Not listening on a TCP address".to_string()))
+    }
+
+    /// Serve requests until the process is stopped
+    ///
+    /// Advertises the daemon's address in the index directory so the CLI can
+    /// find it; the file is removed again when serving stops.
+    pub fn serve(&mut self) -> Result<()> {
+        let addr = self.local_addr()?;
+        let index_dir = self.searcher.config().index_dir.clone();
+        write_server_info(&index_dir, addr)?;
+        info!("Serving on {}", addr);
+
+        while let Ok(request) = self.http.recv() {
+            self.handle(request);
+        }
+
+        let _ = std::fs::remove_file(server_info_path(&index_dir));
+        Ok(())
+    }
+
+    /// Dispatch a single request
+    fn handle(&mut self, mut request: Request) {
+        debug!("{} {}", request.method(), request.url());
+
+        let mut body = String::new();
+        let response = match request.as_reader().read_to_string(&mut body) {
+            Err(e) => Err((400, e.to_string())),
+            Ok(_) => match (request.method(), request.url()) {
+                (Method::Post, "/search") => self.search(&body),
+                (Method::Post, "/reindex") => self.reindex(),
+                _ => Err((404, "Not found".to_string())),
+            },
+        };
+
+        let (status, json) = match response {
+            Ok(json) => (200, json),
+            Err((status, message)) => (status, serde_json::json!({ "error": message }).to_string()),
+        };
+
+        let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+        let response = Response::from_string(json)
+            .with_status_code(status)
+            .with_header(header);
+
+        if let Err(e) = request.respond(response) {
+            warn!("Failed to send response: {}", e);
+        }
+    }
+
+    /// Handle `POST /search`
+    fn search(&self, body: &str) -> std::result::Result<String, (u16, String)> {
+        let request: SearchRequest =
+            serde_json::from_str(body).map_err(|e| (400, e.to_string()))?;
+
+        let results = self
+            .searcher
+            .hybrid_search_with_alpha(
+                &request.query,
+                request.keyword.as_deref(),
+                request.max_count,
+                request.alpha,
+                request.filters.as_ref(),
+            )
+            .map_err(|e| (500, e.to_string()))?;
+
+        if request.full_results {
+            serde_json::to_string(&SearchResponse { results }).map_err(|e| (500, e.to_string()))
+        } else {
+            format_results_json(&results).map_err(|e| (500, e.to_string()))
+        }
+    }
+
+    /// Handle `POST /reindex` with an incremental update
+    fn reindex(&mut self) -> std::result::Result<String, (u16, String)> {
+        if self.indexer.is_none() {
+            let indexer =
+                Indexer::new(self.searcher.config().clone()).map_err(|e| (500, e.to_string()))?;
+            self.indexer = Some(indexer);
+        }
+
+        let indexer = self.indexer.as_ref().unwrap();
+        let stats = indexer
+            .update_index(self.searcher.index_mut())
+            .map_err(|e| (500, e.to_string()))?;
+
+        Ok(serde_json::json!({
+            "added": stats.added,
+            "updated": stats.updated,
+            "removed": stats.removed,
+            "renamed": stats.renamed,
+            "unchanged": stats.unchanged,
+        })
+        .to_string())
+    }
+}
+
+/// Path of the file advertising a running daemon
+fn server_info_path(index_dir: &Path) -> std::path::PathBuf {
+    index_dir.join("server.json")
+}
+
+fn write_server_info(index_dir: &Path, addr: SocketAddr) -> Result<()> {
+    std::fs::create_dir_all(index_dir)?;
+    let info = ServerInfo {
+        addr,
+        pid: std::process::id(),
+    };
+    std::fs::write(server_info_path(index_dir), serde_json::to_string(&info)?)?;
+    Ok(())
+}
+
+/// Find a running daemon for the index in `index_dir`, if one answers
+pub fn find_server(index_dir: &Path) -> Option<SocketAddr> {
+    let json = std::fs::read_to_string(server_info_path(index_dir)).ok()?;
+    let info: ServerInfo = serde_json::from_str(&json).ok()?;
+
+    TcpStream::connect_timeout(&info.addr, CONNECT_TIMEOUT)
+        .ok()
+        .map(|_| info.addr)
+}
+
+/// Run a search against a daemon
+pub fn remote_search(addr: SocketAddr, request: &SearchRequest) -> Result<Vec<SearchResult>> {
+    let request = SearchRequest {
+        full_results: true,
+        ..request.clone()
+    };
+    let body = post(
+        addr,
+        "/search",
+        &serde_json::to_string(&request)?,
+        SEARCH_TIMEOUT,
+    )?;
+    let response: SearchResponse = serde_json::from_str(&body)?;
+    Ok(response.results)
+}
+
+/// Ask a daemon to incrementally update its index
+pub fn remote_reindex(addr: SocketAddr) -> Result<UpdateStats> {
+    let body = post(addr, "/reindex", "", REINDEX_TIMEOUT)?;
+    let value: serde_json::Value = serde_json::from_str(&body)?;
+    let count = |key: &str| value[key].as_u64().unwrap_or(0) as usize;
+
+    Ok(UpdateStats {
+        added: count("added"),
+        updated: count("updated"),
+        removed: count("removed"),
+        renamed: count("renamed"),
+        unchanged: count("unchanged"),
+    })
+}
+
+/// Minimal HTTP/1.1 POST returning the response body
+///
+/// Fails if the daemon doesn't answer within `timeout`, so a hung daemon
+/// can't hang the CLI.
+fn post(addr: SocketAddr, path: &str, body: &str, timeout: Duration) -> Result<String> {
+    let mut stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        path,
+        addr,
+        body.len(),
+        body
+    )?;
+
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => {
+                LgrepError::Server(format!("No response within {} seconds", timeout.as_secs()))
+            }
+            _ => e.into(),
+        })?;
+
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| LgrepError::Server("Malformed response".to_string()))?;
+    let status = head.split_whitespace().nth(1).unwrap_or_default();
+
+    if status != "200" {
+        return Err(LgrepError::Server(format!("HTTP {}: {}", status, body)));
+    }
+
+    Ok(body.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_post_gives_up_on_a_silent_daemon() {
+        // Accepts the connection but never answers
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let result = post(addr, "/search", "{}", Duration::from_millis(100));
+        assert!(matches!(result, Err(LgrepError::Server(_))));
+        drop(listener);
+    }
+}
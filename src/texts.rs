@@ -0,0 +1,215 @@
+//! Chunk text kept on disk and read on demand
+//!
+//! Searching only needs the text of the chunks it returns, so `texts.bin`
+//! holds chunk text apart from the metadata loaded for every search. Layout:
+//! [`TEXTS_MAGIC`], the UTF-8 texts back to back, a table of
+//! `(id: u64, offset: u64, len: u32)` entries, then the table's offset and
+//! entry count as `u64`s. All integers are little-endian. Loading reads only
+//! the table; texts are read when asked for.
+
+use crate::error::{LgrepError, Result};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Marker at the start of a chunk text file
+pub const TEXTS_MAGIC: &[u8; 4] = b"LGRT";
+
+/// Bytes per table entry: id, offset and length
+const ENTRY_LEN: usize = 8 + 8 + 4;
+
+/// Bytes of the footer: table offset and entry count
+const FOOTER_LEN: u64 = 8 + 8;
+
+/// Text of every chunk in an index, by chunk ID
+#[derive(Default)]
+pub struct ChunkTexts {
+    /// File the index was loaded from, if any
+    stored: Option<StoredTexts>,
+    /// Texts added or replaced since loading
+    added: HashMap<u64, String>,
+}
+
+/// An open `texts.bin` and its entry table
+struct StoredTexts {
+    /// Kept open: a save renames a new file over the path, and the offsets
+    /// below belong to this one
+    file: Mutex<File>,
+    entries: HashMap<u64, (u64, u32)>,
+}
+
+impl ChunkTexts {
+    /// Open a text file, reading only its entry table
+    pub fn open(path: &Path) -> Result<Self> {
+        let damaged = || LgrepError::Index(format!("{} is damaged or truncated", path.display()));
+
+        let mut file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let mut magic = [0u8; 4];
+        file.read_exact(&mut magic)?;
+        if &magic != TEXTS_MAGIC || len < TEXTS_MAGIC.len() as u64 + FOOTER_LEN {
+            return Err(damaged());
+        }
+
+        let mut footer = [0u8; FOOTER_LEN as usize];
+        file.seek(SeekFrom::Start(len - FOOTER_LEN))?;
+        file.read_exact(&mut footer)?;
+        let table_offset = u64::from_le_bytes(footer[..8].try_into().unwrap());
+        let count = u64::from_le_bytes(footer[8..].try_into().unwrap());
+        if table_offset.checked_add(count.saturating_mul(ENTRY_LEN as u64))
+            != Some(len - FOOTER_LEN)
+        {
+            return Err(damaged());
+        }
+
+        let mut table = vec![0u8; count as usize * ENTRY_LEN];
+        file.seek(SeekFrom::Start(table_offset))?;
+        file.read_exact(&mut table)?;
+        let mut entries = HashMap::with_capacity(count as usize);
+        for entry in table.chunks_exact(ENTRY_LEN) {
+            let id = u64::from_le_bytes(entry[..8].try_into().unwrap());
+            let offset = u64::from_le_bytes(entry[8..16].try_into().unwrap());
+            let text_len = u32::from_le_bytes(entry[16..].try_into().unwrap());
+            if offset.saturating_add(text_len as u64) > table_offset {
+                return Err(damaged());
+            }
+            entries.insert(id, (offset, text_len));
+        }
+
+        Ok(Self {
+            stored: Some(StoredTexts {
+                file: Mutex::new(file),
+                entries,
+            }),
+            added: HashMap::new(),
+        })
+    }
+
+    /// Text of a chunk, reading it from disk if it wasn't added since loading
+    pub fn get(&self, id: u64) -> Result<Option<String>> {
+        if let Some(text) = self.added.get(&id) {
+            return Ok(Some(text.clone()));
+        }
+        let Some(stored) = &self.stored else {
+            return Ok(None);
+        };
+        let Some(&(offset, len)) = stored.entries.get(&id) else {
+            return Ok(None);
+        };
+
+        let mut bytes = vec![0u8; len as usize];
+        {
+            let mut file = stored.file.lock().unwrap_or_else(|e| e.into_inner());
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut bytes)?;
+        }
+        String::from_utf8(bytes)
+            .map(Some)
+            .map_err(|_| LgrepError::Index(format!("Text of chunk {} is not UTF-8", id)))
+    }
+
+    /// Length in bytes of a chunk's text, without reading it
+    pub fn text_len(&self, id: u64) -> Option<usize> {
+        if let Some(text) = self.added.get(&id) {
+            return Some(text.len());
+        }
+        let stored = self.stored.as_ref()?;
+        stored.entries.get(&id).map(|&(_, len)| len as usize)
+    }
+
+    /// Set the text of a chunk
+    pub fn insert(&mut self, id: u64, text: String) {
+        self.added.insert(id, text);
+    }
+
+    /// Forget the text of a removed chunk
+    pub fn remove(&mut self, id: u64) {
+        self.added.remove(&id);
+        if let Some(stored) = &mut self.stored {
+            stored.entries.remove(&id);
+        }
+    }
+
+    /// Write the texts of `ids` to `path`, one text in memory at a time
+    pub fn write(&self, path: &Path, ids: impl IntoIterator<Item = u64>) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(TEXTS_MAGIC)?;
+
+        let mut offset = TEXTS_MAGIC.len() as u64;
+        let mut table = Vec::new();
+        for id in ids {
+            let text = self.get(id)?.unwrap_or_default();
+            let len = u32::try_from(text.len()).map_err(|_| {
+                LgrepError::Index(format!("Text of chunk {} is too large to store", id))
+            })?;
+            writer.write_all(text.as_bytes())?;
+            table.extend_from_slice(&id.to_le_bytes());
+            table.extend_from_slice(&offset.to_le_bytes());
+            table.extend_from_slice(&len.to_le_bytes());
+            offset += len as u64;
+        }
+
+        writer.write_all(&table)?;
+        writer.write_all(&offset.to_le_bytes())?;
+        writer.write_all(&((table.len() / ENTRY_LEN) as u64).to_le_bytes())?;
+        writer
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+        Ok(())
+    }
+}
+
+impl From<HashMap<u64, String>> for ChunkTexts {
+    fn from(added: HashMap<u64, String>) -> Self {
+        Self {
+            stored: None,
+            added,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_reads_on_demand() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("texts.bin");
+
+        let mut texts = ChunkTexts::default();
+        texts.insert(3, "fn three() {}".to_string());
+        texts.insert(7, "ünïcode".to_string());
+        texts.insert(9, String::new());
+        texts.write(&path, [3, 7, 9]).unwrap();
+
+        let mut loaded = ChunkTexts::open(&path).unwrap();
+        assert!(loaded.added.is_empty());
+        assert_eq!(loaded.get(7).unwrap().as_deref(), Some("ünïcode"));
+        assert_eq!(loaded.get(3).unwrap().as_deref(), Some("fn three() {}"));
+        assert_eq!(loaded.get(9).unwrap().as_deref(), Some(""));
+        assert_eq!(loaded.text_len(7), Some("ünïcode".len()));
+        assert_eq!(loaded.get(4).unwrap(), None);
+
+        loaded.insert(3, "fn changed() {}".to_string());
+        loaded.remove(7);
+        assert_eq!(loaded.get(3).unwrap().as_deref(), Some("fn changed() {}"));
+        assert_eq!(loaded.get(7).unwrap(), None);
+    }
+
+    #[test]
+    fn test_truncated_file_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("texts.bin");
+        let mut texts = ChunkTexts::default();
+        texts.insert(0, "fn a() {}".to_string());
+        texts.write(&path, [0]).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 3]).unwrap();
+        assert!(matches!(ChunkTexts::open(&path), Err(LgrepError::Index(_))));
+    }
+}
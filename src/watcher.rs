@@ -2,13 +2,17 @@
 //!
 //! Watches for file changes and automatically updates the index.
 //! Uses debouncing to avoid excessive updates on rapid changes.
+//! A shutdown request (Ctrl+C in the CLI) stops watching cleanly: the index
+//! is saved and a summary printed.
 
-use crate::config::{should_index_file, Config};
+use crate::config::{is_editor_temp_file, Config};
 use crate::error::{LgrepError, Result};
 use crate::index::VectorIndex;
-use crate::indexer::Indexer;
+use crate::indexer::{Indexer, UpdateStats};
+use chrono::{DateTime, Local};
+use indicatif::{ProgressBar, ProgressStyle};
 use notify::RecursiveMode;
-use notify_debouncer_mini::{new_debouncer, DebouncedEvent};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, DebouncedEvent};
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::{channel, Receiver};
@@ -16,24 +20,47 @@ use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tracing::{debug, info, warn};
 
+/// How long to wait for an in-flight rename to settle before re-reading
+const RENAME_SETTLE: Duration = Duration::from_millis(100);
+
+/// What the watch loop receives: file events, or a request to stop
+enum WatchMessage {
+    Events(DebounceEventResult),
+    Shutdown,
+}
+
 /// File system watcher for live index updates
 pub struct IndexWatcher {
     config: Config,
     indexer: Indexer,
     index: Arc<Mutex<VectorIndex>>,
+    /// Whether to keep a live status line at the bottom of the terminal
+    show_status: bool,
+    status: Option<ProgressBar>,
+    /// Changed paths handled since watching started
+    events_processed: usize,
+    /// Files added, updated and removed since watching started
+    session: UpdateStats,
+    last_update: Option<DateTime<Local>>,
 }
 
 impl IndexWatcher {
     /// Create a new watcher for the given configuration
     pub fn new(config: Config) -> Result<Self> {
         let indexer = Indexer::new(config.clone())?;
+        Self::with_indexer(config, indexer)
+    }
 
+    /// Create a watcher that updates the index with an existing indexer
+    pub fn with_indexer(config: Config, indexer: Indexer) -> Result<Self> {
         // Try to load existing index or build new one
         let index = match VectorIndex::load(config.clone()) {
             Ok(idx) => {
                 info!("Loaded existing index");
                 idx
             }
+            // Built with another model: rebuilding would silently replace it
+            Err(e @ LgrepError::Config(_)) => return Err(e),
             Err(_) => {
                 info!("Building new index...");
                 indexer.build_index()?
@@ -44,81 +71,136 @@ impl IndexWatcher {
             config,
             indexer,
             index: Arc::new(Mutex::new(index)),
+            show_status: false,
+            status: None,
+            events_processed: 0,
+            session: UpdateStats::default(),
+            last_update: None,
         })
     }
 
+    /// Keep a live status line with counts and the last update time
+    ///
+    /// Should stay off when logging to the terminal, since log lines would
+    /// be drawn over the status line.
+    pub fn with_status_line(mut self, enabled: bool) -> Self {
+        self.show_status = enabled;
+        self
+    }
+
+    /// Catch up with changes made while nothing was watching
+    ///
+    /// Meant to run before [`watch`](Self::watch), and before any shutdown
+    /// handler is installed, so a long sync can still be interrupted.
+    pub fn sync(&mut self) -> Result<UpdateStats> {
+        let mut index = self.index.lock().unwrap();
+        let stats = self.indexer.update_index(&mut index)?;
+        info!("Initial sync: {}", stats);
+        Ok(stats)
+    }
+
     /// Start watching for file changes
     ///
-    /// This blocks until interrupted (Ctrl+C).
-    pub fn watch(&mut self) -> Result<()> {
+    /// This blocks until a message arrives on `shutdown` (or its sender is
+    /// dropped), then saves the index and prints a summary of the session.
+    /// Installing a signal handler that sends it is up to the caller.
+    pub fn watch(&mut self, shutdown: Receiver<()>) -> Result<()> {
         let root = self.config.root_path.canonicalize()?;
         info!("Watching {:?} for changes...", root);
 
-        // First do an incremental update
-        {
-            let mut index = self.index.lock().unwrap();
-            let stats = self.indexer.update_index(&mut index)?;
-            info!("Initial sync: {}", stats);
-        }
-
         // Set up file watcher with debouncing
         let (tx, rx) = channel();
 
-        let mut debouncer = new_debouncer(Duration::from_millis(500), tx)
-            .map_err(|e| LgrepError::Watch(e.to_string()))?;
+        // Shutdown is queued behind any pending events, so an update in
+        // progress finishes before the final save
+        let shutdown_tx = tx.clone();
+        std::thread::spawn(move || {
+            let _ = shutdown.recv();
+            let _ = shutdown_tx.send(WatchMessage::Shutdown);
+        });
 
-        debouncer
-            .watcher()
-            .watch(&root, RecursiveMode::Recursive)
-            .map_err(|e| LgrepError::Watch(e.to_string()))?;
+        let mut debouncer = new_debouncer(Duration::from_millis(500), move |result| {
+            let _ = tx.send(WatchMessage::Events(result));
+        })
+        .map_err(|e| LgrepError::Watch(e.to_string()))?;
 
-        println!(
-            "\n✓ Index ready ({} files, {} chunks)",
-            self.index.lock().unwrap().file_count(),
-            self.index.lock().unwrap().chunk_count()
-        );
+        for workspace_root in self.config.roots()? {
+            debouncer
+                .watcher()
+                .watch(&workspace_root.path, RecursiveMode::Recursive)
+                .map_err(|e| LgrepError::Watch(e.to_string()))?;
+        }
+
+        // One guard: the mutex isn't re-entrant
+        {
+            let index = self.index.lock().unwrap();
+            println!(
+                "\n✓ Index ready ({} files, {} chunks)",
+                index.file_count(),
+                index.chunk_count()
+            );
+        }
         println!("  Watching for changes. Press Ctrl+C to stop.\n");
 
+        if self.show_status {
+            let status = ProgressBar::new_spinner();
+            status.set_style(
+                ProgressStyle::default_spinner()
+                    .template("{spinner:.green} {msg}")
+                    .unwrap(),
+            );
+            status.enable_steady_tick(Duration::from_secs(1));
+            self.status = Some(status);
+            self.refresh_status();
+        }
+
         // Process events
         self.process_events(rx, &root)?;
+        drop(debouncer);
 
+        self.shutdown()
+    }
+
+    /// Save the index and report the session once watching stops
+    fn shutdown(&mut self) -> Result<()> {
+        if let Some(status) = self.status.take() {
+            status.finish_and_clear();
+        }
+
+        // Saved under the lock so no other holder of the index can be
+        // midway through changing it
+        self.index.lock().unwrap().save()?;
+
+        println!(
+            "\n{}",
+            format_session_summary(self.events_processed, &self.session)
+        );
         Ok(())
     }
 
     /// Process file system events from the watcher
-    fn process_events(
-        &mut self,
-        rx: Receiver<std::result::Result<Vec<DebouncedEvent>, notify::Error>>,
-        root: &Path,
-    ) -> Result<()> {
+    fn process_events(&mut self, rx: Receiver<WatchMessage>, root: &Path) -> Result<()> {
         loop {
             match rx.recv() {
-                Ok(Ok(events)) => {
-                    let mut changed_files: HashSet<PathBuf> = HashSet::new();
-
-                    for event in events {
-                        let path = &event.path;
-
-                        // Skip non-indexable files
-                        if !should_index_file(path) {
-                            continue;
-                        }
-
-                        // Skip files in .lgrep directory
-                        if path.starts_with(root.join(".lgrep")) {
-                            continue;
-                        }
-
-                        changed_files.insert(path.clone());
-                    }
+                Ok(WatchMessage::Events(Ok(events))) => {
+                    let mut changed_files = changed_paths(events, root, &self.config);
+                    let interrupted = coalesce_pending(&rx, root, &self.config, &mut changed_files);
 
                     if !changed_files.is_empty() {
                         self.handle_changes(changed_files)?;
                     }
+                    if interrupted {
+                        debug!("Interrupted, stopping watch");
+                        break;
+                    }
                 }
-                Ok(Err(e)) => {
+                Ok(WatchMessage::Events(Err(e))) => {
                     warn!("Watch error: {:?}", e);
                 }
+                Ok(WatchMessage::Shutdown) => {
+                    debug!("Interrupted, stopping watch");
+                    break;
+                }
                 Err(e) => {
                     debug!("Watch channel closed: {:?}", e);
                     break;
@@ -133,25 +215,273 @@ impl IndexWatcher {
     fn handle_changes(&mut self, changed_files: HashSet<PathBuf>) -> Result<()> {
         info!("Processing {} changed files...", changed_files.len());
 
+        // A path missing right now may be mid atomic-save (temp file renamed
+        // over the target), so give the rename a moment to land before reading
+        if changed_files.iter().any(|p| !p.exists()) {
+            std::thread::sleep(RENAME_SETTLE);
+        }
+
+        let paths: Vec<PathBuf> = changed_files.into_iter().collect();
         let mut index = self.index.lock().unwrap();
-        let stats = self.indexer.update_index(&mut index)?;
+        let stats = self.indexer.update_paths(&mut index, &paths)?;
 
-        if stats.added > 0 || stats.updated > 0 || stats.removed > 0 {
-            println!(
-                "  Updated: +{} ~{} -{} (total: {} chunks)",
+        if stats.changed() {
+            let line = format!(
+                "  Updated: +{} ~{} >{} -{} (total: {} chunks)",
                 stats.added,
                 stats.updated,
+                stats.renamed,
                 stats.removed,
                 index.chunk_count()
             );
+            // Printed above the status line rather than over it
+            match &self.status {
+                Some(status) => status.println(line),
+                None => println!("{}", line),
+            }
         }
+        drop(index);
+
+        self.session.added += stats.added;
+        self.session.updated += stats.updated;
+        self.session.renamed += stats.renamed;
+        self.session.removed += stats.removed;
+        self.events_processed += paths.len();
+        self.last_update = Some(Local::now());
+        self.refresh_status();
 
         Ok(())
     }
 
+    /// Redraw the status line, if enabled
+    fn refresh_status(&self) {
+        let Some(status) = &self.status else {
+            return;
+        };
+
+        let index = self.index.lock().unwrap();
+        status.set_message(format_status(
+            index.file_count(),
+            index.chunk_count(),
+            self.events_processed,
+            self.last_update.as_ref(),
+        ));
+    }
+
     /// Get the current index for searching
     #[allow(dead_code)]
     pub fn index(&self) -> Arc<Mutex<VectorIndex>> {
         Arc::clone(&self.index)
     }
 }
+
+/// Text of the watch status line
+fn format_status(
+    files: usize,
+    chunks: usize,
+    events: usize,
+    last_update: Option<&DateTime<Local>>,
+) -> String {
+    let last = last_update
+        .map(|t| t.format("%H:%M:%S").to_string())
+        .unwrap_or_else(|| "never".to_string());
+
+    format!(
+        "Watching: {} files, {} chunks | {} events processed | last update {}",
+        files, chunks, events, last
+    )
+}
+
+/// Line printed when watching stops
+fn format_session_summary(events: usize, session: &UpdateStats) -> String {
+    format!(
+        "Stopped watching: {} events processed, +{} ~{} >{} -{} files this session",
+        events, session.added, session.updated, session.renamed, session.removed
+    )
+}
+
+/// Fold batches that queued up during the last update into `changed_files`
+///
+/// A build touching many files produces a debounced batch every interval
+/// while the previous one is still being embedded; handling the backlog as
+/// one update re-reads each path once. Returns whether a shutdown was
+/// queued, which ends the backlog.
+fn coalesce_pending(
+    rx: &Receiver<WatchMessage>,
+    root: &Path,
+    config: &Config,
+    changed_files: &mut HashSet<PathBuf>,
+) -> bool {
+    while let Ok(message) = rx.try_recv() {
+        match message {
+            WatchMessage::Events(Ok(events)) => {
+                changed_files.extend(changed_paths(events, root, config));
+            }
+            WatchMessage::Events(Err(e)) => warn!("Watch error: {:?}", e),
+            WatchMessage::Shutdown => return true,
+        }
+    }
+    false
+}
+
+/// Reduce a batch of debounced events to the set of paths worth re-indexing
+///
+/// Editor swap/temp files are dropped so an atomic save (write temp, rename
+/// over target) only yields the final target path, once.
+fn changed_paths(events: Vec<DebouncedEvent>, root: &Path, config: &Config) -> HashSet<PathBuf> {
+    let mut changed_files = HashSet::new();
+
+    for event in events {
+        let path = event.path;
+
+        // Skip editor swap, backup and atomic-save temp files
+        if is_editor_temp_file(&path) {
+            continue;
+        }
+
+        // Skip non-indexable files (deleted paths are kept so
+        // removed directories can be purged from the index)
+        if !config.should_index(&path) && path.exists() {
+            continue;
+        }
+
+        // Skip files in .lgrep directory
+        if path.starts_with(root.join(".lgrep")) {
+            continue;
+        }
+
+        changed_files.insert(path);
+    }
+
+    changed_files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedder::StubEmbedder;
+    use notify_debouncer_mini::DebouncedEventKind;
+    use tempfile::tempdir;
+
+    fn event(path: PathBuf) -> DebouncedEvent {
+        DebouncedEvent {
+            path,
+            kind: DebouncedEventKind::Any,
+        }
+    }
+
+    #[test]
+    fn test_watch_stops_on_shutdown_request() {
+        let dir = tempdir().unwrap();
+        let config = Config::new(dir.path().to_path_buf());
+
+        // Twice, since nothing process-wide is installed
+        for _ in 0..2 {
+            let mut watcher = IndexWatcher::new(config.clone()).unwrap();
+            watcher.sync().unwrap();
+            let (stop_tx, stop_rx) = channel();
+            stop_tx.send(()).unwrap();
+            watcher.watch(stop_rx).unwrap();
+        }
+        assert!(config.index_path().exists());
+    }
+
+    #[test]
+    fn test_sync_picks_up_files_written_before_watching() {
+        let dir = tempdir().unwrap();
+        let config = Config::new(dir.path().to_path_buf());
+        let embedder = StubEmbedder {
+            dimension: config.model.dimension(),
+        };
+        let indexer = Indexer::with_embedder(config.clone(), Box::new(embedder));
+        let mut watcher = IndexWatcher::with_indexer(config, indexer).unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+
+        let stats = watcher.sync().unwrap();
+        assert_eq!(stats.added, 1);
+        assert_eq!(watcher.index().lock().unwrap().file_count(), 1);
+    }
+
+    #[test]
+    fn test_format_status() {
+        use chrono::TimeZone;
+
+        assert_eq!(
+            format_status(12, 340, 0, None),
+            "Watching: 12 files, 340 chunks | 0 events processed | last update never"
+        );
+
+        let time = Local.with_ymd_and_hms(2024, 1, 2, 14, 3, 22).unwrap();
+        assert_eq!(
+            format_status(12, 345, 3, Some(&time)),
+            "Watching: 12 files, 345 chunks | 3 events processed | last update 14:03:22"
+        );
+    }
+
+    #[test]
+    fn test_format_session_summary() {
+        let session = UpdateStats {
+            added: 2,
+            updated: 5,
+            removed: 1,
+            renamed: 3,
+            unchanged: 0,
+        };
+        assert_eq!(
+            format_session_summary(9, &session),
+            "Stopped watching: 9 events processed, +2 ~5 >3 -1 files this session"
+        );
+    }
+
+    #[test]
+    fn test_coalesce_pending_batches() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let config = Config::new(root.to_path_buf());
+        let (tx, rx) = channel();
+
+        // Three queued batches touching two files, then Ctrl+C
+        for name in ["a.rs", "b.rs", "a.rs"] {
+            tx.send(WatchMessage::Events(Ok(vec![event(root.join(name))])))
+                .unwrap();
+        }
+        tx.send(WatchMessage::Shutdown).unwrap();
+        tx.send(WatchMessage::Events(Ok(vec![event(root.join("c.rs"))])))
+            .unwrap();
+
+        let mut changed = HashSet::new();
+        assert!(coalesce_pending(&rx, root, &config, &mut changed));
+        assert_eq!(changed.len(), 2);
+        assert!(changed.contains(&root.join("a.rs")));
+        assert!(changed.contains(&root.join("b.rs")));
+
+        // Nothing queued: no shutdown, nothing added
+        let mut changed = HashSet::new();
+        let (_tx, rx) = channel();
+        assert!(!coalesce_pending(&rx, root, &config, &mut changed));
+        assert!(changed.is_empty());
+    }
+
+    #[test]
+    fn test_atomic_save_yields_only_target() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let target = root.join("main.rs");
+        std::fs::write(&target, "fn main() {}").unwrap();
+
+        // vim-style save: probe file, swap file, backup, then the target itself
+        let events = vec![
+            event(root.join("4913")),
+            event(root.join(".main.rs.swp")),
+            event(root.join("main.rs~")),
+            event(root.join("main.rs.tmp")),
+            event(target.clone()),
+            event(target.clone()),
+            event(root.join(".lgrep/metadata.bin")),
+        ];
+
+        let changed = changed_paths(events, root, &Config::new(root.to_path_buf()));
+        assert_eq!(changed.len(), 1);
+        assert!(changed.contains(&target));
+    }
+}
@@ -0,0 +1,24 @@
+//! Integration tests for the command-line interface
+
+use std::process::Command;
+
+#[test]
+fn test_json_error_for_missing_index() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_lgrep"))
+        .args(["search", "database connection"])
+        .arg(dir.path())
+        .args(["--error-format", "json"])
+        .env_remove("LGREP_ERROR_FORMAT")
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let error: serde_json::Value = serde_json::from_str(stderr.trim()).unwrap();
+    assert_eq!(error["error"], "NoIndex");
+    assert_eq!(error["hint"], "run lgrep index");
+    let message = error["message"].as_str().unwrap();
+    assert!(message.contains("No index found"), "{}", message);
+}
@@ -0,0 +1,146 @@
+//! Integration tests for the search daemon
+
+#![cfg(feature = "server")]
+
+use lgrep::server::{remote_search, SearchRequest, Server};
+use lgrep::{
+    Chunk, Config, ContentKind, Embed, Result, SearchFilter, Searcher, VectorIndex,
+    DEFAULT_HYBRID_ALPHA,
+};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+/// Embeds text as a normalized bag of characters (no model download)
+struct CharEmbedder {
+    dimension: usize,
+}
+
+impl Embed for CharEmbedder {
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn embed_one(&self, text: &str) -> Result<Vec<f32>> {
+        let mut embedding = vec![0.0; self.dimension];
+        embedding[0] = 0.01;
+        for c in text.chars().filter(|c| c.is_alphanumeric()) {
+            embedding[1 + (c as usize) % (self.dimension - 1)] += 1.0;
+        }
+        let norm: f32 = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+        Ok(embedding.into_iter().map(|x| x / norm).collect())
+    }
+
+    fn embed_batch(&self, texts: Vec<&str>) -> Result<Vec<Vec<f32>>> {
+        texts.into_iter().map(|t| self.embed_one(t)).collect()
+    }
+}
+
+fn chunk(id: u64, file_path: &str, text: &str) -> Chunk {
+    Chunk {
+        id,
+        text: text.to_string(),
+        file_path: file_path.to_string(),
+        start_line: 1,
+        end_line: 1,
+        file_hash: "hash".to_string(),
+        language: None,
+        overlap_with_prev: 0,
+        content_kind: ContentKind::Code,
+        text_hash: String::new(),
+        symbols: Vec::new(),
+    }
+}
+
+#[test]
+fn test_search_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = Config::new(PathBuf::from(dir.path()));
+    let embedder = CharEmbedder {
+        dimension: config.model.dimension(),
+    };
+
+    let chunks = vec![
+        chunk(0, "src/db.rs", "open database connection pool"),
+        chunk(1, "src/auth.rs", "verify password hash"),
+        chunk(2, "docs/db.md", "database connection pool"),
+    ];
+    let embeddings = chunks
+        .iter()
+        .map(|c| embedder.embed_one(&c.text).unwrap())
+        .collect();
+    let mut index = VectorIndex::new(config.clone()).unwrap();
+    index.add_chunks(chunks, embeddings).unwrap();
+
+    let searcher = Searcher::with_embedder(index, Box::new(embedder));
+    let mut server = Server::bind("127.0.0.1:0", searcher).unwrap();
+    let addr = server.local_addr().unwrap();
+    std::thread::spawn(move || server.serve());
+
+    let request = SearchRequest {
+        query: "database connection pool".to_string(),
+        max_count: 2,
+        filters: None,
+        keyword: None,
+        alpha: DEFAULT_HYBRID_ALPHA,
+        full_results: false,
+    };
+    let results = remote_search(addr, &request).unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results[0].score >= results[1].score);
+
+    let request = SearchRequest {
+        filters: Some(SearchFilter::new().with_extensions(vec!["rs".to_string()])),
+        ..request
+    };
+    let results = remote_search(addr, &request).unwrap();
+    assert!(results.iter().all(|r| r.chunk.file_path.ends_with(".rs")));
+    assert_eq!(results[0].chunk.file_path, "src/db.rs");
+}
+
+#[test]
+fn test_search_answers_in_json_output_shape() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = Config::new(PathBuf::from(dir.path()));
+    let embedder = CharEmbedder {
+        dimension: config.model.dimension(),
+    };
+
+    let chunks = vec![
+        chunk(0, "src/db.rs", "open database connection pool"),
+        chunk(1, "src/auth.rs", "verify password hash"),
+    ];
+    let embeddings = chunks
+        .iter()
+        .map(|c| embedder.embed_one(&c.text).unwrap())
+        .collect();
+    let mut index = VectorIndex::new(config).unwrap();
+    index.add_chunks(chunks, embeddings).unwrap();
+
+    let searcher = Searcher::with_embedder(index, Box::new(embedder));
+    let mut server = Server::bind("127.0.0.1:0", searcher).unwrap();
+    let addr = server.local_addr().unwrap();
+    std::thread::spawn(move || server.serve());
+
+    // A plain client, as an editor plugin would send it
+    let body = r#"{"query": "database pool", "top_k": 1, "filters": {"extensions": ["rs"]}}"#;
+    let mut stream = std::net::TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "POST /search HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        addr,
+        body.len(),
+        body
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    assert!(head.starts_with("HTTP/1.1 200"));
+    let results: serde_json::Value = serde_json::from_str(body).unwrap();
+    let results = results.as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["file"], "src/db.rs");
+    assert!(results[0]["score_pct"].is_u64());
+    assert_eq!(results[0]["content"], "open database connection pool");
+}